@@ -1,4 +1,7 @@
+use crate::commands::walk::{GlobPattern, WalkOptions};
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use crush_core::parity::MAX_PARITY_SHARDS;
+use crush_core::HashAlgorithm;
 use std::path::PathBuf;
 
 /// High-performance parallel compression
@@ -22,6 +25,9 @@ or via environment variables.")]
     # List available plugins
     crush plugins list
 
+    # Bundle a directory into an archive
+    crush archive create backup.crar docs/
+
     # Configure default compression level
     crush config set compression.level fast
 
@@ -33,6 +39,7 @@ EXIT CODES:
     0   - Success
     1   - Operational error (I/O, compression failure, etc.)
     2   - Configuration or usage error
+    3   - Some files failed (--keep-going)
     130 - Operation cancelled (Ctrl+C/SIGINT)
 
 For more information about a specific command, run:
@@ -49,9 +56,9 @@ pub struct Cli {
     #[arg(short, long, conflicts_with = "verbose")]
     pub quiet: bool,
 
-    /// Log format: human or json
-    #[arg(long, value_name = "FORMAT", default_value = "human")]
-    pub log_format: LogFormat,
+    /// Log format: human or json (default: logging.format from config)
+    #[arg(long, value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
 
     /// Log output file (default: stderr)
     #[arg(long, value_name = "FILE")]
@@ -71,6 +78,16 @@ pub enum Commands {
     Config(ConfigArgs),
     /// Manage compression plugins
     Plugins(PluginsArgs),
+    /// Bundle files and directories into a single archive
+    Archive(ArchiveArgs),
+    /// Sign compressed files with an Ed25519 key
+    Sign(SignArgs),
+    /// Verify the signatures of compressed files
+    VerifySignature(VerifySignatureArgs),
+    /// Generate an Ed25519 key pair for signing
+    Keygen(KeygenArgs),
+    /// Repair damaged blocks of files compressed with --parity
+    Repair(RepairArgs),
 }
 
 /// Compress command arguments
@@ -88,12 +105,37 @@ pub enum Commands {
     # Compress with fast preset
     crush compress --level fast largefile.dat
 
+    # Tune plugin parameters (see 'crush plugins info <PLUGIN>')
+    crush compress --plugin zstd --param level=19 --param window_log=27 data.bin
+
     # Compress to specific output
     crush compress input.txt --output /backup/input.txt.crush
 
     # Force overwrite existing compressed file
     crush compress --force document.txt
 
+    # Make the output seekable for random-access reads
+    crush compress --seekable trace.bin
+
+    # Record a BLAKE3 hash of the content, checked on decompression
+    crush compress --hash blake3 dataset.parquet
+
+    # Store parity so up to 4 damaged bytes per block are repaired
+    crush compress --parity 4 archive.tar
+
+    # Encrypt a backup with a passphrase, or with a key file
+    crush compress --encrypt backup.tar
+    crush compress --key-file backup.key backup.tar
+
+    # Compress every log file under a directory, in place
+    crush compress -r --include '*.log' /var/log/myapp
+
+    # Compress many files four at a time
+    crush compress -j 4 *.csv
+
+    # Carry on past failures and record every file's outcome
+    crush compress -r --keep-going --report report.json data/
+
     # Pipeline: read from stdin, write to file
     cat file.txt | crush compress --output file.txt.crush
 
@@ -116,9 +158,9 @@ pub struct CompressArgs {
     #[arg(short, long, value_name = "PLUGIN")]
     pub plugin: Option<String>,
 
-    /// Compression level preset
-    #[arg(short, long, value_name = "LEVEL", default_value = "balanced")]
-    pub level: CompressionLevel,
+    /// Compression level preset (default: compression.level from config)
+    #[arg(short, long, value_name = "LEVEL")]
+    pub level: Option<CompressionLevel>,
 
     /// Force overwrite of existing files
     #[arg(short, long)]
@@ -127,6 +169,111 @@ pub struct CompressArgs {
     /// Compression timeout in seconds (0 = no timeout)
     #[arg(long, value_name = "SECONDS")]
     pub timeout: Option<u64>,
+
+    /// Append a block index so byte ranges can be read without full decompression
+    #[arg(long)]
+    pub seekable: bool,
+
+    /// Plugin-specific parameter, repeatable (e.g. --param level=19)
+    #[arg(short = 'P', long = "param", value_name = "KEY=VALUE", value_parser = parse_plugin_param)]
+    pub params: Vec<(String, i64)>,
+
+    /// Record a hash of the uncompressed content (blake3, sha256 or xxh3)
+    #[arg(long, value_name = "ALGORITHM", value_parser = str::parse::<HashAlgorithm>)]
+    pub hash: Option<HashAlgorithm>,
+
+    /// Store Reed-Solomon parity with every block, repairing up to N damaged bytes per block
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(1..=i64::from(MAX_PARITY_SHARDS))
+    )]
+    pub parity: Option<u8>,
+
+    /// Encrypt the output with a passphrase (prompted, or from CRUSH_PASSPHRASE)
+    #[arg(long, conflicts_with_all = ["seekable", "parity"])]
+    pub encrypt: bool,
+
+    /// Encrypt the output with the 256-bit key in this file (32 bytes or 64 hex characters)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["seekable", "parity"])]
+    pub key_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub batch: BatchArgs,
+
+    #[command(flatten)]
+    pub walk: RecursiveArgs,
+}
+
+/// Options for processing many files, shared by compress and decompress
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Number of files to process at once (0 = one per CPU)
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        conflicts_with = "stdout"
+    )]
+    pub jobs: usize,
+
+    /// Carry on past files that fail, then exit with code 3
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Write a JSON report of every file's outcome to this path
+    #[arg(long, value_name = "FILE", conflicts_with = "stdout")]
+    pub report: Option<PathBuf>,
+}
+
+/// Options for walking directory inputs, shared by compress and decompress
+#[derive(Args, Debug)]
+pub struct RecursiveArgs {
+    /// Process every file under directory inputs, writing outputs in place
+    #[arg(short, long, conflicts_with_all = ["output", "stdout"])]
+    pub recursive: bool,
+
+    /// Only process files matching this glob, repeatable (e.g. --include '*.log')
+    #[arg(long, value_name = "GLOB", requires = "recursive", value_parser = GlobPattern::parse)]
+    pub include: Vec<GlobPattern>,
+
+    /// Skip files and directories matching this glob, repeatable
+    #[arg(long, value_name = "GLOB", requires = "recursive", value_parser = GlobPattern::parse)]
+    pub exclude: Vec<GlobPattern>,
+
+    /// Follow symlinks while walking (default: skip them)
+    #[arg(long, requires = "recursive")]
+    pub follow_symlinks: bool,
+}
+
+impl RecursiveArgs {
+    /// Walk options for [`crate::commands::walk::expand_inputs`]
+    pub fn to_walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            follow_symlinks: self.follow_symlinks,
+        }
+    }
+}
+
+/// Parse a `KEY=VALUE` plugin parameter with an integer value
+fn parse_plugin_param(s: &str) -> Result<(String, i64), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{s}'"))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("missing parameter name in '{s}'"));
+    }
+    let value = value.trim().parse::<i64>().map_err(|_| {
+        format!(
+            "parameter '{name}' must be an integer, got '{}'",
+            value.trim()
+        )
+    })?;
+    Ok((name.to_string(), value))
 }
 
 /// Decompress command arguments
@@ -148,7 +295,22 @@ pub struct CompressArgs {
     cat data.crush | crush decompress --stdout
 
     # Force overwrite existing file
-    crush decompress --force document.txt.crush")]
+    crush decompress --force document.txt.crush
+
+    # Decrypt with a key file (passphrases are prompted for)
+    crush decompress --key-file backup.key backup.tar.crush
+
+    # Decompress every .crush file under a directory, in place
+    crush decompress -r backups/
+
+    # Decompress on every CPU
+    crush decompress -r -j 0 backups/
+
+    # Salvage the intact data of a truncated or damaged file
+    crush decompress --recover damaged.log.crush
+
+    # List the damaged regions as JSON
+    crush decompress --recover --report recovery.json damaged.log.crush")]
 pub struct DecompressArgs {
     /// Compressed files to decompress (reads from stdin if not provided with --stdout)
     #[arg(value_name = "FILE")]
@@ -165,6 +327,22 @@ pub struct DecompressArgs {
     /// Write output to stdout (for piping)
     #[arg(long, conflicts_with = "output")]
     pub stdout: bool,
+
+    /// Decrypt with the key in this file instead of a passphrase
+    #[arg(long, value_name = "FILE")]
+    pub key_file: Option<PathBuf>,
+
+    /// Keep whatever can be decoded from damaged or truncated files and
+    /// report the damaged regions instead of failing; exits with code 3 if
+    /// data was lost
+    #[arg(long)]
+    pub recover: bool,
+
+    #[command(flatten)]
+    pub batch: BatchArgs,
+
+    #[command(flatten)]
+    pub walk: RecursiveArgs,
 }
 
 /// Inspect command arguments
@@ -195,6 +373,97 @@ pub struct InspectArgs {
     pub summary: bool,
 }
 
+/// Sign command arguments
+#[derive(Args, Debug)]
+#[command(after_help = "EXAMPLES:
+    # Create a key pair: release.key (secret) and release.pub
+    crush keygen release
+
+    # Embed a signature in a compressed file
+    crush sign --key release.key app.tar.crush
+
+    # Write the signature to app.tar.crush.sig instead
+    crush sign --key release.key --detached app.tar.crush")]
+pub struct SignArgs {
+    /// Compressed files to sign
+    #[arg(required = true, value_name = "FILE")]
+    pub input: Vec<PathBuf>,
+
+    /// Ed25519 signing key file (32-byte seed, raw or as 64 hex characters)
+    #[arg(short, long, value_name = "FILE")]
+    pub key: PathBuf,
+
+    /// Write the signature to FILE.sig and leave the file untouched
+    #[arg(long)]
+    pub detached: bool,
+
+    /// Overwrite existing .sig files
+    #[arg(short, long, requires = "detached")]
+    pub force: bool,
+}
+
+/// Verify-signature command arguments
+#[derive(Args, Debug)]
+#[command(after_help = "EXAMPLES:
+    # Verify an embedded signature, or FILE.sig next to the file
+    crush verify-signature --public-key release.pub app.tar.crush
+
+    # Verify against a detached signature stored elsewhere
+    crush verify-signature --public-key release.pub --signature app.sig app.tar.crush
+
+Without --public-key the signature is only checked against the key recorded
+in it, which proves the file is intact but not who signed it.")]
+pub struct VerifySignatureArgs {
+    /// Compressed files to verify
+    #[arg(required = true, value_name = "FILE")]
+    pub input: Vec<PathBuf>,
+
+    /// Public key of the trusted signer
+    #[arg(short = 'k', long, value_name = "FILE")]
+    pub public_key: Option<PathBuf>,
+
+    /// Detached signature file (default: FILE.sig if the file has no embedded signature)
+    #[arg(short, long, value_name = "FILE")]
+    pub signature: Option<PathBuf>,
+}
+
+/// Keygen command arguments
+#[derive(Args, Debug)]
+pub struct KeygenArgs {
+    /// Path of the key pair, without extension: writes NAME.key and NAME.pub
+    #[arg(value_name = "NAME")]
+    pub name: PathBuf,
+
+    /// Overwrite existing key files
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// Repair command arguments
+#[derive(Args, Debug)]
+#[command(after_help = "EXAMPLES:
+    # Repair a damaged file in place
+    crush repair archive.tar.crush
+
+    # Write the repaired file elsewhere and leave the damaged one untouched
+    crush repair archive.tar.crush --output /restore/archive.tar.crush
+
+Only files compressed with --parity can be repaired. The repaired file is
+checked by decompressing it before anything is written.")]
+pub struct RepairArgs {
+    /// Compressed files to repair
+    #[arg(required = true, value_name = "FILE")]
+    pub input: Vec<PathBuf>,
+
+    /// Write the repaired file here instead of replacing the input (single file only)
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite an existing output file
+    #[arg(short, long, requires = "output")]
+    pub force: bool,
+}
+
 /// Config subcommand arguments
 #[derive(Args, Debug)]
 #[command(after_help = "EXAMPLES:
@@ -262,7 +531,10 @@ pub enum ConfigAction {
     crush plugins info deflate
 
     # Test a plugin's functionality
-    crush plugins test deflate")]
+    crush plugins test deflate
+
+    # Measure plugins on this machine for automatic selection
+    crush plugins calibrate")]
 pub struct PluginsArgs {
     #[command(subcommand)]
     pub action: PluginsAction,
@@ -286,6 +558,12 @@ pub enum PluginsAction {
         #[arg(value_name = "PLUGIN")]
         name: String,
     },
+    /// Benchmark plugins on this machine and cache the results for auto-selection
+    Calibrate {
+        /// Remove the calibration cache and use the built-in plugin metadata
+        #[arg(long)]
+        reset: bool,
+    },
 }
 
 /// Compression level presets
@@ -300,6 +578,15 @@ pub enum CompressionLevel {
 }
 
 impl CompressionLevel {
+    /// Name used in the config file and on the command line
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Balanced => "balanced",
+            Self::Best => "best",
+        }
+    }
+
     /// Convert to ScoringWeights for crush-core
     pub fn to_weights(self) -> crush_core::ScoringWeights {
         match self {
@@ -319,6 +606,78 @@ impl CompressionLevel {
     }
 }
 
+/// Archive subcommand arguments
+#[derive(Args, Debug)]
+#[command(after_help = "EXAMPLES:
+    # Bundle a directory and a file into one archive
+    crush archive create backup.crar docs/ notes.txt
+
+    # Use a specific plugin for every file
+    crush archive create --plugin zstd backup.crar docs/
+
+    # List the entries in an archive
+    crush archive list backup.crar
+
+    # Extract into a directory
+    crush archive extract backup.crar --output restored/
+
+Entries with absolute paths or '..' components are refused on extraction,
+as are entries that would be written through a symlink.")]
+pub struct ArchiveArgs {
+    #[command(subcommand)]
+    pub action: ArchiveAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ArchiveAction {
+    /// Create an archive from files and directories
+    Create {
+        /// Archive file to write
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// Files and directories to add (directories are added recursively)
+        #[arg(value_name = "PATH", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Compression plugin to use (default: auto-select per file)
+        #[arg(short, long, value_name = "PLUGIN")]
+        plugin: Option<String>,
+
+        /// Compression level preset (default: compression.level from config)
+        #[arg(short, long, value_name = "LEVEL")]
+        level: Option<CompressionLevel>,
+
+        /// Force overwrite of an existing archive
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Extract all entries of an archive
+    Extract {
+        /// Archive file to read
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// Directory to extract into (default: current directory)
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// Overwrite existing files
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// List the entries of an archive
+    List {
+        /// Archive file to read
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// Output format: human, json, csv
+        #[arg(short, long, value_name = "FORMAT", default_value = "human")]
+        format: OutputFormat,
+    },
+}
+
 /// Output format options
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum OutputFormat {
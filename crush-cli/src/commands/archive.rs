@@ -0,0 +1,353 @@
+//! `crush archive`: bundle files, directories and symlinks into one archive
+
+use crate::cli::{ArchiveAction, ArchiveArgs, OutputFormat};
+use crate::commands::utils;
+use crate::config::{CompressionConfig, Config};
+use crate::error::{CliError, Result};
+use crate::output;
+use crush_core::cancel::CancellationToken;
+use crush_core::plugin::FileMetadata;
+use crush_core::{
+    ArchiveReader, ArchiveWriter, CompressionOptions, DecompressionOptions, EntryKind,
+};
+use filetime::{set_file_mtime, FileTime};
+use is_terminal::IsTerminal;
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info, instrument, trace};
+
+pub fn run(
+    args: &ArchiveArgs,
+    config: &Config,
+    interrupted: Arc<dyn CancellationToken>,
+) -> Result<()> {
+    match &args.action {
+        ArchiveAction::Create {
+            archive,
+            inputs,
+            force,
+            ..
+        } => create(archive, inputs, &config.compression, *force, &interrupted),
+        ArchiveAction::Extract {
+            archive,
+            output,
+            force,
+        } => extract(archive, output.as_deref(), *force, &interrupted),
+        ArchiveAction::List { archive, format } => list(archive, *format),
+    }
+}
+
+/// Write `inputs` (recursively) into a new archive at `archive_path`
+#[instrument(skip(inputs, config, interrupted), fields(archive = %archive_path.display()))]
+fn create(
+    archive_path: &Path,
+    inputs: &[PathBuf],
+    config: &CompressionConfig,
+    force: bool,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<()> {
+    utils::check_cancelled(interrupted)?;
+    utils::validate_output(archive_path, force)?;
+
+    // Resolve entry names up front so a bad input fails before anything is written
+    let roots = inputs
+        .iter()
+        .map(|input| Ok((input.as_path(), root_name(input)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut options = CompressionOptions::default()
+        .with_weights(config.level().to_weights())
+        .with_cancel_token(Arc::clone(interrupted));
+    if let Some(plugin) = config.plugin() {
+        debug!("Using manually selected plugin: {}", plugin);
+        options = options.with_plugin(plugin);
+    }
+
+    let entries = utils::write_stream_with_cleanup(archive_path, |output| {
+        // Never add the archive to itself when it lies inside an input directory
+        let skip = fs::canonicalize(archive_path)?;
+        let mut writer = ArchiveWriter::new(output, &options)?;
+        let mut entries = 0;
+        for (input, name) in &roots {
+            entries += add_path(&mut writer, input, name, &skip, interrupted)?;
+        }
+        writer.finish()?;
+        Ok(entries)
+    })?;
+
+    let archive_size = fs::metadata(archive_path)?.len();
+    info!(entries, archive_size, "Created archive");
+    if !output::is_quiet() {
+        println!(
+            "Created {} ({} entries, {} bytes)",
+            archive_path.display(),
+            entries,
+            archive_size
+        );
+    }
+    Ok(())
+}
+
+/// Name of an input's top-level entry: its final path component
+fn root_name(input: &Path) -> Result<String> {
+    // "." and ".." have no file name of their own; use the directory's real name
+    let resolved;
+    let path = if input.file_name().is_some() {
+        input
+    } else {
+        resolved = fs::canonicalize(input)?;
+        resolved.as_path()
+    };
+
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            CliError::InvalidInput(format!(
+                "Cannot derive an archive entry name from {}",
+                input.display()
+            ))
+        })
+}
+
+/// Add `path` as entry `name`, recursing into directories
+///
+/// Symlinks are stored as links, never followed. Returns the number of
+/// entries added.
+fn add_path<W: Write>(
+    writer: &mut ArchiveWriter<W>,
+    path: &Path,
+    name: &str,
+    skip: &Path,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<u64> {
+    utils::check_cancelled(interrupted)?;
+    let metadata = fs::symlink_metadata(path)?;
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        let target = target.to_str().ok_or_else(|| {
+            CliError::InvalidInput(format!(
+                "Symlink target of {} is not valid UTF-8",
+                path.display()
+            ))
+        })?;
+        trace!("Adding symlink {} -> {}", name, target);
+        writer.add_symlink(name, target, &entry_metadata(&metadata))?;
+        return Ok(1);
+    }
+
+    if metadata.is_dir() {
+        trace!("Adding directory {}", name);
+        writer.add_directory(name, &entry_metadata(&metadata))?;
+
+        let mut children = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        children.sort();
+
+        let mut entries = 1;
+        for child in children {
+            let child_name = child.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+                CliError::InvalidInput(format!("File name is not valid UTF-8: {}", child.display()))
+            })?;
+            entries += add_path(
+                writer,
+                &child,
+                &format!("{name}/{child_name}"),
+                skip,
+                interrupted,
+            )?;
+        }
+        return Ok(entries);
+    }
+
+    if fs::canonicalize(path).is_ok_and(|p| p == skip) {
+        debug!("Skipping the archive being written: {}", path.display());
+        return Ok(0);
+    }
+
+    trace!("Adding file {}", name);
+    let data = fs::read(path)?;
+    writer.add_file(name, &data, &entry_metadata(&metadata))?;
+    Ok(1)
+}
+
+/// Modification time and permissions recorded for an entry
+fn entry_metadata(metadata: &fs::Metadata) -> FileMetadata {
+    FileMetadata {
+        mtime: Some(FileTime::from_last_modification_time(metadata).unix_seconds()),
+        #[cfg(unix)]
+        permissions: if metadata.file_type().is_symlink() {
+            None
+        } else {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode())
+        },
+    }
+}
+
+/// Extract every entry of `archive_path` below `output` (default: current directory)
+#[instrument(skip(interrupted), fields(archive = %archive_path.display()))]
+fn extract(
+    archive_path: &Path,
+    output: Option<&Path>,
+    force: bool,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<()> {
+    utils::check_cancelled(interrupted)?;
+    let dest = output.unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dest)?;
+
+    let mut reader = ArchiveReader::new(BufReader::new(File::open(archive_path)?))?;
+    let options = DecompressionOptions::default().with_cancel_token(Arc::clone(interrupted));
+
+    // Directory times are restored last, since extracting children changes them
+    let mut directories = Vec::new();
+    let mut entries = 0u64;
+
+    while let Some(entry) = reader.next_entry()? {
+        utils::check_cancelled(interrupted)?;
+        let target = prepare_target(dest, &entry.path, entry.kind, force)?;
+        trace!("Extracting {:?} {}", entry.kind, target.display());
+
+        match entry.kind {
+            EntryKind::Directory => {
+                fs::create_dir_all(&target)?;
+                directories.push((target, entry.metadata));
+            }
+            EntryKind::File => {
+                let data = reader.read_data(&options)?;
+                utils::write_stream_with_cleanup(&target, |output| Ok(output.write_all(&data)?))?;
+                restore_metadata(&target, &entry.metadata);
+            }
+            EntryKind::Symlink => {
+                let link_target = entry.link_target.unwrap_or_default();
+                create_symlink(&link_target, &target)?;
+            }
+        }
+        entries += 1;
+    }
+
+    for (path, metadata) in directories.iter().rev() {
+        restore_metadata(path, metadata);
+    }
+
+    info!(entries, "Extracted archive");
+    if !output::is_quiet() {
+        println!(
+            "Extracted {} entries from {} to {}",
+            entries,
+            archive_path.display(),
+            dest.display()
+        );
+    }
+    Ok(())
+}
+
+/// Resolve where entry `path` goes below `dest`, refusing unsafe targets
+///
+/// The core library already rejects absolute paths and `..` components; this
+/// also refuses to write through a symlink created by an earlier entry (or
+/// already present), and to replace existing files unless `force` is set.
+fn prepare_target(dest: &Path, path: &str, kind: EntryKind, force: bool) -> Result<PathBuf> {
+    let mut target = dest.to_path_buf();
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    for (i, component) in components.iter().enumerate() {
+        target.push(component);
+        let is_last = i + 1 == components.len();
+        if !is_last && fs::symlink_metadata(&target).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(CliError::InvalidInput(format!(
+                "Refusing to extract '{}' through symlink {}",
+                path,
+                target.display()
+            )));
+        }
+    }
+
+    if let Ok(existing) = fs::symlink_metadata(&target) {
+        if kind == EntryKind::Directory && existing.is_dir() {
+            return Ok(target);
+        }
+        if !force {
+            return Err(CliError::InvalidInput(format!(
+                "Output file already exists: {}. Use --force to overwrite.",
+                target.display()
+            )));
+        }
+        if existing.is_dir() {
+            return Err(CliError::InvalidInput(format!(
+                "Cannot replace directory {} with a {:?} entry",
+                target.display(),
+                kind
+            )));
+        }
+        // Remove rather than overwrite, so an existing symlink is never followed
+        fs::remove_file(&target)?;
+    }
+
+    Ok(target)
+}
+
+/// Restore modification time and permissions, warning on failure
+fn restore_metadata(path: &Path, metadata: &FileMetadata) {
+    #[cfg(unix)]
+    if let Some(mode) = metadata.permissions {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(mode)) {
+            debug!("Could not set permissions on {}: {}", path.display(), e);
+        }
+    }
+
+    if let Some(mtime) = metadata.mtime {
+        if let Err(e) = set_file_mtime(path, FileTime::from_unix_time(mtime, 0)) {
+            output::format_warning(
+                &format!(
+                    "Could not set modification time for {}: {}",
+                    path.display(),
+                    e
+                ),
+                std::io::stderr().is_terminal(),
+            );
+        }
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(link_target: &str, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(link_target, path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_symlink(link_target: &str, path: &Path) -> Result<()> {
+    output::format_warning(
+        &format!(
+            "Skipping symlink {} -> {}: not supported on this platform",
+            path.display(),
+            link_target
+        ),
+        std::io::stderr().is_terminal(),
+    );
+    Ok(())
+}
+
+/// Print the entries of `archive_path`
+fn list(archive_path: &Path, format: OutputFormat) -> Result<()> {
+    let mut reader = ArchiveReader::new(BufReader::new(File::open(archive_path)?))?;
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_entry()? {
+        entries.push(entry);
+    }
+
+    match format {
+        OutputFormat::Human => {
+            output::format_archive_list_human(&entries, std::io::stdout().is_terminal());
+        }
+        OutputFormat::Json => output::format_archive_list_json(&entries),
+        OutputFormat::Csv => output::format_archive_list_csv(&entries),
+    }
+    Ok(())
+}
@@ -1,36 +1,129 @@
 use crate::cli::CompressArgs;
-use crate::commands::utils;
+use crate::commands::keys::KeyProvider;
+use crate::commands::utils::{self, ByteCounter};
+use crate::commands::{parallel, walk};
+use crate::config::{CompressionConfig, Config};
 use crate::error::{CliError, Result};
 use crate::output::{self, CompressionResult};
 use crush_core::cancel::CancellationToken;
 use crush_core::plugin::FileMetadata;
-use crush_core::{compress_with_options, CompressionOptions};
+use crush_core::{CompressionOptions, CrushWriter, EncryptionKey};
 use filetime::FileTime;
 use indicatif::{ProgressBar, ProgressStyle};
 use is_terminal::IsTerminal;
-use std::fs;
-use std::io::{self, Read};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, info, instrument, trace};
 
-pub fn run(args: &CompressArgs, interrupted: Arc<dyn CancellationToken>) -> Result<()> {
+pub fn run(
+    args: &CompressArgs,
+    config: &Config,
+    interrupted: Arc<dyn CancellationToken>,
+) -> Result<()> {
+    // Ask for the key once, before any output is written
+    let key = if args.encrypt || args.key_file.is_some() {
+        Some(KeyProvider::new(args.key_file.clone(), true).get()?)
+    } else {
+        None
+    };
+
     // Check if reading from stdin (no input files provided)
     if args.input.is_empty() {
-        compress_stdin(args, interrupted)?;
-    } else {
-        // Process each input file
-        for input_path in &args.input {
-            compress_file(input_path, args, interrupted.clone())?;
+        if args.walk.recursive {
+            return Err(CliError::InvalidInput(
+                "--recursive needs at least one file or directory".to_string(),
+            ));
         }
+        compress_stdin(args, config, key.as_ref(), interrupted)?;
+    } else {
+        compress_files(args, config, key.as_ref(), &interrupted)?;
     }
     Ok(())
 }
 
+/// Compress the input files, `--jobs` at a time
+///
+/// With `--recursive`, directory inputs are expanded and every regular file
+/// under them is compressed in place, like `gzip -r`; files that already end
+/// in `.crush` and empty files are skipped.
+#[instrument(skip(args, config, key, interrupted))]
+fn compress_files(
+    args: &CompressArgs,
+    config: &Config,
+    key: Option<&EncryptionKey>,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<()> {
+    let walked = if args.walk.recursive {
+        walk::expand_inputs(&args.input, &args.walk.to_walk_options())?
+    } else {
+        walk::WalkResult {
+            files: args.input.clone(),
+            skipped: 0,
+        }
+    };
+
+    utils::validate_output_target(args.output.as_deref(), walked.files.len())?;
+
+    let jobs = parallel::resolve_jobs(args.batch.jobs);
+    let concurrent = jobs > 1 && walked.files.len() > 1;
+    let (mut summary, error) = parallel::process_files(
+        &walked.files,
+        jobs,
+        args.batch.keep_going,
+        interrupted,
+        |path| {
+            if args.walk.recursive {
+                if path.extension().is_some_and(|ext| ext == "crush") {
+                    debug!("Skipping already compressed {}", path.display());
+                    return Ok(None);
+                }
+                if fs::metadata(path)?.len() == 0 {
+                    debug!("Skipping empty file {}", path.display());
+                    return Ok(None);
+                }
+            }
+            compress_file(
+                path,
+                args,
+                config,
+                key,
+                Arc::clone(interrupted),
+                !concurrent,
+            )
+            .map(Some)
+        },
+    )?;
+    summary.skipped += walked.skipped;
+
+    info!(
+        files = summary.files,
+        skipped = summary.skipped,
+        failed = summary.failed,
+        jobs,
+        "Compression of {} inputs finished",
+        walked.files.len()
+    );
+    parallel::finish_batch(
+        "compress",
+        "Compressed",
+        &summary,
+        error,
+        &args.batch,
+        args.walk.recursive || concurrent,
+    )
+}
+
 /// Compress data from stdin
-#[instrument(skip(args, interrupted))]
-fn compress_stdin(args: &CompressArgs, interrupted: Arc<dyn CancellationToken>) -> Result<()> {
+#[instrument(skip(args, config, key, interrupted))]
+fn compress_stdin(
+    args: &CompressArgs,
+    config: &Config,
+    key: Option<&EncryptionKey>,
+    interrupted: Arc<dyn CancellationToken>,
+) -> Result<()> {
     info!("Compressing from stdin");
 
     // Check for cancellation before starting
@@ -45,79 +138,47 @@ fn compress_stdin(args: &CompressArgs, interrupted: Arc<dyn CancellationToken>)
         ));
     }
 
-    // Read all data from stdin
-    trace!("Reading from stdin");
-    let mut input_data = Vec::new();
-    io::stdin().read_to_end(&mut input_data)?;
-    let input_size = input_data.len() as u64;
-    debug!("Read {} bytes from stdin", input_size);
-
-    // Check for cancellation after reading
-    if interrupted.is_cancelled() {
-        return Err(CliError::Interrupted);
-    }
-
     // Prepare compression options (no file metadata for stdin)
-    let mut options = CompressionOptions::default()
-        .with_weights(args.level.to_weights())
-        .with_cancel_token(Arc::clone(&interrupted));
-
-    if let Some(ref plugin) = args.plugin {
-        debug!("Using manually selected plugin: {}", plugin);
-        options = options.with_plugin(plugin);
-    } else {
-        debug!(
-            "Using automatic plugin selection with level: {:?}",
-            args.level
-        );
-    }
-
-    if let Some(timeout_secs) = args.timeout {
-        debug!("Setting compression timeout: {} seconds", timeout_secs);
-        options = options.with_timeout(Duration::from_secs(timeout_secs));
-    }
+    let options = compression_options(args, &config.compression, key, &interrupted);
 
     // Start timing
     let start = Instant::now();
 
-    // Compress (cancellation is handled internally by compress_with_options)
-    trace!("Starting compression operation");
-    let compressed_data = compress_with_options(&input_data, &options)?;
+    // Stream stdin through the compressor in fixed-size chunks
+    trace!("Starting streaming compression from stdin");
+    let mut input = io::stdin().lock();
+    let (input_size, output_size) = match args.output {
+        Some(ref output_path) if !args.stdout => {
+            trace!("Writing compressed data to {}", output_path.display());
+            utils::validate_output(output_path, args.force)?;
+            let sizes = utils::write_stream_with_cleanup(output_path, |output| {
+                compress_stream(&mut input, output, &options, &interrupted)
+            })?;
+
+            // Check for cancellation after writing (cleanup partial file if cancelled)
+            utils::check_cancelled_with_cleanup(&interrupted, output_path)?;
+            sizes
+        }
+        _ => {
+            trace!("Writing compressed data to stdout");
+            compress_stream(&mut input, utils::stdout_writer(), &options, &interrupted)?
+        }
+    };
 
     // Stop timing
     let duration = start.elapsed();
     debug!(
-        "Compression completed in {:.3}s, output size: {} bytes",
+        "Compression completed in {:.3}s, read {} bytes, output size: {} bytes",
         duration.as_secs_f64(),
-        compressed_data.len()
+        input_size,
+        output_size
     );
 
-    // Check for interrupt before writing
-    if interrupted.is_cancelled() {
-        return Err(CliError::Interrupted);
-    }
-
-    // Write output
-    if args.stdout {
-        // Write to stdout
-        trace!("Writing compressed data to stdout");
-        utils::write_to_stdout(&compressed_data)?;
-    } else if let Some(ref output_path) = args.output {
-        // Write to file
-        trace!("Writing compressed data to {}", output_path.display());
-        utils::validate_output(output_path, args.force)?;
-        utils::write_with_cleanup(output_path, &compressed_data)?;
-
-        // Check for cancellation after writing (cleanup partial file if cancelled)
-        utils::check_cancelled_with_cleanup(&interrupted, output_path)?;
-    }
-
     // Calculate statistics
-    let output_size = compressed_data.len() as u64;
     let compression_ratio = utils::calculate_compression_ratio(input_size, output_size);
     let throughput_mbps = utils::calculate_throughput_mbps(input_size, duration);
 
-    let plugin_used = args.plugin.clone().unwrap_or_else(|| "auto".to_string());
+    let plugin_used = config.compression.plugin().unwrap_or("auto").to_string();
 
     // Log performance metrics (but don't print to stdout/stderr if using stdout mode)
     debug!(
@@ -148,12 +209,19 @@ fn compress_stdin(args: &CompressArgs, interrupted: Arc<dyn CancellationToken>)
     Ok(())
 }
 
-#[instrument(skip(args, interrupted), fields(file = %input_path.display()))]
+/// Compress one file, returning its uncompressed and compressed sizes
+///
+/// `show_progress` enables the cancel hint and spinner for large files; it is
+/// off when several files run at once and share one progress bar.
+#[instrument(skip(args, config, key, interrupted), fields(file = %input_path.display()))]
 fn compress_file(
     input_path: &Path,
     args: &CompressArgs,
+    config: &Config,
+    key: Option<&EncryptionKey>,
     interrupted: Arc<dyn CancellationToken>,
-) -> Result<()> {
+    show_progress: bool,
+) -> Result<(u64, u64)> {
     info!("Starting compression of {}", input_path.display());
     // Check for cancellation before starting
     utils::check_cancelled(&interrupted)?;
@@ -173,13 +241,14 @@ fn compress_file(
     let input_size = file_metadata.len();
 
     // Show cancel hint for large files (>1MB)
-    if !args.stdout {
+    let show_progress = show_progress && output::progress_enabled();
+    if !args.stdout && show_progress {
         crate::feedback::show_cancel_hint(crate::feedback::should_show_hint(input_size));
     }
 
     // Create progress indicator for larger files (but not when writing to stdout)
-    let show_progress = std::io::stderr().is_terminal() && !args.stdout;
-    let spinner = if show_progress && input_size > 1024 * 1024 {
+    let use_colors = std::io::stderr().is_terminal() && !args.stdout;
+    let spinner = if use_colors && show_progress && input_size > 1024 * 1024 {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -203,87 +272,53 @@ fn compress_file(
         },
     };
 
-    let mut options = CompressionOptions::default()
-        .with_weights(args.level.to_weights())
-        .with_file_metadata(file_meta)
-        .with_cancel_token(Arc::clone(&interrupted));
-
-    if let Some(ref plugin) = args.plugin {
-        debug!("Using manually selected plugin: {}", plugin);
-        options = options.with_plugin(plugin);
-    } else {
-        debug!(
-            "Using automatic plugin selection with level: {:?}",
-            args.level
-        );
-    }
-
-    if let Some(timeout_secs) = args.timeout {
-        debug!("Setting compression timeout: {} seconds", timeout_secs);
-        options = options.with_timeout(Duration::from_secs(timeout_secs));
-    }
-
-    // Read input file
-    trace!("Reading input file: {}", input_path.display());
-    let input_data = fs::read(input_path)?;
-    debug!("Read {} bytes from input file", input_data.len());
+    let options = compression_options(args, &config.compression, key, &interrupted)
+        .with_file_metadata(file_meta);
 
-    // Check for cancellation after reading
-    utils::check_cancelled(&interrupted)?;
+    // Open input file; data is streamed in fixed-size chunks, never read whole
+    trace!("Opening input file: {}", input_path.display());
+    let mut input = File::open(input_path)?;
 
     // Start timing
     let start = Instant::now();
 
     // Compress
-    trace!("Starting compression operation");
-    let compressed_data = compress_with_options(&input_data, &options)?;
+    trace!("Starting streaming compression");
+    let result = if args.stdout {
+        trace!("Writing compressed data to stdout");
+        compress_stream(&mut input, utils::stdout_writer(), &options, &interrupted)
+    } else {
+        // Write output file (T085: cleanup on failure/interrupt)
+        utils::write_stream_with_cleanup(&output_path, |output| {
+            compress_stream(&mut input, output, &options, &interrupted)
+        })
+    };
+
+    // Clear spinner
+    if let Some(pb) = spinner {
+        pb.finish_and_clear();
+    }
+    let (_, output_size) = result?;
 
     // Stop timing
     let duration = start.elapsed();
     debug!(
         "Compression completed in {:.3}s, output size: {} bytes",
         duration.as_secs_f64(),
-        compressed_data.len()
+        output_size
     );
 
-    // Clear spinner
-    if let Some(pb) = spinner {
-        pb.finish_and_clear();
-    }
-
-    // Check for interrupt before writing
-    if interrupted.is_cancelled() {
-        return Err(CliError::Interrupted);
-    }
-
-    // Write output
-    if args.stdout {
-        // Write to stdout
-        trace!("Writing compressed data to stdout");
-        utils::write_to_stdout(&compressed_data)?;
-    } else {
-        // Write output file (T085: cleanup on failure/interrupt)
-        if let Err(e) = fs::write(&output_path, &compressed_data) {
-            // If write failed, ensure no partial file remains
-            let _ = fs::remove_file(&output_path);
-            return Err(e.into());
-        }
-
-        // Check for interrupt after writing (cleanup partial file if interrupted)
-        if interrupted.is_cancelled() {
-            // Remove the output file we just wrote
-            let _ = fs::remove_file(&output_path);
-            return Err(CliError::Interrupted);
-        }
+    // Check for interrupt after writing (cleanup partial file if interrupted)
+    if !args.stdout {
+        utils::check_cancelled_with_cleanup(&interrupted, &output_path)?;
     }
 
     // Calculate statistics
-    let output_size = compressed_data.len() as u64;
     let compression_ratio = utils::calculate_compression_ratio(input_size, output_size);
     let throughput_mbps = utils::calculate_throughput_mbps(input_size, duration);
 
     // Get plugin name from options (default to "auto" if not specified)
-    let plugin_used = args.plugin.clone().unwrap_or_else(|| "auto".to_string());
+    let plugin_used = config.compression.plugin().unwrap_or("auto").to_string();
 
     // Log performance metrics with structured fields
     debug!(
@@ -330,14 +365,80 @@ fn compress_file(
             plugin_used,
         };
 
-        output::format_compression_result(&result, show_progress);
+        output::format_compression_result(&result, use_colors);
     }
 
     // NOTE: Original files are kept by default (safe behavior)
     // To delete originals after compression, users should manually delete them
     // TODO: Consider adding a --remove or --delete flag in the future if needed
 
-    Ok(())
+    Ok((input_size, output_size))
+}
+
+/// Compression options from the merged config (which already holds the
+/// `--plugin`, `--level` and `--timeout` overrides) and the remaining flags
+fn compression_options(
+    args: &CompressArgs,
+    config: &CompressionConfig,
+    key: Option<&EncryptionKey>,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> CompressionOptions {
+    let level = config.level();
+    let mut options = CompressionOptions::default()
+        .with_weights(level.to_weights())
+        .with_seekable(args.seekable)
+        .with_cancel_token(Arc::clone(interrupted));
+
+    if let Some(plugin) = config.plugin() {
+        debug!("Using manually selected plugin: {}", plugin);
+        options = options.with_plugin(plugin);
+    } else {
+        debug!("Using automatic plugin selection with level: {:?}", level);
+    }
+
+    if config.timeout_seconds > 0 {
+        debug!(
+            "Setting compression timeout: {} seconds",
+            config.timeout_seconds
+        );
+        options = options.with_timeout(Duration::from_secs(config.timeout_seconds));
+    }
+
+    for (name, value) in &args.params {
+        debug!("Setting plugin parameter: {}={}", name, value);
+        options = options.with_plugin_param(name, *value);
+    }
+
+    if let Some(algorithm) = args.hash {
+        debug!("Recording {} content hash", algorithm);
+        options = options.with_content_hash(algorithm);
+    }
+
+    if let Some(parity_shards) = args.parity {
+        debug!("Storing {} parity shards per block", parity_shards);
+        options = options.with_parity(parity_shards);
+    }
+
+    if let Some(key) = key {
+        debug!("Encrypting output with {:?}", key);
+        options = options.with_encryption(key.clone());
+    }
+    options
+}
+
+/// Stream `input` through a [`CrushWriter`] into `output` in fixed-size chunks
+///
+/// Returns the number of uncompressed bytes read and compressed bytes written.
+fn compress_stream<R: Read, W: Write>(
+    input: &mut R,
+    output: W,
+    options: &CompressionOptions,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<(u64, u64)> {
+    let mut writer = CrushWriter::new(ByteCounter::new(output), options)?;
+    let input_size = utils::copy_with_cancel(input, &mut writer, interrupted)?;
+    let output = writer.finish()?;
+    Ok((input_size, output.count()))
 }
 
 /// Determine the output file path
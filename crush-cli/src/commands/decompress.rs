@@ -1,24 +1,42 @@
 use crate::cli::DecompressArgs;
-use crate::commands::utils;
+use crate::commands::keys::{self, KeyProvider};
+use crate::commands::utils::{self, ByteCounter};
+use crate::commands::{parallel, repair, walk};
+use crate::config::Config;
 use crate::error::{CliError, Result};
-use crate::output::{self, DecompressionResult};
+use crate::output::{self, DecompressionResult, RecoveryDetails};
 use crush_core::cancel::CancellationToken;
-use crush_core::decompress;
+use crush_core::plugin::FileMetadata;
+use crush_core::{
+    CrushReader, DecompressionOptions, EncryptionKey, RecoveryReport, RecoveryResult,
+};
 use filetime::{set_file_mtime, FileTime};
 use indicatif::{ProgressBar, ProgressStyle};
 use is_terminal::IsTerminal;
-use std::fs;
-use std::io::{self, Read};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Cursor, Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::{Duration, Instant};
 use tracing::{debug, info, instrument, trace};
 
-pub fn run(args: &DecompressArgs, interrupted: Arc<dyn CancellationToken>) -> Result<()> {
+pub fn run(
+    args: &DecompressArgs,
+    config: &Config,
+    interrupted: Arc<dyn CancellationToken>,
+) -> Result<()> {
+    // Passphrases are only asked for once an encrypted file turns up
+    let keys = KeyProvider::new(args.key_file.clone(), false);
+
     // Check if reading from stdin (no input files and stdout mode)
     if args.input.is_empty() {
+        if args.walk.recursive {
+            return Err(CliError::InvalidInput(
+                "--recursive needs at least one file or directory".to_string(),
+            ));
+        }
         if args.stdout {
-            decompress_stdin(args, interrupted)?;
+            decompress_stdin(args, config, &keys, interrupted)?;
         } else {
             return Err(CliError::InvalidInput(
                 "No input files specified. Use --stdout with stdin, or provide file paths."
@@ -26,57 +44,147 @@ pub fn run(args: &DecompressArgs, interrupted: Arc<dyn CancellationToken>) -> Re
             ));
         }
     } else {
-        // Process each input file
-        for input_path in &args.input {
-            decompress_file(input_path, args, interrupted.clone())?;
-        }
+        decompress_files(args, config, &keys, &interrupted)?;
     }
     Ok(())
 }
 
+/// Decompress the input files, `--jobs` at a time
+///
+/// With `--recursive`, directory inputs are expanded and every `.crush` file
+/// under them is decompressed in place, like `gzip -dr`. Files that
+/// `--recover` only partly salvages are reported as damaged.
+#[instrument(skip(args, config, keys, interrupted))]
+fn decompress_files(
+    args: &DecompressArgs,
+    config: &Config,
+    keys: &KeyProvider,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<()> {
+    let walked = if args.walk.recursive {
+        walk::expand_inputs(&args.input, &args.walk.to_walk_options())?
+    } else {
+        walk::WalkResult {
+            files: args.input.clone(),
+            skipped: 0,
+        }
+    };
+
+    utils::validate_output_target(args.output.as_deref(), walked.files.len())?;
+
+    let jobs = parallel::resolve_jobs(args.batch.jobs);
+    let concurrent = jobs > 1 && walked.files.len() > 1;
+    let damaged = Mutex::new(Vec::new());
+    let (mut summary, error) = parallel::process_files(
+        &walked.files,
+        jobs,
+        args.batch.keep_going,
+        interrupted,
+        |path| {
+            if args.walk.recursive && path.extension().is_none_or(|ext| ext != "crush") {
+                debug!("Skipping {}: no .crush extension", path.display());
+                return Ok(None);
+            }
+            let (input_size, output_size, recovery) = decompress_file(
+                path,
+                args,
+                config,
+                keys,
+                Arc::clone(interrupted),
+                !concurrent,
+            )?;
+            if let Some(recovery) = recovery {
+                damaged
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push((path.to_path_buf(), recovery));
+            }
+            Ok(Some((input_size, output_size)))
+        },
+    )?;
+    summary.skipped += walked.skipped;
+    for (path, recovery) in damaged.into_inner().unwrap_or_else(PoisonError::into_inner) {
+        summary.record_damaged(&path, recovery);
+    }
+
+    info!(
+        files = summary.files,
+        skipped = summary.skipped,
+        failed = summary.failed,
+        damaged = summary.damaged,
+        jobs,
+        "Decompression of {} inputs finished",
+        walked.files.len()
+    );
+    parallel::finish_batch(
+        "decompress",
+        "Decompressed",
+        &summary,
+        error,
+        &args.batch,
+        args.walk.recursive || concurrent,
+    )
+}
+
 /// Decompress data from stdin
-#[instrument(skip(_args, interrupted))]
-fn decompress_stdin(_args: &DecompressArgs, interrupted: Arc<dyn CancellationToken>) -> Result<()> {
+///
+/// # Errors
+///
+/// Returns `CliError::Incomplete` if `--recover` lost data
+#[instrument(skip(args, config, keys, interrupted))]
+fn decompress_stdin(
+    args: &DecompressArgs,
+    config: &Config,
+    keys: &KeyProvider,
+    interrupted: Arc<dyn CancellationToken>,
+) -> Result<()> {
     info!("Decompressing from stdin");
 
     // Check for interrupt before starting
     utils::check_cancelled(&interrupted)?;
 
-    // Read all data from stdin
-    trace!("Reading from stdin");
-    let mut compressed_data = Vec::new();
-    io::stdin().read_to_end(&mut compressed_data)?;
-    let input_size = compressed_data.len() as u64;
-    debug!("Read {} bytes from stdin", input_size);
-
-    // Check for interrupt after reading
-    utils::check_cancelled(&interrupted)?;
-
     // Start timing
     let start = Instant::now();
 
-    // Decompress
-    trace!("Starting decompression operation");
-    let result = decompress(&compressed_data)?;
-    let decompressed_data = result.data;
+    let (input_size, output_size, intact) = if args.recover {
+        // Recovery needs the whole input to look past the damage
+        let mut input = Vec::new();
+        io::stdin().lock().read_to_end(&mut input)?;
+        let recovered = recover_data(&input, config, keys, &interrupted)?;
+        let mut output = utils::stdout_writer();
+        output.write_all(&recovered.data)?;
+        output.flush()?;
+        repair::warn_repaired(None, &recovered.report.repairs);
+        warn_damaged(None, &recovered);
+        (
+            input.len() as u64,
+            recovered.data.len() as u64,
+            recovered.report.is_intact(),
+        )
+    } else {
+        // Stream stdin through the decompressor to stdout in fixed-size chunks
+        trace!("Starting streaming decompression from stdin");
+        let mut input = ByteCounter::new(io::stdin().lock());
+        let (header, encrypted) = keys::peek_encrypted(&mut input)?;
+        let key = if encrypted { Some(keys.get()?) } else { None };
+        let mut reader = CrushReader::with_options(
+            Cursor::new(header).chain(input),
+            &decompression_options(config, key, &interrupted),
+        )?;
+        let output_size = decompress_stream(&mut reader, utils::stdout_writer(), &interrupted)?;
+        repair::warn_repaired(None, reader.repairs());
+        (reader.get_ref().get_ref().1.count(), output_size, true)
+    };
 
     // Stop timing
     let duration = start.elapsed();
     debug!(
         "Decompression completed in {:.3}s, output size: {} bytes",
         duration.as_secs_f64(),
-        decompressed_data.len()
+        output_size
     );
 
-    // Check for interrupt before writing
-    utils::check_cancelled(&interrupted)?;
-
-    // Write to stdout
-    trace!("Writing decompressed data to stdout");
-    utils::write_to_stdout(&decompressed_data)?;
-
     // Calculate statistics
-    let output_size = decompressed_data.len() as u64;
     let throughput_mbps = utils::calculate_throughput_mbps(output_size, duration);
 
     // Log performance metrics
@@ -97,15 +205,30 @@ fn decompress_stdin(_args: &DecompressArgs, interrupted: Arc<dyn CancellationTok
         throughput_mbps
     );
 
-    Ok(())
+    if intact {
+        Ok(())
+    } else {
+        Err(CliError::Incomplete {
+            damaged: 1,
+            total: 1,
+        })
+    }
 }
 
-#[instrument(skip(args, interrupted), fields(file = %input_path.display()))]
+/// Decompress one file, returning its compressed and decompressed sizes, and
+/// what was lost if `--recover` could only salvage part of it
+///
+/// `show_progress` enables the cancel hint and spinner for large files; it is
+/// off when several files run at once and share one progress bar.
+#[instrument(skip(args, config, keys, interrupted), fields(file = %input_path.display()))]
 fn decompress_file(
     input_path: &Path,
     args: &DecompressArgs,
+    config: &Config,
+    keys: &KeyProvider,
     interrupted: Arc<dyn CancellationToken>,
-) -> Result<()> {
+    show_progress: bool,
+) -> Result<(u64, u64, Option<RecoveryDetails>)> {
     info!("Starting decompression of {}", input_path.display());
     // Check for interrupt before starting
     utils::check_cancelled(&interrupted)?;
@@ -127,13 +250,14 @@ fn decompress_file(
     let input_size = fs::metadata(input_path)?.len();
 
     // Show cancel hint for large files (>1MB)
-    if !args.stdout {
+    let show_progress = show_progress && output::progress_enabled();
+    if !args.stdout && show_progress {
         crate::feedback::show_cancel_hint(crate::feedback::should_show_hint(input_size));
     }
 
     // Create progress indicator for larger files
-    let show_progress = std::io::stderr().is_terminal() && !args.stdout;
-    let spinner = if show_progress && input_size > 1024 * 1024 {
+    let use_colors = std::io::stderr().is_terminal() && !args.stdout;
+    let spinner = if use_colors && show_progress && input_size > 1024 * 1024 {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -147,47 +271,32 @@ fn decompress_file(
         None
     };
 
-    // Read compressed file
-    trace!("Reading compressed file: {}", input_path.display());
-    let compressed_data = fs::read(input_path)?;
-    debug!("Read {} bytes from compressed file", compressed_data.len());
-
-    // Check for interrupt after reading
-    utils::check_cancelled(&interrupted)?;
-
     // Start timing
     let start = Instant::now();
 
-    // Decompress
-    trace!("Starting decompression operation");
-    let result = decompress(&compressed_data)?;
-    let decompressed_data = result.data;
-    let metadata = result.metadata;
+    let target = (!args.stdout).then_some(output_path.as_path());
+    let result = if args.recover {
+        recover_file(input_path, target, config, keys, &interrupted)
+    } else {
+        stream_file(input_path, target, config, keys, &interrupted)
+    };
+
+    // Clear spinner
+    if let Some(pb) = spinner {
+        pb.finish_and_clear();
+    }
+    let (metadata, output_size, recovery) = result?;
+    let crc_valid = recovery.is_none();
 
     // Stop timing
     let duration = start.elapsed();
     debug!(
         "Decompression completed in {:.3}s, output size: {} bytes",
         duration.as_secs_f64(),
-        decompressed_data.len()
+        output_size
     );
 
-    // Clear spinner
-    if let Some(pb) = spinner {
-        pb.finish_and_clear();
-    }
-
-    // Check for interrupt before writing
-    utils::check_cancelled(&interrupted)?;
-
-    // Write output
-    if args.stdout {
-        // Write to stdout
-        utils::write_to_stdout(&decompressed_data)?;
-    } else {
-        // Write to file (with cleanup on failure/interrupt)
-        utils::write_with_cleanup(&output_path, &decompressed_data)?;
-
+    if !args.stdout {
         // Check for interrupt after writing (cleanup partial file if interrupted)
         utils::check_cancelled_with_cleanup(&interrupted, &output_path)?;
 
@@ -237,7 +346,6 @@ fn decompress_file(
         }
 
         // Calculate statistics
-        let output_size = decompressed_data.len() as u64;
         let throughput_mbps = utils::calculate_throughput_mbps(output_size, duration);
 
         // Log performance metrics with structured fields
@@ -256,7 +364,7 @@ fn decompress_file(
             output_size,
             throughput_mbps,
             duration_secs = duration.as_secs_f64(),
-            crc_valid = true,
+            crc_valid,
             "Decompressed {} -> {} in {:.3}s at {:.2} MB/s",
             input_path.display(),
             output_path.display(),
@@ -272,10 +380,11 @@ fn decompress_file(
             output_size,
             duration,
             throughput_mbps,
-            crc_valid: true, // If decompression succeeded, CRC is valid
+            // Salvaged output from --recover did not pass the checks
+            crc_valid,
         };
 
-        output::format_decompression_result(&decomp_result, show_progress);
+        output::format_decompression_result(&decomp_result, use_colors);
 
         // NOTE: Compressed files are kept by default (safe behavior)
         // The --keep flag is retained for compatibility but is now the default behavior
@@ -283,7 +392,184 @@ fn decompress_file(
         // TODO: Consider adding a --remove or --delete flag in the future if needed
     }
 
-    Ok(())
+    Ok((input_size, output_size, recovery))
+}
+
+/// Stream `input_path` through the decompressor to `output_path`, or to
+/// stdout when it is `None`
+///
+/// Returns the stored file metadata and the number of bytes written. The
+/// output always passed its integrity checks, so nothing was lost.
+fn stream_file(
+    input_path: &Path,
+    output_path: Option<&Path>,
+    config: &Config,
+    keys: &KeyProvider,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<(FileMetadata, u64, Option<RecoveryDetails>)> {
+    // Open compressed file; the header is validated before any output is created
+    trace!("Opening compressed file: {}", input_path.display());
+    let mut input = BufReader::new(File::open(input_path)?);
+    let (header, encrypted) = keys::peek_encrypted(&mut input)?;
+    let key = if encrypted {
+        debug!("{} is encrypted", input_path.display());
+        Some(keys.get()?)
+    } else {
+        None
+    };
+    let mut reader = CrushReader::with_options(
+        Cursor::new(header).chain(input),
+        &decompression_options(config, key, interrupted),
+    )?;
+    let metadata = reader.metadata().clone();
+
+    // Decompress, streaming blocks to the output as they are decoded
+    trace!("Starting streaming decompression");
+    let output_size = match output_path {
+        // Write to file (with cleanup on failure/interrupt)
+        Some(path) => utils::write_stream_with_cleanup(path, |output| {
+            decompress_stream(&mut reader, output, interrupted)
+        })?,
+        None => decompress_stream(&mut reader, utils::stdout_writer(), interrupted)?,
+    };
+    repair::warn_repaired(Some(input_path), reader.repairs());
+    Ok((metadata, output_size, None))
+}
+
+/// Salvage what can be decoded from a damaged `input_path` (`--recover`)
+///
+/// Writes like [`stream_file`], but the whole file is decoded in memory so
+/// that intact blocks after the damage can be kept. Also returns what was
+/// lost, or `None` if the file was intact.
+fn recover_file(
+    input_path: &Path,
+    output_path: Option<&Path>,
+    config: &Config,
+    keys: &KeyProvider,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<(FileMetadata, u64, Option<RecoveryDetails>)> {
+    let input = fs::read(input_path)?;
+    let recovered = recover_data(&input, config, keys, interrupted)?;
+    match output_path {
+        Some(path) => utils::write_stream_with_cleanup(path, |output| {
+            output.write_all(&recovered.data)?;
+            Ok(())
+        })?,
+        None => {
+            let mut output = utils::stdout_writer();
+            output.write_all(&recovered.data)?;
+            output.flush()?;
+        }
+    }
+    repair::warn_repaired(Some(input_path), &recovered.report.repairs);
+    warn_damaged(Some(input_path), &recovered);
+    let recovery = recovery_details(&recovered.report);
+    Ok((recovered.metadata, recovered.data.len() as u64, recovery))
+}
+
+/// What a recovery lost, for the batch report, or `None` if nothing was
+fn recovery_details(report: &RecoveryReport) -> Option<RecoveryDetails> {
+    report.error.as_ref().map(|error| RecoveryDetails {
+        error: error.clone(),
+        expected_size: report.expected_size,
+        lost_bytes: report.lost_bytes(),
+        damaged: report.damaged.clone(),
+    })
+}
+
+/// Run recovery on a compressed file held in memory, asking for a key if it
+/// is encrypted
+fn recover_data(
+    input: &[u8],
+    config: &Config,
+    keys: &KeyProvider,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<RecoveryResult> {
+    let (_, encrypted) = keys::peek_encrypted(&mut &input[..])?;
+    let key = if encrypted { Some(keys.get()?) } else { None };
+    let recovered =
+        crush_core::recover_with_options(input, &decompression_options(config, key, interrupted))?;
+    if let Some(error) = &recovered.report.error {
+        info!(
+            regions = recovered.report.damaged.len(),
+            recovered = recovered.data.len(),
+            "Recovered data from damaged input: {}",
+            error
+        );
+    }
+    Ok(recovered)
+}
+
+/// Warn about each damaged region that `--recover` skipped, then summarise
+/// how much was salvaged
+fn warn_damaged(input: Option<&Path>, recovered: &RecoveryResult) {
+    let report = &recovered.report;
+    let Some(error) = &report.error else {
+        return;
+    };
+    let name = input.map_or_else(|| "stdin".to_string(), |path| path.display().to_string());
+    let use_colors = io::stderr().is_terminal();
+    output::format_warning(&format!("{name}: {error}"), use_colors);
+
+    for region in &report.damaged {
+        let lost = region
+            .lost_bytes
+            .map_or_else(|| "an unknown number of".to_string(), |n| n.to_string());
+        let resumed = region.resumed_at.map_or_else(
+            || "nothing after it could be decoded".to_string(),
+            |at| format!("decoding resumed at offset {at}"),
+        );
+        output::format_warning(
+            &format!(
+                "{name}: damage at offset {} ({}); lost {lost} bytes of output from {}, {resumed}",
+                region.offset, region.reason, region.output_offset
+            ),
+            use_colors,
+        );
+    }
+
+    let recovered_size = recovered.data.len();
+    let summary = match report.expected_size {
+        Some(expected) => format!("{name}: recovered {recovered_size} of {expected} bytes"),
+        None => format!("{name}: recovered {recovered_size} bytes; the original size is unknown"),
+    };
+    output::format_warning(&summary, use_colors);
+}
+
+/// Decompression options that stop decoding as soon as `interrupted` fires
+///
+/// `compression.timeout_seconds` from the config bounds decompression too.
+/// `key` decrypts encrypted input.
+fn decompression_options(
+    config: &Config,
+    key: Option<EncryptionKey>,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> DecompressionOptions {
+    let mut options = DecompressionOptions::default().with_cancel_token(Arc::clone(interrupted));
+    if let Some(key) = key {
+        options = options.with_encryption(key);
+    }
+    if config.compression.timeout_seconds > 0 {
+        debug!(
+            "Setting decompression timeout: {} seconds",
+            config.compression.timeout_seconds
+        );
+        options = options.with_timeout(Duration::from_secs(config.compression.timeout_seconds));
+    }
+    options
+}
+
+/// Copy decompressed data from `reader` to `output` in fixed-size chunks
+///
+/// Returns the number of decompressed bytes written.
+fn decompress_stream<R: Read, W: Write>(
+    reader: &mut CrushReader<R>,
+    mut output: W,
+    interrupted: &Arc<dyn CancellationToken>,
+) -> Result<u64> {
+    let output_size = utils::copy_with_cancel(reader, &mut output, interrupted)?;
+    output.flush()?;
+    Ok(output_size)
 }
 
 /// Determine the output file path
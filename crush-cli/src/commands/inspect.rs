@@ -1,20 +1,33 @@
 use crate::cli::{InspectArgs, OutputFormat};
+use crate::commands::signature;
+use crate::config::Config;
 use crate::error::{CliError, Result};
-use crate::output;
-use crush_core::inspect;
+use crate::output::{self, OutputSettings};
+use crush_core::{inspect, SignatureStatus};
 use is_terminal::IsTerminal;
 use std::fs;
 use tracing::info;
 
-pub fn run(args: &InspectArgs) -> Result<()> {
+/// Inspect the input files and print their headers in `args.format`
+///
+/// `output.color` from `config` decides whether results are colored. The
+/// results are the command's output, so `output.quiet` does not hide them, and
+/// inspect shows no progress bars.
+pub fn run(args: &InspectArgs, config: &Config) -> Result<()> {
     info!(file_count = args.input.len(), format = ?args.format, "Inspecting compressed files");
-    let use_colors = std::io::stdout().is_terminal();
+    let settings = OutputSettings::from(&config.output);
+    let use_colors = settings.colors_enabled(std::io::stdout().is_terminal());
 
     let mut results = Vec::new();
     for input_path in &args.input {
         let compressed_data = fs::read(input_path).map_err(CliError::Io)?;
 
-        let result = inspect(&compressed_data)?;
+        let mut result = inspect(&compressed_data)?;
+        if result.signature.is_none() {
+            if let Some(detached) = signature::read_detached(input_path)? {
+                result.signature = Some(SignatureStatus::detached(&compressed_data, &detached));
+            }
+        }
         results.push((input_path.clone().to_path_buf(), result));
     }
 
@@ -0,0 +1,235 @@
+//! Encryption keys for `--encrypt` and `--key-file`, and signing keys
+
+use crate::error::{CliError, Result};
+use crush_core::{CrushHeader, EncryptionKey, SigningKey, VerifyingKey};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use tracing::debug;
+use zeroize::Zeroizing;
+
+/// Environment variable read for the passphrase instead of prompting
+pub const PASSPHRASE_ENV: &str = "CRUSH_PASSPHRASE";
+
+/// Key loaded on first use and shared by every file of a run
+///
+/// Decompression only needs a key once it meets an encrypted file, so the
+/// passphrase prompt is deferred until then and shown at most once, even
+/// with `--jobs`.
+pub struct KeyProvider {
+    key_file: Option<PathBuf>,
+    confirm: bool,
+    key: Mutex<Option<EncryptionKey>>,
+}
+
+impl KeyProvider {
+    /// Read the key from `key_file`, or ask for a passphrase (twice if `confirm`)
+    pub fn new(key_file: Option<PathBuf>, confirm: bool) -> Self {
+        Self {
+            key_file,
+            confirm,
+            key: Mutex::new(None),
+        }
+    }
+
+    /// The key, loading it on the first call
+    pub fn get(&self) -> Result<EncryptionKey> {
+        let mut key = self.key.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ref key) = *key {
+            return Ok(key.clone());
+        }
+        let loaded = match self.key_file {
+            Some(ref path) => read_key_file(path)?,
+            None => read_passphrase(self.confirm)?,
+        };
+        *key = Some(loaded.clone());
+        Ok(loaded)
+    }
+}
+
+/// Read a 256-bit encryption key file
+pub fn read_key_file(path: &Path) -> Result<EncryptionKey> {
+    debug!("Reading encryption key from {}", path.display());
+    Ok(EncryptionKey::from_bytes(*read_key_bytes(path)?))
+}
+
+/// Read an Ed25519 signing key file (the 32-byte secret seed)
+pub fn read_signing_key(path: &Path) -> Result<SigningKey> {
+    debug!("Reading signing key from {}", path.display());
+    Ok(SigningKey::from_bytes(&*read_key_bytes(path)?))
+}
+
+/// Read an Ed25519 public key file
+pub fn read_public_key(path: &Path) -> Result<VerifyingKey> {
+    debug!("Reading public key from {}", path.display());
+    VerifyingKey::from_bytes(&*read_key_bytes(path)?).map_err(|_| {
+        CliError::InvalidInput(format!(
+            "Key file {} does not hold an Ed25519 public key",
+            path.display()
+        ))
+    })
+}
+
+/// Write a key file as 64 hex characters, readable only by the owner
+pub fn write_key_file(path: &Path, key: &[u8; 32], force: bool) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            CliError::InvalidInput(format!(
+                "Output file already exists: {}. Use --force to overwrite.",
+                path.display()
+            ))
+        } else {
+            CliError::Io(e)
+        }
+    })?;
+    let hex = Zeroizing::new(
+        key.iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>(),
+    );
+    writeln!(file, "{}", *hex)?;
+    Ok(())
+}
+
+/// Read a 256-bit key stored as 32 raw bytes or 64 hex characters
+fn read_key_bytes(path: &Path) -> Result<Zeroizing<[u8; 32]>> {
+    let contents = Zeroizing::new(fs::read(path).map_err(|e| {
+        CliError::InvalidInput(format!("Cannot read key file {}: {}", path.display(), e))
+    })?);
+    if let Ok(key) = <[u8; 32]>::try_from(contents.as_slice()) {
+        return Ok(Zeroizing::new(key));
+    }
+
+    let invalid = || {
+        CliError::InvalidInput(format!(
+            "Key file {} must hold 32 raw bytes or 64 hex characters",
+            path.display()
+        ))
+    };
+    let text = std::str::from_utf8(&contents)
+        .map_err(|_| invalid())?
+        .trim();
+    if text.len() != 64 {
+        return Err(invalid());
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    for (byte, pair) in key.iter_mut().zip(text.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
+
+/// Passphrase from `CRUSH_PASSPHRASE`, or prompted on the terminal
+fn read_passphrase(confirm: bool) -> Result<EncryptionKey> {
+    let passphrase = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => {
+            debug!("Using passphrase from {}", PASSPHRASE_ENV);
+            Zeroizing::new(passphrase)
+        }
+        Err(_) => {
+            let passphrase = prompt("Passphrase: ")?;
+            if confirm && *prompt("Confirm passphrase: ")? != *passphrase {
+                return Err(CliError::InvalidInput(
+                    "Passphrases do not match".to_string(),
+                ));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(CliError::InvalidInput(
+            "Passphrase must not be empty".to_string(),
+        ));
+    }
+    Ok(EncryptionKey::from_passphrase(&passphrase))
+}
+
+fn prompt(message: &str) -> Result<Zeroizing<String>> {
+    rpassword::prompt_password(message)
+        .map(Zeroizing::new)
+        .map_err(|e| {
+            CliError::InvalidInput(format!(
+                "Cannot read passphrase: {e} (set {PASSPHRASE_ENV} or use --key-file)"
+            ))
+        })
+}
+
+/// Read the Crush header from `input` and report whether it is encrypted
+///
+/// Returns the bytes read, so callers can replay them in front of the rest of
+/// the input. Short or invalid headers count as unencrypted and are left for
+/// the decompressor to reject.
+pub fn peek_encrypted<R: Read>(input: &mut R) -> io::Result<(Vec<u8>, bool)> {
+    let mut header = Vec::with_capacity(CrushHeader::SIZE);
+    input
+        .by_ref()
+        .take(CrushHeader::SIZE as u64)
+        .read_to_end(&mut header)?;
+    let encrypted = <&[u8; CrushHeader::SIZE]>::try_from(header.as_slice())
+        .ok()
+        .and_then(|bytes| CrushHeader::from_bytes(bytes).ok())
+        .is_some_and(|header| header.is_encrypted());
+    Ok((header, encrypted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_read_key_file_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("raw.key");
+        fs::write(&raw, [0xab; 32]).unwrap();
+        assert!(read_key_file(&raw).is_ok());
+
+        let hex = dir.path().join("hex.key");
+        fs::write(&hex, format!("{}\n", "ab".repeat(32))).unwrap();
+        assert!(read_key_file(&hex).is_ok());
+
+        let short = dir.path().join("short.key");
+        fs::write(&short, "abcd").unwrap();
+        assert!(matches!(
+            read_key_file(&short),
+            Err(CliError::InvalidInput(msg)) if msg.contains("64 hex characters")
+        ));
+
+        let not_hex = dir.path().join("not-hex.key");
+        fs::write(&not_hex, "zz".repeat(32)).unwrap();
+        assert!(read_key_file(&not_hex).is_err());
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_signing_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::generate();
+        let secret = dir.path().join("release.key");
+        let public = dir.path().join("release.pub");
+        write_key_file(&secret, &key.to_bytes(), false).unwrap();
+        write_key_file(&public, &key.verifying_key().to_bytes(), false).unwrap();
+        assert!(write_key_file(&public, &[0; 32], false).is_err());
+
+        let read_back = read_signing_key(&secret).unwrap();
+        assert_eq!(read_back.verifying_key(), key.verifying_key());
+        assert_eq!(read_public_key(&public).unwrap(), key.verifying_key());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&secret).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+    }
+}
@@ -1,6 +1,12 @@
+pub mod archive;
 pub mod compress;
 pub mod config;
 pub mod decompress;
 pub mod inspect;
+pub mod keys;
+pub mod parallel;
 pub mod plugins;
+pub mod repair;
+pub mod signature;
 mod utils;
+pub mod walk;
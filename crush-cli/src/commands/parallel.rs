@@ -0,0 +1,316 @@
+//! Worker pool for processing several files at once (`-j/--jobs`)
+
+use crate::cli::BatchArgs;
+use crate::commands::utils;
+use crate::error::{CliError, Result};
+use crate::output::{self, BatchSummary};
+use crush_core::cancel::CancellationToken;
+use indicatif::{ProgressBar, ProgressStyle};
+use is_terminal::IsTerminal;
+use rayon::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+use tracing::{debug, warn};
+
+/// Number of workers for a `--jobs` value, where 0 means one per CPU
+#[must_use]
+pub fn resolve_jobs(jobs: usize) -> usize {
+    if jobs == 0 {
+        std::thread::available_parallelism().map_or(1, usize::from)
+    } else {
+        jobs
+    }
+}
+
+/// Run `process` on every file, `jobs` files at a time
+///
+/// `process` returns the input and output sizes of a processed file, or
+/// `None` if it skipped the file. Every file's outcome is recorded in the
+/// returned summary. Unless `keep_going` is set, the first failure stops new
+/// files from starting (files already running on other workers finish) and
+/// is returned alongside the summary. Ctrl+C stops all workers through the
+/// shared token; each worker removes its own partial output.
+///
+/// # Errors
+///
+/// Returns `CliError::Interrupted` if cancelled
+pub fn process_files<F>(
+    files: &[PathBuf],
+    jobs: usize,
+    keep_going: bool,
+    interrupted: &Arc<dyn CancellationToken>,
+    process: F,
+) -> Result<(BatchSummary, Option<CliError>)>
+where
+    F: Fn(&Path) -> Result<Option<(u64, u64)>> + Sync,
+{
+    let start = Instant::now();
+    let (mut summary, error) = if jobs <= 1 || files.len() <= 1 {
+        process_sequential(files, keep_going, interrupted, &process)?
+    } else {
+        process_parallel(files, jobs, keep_going, interrupted, &process)?
+    };
+    summary.duration = start.elapsed();
+    Ok((summary, error))
+}
+
+fn process_sequential<F>(
+    files: &[PathBuf],
+    keep_going: bool,
+    interrupted: &Arc<dyn CancellationToken>,
+    process: &F,
+) -> Result<(BatchSummary, Option<CliError>)>
+where
+    F: Fn(&Path) -> Result<Option<(u64, u64)>>,
+{
+    let mut summary = BatchSummary::default();
+    for path in files {
+        utils::check_cancelled(interrupted)?;
+        match process(path) {
+            Ok(Some((input_size, output_size))) => summary.record(path, input_size, output_size),
+            Ok(None) => summary.record_skipped(path),
+            Err(CliError::Interrupted) => return Err(CliError::Interrupted),
+            Err(e) => {
+                warn!("Failed to process {}: {}", path.display(), e);
+                summary.record_failure(path, &e);
+                if !keep_going {
+                    return Ok((summary, Some(e)));
+                }
+            }
+        }
+    }
+    Ok((summary, None))
+}
+
+fn process_parallel<F>(
+    files: &[PathBuf],
+    jobs: usize,
+    keep_going: bool,
+    interrupted: &Arc<dyn CancellationToken>,
+    process: &F,
+) -> Result<(BatchSummary, Option<CliError>)>
+where
+    F: Fn(&Path) -> Result<Option<(u64, u64)>> + Sync,
+{
+    debug!(jobs, files = files.len(), "Processing files in parallel");
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .thread_name(|i| format!("crush-job-{i}"))
+        .build()
+        .map_err(|e| CliError::Io(std::io::Error::other(e)))?;
+
+    let progress = progress_bar(files.len() as u64);
+    let stop = AtomicBool::new(false);
+    let summary = Mutex::new(BatchSummary::default());
+    let first_error = Mutex::new(None);
+
+    pool.install(|| {
+        files.par_iter().for_each(|path| {
+            if stop.load(Ordering::Relaxed) || interrupted.is_cancelled() {
+                return;
+            }
+            let result = process(path);
+            let mut summary = summary.lock().unwrap_or_else(PoisonError::into_inner);
+            match result {
+                Ok(Some((input_size, output_size))) => {
+                    summary.record(path, input_size, output_size);
+                }
+                Ok(None) => summary.record_skipped(path),
+                // Cancellation is reported once, after the pool drains
+                Err(CliError::Interrupted) => {}
+                Err(e) => {
+                    warn!("Failed to process {}: {}", path.display(), e);
+                    summary.record_failure(path, &e);
+                    if !keep_going {
+                        stop.store(true, Ordering::Relaxed);
+                        first_error
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .get_or_insert(e);
+                    }
+                }
+            }
+            progress.inc(1);
+        });
+    });
+    progress.finish_and_clear();
+
+    utils::check_cancelled(interrupted)?;
+
+    Ok((
+        summary.into_inner().unwrap_or_else(PoisonError::into_inner),
+        first_error
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner),
+    ))
+}
+
+/// Report the outcome of a batch and turn its failures into an error
+///
+/// Writes the `--report` file, prints the summary when `show_summary` is set
+/// (always with `--keep-going`), and lists failed files when there is more
+/// than one or the batch kept going. `action` is the past-tense verb for the
+/// summary line and `operation` names the batch in the report.
+///
+/// # Errors
+///
+/// Returns `error` (the failure that stopped the batch) if set, otherwise
+/// `CliError::PartialFailure` if any file failed, `CliError::Incomplete` if
+/// any file was only partly recovered, or an error if the report cannot be
+/// written
+pub fn finish_batch(
+    operation: &str,
+    action: &str,
+    summary: &BatchSummary,
+    error: Option<CliError>,
+    args: &BatchArgs,
+    show_summary: bool,
+) -> Result<()> {
+    if let Some(report) = &args.report {
+        debug!("Writing batch report to {}", report.display());
+        fs::write(
+            report,
+            output::format_batch_report_json(operation, summary) + "\n",
+        )?;
+    }
+
+    if show_summary || args.keep_going || args.report.is_some() {
+        output::format_batch_summary(action, summary, std::io::stdout().is_terminal());
+    }
+    if summary.failed > 1 || (args.keep_going && summary.failed > 0) {
+        output::format_batch_failures(summary, std::io::stderr().is_terminal());
+    }
+
+    match error {
+        Some(e) => Err(e),
+        None if summary.failed > 0 => Err(CliError::PartialFailure {
+            failed: summary.failed,
+            total: summary.results.len(),
+        }),
+        None if summary.damaged > 0 => Err(CliError::Incomplete {
+            damaged: summary.damaged,
+            total: summary.results.len(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Overall "files done" bar, hidden unless stderr is a terminal and
+/// progress output is enabled
+fn progress_bar(total: u64) -> ProgressBar {
+    if !std::io::stderr().is_terminal() || !output::progress_enabled() {
+        return ProgressBar::hidden();
+    }
+    crate::feedback::show_cancel_hint(true);
+    let bar = ProgressBar::new(total);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:30}] {pos}/{len} files")
+            .expect("Invalid progress template"),
+    );
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::FileStatus;
+    use crush_core::cancel::AtomicCancellationToken;
+    use std::sync::atomic::AtomicUsize;
+
+    fn paths(n: usize) -> Vec<PathBuf> {
+        (0..n).map(|i| PathBuf::from(format!("file{i}"))).collect()
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_process_files_parallel_summary() {
+        let token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+        let (summary, error) = process_files(&paths(20), 4, false, &token, |path| {
+            if path == Path::new("file0") {
+                Ok(None)
+            } else {
+                Ok(Some((10, 4)))
+            }
+        })
+        .unwrap();
+
+        assert!(error.is_none());
+        assert_eq!(summary.files, 19);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.input_size, 190);
+        assert_eq!(summary.output_size, 76);
+        assert_eq!(summary.results.len(), 20);
+    }
+
+    #[test]
+    fn test_process_files_cancelled() {
+        let token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+        let started = AtomicUsize::new(0);
+        let result = process_files(&paths(100), 4, true, &token, |_| {
+            started.fetch_add(1, Ordering::SeqCst);
+            token.cancel();
+            Err(CliError::Interrupted)
+        });
+
+        assert!(matches!(result, Err(CliError::Interrupted)));
+        // Workers stop picking up files once the token fires, even with keep_going
+        assert!(started.load(Ordering::SeqCst) <= 4);
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_process_files_stops_at_failure() {
+        let token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+        for jobs in [1, 2] {
+            let (summary, error) = process_files(&paths(8), jobs, false, &token, |path| {
+                if path == Path::new("file3") {
+                    Err(CliError::InvalidInput("bad file".to_string()))
+                } else {
+                    Ok(Some((1, 1)))
+                }
+            })
+            .unwrap();
+            assert!(matches!(error, Some(CliError::InvalidInput(msg)) if msg == "bad file"));
+            assert_eq!(summary.failed, 1);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_process_files_keep_going() {
+        let token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+        for jobs in [1, 3] {
+            let (summary, error) = process_files(&paths(10), jobs, true, &token, |path| {
+                if path == Path::new("file2") || path == Path::new("file7") {
+                    Err(CliError::InvalidInput("bad file".to_string()))
+                } else {
+                    Ok(Some((1, 1)))
+                }
+            })
+            .unwrap();
+
+            assert!(error.is_none());
+            assert_eq!(summary.files, 8);
+            assert_eq!(summary.failed, 2);
+            let failed: Vec<_> = summary
+                .results
+                .iter()
+                .filter(|r| r.status == FileStatus::Failed)
+                .collect();
+            assert_eq!(failed.len(), 2);
+            assert!(failed
+                .iter()
+                .all(|r| r.error.as_deref() == Some("Invalid input: bad file")));
+        }
+    }
+
+    #[test]
+    fn test_resolve_jobs() {
+        assert_eq!(resolve_jobs(3), 3);
+        assert!(resolve_jobs(0) >= 1);
+    }
+}
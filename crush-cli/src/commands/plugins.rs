@@ -1,7 +1,10 @@
 use crate::cli::{OutputFormat, PluginsAction, PluginsArgs};
+use crate::config;
 use crate::error::{CliError, Result};
 use crate::output;
-use crush_core::{compress_with_options, decompress, list_plugins, CompressionOptions};
+use crush_core::{
+    calibrate, compress_with_options, decompress, list_plugins, set_calibration, CompressionOptions,
+};
 use tracing::info;
 
 pub fn run(args: &PluginsArgs) -> Result<()> {
@@ -87,5 +90,33 @@ pub fn run(args: &PluginsArgs) -> Result<()> {
                 )))
             }
         }
+
+        PluginsAction::Calibrate { reset } => {
+            let path = config::calibration_file_path()?;
+
+            if *reset {
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+                set_calibration(None);
+                println!("Removed calibration cache {}", path.display());
+                return Ok(());
+            }
+
+            println!("Calibrating {} plugins...", list_plugins().len());
+            let calibration = calibrate()?;
+            info!(
+                plugin_count = calibration.plugins.len(),
+                path = %path.display(),
+                "Saving plugin calibration"
+            );
+            calibration.save(&path)?;
+
+            output::format_calibration(&calibration);
+            println!("\nSaved calibration to {}", path.display());
+            set_calibration(Some(calibration));
+
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,107 @@
+//! `crush repair`
+
+use crate::cli::RepairArgs;
+use crate::commands::utils::{replace_file, validate_output};
+use crate::error::{CliError, Result};
+use crate::output;
+use crush_core::RepairReport;
+use is_terminal::IsTerminal;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Repair each input from its parity records, in place or into `--output`
+pub fn run(args: &RepairArgs) -> Result<()> {
+    if args.output.is_some() && args.input.len() > 1 {
+        return Err(CliError::InvalidInput(
+            "--output can only be used with a single file".to_string(),
+        ));
+    }
+    if let Some(ref output) = args.output {
+        validate_output(output, args.force)?;
+    }
+
+    for input in &args.input {
+        let data = fs::read(input)?;
+        let (repaired, report) = crush_core::repair(&data)?;
+        // Only the blocks carry parity; make sure the rest of the file is
+        // intact before anything is written
+        crush_core::decompress(&repaired)?;
+        info!(
+            file = %input.display(),
+            blocks = report.blocks.len(),
+            bytes = report.repaired_bytes(),
+            "Repaired file"
+        );
+
+        match args.output {
+            Some(ref output) => fs::write(output, &repaired)?,
+            None if !report.is_empty() => replace_file(input, &repaired, ".repairing")?,
+            None => {}
+        }
+        if !output::is_quiet() {
+            println!("{}: {}", input.display(), describe(&report));
+        }
+    }
+    Ok(())
+}
+
+/// Warn that `input` (stdin if `None`) decompressed only after repairs
+pub fn warn_repaired(input: Option<&Path>, report: &RepairReport) {
+    if report.is_empty() {
+        return;
+    }
+    info!(
+        blocks = report.blocks.len(),
+        bytes = report.repaired_bytes(),
+        "Repaired damaged blocks from parity"
+    );
+    let message = match input {
+        Some(path) => format!(
+            "{}: {} from parity; run 'crush repair {}' to fix the file",
+            path.display(),
+            describe(report),
+            path.display()
+        ),
+        None => format!("stdin: {} from parity", describe(report)),
+    };
+    output::format_warning(&message, std::io::stderr().is_terminal());
+}
+
+/// One-line summary of a repair, e.g. "repaired 3 damaged bytes in 2 blocks"
+fn describe(report: &RepairReport) -> String {
+    if report.is_empty() {
+        return "no damage found".to_string();
+    }
+    let blocks = report.blocks.len();
+    format!(
+        "repaired {} damaged bytes in {} {}",
+        report.repaired_bytes(),
+        blocks,
+        if blocks == 1 { "block" } else { "blocks" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crush_core::BlockRepair;
+
+    #[test]
+    fn test_describe() {
+        let mut report = RepairReport::default();
+        assert_eq!(describe(&report), "no damage found");
+
+        report.blocks.push(BlockRepair {
+            block: 4,
+            repaired_bytes: 2,
+        });
+        assert_eq!(describe(&report), "repaired 2 damaged bytes in 1 block");
+
+        report.blocks.push(BlockRepair {
+            block: 9,
+            repaired_bytes: 1,
+        });
+        assert_eq!(describe(&report), "repaired 3 damaged bytes in 2 blocks");
+    }
+}
@@ -0,0 +1,142 @@
+//! `crush sign`, `crush verify-signature` and `crush keygen`
+
+use crate::cli::{KeygenArgs, SignArgs, VerifySignatureArgs};
+use crate::commands::keys;
+use crate::commands::utils::{replace_file, validate_output, with_suffix};
+use crate::error::{CliError, Result};
+use crate::output;
+use crush_core::{
+    sign_detached, verify_detached, verify_signature, CrushError, Signature, SignatureError,
+    SigningKey, VerifyingKey,
+};
+use is_terminal::IsTerminal;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Sign each input, in place or into a detached `.sig` file
+pub fn sign(args: &SignArgs) -> Result<()> {
+    let key = keys::read_signing_key(&args.key)?;
+    info!(file_count = args.input.len(), detached = args.detached, key = %key.verifying_key(), "Signing files");
+
+    for input in &args.input {
+        let data = fs::read(input)?;
+        if args.detached {
+            let signature_path = detached_path(input);
+            validate_output(&signature_path, args.force)?;
+            let signature = sign_detached(&data, &key)?;
+            fs::write(&signature_path, signature.to_bytes())?;
+            if !output::is_quiet() {
+                println!("Signed {} -> {}", input.display(), signature_path.display());
+            }
+        } else {
+            replace_file(input, &crush_core::sign(&data, &key)?, ".signing")?;
+            if !output::is_quiet() {
+                println!("Signed {}", input.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Verify each input's embedded or detached signature
+pub fn verify(args: &VerifySignatureArgs) -> Result<()> {
+    if args.signature.is_some() && args.input.len() > 1 {
+        return Err(CliError::InvalidInput(
+            "--signature can only be used with a single file".to_string(),
+        ));
+    }
+    let trusted = args
+        .public_key
+        .as_deref()
+        .map(keys::read_public_key)
+        .transpose()?;
+    info!(
+        file_count = args.input.len(),
+        trusted = trusted.is_some(),
+        "Verifying signatures"
+    );
+
+    for input in &args.input {
+        let data = fs::read(input)?;
+        let signer = verify_file(input, &data, args.signature.as_deref(), trusted.as_ref())?;
+        if !output::is_quiet() {
+            println!("{}: signature OK (ed25519 key {})", input.display(), signer);
+        }
+    }
+
+    if trusted.is_none() {
+        output::format_warning(
+            "signer keys were not checked against a trusted key; pass --public-key to prove who signed",
+            std::io::stderr().is_terminal(),
+        );
+    }
+    Ok(())
+}
+
+/// Generate a key pair as `NAME.key` and `NAME.pub`
+pub fn keygen(args: &KeygenArgs) -> Result<()> {
+    let secret_path = with_suffix(&args.name, ".key");
+    let public_path = with_suffix(&args.name, ".pub");
+    validate_output(&secret_path, args.force)?;
+    validate_output(&public_path, args.force)?;
+
+    let key = SigningKey::generate();
+    keys::write_key_file(&secret_path, &key.to_bytes(), args.force)?;
+    fs::write(&public_path, format!("{}\n", key.verifying_key()))?;
+    info!(public_key = %key.verifying_key(), "Generated signing key");
+
+    if !output::is_quiet() {
+        println!(
+            "Wrote {} (keep secret) and {}",
+            secret_path.display(),
+            public_path.display()
+        );
+        println!("Public key: {}", key.verifying_key());
+    }
+    Ok(())
+}
+
+/// Verify the signature of one file, returning the signer's key
+///
+/// An explicit `signature_path` wins; otherwise the embedded signature is
+/// checked, falling back to `FILE.sig` if there is none.
+fn verify_file(
+    input: &Path,
+    data: &[u8],
+    signature_path: Option<&Path>,
+    trusted: Option<&VerifyingKey>,
+) -> Result<VerifyingKey> {
+    if let Some(path) = signature_path {
+        return Ok(verify_detached(data, &read_signature(path)?, trusted)?);
+    }
+    match verify_signature(data, trusted) {
+        Err(CrushError::Signature(SignatureError::NotSigned)) => {
+            let path = detached_path(input);
+            if !path.exists() {
+                return Err(CliError::Core(SignatureError::NotSigned.into()));
+            }
+            Ok(verify_detached(data, &read_signature(&path)?, trusted)?)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Detached signature next to `input`, for inspection
+///
+/// Returns `None` if there is no `FILE.sig`.
+pub fn read_detached(input: &Path) -> Result<Option<Signature>> {
+    let path = detached_path(input);
+    if !path.exists() {
+        return Ok(None);
+    }
+    read_signature(&path).map(Some)
+}
+
+fn read_signature(path: &Path) -> Result<Signature> {
+    Ok(Signature::from_bytes(&fs::read(path)?)?)
+}
+
+fn detached_path(input: &Path) -> PathBuf {
+    with_suffix(input, ".sig")
+}
@@ -2,9 +2,10 @@
 
 use crate::error::{CliError, Result};
 use crush_core::cancel::CancellationToken;
-use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -52,6 +53,13 @@ pub fn validate_input(path: &Path) -> Result<()> {
         )));
     }
 
+    if path.is_dir() {
+        return Err(CliError::InvalidInput(format!(
+            "Input path is a directory: {}. Use --recursive to process its files.",
+            path.display()
+        )));
+    }
+
     // Check if it's a file (not a directory)
     if !path.is_file() {
         return Err(CliError::InvalidInput(format!(
@@ -111,6 +119,26 @@ pub fn validate_output(path: &Path, force: bool) -> Result<()> {
     Ok(())
 }
 
+/// Validate `--output` against the number of input files
+///
+/// Several inputs can only share an output directory; a single output file
+/// would be written by every input, concurrently with `--jobs`.
+///
+/// # Errors
+///
+/// Returns an error if there is more than one input and `output` is not an
+/// existing directory
+pub fn validate_output_target(output: Option<&Path>, inputs: usize) -> Result<()> {
+    match output {
+        Some(output) if inputs > 1 && !output.is_dir() => Err(CliError::InvalidInput(format!(
+            "--output {} must be an existing directory when there are {} input files",
+            output.display(),
+            inputs
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// Calculate throughput in MB/s
 #[must_use]
 pub fn calculate_throughput_mbps(size_bytes: u64, duration: Duration) -> f64 {
@@ -131,31 +159,132 @@ pub fn calculate_compression_ratio(input_size: u64, output_size: u64) -> f64 {
     }
 }
 
-/// Write data to stdout and flush
+/// Size of the chunks read from input files and stdin when streaming
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reader/writer adapter that counts the bytes passing through it
+pub struct ByteCounter<T> {
+    inner: T,
+    count: u64,
+}
+
+impl<T> ByteCounter<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Number of bytes read or written so far
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R: Read> Read for ByteCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for ByteCounter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Copy `reader` to `writer` in fixed-size chunks, checking for cancellation
+/// between chunks
+///
+/// Memory use is bounded by [`STREAM_CHUNK_SIZE`] regardless of input size.
 ///
 /// # Errors
 ///
-/// Returns an error if writing or flushing fails
-pub fn write_to_stdout(data: &[u8]) -> Result<()> {
-    io::stdout().write_all(data)?;
-    io::stdout().flush()?;
-    Ok(())
+/// Returns `CliError::Interrupted` if cancellation was requested, or an error
+/// if reading or writing fails
+pub fn copy_with_cancel<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    token: &Arc<dyn CancellationToken>,
+) -> Result<u64> {
+    let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+    let mut total = 0u64;
+    loop {
+        check_cancelled(token)?;
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted && !token.is_cancelled() => continue,
+            Err(e) => return Err(e.into()),
+        };
+        writer.write_all(&buffer[..n])?;
+        total += n as u64;
+    }
+}
+
+/// Buffered stdout handle for streaming binary output
+pub fn stdout_writer() -> BufWriter<io::StdoutLock<'static>> {
+    BufWriter::with_capacity(STREAM_CHUNK_SIZE, io::stdout().lock())
 }
 
-/// Write data to file with automatic cleanup on error
+/// Stream output into a new file with automatic cleanup on error
 ///
-/// If the write fails, attempts to remove the partial file before returning the error.
+/// Creates `path`, passes a buffered writer to `write`, and flushes it. If
+/// any step fails (including cancellation), the partial file is removed
+/// before returning the error.
 ///
 /// # Errors
 ///
-/// Returns an error if the write operation fails
-pub fn write_with_cleanup(path: &Path, data: &[u8]) -> Result<()> {
-    if let Err(e) = fs::write(path, data) {
+/// Returns an error if creating the file, the `write` callback, or the final
+/// flush fails
+pub fn write_stream_with_cleanup<T>(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<T>,
+) -> Result<T> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::with_capacity(STREAM_CHUNK_SIZE, file);
+    let result = write(&mut writer).and_then(|value| {
+        writer.flush()?;
+        Ok(value)
+    });
+    drop(writer);
+
+    if result.is_err() {
         let _ = fs::remove_file(path);
-        Err(e.into())
-    } else {
-        Ok(())
     }
+    result
+}
+
+/// `path` with `suffix` appended to its file name (`a.crush` -> `a.crush.sig`)
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Replace `path` with `contents` through a temporary file named with
+/// `temp_suffix`, so an interrupted write never leaves a truncated file behind
+///
+/// # Errors
+///
+/// Returns an error if writing, copying permissions or renaming fails; the
+/// temporary file is removed and `path` is left untouched
+pub fn replace_file(path: &Path, contents: &[u8], temp_suffix: &str) -> Result<()> {
+    let temp = with_suffix(path, temp_suffix);
+    let result = fs::write(&temp, contents)
+        .and_then(|()| fs::set_permissions(&temp, fs::metadata(path)?.permissions()))
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(result?)
 }
 
 #[cfg(test)]
@@ -213,15 +342,51 @@ mod tests {
 
     #[test]
     #[allow(clippy::unwrap_used)]
-    fn test_write_with_cleanup() {
+    fn test_write_stream_with_cleanup() {
         let temp = NamedTempFile::new().unwrap();
         let path = temp.path();
 
         // Successful write
-        assert!(write_with_cleanup(path, b"test data").is_ok());
+        let written = write_stream_with_cleanup(path, |writer| {
+            writer.write_all(b"test data")?;
+            Ok(9)
+        })
+        .unwrap();
+        assert_eq!(written, 9);
 
         // Verify data was written
         let content = fs::read(path).unwrap();
         assert_eq!(content, b"test data");
     }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_write_stream_with_cleanup_removes_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial.out");
+
+        let result: Result<()> = write_stream_with_cleanup(&path, |writer| {
+            writer.write_all(b"partial")?;
+            Err(CliError::Interrupted)
+        });
+
+        assert!(matches!(result, Err(CliError::Interrupted)));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_copy_with_cancel() {
+        let token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+        let input = vec![7u8; 3 * STREAM_CHUNK_SIZE + 17];
+        let mut output = ByteCounter::new(Vec::new());
+
+        let copied = copy_with_cancel(&mut input.as_slice(), &mut output, &token).unwrap();
+        assert_eq!(copied, input.len() as u64);
+        assert_eq!(output.count(), input.len() as u64);
+
+        token.cancel();
+        let result = copy_with_cancel(&mut input.as_slice(), &mut Vec::new(), &token);
+        assert!(matches!(result, Err(CliError::Interrupted)));
+    }
 }
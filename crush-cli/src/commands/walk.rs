@@ -0,0 +1,312 @@
+//! Expansion of directory inputs for `--recursive`
+
+use crate::error::{CliError, Result};
+use crate::output;
+use is_terminal::IsTerminal;
+use std::path::{Path, PathBuf};
+use tracing::{debug, trace};
+use walkdir::WalkDir;
+
+/// Shell-style glob used by `--include` and `--exclude`
+///
+/// `*` matches any run of characters except `/`, `**` also crosses `/`, `?`
+/// matches one character and `[abc]`, `[a-z]` or `[!abc]` match a class.
+/// A pattern without `/` is matched against the file name; a pattern with `/`
+/// is matched against the path relative to the directory being walked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobPattern {
+    pattern: Vec<char>,
+    match_path: bool,
+}
+
+impl GlobPattern {
+    /// Parse a glob, rejecting unterminated character classes
+    pub fn parse(pattern: &str) -> std::result::Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("glob pattern must not be empty".to_string());
+        }
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '[' && class_end(&chars, i).is_none() {
+                return Err(format!("unterminated '[' in glob '{pattern}'"));
+            }
+            i += 1;
+        }
+        Ok(Self {
+            match_path: pattern.contains('/'),
+            pattern: chars,
+        })
+    }
+
+    /// Whether the entry at `relative` (a `/`-separated path) matches
+    #[must_use]
+    pub fn matches(&self, relative: &str) -> bool {
+        let subject = if self.match_path {
+            relative
+        } else {
+            relative.rsplit('/').next().unwrap_or(relative)
+        };
+        let subject: Vec<char> = subject.chars().collect();
+        glob_match(&self.pattern, &subject)
+    }
+}
+
+/// Index of the `]` closing the class that opens at `start`
+fn class_end(pattern: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if pattern.get(i) == Some(&'!') {
+        i += 1;
+    }
+    // A leading ']' is a literal member of the class
+    if pattern.get(i) == Some(&']') {
+        i += 1;
+    }
+    pattern[i..]
+        .iter()
+        .position(|&c| c == ']')
+        .map(|offset| i + offset)
+}
+
+/// Whether `c` is a member of the class `pattern[start..=end]`
+fn class_matches(pattern: &[char], start: usize, end: usize, c: char) -> bool {
+    let mut i = start + 1;
+    let negated = pattern[i] == '!';
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    while i < end {
+        if i + 2 < end && pattern[i + 1] == '-' {
+            found |= (pattern[i]..=pattern[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= pattern[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+fn glob_match(pattern: &[char], subject: &[char]) -> bool {
+    match pattern.first() {
+        None => subject.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            // "**/" also matches zero directories
+            let rest = &pattern[2..];
+            if rest.first() == Some(&'/') && glob_match(&rest[1..], subject) {
+                return true;
+            }
+            (0..=subject.len()).any(|skip| glob_match(rest, &subject[skip..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for skip in 0..=subject.len() {
+                if glob_match(rest, &subject[skip..]) {
+                    return true;
+                }
+                if subject.get(skip) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => {
+            matches!(subject.first(), Some(&c) if c != '/')
+                && glob_match(&pattern[1..], &subject[1..])
+        }
+        Some('[') => {
+            let Some(end) = class_end(pattern, 0) else {
+                return false;
+            };
+            matches!(subject.first(), Some(&c) if c != '/' && class_matches(pattern, 0, end, c))
+                && glob_match(&pattern[end + 1..], &subject[1..])
+        }
+        Some(&literal) => {
+            subject.first() == Some(&literal) && glob_match(&pattern[1..], &subject[1..])
+        }
+    }
+}
+
+/// How directory inputs are walked
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Only files matching at least one of these are kept (all if empty)
+    pub include: Vec<GlobPattern>,
+    /// Files and directories matching any of these are skipped
+    pub exclude: Vec<GlobPattern>,
+    /// Follow symlinks instead of skipping them
+    pub follow_symlinks: bool,
+}
+
+/// Files found by [`expand_inputs`]
+#[derive(Debug, Default)]
+pub struct WalkResult {
+    /// Regular files to process, in walk order
+    pub files: Vec<PathBuf>,
+    /// Entries left out by filters or the symlink policy
+    pub skipped: usize,
+}
+
+/// Expand `inputs` into the regular files to process
+///
+/// File inputs are kept as given; directory inputs are walked recursively in
+/// sorted order, applying the include/exclude filters and symlink policy.
+///
+/// # Errors
+///
+/// Returns an error if an input does not exist or a directory cannot be read
+pub fn expand_inputs(inputs: &[PathBuf], options: &WalkOptions) -> Result<WalkResult> {
+    let mut result = WalkResult::default();
+    for input in inputs {
+        if input.is_dir() {
+            walk_directory(input, options, &mut result)?;
+        } else {
+            result.files.push(input.clone());
+        }
+    }
+    debug!(
+        files = result.files.len(),
+        skipped = result.skipped,
+        "Expanded recursive inputs"
+    );
+    Ok(result)
+}
+
+fn walk_directory(root: &Path, options: &WalkOptions, result: &mut WalkResult) -> Result<()> {
+    let excluded = |path: &Path| {
+        let relative = relative_path(root, path);
+        options.exclude.iter().any(|glob| glob.matches(&relative))
+    };
+
+    let mut pruned = 0;
+    let walker = WalkDir::new(root)
+        .follow_links(options.follow_symlinks)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            // Prune excluded directories instead of walking into them
+            let prune = entry.depth() > 0 && entry.file_type().is_dir() && excluded(entry.path());
+            if prune {
+                pruned += 1;
+            }
+            !prune
+        });
+
+    let mut skipped = 0;
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.loop_ancestor().is_some() => {
+                output::format_warning(
+                    &format!("Skipping symlink loop: {e}"),
+                    std::io::stderr().is_terminal(),
+                );
+                skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                return Err(CliError::InvalidInput(format!(
+                    "Cannot walk {}: {}",
+                    root.display(),
+                    e
+                )))
+            }
+        };
+
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            continue;
+        }
+        if file_type.is_symlink() || !file_type.is_file() {
+            // Unfollowed symlinks, sockets, FIFOs and devices are never processed
+            trace!("Skipping non-regular file {}", entry.path().display());
+            skipped += 1;
+            continue;
+        }
+
+        let relative = relative_path(root, entry.path());
+        let included = options.include.is_empty()
+            || options.include.iter().any(|glob| glob.matches(&relative));
+        if !included || excluded(entry.path()) {
+            trace!("Filtered out {}", entry.path().display());
+            skipped += 1;
+            continue;
+        }
+        result.files.push(entry.into_path());
+    }
+
+    result.skipped += skipped + pruned;
+    Ok(())
+}
+
+/// `path` relative to `root`, with `/` separators on every platform
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn glob(pattern: &str) -> GlobPattern {
+        GlobPattern::parse(pattern).expect("valid glob")
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(glob("*.log").matches("app.log"));
+        assert!(glob("*.log").matches("nested/dir/app.log"));
+        assert!(!glob("*.log").matches("app.log.1"));
+        assert!(glob("app.?").matches("app.1"));
+        assert!(glob("[a-c]*.txt").matches("b.txt"));
+        assert!(!glob("[!a-c]*.txt").matches("b.txt"));
+        assert!(glob("logs/*.log").matches("logs/x.log"));
+        assert!(!glob("logs/*.log").matches("logs/old/x.log"));
+        assert!(glob("logs/**/*.log").matches("logs/x.log"));
+        assert!(glob("logs/**/*.log").matches("logs/old/x.log"));
+        assert!(glob("**/*.log").matches("a/b/c.log"));
+    }
+
+    #[test]
+    fn test_glob_parse_errors() {
+        assert!(GlobPattern::parse("").is_err());
+        assert!(GlobPattern::parse("[abc").is_err());
+        assert!(GlobPattern::parse("[]]").is_ok());
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_expand_inputs_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/cache")).unwrap();
+        fs::write(root.join("a/one.log"), b"1").unwrap();
+        fs::write(root.join("a/two.txt"), b"2").unwrap();
+        fs::write(root.join("a/cache/three.log"), b"3").unwrap();
+        fs::write(root.join("four.log"), b"4").unwrap();
+
+        let all = expand_inputs(&[root.to_path_buf()], &WalkOptions::default()).unwrap();
+        assert_eq!(all.files.len(), 4);
+        assert_eq!(all.skipped, 0);
+
+        let options = WalkOptions {
+            include: vec![glob("*.log")],
+            exclude: vec![glob("cache")],
+            follow_symlinks: false,
+        };
+        let logs = expand_inputs(&[root.to_path_buf()], &options).unwrap();
+        assert_eq!(
+            logs.files,
+            vec![root.join("a/one.log"), root.join("four.log")]
+        );
+        // two.txt is not included and the cache directory is pruned
+        assert_eq!(logs.skipped, 2);
+    }
+}
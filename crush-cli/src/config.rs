@@ -1,5 +1,6 @@
-use crate::cli::{Cli, LogFormat};
+use crate::cli::{ArchiveAction, ArchiveArgs, Cli, Commands, CompressionLevel, LogFormat};
 use crate::error::{CliError, Result};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -71,6 +72,21 @@ pub struct CompressionConfig {
     pub timeout_seconds: u64,
 }
 
+impl CompressionConfig {
+    /// Plugin to force, or `None` for automatic selection
+    pub fn plugin(&self) -> Option<&str> {
+        match self.default_plugin.as_str() {
+            "" | "auto" => None,
+            plugin => Some(plugin),
+        }
+    }
+
+    /// Level preset; `validate` rejects unknown names, which fall back to balanced
+    pub fn level(&self) -> CompressionLevel {
+        CompressionLevel::from_str(&self.level, true).unwrap_or(CompressionLevel::Balanced)
+    }
+}
+
 impl Default for CompressionConfig {
     fn default() -> Self {
         Self {
@@ -222,10 +238,38 @@ pub fn merge_cli_args(mut config: Config, args: &Cli) -> Result<Config> {
     }
 
     // Log format
-    config.logging.format = match args.log_format {
-        LogFormat::Human => "human".to_string(),
-        LogFormat::Json => "json".to_string(),
-    };
+    if let Some(log_format) = args.log_format {
+        config.logging.format = match log_format {
+            LogFormat::Human => "human".to_string(),
+            LogFormat::Json => "json".to_string(),
+        };
+    }
+
+    // Compression settings given on the command line
+    match &args.command {
+        Commands::Compress(compress) => {
+            if let Some(ref plugin) = compress.plugin {
+                config.compression.default_plugin.clone_from(plugin);
+            }
+            if let Some(level) = compress.level {
+                config.compression.level = level.as_str().to_string();
+            }
+            if let Some(timeout) = compress.timeout {
+                config.compression.timeout_seconds = timeout;
+            }
+        }
+        Commands::Archive(ArchiveArgs {
+            action: ArchiveAction::Create { plugin, level, .. },
+        }) => {
+            if let Some(plugin) = plugin {
+                config.compression.default_plugin.clone_from(plugin);
+            }
+            if let Some(level) = level {
+                config.compression.level = level.as_str().to_string();
+            }
+        }
+        _ => {}
+    }
 
     // Log file
     if let Some(ref log_file) = args.log_file {
@@ -277,6 +321,43 @@ pub fn load_config() -> Result<Config> {
         .map_err(|e| CliError::Config(format!("Invalid config file format: {}", e)))
 }
 
+/// Get the plugin calibration cache path for the current OS
+///
+/// Set `CRUSH_CALIBRATION_FILE` to use a custom path (e.g. in tests).
+pub fn calibration_file_path() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("CRUSH_CALIBRATION_FILE") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let cache_dir = dirs::cache_dir()
+        .ok_or_else(|| CliError::Config("Could not determine cache directory".to_string()))?;
+
+    Ok(cache_dir.join("crush").join("calibration.json"))
+}
+
+/// Activate the plugin calibration cache written by `crush plugins calibrate`
+///
+/// A missing cache is not an error; an unreadable one is reported and
+/// ignored, so plugin scoring falls back to the built-in metadata.
+pub fn load_calibration() {
+    let Ok(path) = calibration_file_path() else {
+        return;
+    };
+    if !path.exists() {
+        return;
+    }
+
+    match crush_core::Calibration::load(&path) {
+        Ok(calibration) => {
+            tracing::debug!("Using plugin calibration from {}", path.display());
+            crush_core::set_calibration(Some(calibration));
+        }
+        Err(e) => {
+            tracing::warn!("Ignoring calibration cache {}: {}", path.display(), e);
+        }
+    }
+}
+
 /// Save configuration to file
 pub fn save_config(config: &Config) -> Result<()> {
     let path = config_file_path()?;
@@ -665,4 +746,48 @@ mod tests {
         assert_eq!(default_human(), "human");
         assert_eq!(default_info(), "info");
     }
+
+    #[test]
+    fn test_merge_cli_args_only_overrides_given_flags() {
+        use clap::Parser;
+
+        let file_config = Config {
+            compression: CompressionConfig {
+                default_plugin: "deflate".to_string(),
+                level: "best".to_string(),
+                timeout_seconds: 30,
+            },
+            logging: LoggingConfig {
+                format: "json".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let cli = Cli::parse_from(["crush", "compress", "input.txt"]);
+        let config = merge_cli_args(file_config.clone(), &cli).unwrap();
+        assert_eq!(config.compression.plugin(), Some("deflate"));
+        assert!(matches!(config.compression.level(), CompressionLevel::Best));
+        assert_eq!(config.compression.timeout_seconds, 30);
+        assert_eq!(config.logging.format, "json");
+
+        let cli = Cli::parse_from([
+            "crush",
+            "--log-format",
+            "human",
+            "compress",
+            "-p",
+            "auto",
+            "--level",
+            "fast",
+            "--timeout",
+            "5",
+            "input.txt",
+        ]);
+        let config = merge_cli_args(file_config, &cli).unwrap();
+        assert_eq!(config.compression.plugin(), None);
+        assert!(matches!(config.compression.level(), CompressionLevel::Fast));
+        assert_eq!(config.compression.timeout_seconds, 5);
+        assert_eq!(config.logging.format, "human");
+    }
 }
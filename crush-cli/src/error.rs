@@ -14,6 +14,10 @@ pub enum CliError {
     InvalidInput(String),
     /// Operation was interrupted (Ctrl+C)
     Interrupted,
+    /// Some files of a `--keep-going` batch failed
+    PartialFailure { failed: usize, total: usize },
+    /// `--recover` lost data from some inputs
+    Incomplete { damaged: usize, total: usize },
 }
 
 impl fmt::Display for CliError {
@@ -24,6 +28,16 @@ impl fmt::Display for CliError {
             CliError::Io(e) => write!(f, "I/O error: {}", e),
             CliError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
             CliError::Interrupted => write!(f, "Operation cancelled"),
+            CliError::PartialFailure { failed, total } => {
+                write!(f, "{} of {} files failed", failed, total)
+            }
+            CliError::Incomplete { damaged, total } => {
+                write!(
+                    f,
+                    "{} of {} inputs were only partly recovered",
+                    damaged, total
+                )
+            }
         }
     }
 }
@@ -50,7 +64,12 @@ impl From<crush_core::CrushError> for CliError {
 
 impl From<io::Error> for CliError {
     fn from(e: io::Error) -> Self {
-        CliError::Io(e)
+        // Streaming readers and writers surface core errors through io::Error;
+        // unwrap them so they get the same messages and exit codes
+        match e.downcast::<crush_core::CrushError>() {
+            Ok(core) => core.into(),
+            Err(e) => CliError::Io(e),
+        }
     }
 }
 
@@ -72,12 +91,54 @@ fn user_friendly_message(error: &crush_core::CrushError) -> String {
                 expected, actual
             )
         }
+        crush_core::CrushError::Validation(crush_core::ValidationError::HashMismatch {
+            algorithm,
+            expected,
+            actual,
+        }) => {
+            format!(
+                "File corrupted: {} content hash mismatch (expected {}, got {}). The decompressed data does not match the original.",
+                algorithm, expected, actual
+            )
+        }
+        crush_core::CrushError::Validation(crush_core::ValidationError::CorruptedData(msg)) => {
+            format!(
+                "File corrupted: {}. The compressed file may be damaged.",
+                msg
+            )
+        }
+        crush_core::CrushError::Validation(crush_core::ValidationError::Unrepairable {
+            block,
+            damaged_shards,
+            parity_shards,
+        }) => {
+            format!(
+                "File damaged beyond repair: block {} has {} damaged shards but only {} parity shards.",
+                block, damaged_shards, parity_shards
+            )
+        }
+        crush_core::CrushError::Validation(crush_core::ValidationError::NoParity) => {
+            "File has no parity data: only files compressed with --parity can be repaired"
+                .to_string()
+        }
         crush_core::CrushError::Validation(crush_core::ValidationError::InvalidHeader(_)) => {
             "Not a valid Crush archive: invalid file header".to_string()
         }
         crush_core::CrushError::Validation(crush_core::ValidationError::InvalidMagic(_)) => {
             "Not a valid Crush archive: invalid magic number".to_string()
         }
+        crush_core::CrushError::Encryption(crush_core::EncryptionError::WrongKey) => {
+            "Wrong passphrase or key: the file could not be decrypted".to_string()
+        }
+        crush_core::CrushError::Encryption(crush_core::EncryptionError::KeyRequired) => {
+            "File is encrypted: a passphrase or --key-file is required".to_string()
+        }
+        crush_core::CrushError::Signature(crush_core::SignatureError::NotSigned) => {
+            "File is not signed: no embedded signature and no .sig file".to_string()
+        }
+        crush_core::CrushError::Signature(crush_core::SignatureError::Invalid) => {
+            "Signature verification failed: the file or its signature has been modified".to_string()
+        }
         crush_core::CrushError::Timeout(crush_core::TimeoutError::Timeout(duration)) => {
             format!("Compression timeout after {}s", duration.as_secs())
         }
@@ -92,11 +153,13 @@ fn user_friendly_message(error: &crush_core::CrushError) -> String {
 impl CliError {
     pub fn exit_code(&self) -> i32 {
         match self {
-            CliError::Core(_) => 1,         // Operational error
-            CliError::Config(_) => 2,       // Configuration error
-            CliError::Io(_) => 1,           // Operational error
-            CliError::InvalidInput(_) => 2, // Usage error
-            CliError::Interrupted => 130,   // 128 + SIGINT (2)
+            CliError::Core(_) => 1,               // Operational error
+            CliError::Config(_) => 2,             // Configuration error
+            CliError::Io(_) => 1,                 // Operational error
+            CliError::InvalidInput(_) => 2,       // Usage error
+            CliError::Interrupted => 130,         // 128 + SIGINT (2)
+            CliError::PartialFailure { .. } => 3, // Batch finished with failures
+            CliError::Incomplete { .. } => 3,     // Recovered output is missing data
         }
     }
 }
@@ -123,6 +186,21 @@ mod tests {
 
         let interrupted = CliError::Interrupted;
         assert_eq!(interrupted.to_string(), "Operation cancelled");
+
+        let partial = CliError::PartialFailure {
+            failed: 2,
+            total: 5,
+        };
+        assert_eq!(partial.to_string(), "2 of 5 files failed");
+
+        let incomplete = CliError::Incomplete {
+            damaged: 1,
+            total: 1,
+        };
+        assert_eq!(
+            incomplete.to_string(),
+            "1 of 1 inputs were only partly recovered"
+        );
     }
 
     #[test]
@@ -141,6 +219,22 @@ mod tests {
         );
         assert_eq!(CliError::InvalidInput("test".to_string()).exit_code(), 2);
         assert_eq!(CliError::Interrupted.exit_code(), 130);
+        assert_eq!(
+            CliError::PartialFailure {
+                failed: 1,
+                total: 3
+            }
+            .exit_code(),
+            3
+        );
+        assert_eq!(
+            CliError::Incomplete {
+                damaged: 1,
+                total: 2
+            }
+            .exit_code(),
+            3
+        );
     }
 
     #[test]
@@ -157,6 +251,16 @@ mod tests {
         assert!(matches!(cli_err, CliError::Io(_)));
     }
 
+    #[test]
+    fn test_from_io_error_wrapping_core_error() {
+        let core_err: CrushError = ValidationError::CorruptedData("test".to_string()).into();
+        let cli_err: CliError = io::Error::other(core_err).into();
+        assert!(matches!(cli_err, CliError::Core(_)));
+
+        let cancelled = io::Error::new(io::ErrorKind::Interrupted, CrushError::Cancelled);
+        assert!(matches!(CliError::from(cancelled), CliError::Interrupted));
+    }
+
     #[test]
     fn test_error_source() {
         let core_err: CrushError = PluginError::NotFound("test".to_string()).into();
@@ -197,6 +301,31 @@ mod tests {
         assert!(msg.contains("87654321"));
     }
 
+    #[test]
+    fn test_user_friendly_hash_mismatch() {
+        let err = CrushError::Validation(ValidationError::HashMismatch {
+            algorithm: "sha256",
+            expected: "aa".repeat(32),
+            actual: "bb".repeat(32),
+        });
+        let msg = CliError::Core(err).to_string();
+        assert!(msg.contains("corrupted"));
+        assert!(msg.contains("sha256 content hash mismatch"));
+        assert!(msg.contains(&"bb".repeat(32)));
+    }
+
+    #[test]
+    fn test_user_friendly_unrepairable() {
+        let err = CrushError::Validation(ValidationError::Unrepairable {
+            block: 3,
+            damaged_shards: 5,
+            parity_shards: 4,
+        });
+        let msg = CliError::Core(err).to_string();
+        assert!(msg.contains("damaged beyond repair"));
+        assert!(msg.contains("block 3 has 5 damaged shards but only 4 parity shards"));
+    }
+
     #[test]
     fn test_user_friendly_invalid_header() {
         let err = CrushError::Validation(ValidationError::InvalidHeader("bad header".to_string()));
@@ -232,12 +361,21 @@ mod tests {
         assert!(msg.contains("Plugin panicked"));
     }
 
+    #[test]
+    fn test_user_friendly_corrupted_data() {
+        let err = CrushError::Validation(ValidationError::CorruptedData("Truncated stream".into()));
+        let cli_err = CliError::Core(err);
+        let msg = cli_err.to_string();
+        assert!(msg.contains("corrupted"));
+        assert!(msg.contains("Truncated stream"));
+    }
+
     #[test]
     fn test_user_friendly_other_errors() {
         // Test that other error types fall through to default formatting
-        let err = CrushError::Validation(ValidationError::CorruptedData("test".to_string()));
+        let err = CrushError::Validation(ValidationError::InvalidWeights("test".to_string()));
         let cli_err = CliError::Core(err);
         let msg = cli_err.to_string();
-        assert!(msg.contains("Corrupted") || msg.contains("test"));
+        assert!(msg.contains("Invalid scoring weights") || msg.contains("test"));
     }
 }
@@ -17,7 +17,9 @@ pub fn verbose_to_level(verbose: u8) -> &'static str {
 }
 
 /// Initialize logging with the given level and format
-pub fn init_logging(level: &str, format: &str, log_file: Option<&Path>) {
+///
+/// `ansi` colors human-readable logs written to stderr.
+pub fn init_logging(level: &str, format: &str, log_file: Option<&Path>, ansi: bool) {
     // Parse log level
     let level = match level {
         "error" => Level::ERROR,
@@ -60,6 +62,7 @@ pub fn init_logging(level: &str, format: &str, log_file: Option<&Path>) {
         _ => {
             fmt()
                 .with_env_filter(env_filter)
+                .with_ansi(ansi)
                 .with_writer(std::io::stderr)
                 .init();
         }
@@ -10,6 +10,7 @@ mod signal;
 use clap::Parser;
 use cli::{Cli, Commands};
 use error::Result;
+use is_terminal::IsTerminal;
 
 fn main() {
     let exit_code = match run() {
@@ -35,6 +36,9 @@ fn run() -> Result<()> {
     config = config::merge_cli_args(config, &cli)?;
     config.validate()?;
 
+    // Apply progress, color and quiet settings to all command output
+    output::init(&config.output);
+
     // Initialize logging with config
     // If verbose flag is set, it overrides config log level
     let log_level = if cli.verbose > 0 {
@@ -48,7 +52,16 @@ fn run() -> Result<()> {
     } else {
         None
     };
-    logging::init_logging(log_level, &config.logging.format, log_file_path);
+    logging::init_logging(
+        log_level,
+        &config.logging.format,
+        log_file_path,
+        output::colors_enabled(std::io::stderr().is_terminal()),
+    );
+    tracing::debug!(output = ?config.output, "Output settings");
+
+    // Score plugins with this machine's measurements, if calibrated
+    config::load_calibration();
 
     // Setup signal handler
     let interrupted = signal::setup_handler()
@@ -56,11 +69,16 @@ fn run() -> Result<()> {
 
     // Dispatch to appropriate command
     match &cli.command {
-        Commands::Compress(args) => commands::compress::run(args, interrupted),
-        Commands::Decompress(args) => commands::decompress::run(args, interrupted),
-        Commands::Inspect(args) => commands::inspect::run(args),
+        Commands::Compress(args) => commands::compress::run(args, &config, interrupted),
+        Commands::Decompress(args) => commands::decompress::run(args, &config, interrupted),
+        Commands::Inspect(args) => commands::inspect::run(args, &config),
         Commands::Config(args) => commands::config::run(args),
         Commands::Plugins(args) => commands::plugins::run(args),
+        Commands::Archive(args) => commands::archive::run(args, &config, interrupted),
+        Commands::Sign(args) => commands::signature::sign(args),
+        Commands::VerifySignature(args) => commands::signature::verify(args),
+        Commands::Keygen(args) => commands::signature::keygen(args),
+        Commands::Repair(args) => commands::repair::run(args),
     }
 }
 
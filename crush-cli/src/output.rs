@@ -1,16 +1,118 @@
-use crush_core::InspectResult;
+use crate::config::OutputConfig;
+use crush_core::{DamagedRegion, InspectResult};
+use serde::Serialize;
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use std::time::Duration;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
+/// When to color output, from `output.color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Color when the stream is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Output behaviour from the merged `[output]` config section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputSettings {
+    pub color: ColorMode,
+    pub progress_bars: bool,
+    pub quiet: bool,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            color: ColorMode::Auto,
+            progress_bars: true,
+            quiet: false,
+        }
+    }
+}
+
+impl From<&OutputConfig> for OutputSettings {
+    fn from(config: &OutputConfig) -> Self {
+        Self {
+            color: match config.color.as_str() {
+                "always" => ColorMode::Always,
+                "never" => ColorMode::Never,
+                _ => ColorMode::Auto,
+            },
+            progress_bars: config.progress_bars,
+            quiet: config.quiet,
+        }
+    }
+}
+
+impl OutputSettings {
+    /// Whether to color a stream under `color`, given whether it is a terminal
+    pub fn colors_enabled(&self, is_terminal: bool) -> bool {
+        match self.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => is_terminal,
+        }
+    }
+}
+
+static SETTINGS: OnceLock<OutputSettings> = OnceLock::new();
+
+/// Apply the merged `[output]` config to everything printed from here on
+///
+/// Called once at startup; later calls are ignored.
+pub fn init(config: &OutputConfig) {
+    let _ = SETTINGS.set(OutputSettings::from(config));
+}
+
+fn settings() -> OutputSettings {
+    SETTINGS.get().copied().unwrap_or_default()
+}
+
+/// Whether status output (results, summaries, warnings, hints) is suppressed
+pub fn is_quiet() -> bool {
+    settings().quiet
+}
+
+/// Whether spinners, progress bars and the cancel hint may be shown
+pub fn progress_enabled() -> bool {
+    let settings = settings();
+    settings.progress_bars && !settings.quiet
+}
+
+/// Whether to color a stream under `output.color`, given whether it is a terminal
+pub fn colors_enabled(is_terminal: bool) -> bool {
+    settings().colors_enabled(is_terminal)
+}
+
+/// Resolve `output.color`, where `use_colors` is the caller's `auto` decision
+fn color_choice(use_colors: bool, enabled: ColorChoice) -> ColorChoice {
+    match settings().color {
+        ColorMode::Always => ColorChoice::Always,
+        ColorMode::Never => ColorChoice::Never,
+        ColorMode::Auto if use_colors => enabled,
+        ColorMode::Auto => ColorChoice::Never,
+    }
+}
+
+fn stdout_stream(use_colors: bool) -> StandardStream {
+    StandardStream::stdout(color_choice(use_colors, ColorChoice::Auto))
+}
+
+fn stderr_stream(use_colors: bool) -> StandardStream {
+    StandardStream::stderr(color_choice(use_colors, ColorChoice::Always))
+}
+
 /// Format and print a warning message
 pub fn format_warning(message: &str, use_colors: bool) {
-    let mut stderr = if use_colors {
-        StandardStream::stderr(ColorChoice::Always)
-    } else {
-        StandardStream::stderr(ColorChoice::Never)
-    };
+    if is_quiet() {
+        return;
+    }
+    let mut stderr = stderr_stream(use_colors);
 
     let mut color_spec = ColorSpec::new();
     color_spec.set_fg(Some(Color::Yellow));
@@ -21,11 +123,7 @@ pub fn format_warning(message: &str, use_colors: bool) {
 }
 
 pub fn format_inspect_result(path: &std::path::Path, result: &InspectResult, use_colors: bool) {
-    let mut stdout = if use_colors {
-        StandardStream::stdout(ColorChoice::Auto)
-    } else {
-        StandardStream::stdout(ColorChoice::Never)
-    };
+    let mut stdout = stdout_stream(use_colors);
 
     let _ = writeln!(&mut stdout, "File: {}", path.display());
 
@@ -69,20 +167,88 @@ pub fn format_inspect_result(path: &std::path::Path, result: &InspectResult, use
     let _ = stdout.reset();
     let _ = writeln!(&mut stdout, "{}", result.plugin_name);
 
-    let crc_status_color = if result.crc_valid {
-        Color::Green
+    if !result.plugin_params.is_empty() {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+        let _ = write!(&mut stdout, "  Parameters: ");
+        let _ = stdout.reset();
+        let _ = writeln!(&mut stdout, "{}", result.plugin_params);
+    }
+
+    if let Some(ref encryption) = result.encryption {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+        let _ = write!(&mut stdout, "  Encryption: ");
+        let _ = stdout.reset();
+        let _ = writeln!(&mut stdout, "{} ({})", encryption.cipher, encryption.kdf);
+    }
+
+    let (crc_status, crc_status_color) = if result.encryption.is_some() {
+        ("NOT CHECKED (encrypted)", Color::Yellow)
+    } else if result.crc_valid {
+        ("VALID", Color::Green)
     } else {
-        Color::Red
+        ("INVALID", Color::Red)
     };
     let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
     let _ = write!(&mut stdout, "  CRC32: ");
     let _ = stdout.set_color(ColorSpec::new().set_fg(Some(crc_status_color)));
+    let _ = writeln!(&mut stdout, "{}", crc_status);
+    let _ = stdout.reset();
+
+    if let Some(ref content_hash) = result.content_hash {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+        let _ = write!(&mut stdout, "  Content hash: ");
+        let _ = stdout.reset();
+        let _ = writeln!(&mut stdout, "{}", content_hash);
+    }
+
+    if let Some(ref signature) = result.signature {
+        let (status, color) = if signature.valid {
+            ("VALID", Color::Green)
+        } else {
+            ("INVALID", Color::Red)
+        };
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+        let _ = write!(&mut stdout, "  Signature: ");
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(color)));
+        let _ = write!(&mut stdout, "{}", status);
+        let _ = stdout.reset();
+        let _ = writeln!(
+            &mut stdout,
+            " ({} key {}, {})",
+            signature.algorithm,
+            signature.public_key,
+            if signature.detached {
+                "detached"
+            } else {
+                "embedded"
+            }
+        );
+    }
+
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+    let _ = write!(&mut stdout, "  Blocks: ");
+    let _ = stdout.reset();
+    let _ = writeln!(&mut stdout, "{}", result.block_count);
+
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+    let _ = write!(&mut stdout, "  Seekable: ");
+    let _ = stdout.reset();
     let _ = writeln!(
         &mut stdout,
         "{}",
-        if result.crc_valid { "VALID" } else { "INVALID" }
+        if result.seekable { "yes" } else { "no" }
     );
-    let _ = stdout.reset();
+
+    if let Some(parity_shards) = result.parity_shards {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+        let _ = write!(&mut stdout, "  Parity: ");
+        let _ = stdout.reset();
+        let _ = writeln!(
+            &mut stdout,
+            "{} shards per block (repairs up to {} damaged bytes each)",
+            parity_shards, parity_shards
+        );
+    }
 
     if let Some(mtime) = result.metadata.mtime {
         let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
@@ -93,11 +259,7 @@ pub fn format_inspect_result(path: &std::path::Path, result: &InspectResult, use
 }
 
 pub fn format_inspect_summary(results: &[(std::path::PathBuf, InspectResult)], use_colors: bool) {
-    let mut stdout = if use_colors {
-        StandardStream::stdout(ColorChoice::Auto)
-    } else {
-        StandardStream::stdout(ColorChoice::Never)
-    };
+    let mut stdout = stdout_stream(use_colors);
 
     let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
     let _ = writeln!(&mut stdout, "\n--- Summary ---");
@@ -226,13 +388,25 @@ pub struct DecompressionResult {
     pub crc_valid: bool,
 }
 
+/// Format and print a failure for one file of a batch
+pub fn format_file_error(path: &std::path::Path, error: &str, use_colors: bool) {
+    let stderr = stderr_stream(use_colors);
+    let mut stderr = stderr.lock();
+
+    let _ = stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
+    let _ = write!(&mut stderr, "Failed ");
+    let _ = stderr.reset();
+    let _ = writeln!(&mut stderr, "{}: {}", path.display(), error);
+}
+
 /// Format and display compression results
 pub fn format_compression_result(result: &CompressionResult, use_colors: bool) {
-    let mut stdout = if use_colors {
-        StandardStream::stdout(ColorChoice::Auto)
-    } else {
-        StandardStream::stdout(ColorChoice::Never)
-    };
+    if is_quiet() {
+        return;
+    }
+    let stdout = stdout_stream(use_colors);
+    // Hold the lock so lines from parallel workers don't interleave
+    let mut stdout = stdout.lock();
 
     let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)));
     let _ = write!(&mut stdout, "Compressed ");
@@ -266,11 +440,12 @@ pub fn format_compression_result(result: &CompressionResult, use_colors: bool) {
 
 /// Format and display decompression results
 pub fn format_decompression_result(result: &DecompressionResult, use_colors: bool) {
-    let mut stdout = if use_colors {
-        StandardStream::stdout(ColorChoice::Auto)
-    } else {
-        StandardStream::stdout(ColorChoice::Never)
-    };
+    if is_quiet() {
+        return;
+    }
+    let stdout = stdout_stream(use_colors);
+    // Hold the lock so lines from parallel workers don't interleave
+    let mut stdout = stdout.lock();
 
     let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)));
     let _ = write!(&mut stdout, "Decompressed ");
@@ -312,6 +487,22 @@ pub fn format_plugin_list_human(plugins: &[crush_core::plugin::PluginMetadata])
     println!("\nTotal plugins: {}", plugins.len());
 }
 
+/// Format and print measured plugin performance from `crush plugins calibrate`
+pub fn format_calibration(calibration: &crush_core::Calibration) {
+    println!();
+    println!("{:<15} {:<18} Compression", "Name", "Throughput");
+    println!("{}", "-".repeat(45));
+
+    for plugin in &calibration.plugins {
+        println!(
+            "{:<15} {:<13.1} MB/s {:.1}%",
+            plugin.name,
+            plugin.throughput,
+            plugin.compression_ratio * 100.0
+        );
+    }
+}
+
 /// Format and print plugin list in JSON format
 pub fn format_plugin_list_json(
     plugins: &[crush_core::plugin::PluginMetadata],
@@ -366,7 +557,284 @@ pub fn format_plugin_info(plugin: &crush_core::plugin::PluginMetadata) {
         "  Compression Ratio: {:.1}%",
         plugin.compression_ratio * 100.0
     );
+    if let Some(calibration) = crush_core::active_calibration() {
+        if let Some(measured) = calibration.get(plugin.name) {
+            println!("  Measured Throughput: {:.1} MB/s", measured.throughput);
+            println!(
+                "  Measured Compression Ratio: {:.1}%",
+                measured.compression_ratio * 100.0
+            );
+        }
+    }
     println!();
     println!("Description:");
     println!("  {}", plugin.description);
+
+    let params = crush_core::plugin_parameters(plugin.name).unwrap_or_default();
+    if !params.is_empty() {
+        println!();
+        println!("Parameters (set with --param KEY=VALUE):");
+        for param in params {
+            println!(
+                "  {} ({}..={}, default {}): {}",
+                param.name, param.min, param.max, param.default, param.description
+            );
+        }
+    }
+}
+
+/// Short label for an archive entry kind
+fn entry_kind_label(kind: crush_core::EntryKind) -> &'static str {
+    match kind {
+        crush_core::EntryKind::File => "file",
+        crush_core::EntryKind::Directory => "dir",
+        crush_core::EntryKind::Symlink => "link",
+    }
+}
+
+/// Format and print archive entries as a table
+pub fn format_archive_list_human(entries: &[crush_core::ArchiveEntry], use_colors: bool) {
+    let mut stdout = stdout_stream(use_colors);
+
+    let _ = stdout.set_color(ColorSpec::new().set_bold(true));
+    let _ = writeln!(
+        &mut stdout,
+        "{:<5} {:>12} {:>12} {:<10} Path",
+        "Type", "Size", "Compressed", "Plugin"
+    );
+    let _ = stdout.reset();
+
+    let mut total_size = 0;
+    let mut total_compressed = 0;
+    for entry in entries {
+        total_size += entry.size;
+        total_compressed += entry.compressed_size;
+
+        let path = match entry.link_target {
+            Some(ref target) => format!("{} -> {}", entry.path, target),
+            None => entry.path.clone(),
+        };
+        let _ = writeln!(
+            &mut stdout,
+            "{:<5} {:>12} {:>12} {:<10} {}",
+            entry_kind_label(entry.kind),
+            entry.size,
+            entry.compressed_size,
+            entry.plugin.as_deref().unwrap_or("-"),
+            path
+        );
+    }
+
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)));
+    let _ = writeln!(
+        &mut stdout,
+        "{} entries, {} bytes ({} compressed)",
+        entries.len(),
+        total_size,
+        total_compressed
+    );
+    let _ = stdout.reset();
+}
+
+/// Format and print archive entries as a JSON array
+pub fn format_archive_list_json(entries: &[crush_core::ArchiveEntry]) {
+    let serialized =
+        serde_json::to_string_pretty(entries).expect("Failed to serialize archive entries to JSON");
+    println!("{}", serialized);
+}
+
+/// Format and print archive entries as CSV
+pub fn format_archive_list_csv(entries: &[crush_core::ArchiveEntry]) {
+    println!("path,type,size,compressed_size,plugin,link_target");
+    for entry in entries {
+        println!(
+            "{},{},{},{},{},{}",
+            entry.path,
+            entry_kind_label(entry.kind),
+            entry.size,
+            entry.compressed_size,
+            entry.plugin.as_deref().unwrap_or(""),
+            entry.link_target.as_deref().unwrap_or("")
+        );
+    }
+}
+
+/// Outcome of one file in a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Ok,
+    Skipped,
+    Failed,
+    /// Decompressed with `--recover`, but some data was lost
+    Damaged,
+}
+
+/// Per-file line of a batch report
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<RecoveryDetails>,
+}
+
+/// What `--recover` could not salvage from a damaged file
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryDetails {
+    /// Error that plain decompression failed with
+    pub error: String,
+    /// Original size declared by the file, if it survived
+    pub expected_size: Option<u64>,
+    /// Uncompressed bytes lost, if known for every damaged region
+    pub lost_bytes: Option<u64>,
+    pub damaged: Vec<DamagedRegion>,
+}
+
+/// Totals and per-file results for a batch of files
+#[derive(Debug, Clone, Default)]
+pub struct BatchSummary {
+    /// Files processed successfully
+    pub files: usize,
+    /// Files left out by filters, the symlink policy or their extension
+    pub skipped: usize,
+    /// Files that failed
+    pub failed: usize,
+    /// Files that `--recover` only partly salvaged
+    pub damaged: usize,
+    pub input_size: u64,
+    pub output_size: u64,
+    pub duration: Duration,
+    /// One entry per file handed to the worker, in completion order
+    pub results: Vec<FileReport>,
+}
+
+impl BatchSummary {
+    /// Add one processed file's sizes to the totals
+    pub fn record(&mut self, path: &std::path::Path, input_size: u64, output_size: u64) {
+        self.files += 1;
+        self.input_size += input_size;
+        self.output_size += output_size;
+        self.results.push(FileReport {
+            path: path.to_path_buf(),
+            status: FileStatus::Ok,
+            input_size: Some(input_size),
+            output_size: Some(output_size),
+            error: None,
+            recovery: None,
+        });
+    }
+
+    /// Mark a processed file as only partly recovered, with what was lost
+    pub fn record_damaged(&mut self, path: &std::path::Path, recovery: RecoveryDetails) {
+        if let Some(result) = self
+            .results
+            .iter_mut()
+            .find(|result| result.path == path && result.status == FileStatus::Ok)
+        {
+            self.files -= 1;
+            self.damaged += 1;
+            result.status = FileStatus::Damaged;
+            result.recovery = Some(recovery);
+        }
+    }
+
+    /// Count a file the worker decided not to process
+    pub fn record_skipped(&mut self, path: &std::path::Path) {
+        self.skipped += 1;
+        self.results.push(FileReport {
+            path: path.to_path_buf(),
+            status: FileStatus::Skipped,
+            input_size: None,
+            output_size: None,
+            error: None,
+            recovery: None,
+        });
+    }
+
+    /// Count a file that failed with `error`
+    pub fn record_failure(&mut self, path: &std::path::Path, error: &crate::error::CliError) {
+        self.failed += 1;
+        self.results.push(FileReport {
+            path: path.to_path_buf(),
+            status: FileStatus::Failed,
+            input_size: None,
+            output_size: None,
+            error: Some(error.to_string()),
+            recovery: None,
+        });
+    }
+}
+
+/// Format and display the totals of a batch, then any failed files
+///
+/// `action` is the past-tense verb, e.g. "Compressed".
+pub fn format_batch_summary(action: &str, summary: &BatchSummary, use_colors: bool) {
+    if is_quiet() {
+        return;
+    }
+    let stdout = stdout_stream(use_colors);
+    let mut stdout = stdout.lock();
+
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)));
+    let _ = write!(&mut stdout, "{} ", action);
+    let _ = stdout.reset();
+    let _ = write!(
+        &mut stdout,
+        "{} file{}, skipped {}",
+        summary.files,
+        if summary.files == 1 { "" } else { "s" },
+        summary.skipped
+    );
+    if summary.failed > 0 {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)));
+        let _ = write!(&mut stdout, ", failed {}", summary.failed);
+        let _ = stdout.reset();
+    }
+    if summary.damaged > 0 {
+        let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
+        let _ = write!(&mut stdout, ", damaged {}", summary.damaged);
+        let _ = stdout.reset();
+    }
+    let _ = write!(
+        &mut stdout,
+        ": {} -> {} bytes ",
+        summary.input_size, summary.output_size
+    );
+
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)));
+    let _ = write!(&mut stdout, "in {:.2}s", summary.duration.as_secs_f64());
+    let _ = stdout.reset();
+    let _ = writeln!(&mut stdout);
+}
+
+/// Print every failed file of a batch to stderr
+pub fn format_batch_failures(summary: &BatchSummary, use_colors: bool) {
+    for result in &summary.results {
+        if let Some(error) = &result.error {
+            format_file_error(&result.path, error, use_colors);
+        }
+    }
+}
+
+/// JSON report of a batch, for `--report`
+pub fn format_batch_report_json(operation: &str, summary: &BatchSummary) -> String {
+    let report = serde_json::json!({
+        "operation": operation,
+        "succeeded": summary.files,
+        "skipped": summary.skipped,
+        "failed": summary.failed,
+        "damaged": summary.damaged,
+        "input_size": summary.input_size,
+        "output_size": summary.output_size,
+        "duration_secs": summary.duration.as_secs_f64(),
+        "files": summary.results,
+    });
+    serde_json::to_string_pretty(&report).expect("Failed to serialize batch report to JSON")
 }
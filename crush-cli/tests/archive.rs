@@ -0,0 +1,232 @@
+mod common;
+
+use common::*;
+use crush_core::plugin::FileMetadata;
+use crush_core::{ArchiveWriter, CompressionOptions};
+use predicates::prelude::*;
+use std::fs;
+
+/// Create a small tree: tree/a.txt, tree/sub/b.bin, tree/sub/empty/
+fn create_tree(dir: &std::path::Path) -> std::path::PathBuf {
+    let root = dir.join("tree");
+    fs::create_dir_all(root.join("sub").join("empty")).unwrap();
+    fs::write(root.join("a.txt"), b"archive me ".repeat(500)).unwrap();
+    fs::write(
+        root.join("sub").join("b.bin"),
+        (0..=255u8).collect::<Vec<_>>(),
+    )
+    .unwrap();
+    root
+}
+
+/// Create, list and extract an archive of a directory tree
+#[test]
+fn test_archive_create_list_extract() {
+    let dir = test_dir();
+    let root = create_tree(dir.path());
+    let notes = create_test_file(dir.path(), "notes.txt", b"loose file");
+    let archive = dir.path().join("backup.crar");
+
+    crush_cmd()
+        .args(["archive", "create"])
+        .arg(&archive)
+        .arg(&root)
+        .arg(&notes)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("6 entries"));
+
+    crush_cmd()
+        .args(["archive", "list"])
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tree/sub/b.bin"))
+        .stdout(predicate::str::contains("tree/sub/empty"))
+        .stdout(predicate::str::contains("notes.txt"));
+
+    let out = dir.path().join("restored");
+    crush_cmd()
+        .args(["archive", "extract"])
+        .arg(&archive)
+        .arg("--output")
+        .arg(&out)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Extracted 6 entries"));
+
+    assert_files_equal(&root.join("a.txt"), &out.join("tree/a.txt"));
+    assert_files_equal(&root.join("sub/b.bin"), &out.join("tree/sub/b.bin"));
+    assert_files_equal(&notes, &out.join("notes.txt"));
+    assert!(out.join("tree/sub/empty").is_dir());
+
+    // Existing files are only replaced with --force
+    crush_cmd()
+        .args(["archive", "extract"])
+        .arg(&archive)
+        .arg("--output")
+        .arg(&out)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+    crush_cmd()
+        .args(["archive", "extract", "--force"])
+        .arg(&archive)
+        .arg("--output")
+        .arg(&out)
+        .assert()
+        .success();
+}
+
+/// JSON listing reports kinds, sizes and the plugin used for each file
+#[test]
+fn test_archive_list_json() {
+    let dir = test_dir();
+    let root = create_tree(dir.path());
+    let archive = dir.path().join("backup.crar");
+
+    crush_cmd()
+        .args(["archive", "create", "--plugin", "deflate"])
+        .arg(&archive)
+        .arg(&root)
+        .assert()
+        .success();
+
+    let output = crush_cmd()
+        .args(["archive", "list", "--format", "json"])
+        .arg(&archive)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 5);
+
+    let a = entries
+        .iter()
+        .find(|e| e["path"] == "tree/a.txt")
+        .expect("tree/a.txt listed");
+    assert_eq!(a["kind"], "file");
+    assert_eq!(a["size"], 5500);
+    assert_eq!(a["plugin"], "deflate");
+    assert!(a["metadata"]["mtime"].is_i64());
+
+    let sub = entries.iter().find(|e| e["path"] == "tree/sub").unwrap();
+    assert_eq!(sub["kind"], "directory");
+}
+
+/// Symlinks are archived as links and restored as links
+#[cfg(unix)]
+#[test]
+fn test_archive_symlinks() {
+    let dir = test_dir();
+    let root = create_tree(dir.path());
+    std::os::unix::fs::symlink("a.txt", root.join("link")).unwrap();
+    let archive = dir.path().join("links.crar");
+
+    crush_cmd()
+        .args(["archive", "create"])
+        .arg(&archive)
+        .arg(&root)
+        .assert()
+        .success();
+
+    crush_cmd()
+        .args(["archive", "list"])
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tree/link -> a.txt"));
+
+    let out = dir.path().join("out");
+    crush_cmd()
+        .args(["archive", "extract"])
+        .arg(&archive)
+        .arg("--output")
+        .arg(&out)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_link(out.join("tree/link")).unwrap(),
+        std::path::Path::new("a.txt")
+    );
+}
+
+/// Entries with '..' or absolute paths are refused
+#[test]
+fn test_archive_extract_refuses_path_traversal() {
+    let dir = test_dir();
+    let out = dir.path().join("out");
+
+    for path in ["../evil", "/tmp/evil", "a/../../evil"] {
+        // Hand-built archive: one empty directory entry, then the end marker
+        let mut archive = b"CRAR\x01\0\0\0\x01".to_vec();
+        archive.extend_from_slice(&(path.len() as u16).to_le_bytes());
+        archive.extend_from_slice(path.as_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(&0u64.to_le_bytes());
+        archive.push(0xFF);
+        let archive_path = create_test_file(dir.path(), "evil.crar", &archive);
+
+        crush_cmd()
+            .args(["archive", "extract"])
+            .arg(&archive_path)
+            .arg("--output")
+            .arg(&out)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Unsafe archive path"));
+    }
+    assert!(!dir.path().join("evil").exists());
+}
+
+/// Entries are never written through a symlink extracted earlier
+#[cfg(unix)]
+#[test]
+fn test_archive_extract_refuses_writing_through_symlink() {
+    crush_core::init_plugins().unwrap();
+    let dir = test_dir();
+    let outside = dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+
+    let mut writer = ArchiveWriter::new(Vec::new(), &CompressionOptions::default()).unwrap();
+    writer
+        .add_symlink("link", outside.to_str().unwrap(), &FileMetadata::default())
+        .unwrap();
+    writer
+        .add_file("link/pwned", b"gotcha", &FileMetadata::default())
+        .unwrap();
+    let archive = create_test_file(dir.path(), "sneaky.crar", &writer.finish().unwrap());
+
+    crush_cmd()
+        .args(["archive", "extract"])
+        .arg(&archive)
+        .arg("--output")
+        .arg(dir.path().join("out"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("through symlink"));
+    assert!(!outside.join("pwned").exists());
+}
+
+/// Creating over an existing archive needs --force
+#[test]
+fn test_archive_create_requires_force() {
+    let dir = test_dir();
+    let root = create_tree(dir.path());
+    let archive = create_test_file(dir.path(), "backup.crar", b"existing");
+
+    crush_cmd()
+        .args(["archive", "create"])
+        .arg(&archive)
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+    crush_cmd()
+        .args(["archive", "create", "--force"])
+        .arg(&archive)
+        .arg(&root)
+        .assert()
+        .success();
+}
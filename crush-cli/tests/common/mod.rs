@@ -4,9 +4,17 @@ use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 /// Helper to create a Command for the crush binary
+///
+/// Points the calibration cache and the config file at files that never
+/// exist, so a cache left by `crush plugins calibrate` or a config on the
+/// developer's machine cannot change plugin selection or output in tests.
 #[allow(deprecated)]
 pub fn crush_cmd() -> Command {
-    Command::cargo_bin("crush").expect("Failed to find crush binary")
+    let mut cmd = Command::cargo_bin("crush").expect("Failed to find crush binary");
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    cmd.env("CRUSH_CALIBRATION_FILE", tmp.join("no-calibration.json"));
+    cmd.env("CRUSH_TEST_CONFIG_FILE", tmp.join("no-config.toml"));
+    cmd
 }
 
 /// Create a temporary directory for test files
@@ -94,3 +102,21 @@ pub fn assert_compressed(input: &Path, output: &Path) {
         input_size
     );
 }
+
+/// Name of the plugin recorded in a compressed file, via `crush inspect`
+#[allow(dead_code)]
+pub fn inspect_plugin_name(path: &Path) -> String {
+    let output = crush_cmd()
+        .arg("inspect")
+        .arg("--format")
+        .arg("json")
+        .arg(path)
+        .output()
+        .expect("Failed to run inspect");
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Invalid JSON output");
+    json[0]["plugin_name"]
+        .as_str()
+        .expect("Missing plugin_name")
+        .to_string()
+}
@@ -426,7 +426,7 @@ fn test_compress_interrupt_cleanup() {
     // Use 100MB to ensure it takes long enough to interrupt
     let large_data = vec![0u8; 100 * 1024 * 1024]; // 100MB
     let input = create_test_file(dir.path(), "interrupt_test.bin", &large_data);
-    let _output = dir.path().join("interrupt_test.bin.crush");
+    let output = dir.path().join("interrupt_test.bin.crush");
 
     // Get the path to the crush binary
     #[allow(deprecated)]
@@ -442,21 +442,237 @@ fn test_compress_interrupt_cleanup() {
         .spawn()
         .expect("Failed to start compress process");
 
-    // Give it a moment to start processing
-    thread::sleep(Duration::from_millis(100));
+    // Wait until output is being written, unless compression already finished
+    for _ in 0..1000 {
+        if output.exists() || child.try_wait().unwrap().is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
 
     // Kill the process (simulating Ctrl+C)
     child.kill().expect("Failed to kill process");
     let status = child.wait().expect("Failed to wait for process");
 
-    // Verify the process was interrupted
-    // On Windows, killed processes return exit code 1
-    // On Unix, they typically return 128 + SIGKILL (137) or similar
-    assert!(
-        !status.success(),
-        "Process should not exit successfully after being killed"
-    );
+    // Compression of this input is fast enough to win the race. A run that
+    // finished must have left a complete file; a killed one may leave a partial
+    // file, since cleanup of partial files is best-effort. The important thing
+    // is that the process can be interrupted without hanging.
+    if status.success() {
+        std::fs::remove_file(&input).unwrap();
+        crush_cmd()
+            .arg("decompress")
+            .arg(&output)
+            .assert()
+            .success();
+        assert!(
+            read_file(&input) == large_data,
+            "A finished run must round-trip"
+        );
+    }
+}
+
+/// Test that `--level fast` selects the LZ4 plugin
+#[test]
+fn test_compress_level_fast_selects_lz4() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "fast.txt", &b"fast level data ".repeat(100));
+    let output = dir.path().join("fast.txt.crush");
+
+    crush_cmd()
+        .arg("compress")
+        .arg("--level")
+        .arg("fast")
+        .arg(&input)
+        .assert()
+        .success();
+
+    assert_eq!(inspect_plugin_name(&output), "lz4");
+
+    let restored = dir.path().join("restored.txt");
+    crush_cmd()
+        .arg("decompress")
+        .arg("-o")
+        .arg(&restored)
+        .arg(&output)
+        .assert()
+        .success();
+    assert_files_equal(&input, &restored);
+}
+
+/// Test that `--level best` selects the xz plugin
+#[test]
+fn test_compress_level_best_selects_xz() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "best.txt", &b"best level data ".repeat(100));
+    let output = dir.path().join("best.txt.crush");
+
+    crush_cmd()
+        .arg("compress")
+        .arg("--level")
+        .arg("best")
+        .arg(&input)
+        .assert()
+        .success();
+
+    assert_eq!(inspect_plugin_name(&output), "xz");
+
+    let restored = dir.path().join("restored.txt");
+    crush_cmd()
+        .arg("decompress")
+        .arg("-o")
+        .arg(&restored)
+        .arg(&output)
+        .assert()
+        .success();
+    assert_files_equal(&input, &restored);
+}
+
+/// Plugin parameters are applied, recorded, and shown by inspect
+#[test]
+fn test_compress_with_plugin_params() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "tuned.txt", &b"tuned level data ".repeat(500));
+    let output = dir.path().join("tuned.txt.crush");
+
+    crush_cmd()
+        .arg("compress")
+        .arg("--plugin")
+        .arg("zstd")
+        .arg("--param")
+        .arg("level=19")
+        .arg("-P")
+        .arg("long_distance=1")
+        .arg(&input)
+        .assert()
+        .success();
+
+    crush_cmd()
+        .arg("inspect")
+        .arg(&output)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Parameters: level=19, long_distance=1",
+        ));
+
+    let restored = dir.path().join("restored.txt");
+    crush_cmd()
+        .arg("decompress")
+        .arg("-o")
+        .arg(&restored)
+        .arg(&output)
+        .assert()
+        .success();
+    assert_files_equal(&input, &restored);
+}
+
+/// Unknown, out-of-range and malformed parameters are rejected
+#[test]
+fn test_compress_invalid_plugin_params() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "bad.txt", b"invalid parameter data");
+
+    for (plugin, param, message) in [
+        ("deflate", "level=42", "Invalid plugin parameter"),
+        ("lz4", "strategy=1", "Invalid plugin parameter"),
+        ("deflate", "level", "expected KEY=VALUE"),
+        ("deflate", "level=high", "must be an integer"),
+    ] {
+        crush_cmd()
+            .arg("compress")
+            .arg("--plugin")
+            .arg(plugin)
+            .arg("--param")
+            .arg(param)
+            .arg(&input)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(message));
+    }
+
+    assert_file_not_exists(&dir.path().join("bad.txt.crush"));
+}
+
+/// Already-compressed input is stored instead of recompressed
+#[test]
+fn test_compress_already_compressed_stored() {
+    let dir = test_dir();
+    let mut data = vec![0x1F, 0x8B, 0x08, 0x00];
+    let mut seed = 0x2468_ACE1_u32;
+    data.extend((0..500_000).map(|_| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed.to_le_bytes()[2]
+    }));
+    let input = create_test_file(dir.path(), "archive.gz", &data);
+    let output = dir.path().join("archive.gz.crush");
+
+    crush_cmd()
+        .arg("compress")
+        .arg("--level")
+        .arg("best")
+        .arg(&input)
+        .assert()
+        .success();
+
+    // Blocks that do not shrink are stored, so the output barely grows
+    let output_size = std::fs::metadata(&output).unwrap().len();
+    assert!(output_size <= data.len() as u64 + 128);
+
+    let restored = dir.path().join("restored.gz");
+    crush_cmd()
+        .arg("decompress")
+        .arg("-o")
+        .arg(&restored)
+        .arg(&output)
+        .assert()
+        .success();
+    assert_files_equal(&input, &restored);
+}
+
+/// Input that only starts out looking compressed is still compressed
+#[test]
+fn test_compress_compressed_prefix() {
+    let dir = test_dir();
+    let text: Vec<u8> = (0u32..)
+        .flat_map(|i| format!("{i:08} INFO request served in {} ms\n", i % 97).into_bytes())
+        .take(4 * 1024 * 1024)
+        .collect();
+    let mut seed = 0x1357_9BDF_u32;
+    let mut random_prefix: Vec<u8> = (0..1024 * 1024)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed.to_le_bytes()[2]
+        })
+        .collect();
+    random_prefix.extend_from_slice(&text);
+    let mut png_prefix = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_prefix.extend_from_slice(&text);
+
+    for (name, data) in [("mixed.bin", random_prefix), ("fake.png", png_prefix)] {
+        let input = create_test_file(dir.path(), name, &data);
+        let output = dir.path().join(format!("{name}.crush"));
+
+        crush_cmd().arg("compress").arg(&input).assert().success();
+
+        assert_ne!(inspect_plugin_name(&output), "stored");
+        let output_size = std::fs::metadata(&output).unwrap().len();
+        assert!(
+            output_size < data.len() as u64 / 2,
+            "{name}: {output_size} bytes for {} bytes of input",
+            data.len()
+        );
 
-    // Note: Cleanup of partial files is best-effort. The important thing is that
-    // the process can be interrupted without hanging.
+        std::fs::remove_file(&input).unwrap();
+        crush_cmd()
+            .arg("decompress")
+            .arg(&output)
+            .assert()
+            .success();
+        assert_eq!(read_file(&input), data);
+    }
 }
@@ -175,3 +175,244 @@ fn test_config_affects_compression() {
     let output = dir.path().join("test.txt.crush");
     assert_file_exists(&output);
 }
+
+/// Write one key to the isolated config file
+fn set_config(config_path: &str, key: &str, value: &str) {
+    crush_cmd_with_config(config_path)
+        .args(["config", "set", key, value])
+        .assert()
+        .success();
+}
+
+/// Compress a fresh text file with the given env and flags and return the
+/// plugin `inspect` reports for the result
+fn compressed_plugin(config_path: &str, env: &[(&str, &str)], args: &[&str]) -> String {
+    let dir = test_dir();
+    let numbers: String = (0..20000).map(|i| format!("{i}\n")).collect();
+    let input = create_test_file(dir.path(), "numbers.txt", numbers.as_bytes());
+
+    crush_cmd_with_config(config_path)
+        .envs(env.iter().copied())
+        .arg("compress")
+        .args(args)
+        .arg(&input)
+        .assert()
+        .success();
+
+    let output = crush_cmd_with_config(config_path)
+        .arg("inspect")
+        .arg(dir.path().join("numbers.txt.crush"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Plugin: "))
+        .expect("inspect output names the plugin")
+        .to_string()
+}
+
+/// compression.default-plugin selects the plugin; env beats file, CLI beats env
+#[test]
+fn test_config_default_plugin_precedence() {
+    let (_temp_dir, config_path) = setup_test_config();
+    set_config(&config_path, "compression.default-plugin", "deflate");
+
+    assert_eq!(compressed_plugin(&config_path, &[], &[]), "deflate");
+    let env = [("CRUSH_COMPRESSION_DEFAULT_PLUGIN", "bzip2")];
+    assert_eq!(compressed_plugin(&config_path, &env, &[]), "bzip2");
+    assert_eq!(compressed_plugin(&config_path, &env, &["-p", "lz4"]), "lz4");
+}
+
+/// compression.level steers automatic selection; env beats file, CLI beats env
+#[test]
+fn test_config_level_precedence() {
+    let (_temp_dir, config_path) = setup_test_config();
+    assert_eq!(compressed_plugin(&config_path, &[], &[]), "zstd");
+
+    set_config(&config_path, "compression.level", "best");
+    assert_eq!(compressed_plugin(&config_path, &[], &[]), "xz");
+    let env = [("CRUSH_COMPRESSION_LEVEL", "fast")];
+    assert_eq!(compressed_plugin(&config_path, &env, &[]), "lz4");
+    assert_eq!(
+        compressed_plugin(&config_path, &env, &["--level", "balanced"]),
+        "zstd"
+    );
+}
+
+/// compression.timeout-seconds applies to compress and decompress unless --timeout is given
+#[test]
+fn test_config_timeout_seconds() {
+    let (_temp_dir, config_path) = setup_test_config();
+    set_config(&config_path, "compression.timeout-seconds", "30");
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.txt", b"timeout test data");
+
+    crush_cmd_with_config(&config_path)
+        .args(["-v", "compress"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Setting compression timeout: 30 seconds",
+        ));
+
+    crush_cmd_with_config(&config_path)
+        .args(["-v", "compress", "--force", "--timeout", "5"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Setting compression timeout: 5 seconds",
+        ));
+
+    std::fs::remove_file(&input).unwrap();
+    crush_cmd_with_config(&config_path)
+        .args(["-v", "decompress"])
+        .arg(dir.path().join("data.txt.crush"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Setting decompression timeout: 30 seconds",
+        ));
+}
+
+/// output.progress-bars reaches the output settings; the env var overrides the file
+#[test]
+fn test_config_progress_bars() {
+    let (_temp_dir, config_path) = setup_test_config();
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.txt", b"progress test data");
+
+    crush_cmd_with_config(&config_path)
+        .args(["-v", "compress"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("progress_bars: true"));
+
+    set_config(&config_path, "output.progress-bars", "false");
+    crush_cmd_with_config(&config_path)
+        .args(["-v", "compress", "--force"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("progress_bars: false"));
+
+    crush_cmd_with_config(&config_path)
+        .env("CRUSH_OUTPUT_PROGRESS_BARS", "true")
+        .args(["-v", "compress", "--force"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("progress_bars: true"));
+}
+
+/// output.color = always colors piped output; never (the default off a terminal) does not
+#[test]
+fn test_config_color() {
+    let (_temp_dir, config_path) = setup_test_config();
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.txt", b"color test data");
+
+    crush_cmd_with_config(&config_path)
+        .arg("compress")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    set_config(&config_path, "output.color", "always");
+    crush_cmd_with_config(&config_path)
+        .args(["compress", "--force"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+
+    crush_cmd_with_config(&config_path)
+        .env("CRUSH_OUTPUT_COLOR", "never")
+        .args(["compress", "--force"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    // inspect follows the same setting
+    let compressed = dir.path().join("data.txt.crush");
+    crush_cmd_with_config(&config_path)
+        .arg("inspect")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+
+    crush_cmd_with_config(&config_path)
+        .env("CRUSH_OUTPUT_COLOR", "never")
+        .arg("inspect")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+/// output.quiet hides status output but not inspect results or errors
+#[test]
+fn test_config_quiet() {
+    let (_temp_dir, config_path) = setup_test_config();
+    set_config(&config_path, "output.quiet", "true");
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.txt", b"quiet test data");
+
+    crush_cmd_with_config(&config_path)
+        .arg("compress")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    crush_cmd_with_config(&config_path)
+        .arg("inspect")
+        .arg(dir.path().join("data.txt.crush"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Original size"));
+
+    crush_cmd_with_config(&config_path)
+        .arg("compress")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+
+    crush_cmd_with_config(&config_path)
+        .env("CRUSH_OUTPUT_QUIET", "false")
+        .args(["compress", "--force"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed"));
+}
+
+/// logging.format from the config is no longer overridden by the --log-format default
+#[test]
+fn test_config_log_format() {
+    let (_temp_dir, config_path) = setup_test_config();
+    set_config(&config_path, "logging.format", "json");
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.txt", b"log format test data");
+
+    crush_cmd_with_config(&config_path)
+        .arg("compress")
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("\"level\":\"INFO\""));
+
+    crush_cmd_with_config(&config_path)
+        .args(["--log-format", "human", "compress", "--force"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("\"level\":\"INFO\"").not());
+}
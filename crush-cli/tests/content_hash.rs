@@ -0,0 +1,96 @@
+mod common;
+
+use common::*;
+use crush_core::HashAlgorithm;
+use predicates::prelude::*;
+
+fn dataset() -> Vec<u8> {
+    b"id,name,checksum\n42,crush,deadbeef\n".repeat(3000)
+}
+
+/// `--hash` records a digest that inspect reports and decompress verifies
+#[test]
+fn test_hash_roundtrip_and_inspect() {
+    let dir = test_dir();
+    let data = dataset();
+    let input = create_test_file(dir.path(), "data.csv", &data);
+    let compressed = dir.path().join("data.csv.crush");
+
+    crush_cmd()
+        .args(["compress", "--hash", "sha256"])
+        .arg(&input)
+        .assert()
+        .success();
+
+    let expected = HashAlgorithm::Sha256.hash(&data);
+    let json = crush_cmd()
+        .args(["inspect", "--format", "json"])
+        .arg(&compressed)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json[0]["content_hash"]["algorithm"], "sha256");
+    assert_eq!(json[0]["content_hash"]["digest"], expected.to_hex());
+
+    crush_cmd()
+        .arg("inspect")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Content hash: {expected}"
+        )));
+
+    std::fs::remove_file(&input).unwrap();
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .assert()
+        .success();
+    assert_eq!(read_file(&input), data);
+}
+
+/// A digest that no longer matches the content fails decompression
+#[test]
+fn test_hash_mismatch() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.csv", &dataset());
+    let compressed = dir.path().join("data.csv.crush");
+    crush_cmd()
+        .args(["compress", "--hash", "xxh3"])
+        .arg(&input)
+        .assert()
+        .success();
+    std::fs::remove_file(&input).unwrap();
+
+    let mut bytes = read_file(&compressed);
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&compressed, bytes).unwrap();
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("xxh3 content hash mismatch"));
+    assert!(!input.exists());
+}
+
+/// Unknown algorithms are rejected as usage errors
+#[test]
+fn test_unknown_hash_algorithm() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.csv", b"data");
+    crush_cmd()
+        .args(["compress", "--hash", "md5"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("unknown hash algorithm 'md5'"));
+}
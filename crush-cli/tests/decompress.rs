@@ -148,3 +148,55 @@ fn test_decompress_invalid_header() {
                 .or(predicate::str::contains("format")),
         );
 }
+
+/// Data appended after the end of the stream is rejected, not ignored
+#[test]
+fn test_decompress_rejects_appended_data() {
+    let dir = test_dir();
+    let input = create_random_file(dir.path(), "data.bin", 200_000);
+    crush_cmd().arg("compress").arg(&input).assert().success();
+    let compressed = dir.path().join("data.bin.crush");
+    std::fs::remove_file(&input).unwrap();
+
+    let mut bytes = read_file(&compressed);
+    bytes.extend((0..5000u32).map(|i| (i * 7919 % 251) as u8));
+    std::fs::write(&compressed, bytes).unwrap();
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Unexpected data after end of stream",
+        ));
+    assert_file_not_exists(&input);
+}
+
+/// Input too short to hold a header is rejected as such, not as an I/O error
+#[test]
+fn test_decompress_truncated_header() {
+    let dir = test_dir();
+    let short = create_test_file(dir.path(), "short.crush", b"CR\x01\x00\x01");
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(&short)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Not a valid Crush archive: invalid file header",
+        ))
+        .stderr(predicate::str::contains("failed to fill whole buffer").not());
+
+    // The same from stdin
+    crush_cmd()
+        .args(["decompress", "--stdout"])
+        .write_stdin(&b"CR\x01\x00\x01"[..])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Not a valid Crush archive: invalid file header",
+        ));
+}
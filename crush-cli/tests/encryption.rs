@@ -0,0 +1,186 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::path::Path;
+
+const PASSPHRASE_ENV: &str = "CRUSH_PASSPHRASE";
+
+fn backup_data() -> Vec<u8> {
+    b"nightly backup row 0042, do not leak\n".repeat(2000)
+}
+
+/// Write a 64-character hex key file
+fn key_file(dir: &Path, name: &str, byte: u8) -> std::path::PathBuf {
+    create_test_file(dir, name, format!("{byte:02x}").repeat(32).as_bytes())
+}
+
+/// A passphrase from the environment round-trips and hides the content
+#[test]
+fn test_passphrase_roundtrip() {
+    let dir = test_dir();
+    let data = backup_data();
+    let input = create_test_file(dir.path(), "backup.db", &data);
+    let compressed = dir.path().join("backup.db.crush");
+
+    crush_cmd()
+        .args(["compress", "--encrypt"])
+        .arg(&input)
+        .env(PASSPHRASE_ENV, "correct horse battery staple")
+        .assert()
+        .success();
+    let encrypted = read_file(&compressed);
+    assert!(!encrypted
+        .windows(b"do not leak".len())
+        .any(|w| w == b"do not leak"));
+
+    crush_cmd()
+        .args(["inspect", "--format", "json"])
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("chacha20-poly1305"))
+        .stdout(predicate::str::contains("argon2id"));
+
+    std::fs::remove_file(&input).unwrap();
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .env(PASSPHRASE_ENV, "correct horse battery staple")
+        .assert()
+        .success();
+    assert_eq!(read_file(&input), data);
+}
+
+/// A wrong passphrase is reported as such and leaves no output behind
+#[test]
+fn test_wrong_passphrase() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "secret.txt", &backup_data());
+    crush_cmd()
+        .args(["compress", "--encrypt"])
+        .arg(&input)
+        .env(PASSPHRASE_ENV, "right")
+        .assert()
+        .success();
+    std::fs::remove_file(&input).unwrap();
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(dir.path().join("secret.txt.crush"))
+        .env(PASSPHRASE_ENV, "wrong")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Wrong passphrase or key"))
+        .stderr(predicate::str::contains("CRC32").not());
+    assert!(!input.exists());
+}
+
+/// Key files round-trip through files and pipes; another key is rejected
+#[test]
+fn test_key_file() {
+    let dir = test_dir();
+    let data = backup_data();
+    let key = key_file(dir.path(), "backup.key", 0x5a);
+    let other = key_file(dir.path(), "other.key", 0x17);
+    let input = create_test_file(dir.path(), "data.bin", &data);
+
+    crush_cmd()
+        .args(["compress", "--key-file"])
+        .arg(&key)
+        .arg(&input)
+        .assert()
+        .success();
+    let compressed = dir.path().join("data.bin.crush");
+
+    crush_cmd()
+        .args(["decompress", "--stdout", "--key-file"])
+        .arg(&other)
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Wrong passphrase or key"));
+
+    let decrypted = crush_cmd()
+        .args(["decompress", "--stdout", "--key-file"])
+        .arg(&key)
+        .arg(&compressed)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(decrypted, data);
+
+    let piped = crush_cmd()
+        .args(["compress", "--stdout", "--key-file"])
+        .arg(&key)
+        .write_stdin(data.clone())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let roundtrip = crush_cmd()
+        .args(["decompress", "--stdout", "--key-file"])
+        .arg(&key)
+        .write_stdin(piped)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(roundtrip, data);
+}
+
+/// Key files must hold exactly 256 bits
+#[test]
+fn test_invalid_key_file() {
+    let dir = test_dir();
+    let key = create_test_file(dir.path(), "short.key", b"deadbeef");
+    let input = create_test_file(dir.path(), "data.txt", b"data");
+
+    crush_cmd()
+        .args(["compress", "--key-file"])
+        .arg(&key)
+        .arg(&input)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "32 raw bytes or 64 hex characters",
+        ));
+    assert!(!dir.path().join("data.txt.crush").exists());
+}
+
+/// Unencrypted files decompress without asking for a key
+#[test]
+fn test_unencrypted_file_needs_no_key() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "plain.txt", &backup_data());
+    crush_cmd().arg("compress").arg(&input).assert().success();
+    std::fs::remove_file(&input).unwrap();
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(dir.path().join("plain.txt.crush"))
+        .env_remove(PASSPHRASE_ENV)
+        .assert()
+        .success();
+    assert_eq!(read_file(&input), backup_data());
+}
+
+/// The block index of seekable output cannot be encrypted
+#[test]
+fn test_encrypt_conflicts_with_seekable() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "data.txt", b"data");
+
+    crush_cmd()
+        .args(["compress", "--encrypt", "--seekable"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
@@ -25,7 +25,8 @@ fn test_inspect_basic() {
                 .and(predicate::str::contains("Compressed size:"))
                 .and(predicate::str::contains("Size reduction:"))
                 .and(predicate::str::contains("Plugin:"))
-                .and(predicate::str::contains("CRC32: VALID")),
+                .and(predicate::str::contains("CRC32: VALID"))
+                .and(predicate::str::contains("Seekable: no")),
         );
 }
 
@@ -136,6 +137,44 @@ fn test_inspect_json_output() {
     assert!(item["metadata"]["mtime"].is_number()); // mtime might be null if not set
 }
 
+/// Inspect reports the block count and whether a file is seekable
+#[test]
+fn test_inspect_seekable() {
+    let dir = test_dir();
+    let test_data = b"seekable inspection data ".repeat(100_000);
+    let input = create_test_file(dir.path(), "trace.bin", &test_data);
+    let output = dir.path().join("trace.bin.crush");
+
+    crush_cmd()
+        .arg("compress")
+        .arg("--seekable")
+        .arg(&input)
+        .assert()
+        .success();
+
+    crush_cmd()
+        .arg("inspect")
+        .arg(&output)
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Seekable: yes").and(predicate::str::contains("Blocks: 3")),
+        );
+
+    let output_assert = crush_cmd()
+        .arg("inspect")
+        .arg("--format")
+        .arg("json")
+        .arg(&output)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(output_assert.get_output().stdout.clone()).unwrap();
+    let json_output: Vec<serde_json::Value> =
+        serde_json::from_str(&stdout).expect("Invalid JSON output");
+    assert_eq!(json_output[0]["seekable"], true);
+    assert_eq!(json_output[0]["block_count"], 3);
+}
+
 /// T063: Test CSV output format
 #[test]
 fn test_inspect_csv_output() {
@@ -145,7 +184,13 @@ fn test_inspect_csv_output() {
     let output = dir.path().join("test.txt.crush");
 
     // Compress the file first
-    crush_cmd().arg("compress").arg(&input).assert().success();
+    crush_cmd()
+        .arg("compress")
+        .arg("--plugin")
+        .arg("deflate")
+        .arg(&input)
+        .assert()
+        .success();
 
     // Now inspect it with CSV format
     let output_assert = crush_cmd()
@@ -0,0 +1,141 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Create `count` small text files named file00.txt, file01.txt, ...
+fn create_files(dir: &Path, count: usize) -> Vec<PathBuf> {
+    (0..count)
+        .map(|i| {
+            let content = format!("contents of file {i}\n").repeat(50 + i);
+            create_test_file(dir, &format!("file{i:02}.txt"), content.as_bytes())
+        })
+        .collect()
+}
+
+/// Many files compressed and decompressed on a worker pool round-trip intact
+#[test]
+fn test_compress_decompress_with_jobs() {
+    let dir = test_dir();
+    let files = create_files(dir.path(), 24);
+    let originals: Vec<Vec<u8>> = files.iter().map(|f| read_file(f)).collect();
+
+    crush_cmd()
+        .args(["compress", "-j", "4"])
+        .args(&files)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 24 files, skipped 0"));
+
+    for file in &files {
+        fs::remove_file(file).unwrap();
+    }
+    let compressed: Vec<PathBuf> = files
+        .iter()
+        .map(|f| f.with_extension("txt.crush"))
+        .collect();
+
+    crush_cmd()
+        .args(["decompress", "--jobs", "3"])
+        .args(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Decompressed 24 files"));
+
+    for (file, original) in files.iter().zip(&originals) {
+        assert_eq!(&read_file(file), original);
+    }
+}
+
+/// --jobs combines with --recursive
+#[test]
+fn test_recursive_with_jobs() {
+    let dir = test_dir();
+    let root = dir.path().join("tree");
+    fs::create_dir_all(root.join("nested")).unwrap();
+    create_files(&root, 5);
+    create_files(&root.join("nested"), 5);
+
+    crush_cmd()
+        .args(["compress", "-r", "-j", "3"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 10 files, skipped 0"));
+    assert_file_exists(&root.join("nested/file04.txt.crush"));
+}
+
+/// A failing file fails the run without touching files it cannot write
+#[test]
+fn test_jobs_reports_failures() {
+    let dir = test_dir();
+    let files = create_files(dir.path(), 8);
+    let existing = create_test_file(dir.path(), "file03.txt.crush", b"keep me");
+
+    crush_cmd()
+        .args(["compress", "-j", "2"])
+        .args(&files)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("already exists"));
+
+    assert_eq!(read_file(&existing), b"keep me");
+}
+
+/// Parallel output would interleave, so --jobs and --stdout conflict
+#[test]
+fn test_jobs_conflicts_with_stdout() {
+    let dir = test_dir();
+    let files = create_files(dir.path(), 2);
+
+    crush_cmd()
+        .args(["compress", "-j", "2", "--stdout"])
+        .args(&files)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+/// Several inputs cannot share one output file, only an output directory
+#[test]
+fn test_multiple_inputs_need_output_directory() {
+    let dir = test_dir();
+    let files = create_files(dir.path(), 2);
+    let out = dir.path().join("out.crush");
+
+    crush_cmd()
+        .args(["compress", "-j", "2", "--force", "-o"])
+        .arg(&out)
+        .args(&files)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("must be an existing directory"));
+    assert_file_not_exists(&out);
+
+    let packed = dir.path().join("packed");
+    fs::create_dir(&packed).unwrap();
+    crush_cmd()
+        .args(["compress", "-j", "2", "-o"])
+        .arg(&packed)
+        .args(&files)
+        .assert()
+        .success();
+    let compressed: Vec<PathBuf> = fs::read_dir(&packed)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(compressed.len(), 2);
+
+    crush_cmd()
+        .args(["decompress", "-j", "2", "-o"])
+        .arg(dir.path().join("restored.txt"))
+        .args(&compressed)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("must be an existing directory"));
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// a.txt, missing.txt (never created), c.txt, d.txt
+fn batch_inputs(dir: &Path) -> Vec<PathBuf> {
+    vec![
+        create_test_file(dir, "a.txt", &b"alpha ".repeat(100)),
+        dir.join("missing.txt"),
+        create_test_file(dir, "c.txt", &b"gamma ".repeat(100)),
+        create_test_file(dir, "d.txt", &b"delta ".repeat(100)),
+    ]
+}
+
+/// Without --keep-going the first failure stops the batch
+#[test]
+fn test_batch_stops_at_first_failure() {
+    let dir = test_dir();
+    let inputs = batch_inputs(dir.path());
+
+    crush_cmd()
+        .arg("compress")
+        .args(&inputs)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("does not exist"));
+
+    assert_file_exists(&dir.path().join("a.txt.crush"));
+    assert!(!dir.path().join("c.txt.crush").exists());
+}
+
+/// --keep-going processes every file and exits with the partial-failure code
+#[test]
+fn test_keep_going_compresses_remaining_files() {
+    let dir = test_dir();
+    let inputs = batch_inputs(dir.path());
+
+    crush_cmd()
+        .args(["compress", "--keep-going"])
+        .args(&inputs)
+        .assert()
+        .failure()
+        .code(3)
+        .stdout(predicate::str::contains(
+            "Compressed 3 files, skipped 0, failed 1",
+        ))
+        .stderr(predicate::str::contains("missing.txt"))
+        .stderr(predicate::str::contains("1 of 4 files failed"));
+
+    for name in ["a.txt", "c.txt", "d.txt"] {
+        assert_file_exists(&dir.path().join(format!("{name}.crush")));
+    }
+}
+
+/// The JSON report lists every file with its outcome, also on a worker pool
+#[test]
+fn test_keep_going_json_report() {
+    for jobs in ["1", "3"] {
+        let dir = test_dir();
+        let inputs = batch_inputs(dir.path());
+        let report = dir.path().join("report.json");
+
+        crush_cmd()
+            .args(["compress", "--keep-going", "-j", jobs, "--report"])
+            .arg(&report)
+            .args(&inputs)
+            .assert()
+            .code(3);
+
+        let report: serde_json::Value = serde_json::from_slice(&read_file(&report)).unwrap();
+        assert_eq!(report["operation"], "compress");
+        assert_eq!(report["succeeded"], 3);
+        assert_eq!(report["failed"], 1);
+
+        let files = report["files"].as_array().unwrap();
+        assert_eq!(files.len(), 4);
+        let failed: Vec<_> = files.iter().filter(|f| f["status"] == "failed").collect();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0]["path"].as_str().unwrap().ends_with("missing.txt"));
+        assert!(failed[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("does not exist"));
+        assert!(files
+            .iter()
+            .filter(|f| f["status"] == "ok")
+            .all(|f| f["input_size"] == 600));
+    }
+}
+
+/// A successful batch still writes a report and exits 0
+#[test]
+fn test_report_on_success() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "only.txt", b"just one file");
+    let report = dir.path().join("report.json");
+
+    crush_cmd()
+        .arg("compress")
+        .arg("--report")
+        .arg(&report)
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 1 file, skipped 0"));
+
+    let report: serde_json::Value = serde_json::from_slice(&read_file(&report)).unwrap();
+    assert_eq!(report["succeeded"], 1);
+    assert_eq!(report["failed"], 0);
+    assert_eq!(report["files"][0]["status"], "ok");
+}
+
+/// Corrupt archives are reported per file when decompressing with --keep-going
+#[test]
+fn test_decompress_keep_going() {
+    let dir = test_dir();
+    let good = create_test_file(dir.path(), "good.txt", &b"fine data ".repeat(50));
+    crush_cmd().arg("compress").arg(&good).assert().success();
+    std::fs::remove_file(&good).unwrap();
+    let corrupt = create_test_file(dir.path(), "bad.txt.crush", b"definitely not crush");
+    let report = dir.path().join("report.json");
+
+    crush_cmd()
+        .args(["decompress", "-r", "--keep-going", "--report"])
+        .arg(&report)
+        .arg(dir.path())
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("bad.txt.crush"));
+
+    assert_file_exists(&good);
+    let report: serde_json::Value = serde_json::from_slice(&read_file(&report)).unwrap();
+    assert_eq!(report["operation"], "decompress");
+    let failed = report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["status"] == "failed")
+        .unwrap();
+    assert_eq!(failed["path"].as_str().unwrap(), corrupt.to_str().unwrap());
+}
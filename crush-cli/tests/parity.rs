@@ -0,0 +1,160 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+fn records() -> Vec<u8> {
+    b"2024-06-01,sensor-7,21.4,ok\n".repeat(20_000)
+}
+
+/// Compress the records with `--parity 4` and return the compressed path
+fn compressed_with_parity(dir: &Path) -> PathBuf {
+    let input = create_test_file(dir, "records.csv", &records());
+    crush_cmd()
+        .args(["compress", "--parity", "4"])
+        .arg(&input)
+        .assert()
+        .success();
+    std::fs::remove_file(&input).unwrap();
+    dir.join("records.csv.crush")
+}
+
+/// Flip one byte at each fraction of the file
+fn damage(path: &Path, fractions: &[usize]) {
+    let mut bytes = read_file(path);
+    for &percent in fractions {
+        let at = bytes.len() * percent / 100;
+        bytes[at] ^= 0x04;
+    }
+    std::fs::write(path, bytes).unwrap();
+}
+
+/// Decompress repairs damaged bytes and says so; `crush repair` fixes the file
+#[test]
+fn test_decompress_and_repair() {
+    let dir = test_dir();
+    let compressed = compressed_with_parity(dir.path());
+    let pristine = read_file(&compressed);
+
+    crush_cmd()
+        .arg("inspect")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Parity: 4 shards per block"));
+
+    damage(&compressed, &[25, 50, 75]);
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("repaired 3 damaged bytes"))
+        .stderr(predicate::str::contains("crush repair"));
+    assert_eq!(read_file(&dir.path().join("records.csv")), records());
+
+    crush_cmd()
+        .arg("repair")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("repaired 3 damaged bytes"));
+    assert_eq!(read_file(&compressed), pristine);
+
+    crush_cmd()
+        .arg("repair")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("no damage found"));
+}
+
+/// `--output` writes the repaired copy elsewhere and leaves the input alone
+#[test]
+fn test_repair_to_output() {
+    let dir = test_dir();
+    let compressed = compressed_with_parity(dir.path());
+    let pristine = read_file(&compressed);
+    damage(&compressed, &[40]);
+    let damaged = read_file(&compressed);
+
+    let restored = dir.path().join("restored.crush");
+    crush_cmd()
+        .arg("repair")
+        .arg(&compressed)
+        .arg("--output")
+        .arg(&restored)
+        .assert()
+        .success();
+    assert_eq!(read_file(&restored), pristine);
+    assert_eq!(read_file(&compressed), damaged);
+}
+
+/// Too much damage fails both commands and leaves the file as it was
+#[test]
+fn test_damage_beyond_repair() {
+    let dir = test_dir();
+    let input = create_random_file(dir.path(), "noise.bin", 20_000);
+    crush_cmd()
+        .args(["compress", "--parity", "1"])
+        .arg(&input)
+        .assert()
+        .success();
+    let compressed = dir.path().join("noise.bin.crush");
+    damage(&compressed, &[30, 60]);
+    let damaged = read_file(&compressed);
+
+    crush_cmd()
+        .args(["decompress", "--force"])
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("damaged beyond repair"));
+    crush_cmd()
+        .arg("repair")
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("damaged beyond repair"));
+    assert_eq!(read_file(&compressed), damaged);
+}
+
+/// Files without parity cannot be repaired, and parity rules out encryption
+#[test]
+fn test_parity_usage_errors() {
+    let dir = test_dir();
+    let input = create_test_file(dir.path(), "plain.txt", &records());
+    crush_cmd().arg("compress").arg(&input).assert().success();
+
+    crush_cmd()
+        .arg("repair")
+        .arg(dir.path().join("plain.txt.crush"))
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("no parity data"));
+
+    crush_cmd()
+        .args(["compress", "--force", "--parity", "0"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .code(2);
+    crush_cmd()
+        .args([
+            "compress",
+            "--force",
+            "--parity",
+            "2",
+            "--key-file",
+            "k.key",
+        ])
+        .arg(&input)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+}
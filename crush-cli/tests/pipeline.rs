@@ -178,6 +178,44 @@ fn test_pipeline_full_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Test a multi-block pipeline where output is produced while input is still
+/// being written (stdin is fed from a separate thread to avoid pipe deadlock)
+#[test]
+fn test_pipeline_streaming_large_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+    let test_data: Vec<u8> = (0..4 * 1024 * 1024_usize)
+        .map(|i| (i % 241) as u8)
+        .collect();
+
+    let run_piped = |command: &str, input: Vec<u8>| -> std::io::Result<std::process::Output> {
+        let mut child = Command::new(crush_bin_path())
+            .arg(command)
+            .arg("--stdout")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| std::io::Error::other("Failed to open stdin"))?;
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|_| std::io::Error::other("stdin writer panicked"))??;
+        Ok(output)
+    };
+
+    let compressed = run_piped("compress", test_data.clone())?;
+    assert!(compressed.status.success(), "Compress failed");
+    assert!(compressed.stdout.len() < test_data.len());
+
+    let decompressed = run_piped("decompress", compressed.stdout)?;
+    assert!(decompressed.status.success(), "Decompress failed");
+    assert_eq!(decompressed.stdout, test_data, "Streaming roundtrip failed");
+
+    Ok(())
+}
+
 /// T155: Test progress bars are hidden when using stdin
 #[test]
 fn test_pipeline_no_progress_bars_on_stdin() -> Result<(), Box<dyn std::error::Error>> {
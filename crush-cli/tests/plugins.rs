@@ -133,3 +133,103 @@ fn test_plugins_test() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Test plugin self-test for the LZ4 plugin
+#[test]
+fn test_plugins_test_lz4() {
+    crush_cmd()
+        .arg("plugins")
+        .arg("test")
+        .arg("lz4")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lz4").and(predicate::str::contains("PASS")));
+}
+
+/// The bzip2 plugin is listed and passes its self-test
+#[test]
+fn test_plugins_test_bzip2() {
+    crush_cmd()
+        .arg("plugins")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bzip2"));
+
+    crush_cmd()
+        .arg("plugins")
+        .arg("test")
+        .arg("bzip2")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bzip2").and(predicate::str::contains("PASS")));
+}
+
+/// Plugin info lists the tunable parameters with their ranges
+#[test]
+fn test_plugins_info_parameters() {
+    crush_cmd()
+        .arg("plugins")
+        .arg("info")
+        .arg("zstd")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Parameters"))
+        .stdout(predicate::str::contains("level (-7..=22, default 3)"))
+        .stdout(predicate::str::contains("window_log"));
+}
+
+/// Calibration writes a cache that `plugins info` then reports, and `--reset` removes it
+#[test]
+fn test_plugins_calibrate() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = test_dir();
+    let cache = dir.path().join("crush").join("calibration.json");
+
+    crush_cmd()
+        .env("CRUSH_CALIBRATION_FILE", &cache)
+        .arg("plugins")
+        .arg("calibrate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("zstd").and(predicate::str::contains("MB/s")));
+
+    let json: serde_json::Value = serde_json::from_slice(&read_file(&cache))?;
+    let plugins = json["plugins"].as_array().ok_or("missing plugins array")?;
+    assert!(plugins.iter().any(|p| p["name"] == "bzip2"));
+
+    crush_cmd()
+        .env("CRUSH_CALIBRATION_FILE", &cache)
+        .arg("plugins")
+        .arg("info")
+        .arg("zstd")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Measured Throughput"));
+
+    crush_cmd()
+        .env("CRUSH_CALIBRATION_FILE", &cache)
+        .arg("plugins")
+        .arg("calibrate")
+        .arg("--reset")
+        .assert()
+        .success();
+    assert_file_not_exists(&cache);
+
+    Ok(())
+}
+
+/// A corrupt calibration cache is ignored rather than failing commands
+#[test]
+fn test_plugins_corrupt_calibration_ignored() {
+    let dir = test_dir();
+    let cache = create_test_file(dir.path(), "calibration.json", b"not json");
+
+    crush_cmd()
+        .env("CRUSH_CALIBRATION_FILE", &cache)
+        .arg("plugins")
+        .arg("info")
+        .arg("zstd")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Measured Throughput").not());
+}
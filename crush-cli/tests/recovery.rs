@@ -0,0 +1,171 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+const BLOCK: usize = 1024 * 1024;
+
+fn readings() -> Vec<u8> {
+    (0..)
+        .flat_map(|i: u32| format!("{i:08},sensor-{},{}.{}\n", i % 7, i % 40, i % 10).into_bytes())
+        .take(4 * BLOCK + 5000)
+        .collect()
+}
+
+/// Compress the readings and return the compressed path
+fn compressed_readings(dir: &Path) -> PathBuf {
+    let input = create_test_file(dir, "readings.csv", &readings());
+    crush_cmd().arg("compress").arg(&input).assert().success();
+    std::fs::remove_file(&input).unwrap();
+    dir.join("readings.csv.crush")
+}
+
+/// A truncated file fails to decompress; `--recover` keeps the complete blocks
+#[test]
+fn test_recover_truncated_file() {
+    let dir = test_dir();
+    let compressed = compressed_readings(dir.path());
+    let mut bytes = read_file(&compressed);
+    bytes.truncate(bytes.len() * 3 / 4);
+    std::fs::write(&compressed, bytes).unwrap();
+    let output = dir.path().join("readings.csv");
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1);
+    assert_file_not_exists(&output);
+
+    crush_cmd()
+        .args(["decompress", "--recover"])
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "nothing after it could be decoded",
+        ))
+        .stderr(predicate::str::contains("the original size is unknown"));
+    let recovered = read_file(&output);
+    assert!(recovered.len() >= 2 * BLOCK);
+    assert_eq!(recovered.len() % BLOCK, 0);
+    assert!(readings().starts_with(&recovered));
+}
+
+/// `--recover` skips a damaged block, keeps the blocks after it and exits
+/// with code 3 for the lost data
+#[test]
+fn test_recover_damaged_block() {
+    let dir = test_dir();
+    let compressed = compressed_readings(dir.path());
+    let mut bytes = read_file(&compressed);
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0x10;
+    std::fs::write(&compressed, bytes).unwrap();
+
+    crush_cmd()
+        .args(["decompress", "--recover"])
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("decoding resumed at offset"))
+        .stderr(predicate::str::contains(format!(
+            "recovered {} of {} bytes",
+            readings().len() - BLOCK,
+            readings().len()
+        )));
+
+    let data = readings();
+    let recovered = read_file(&dir.path().join("readings.csv"));
+    let lost = (0..4)
+        .map(|block| block * BLOCK)
+        .find(|&at| recovered[..at] == data[..at] && recovered[at..] == data[at + BLOCK..]);
+    assert!(lost.is_some(), "output is not the input minus one block");
+}
+
+/// Recovery works on stdin; input with a damaged header still fails
+#[test]
+fn test_recover_stdin_and_unrecoverable() {
+    let dir = test_dir();
+    let compressed = compressed_readings(dir.path());
+    let mut bytes = read_file(&compressed);
+    bytes.truncate(bytes.len() / 2);
+
+    let assert = crush_cmd()
+        .args(["decompress", "--recover", "--stdout"])
+        .write_stdin(bytes.clone())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("stdin: recovered"));
+    let recovered = &assert.get_output().stdout;
+    assert!(!recovered.is_empty());
+    assert!(readings().starts_with(recovered));
+
+    bytes[0] ^= 0xFF;
+    crush_cmd()
+        .args(["decompress", "--recover", "--stdout"])
+        .write_stdin(bytes)
+        .assert()
+        .failure()
+        .code(1);
+}
+
+/// `--report` lists the damaged regions of each file; intact files recover
+/// with exit code 0
+#[test]
+fn test_recover_report() {
+    let dir = test_dir();
+    let compressed = compressed_readings(dir.path());
+    let intact = dir.path().join("intact.csv.crush");
+    std::fs::copy(&compressed, &intact).unwrap();
+    let mut bytes = read_file(&compressed);
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0x10;
+    std::fs::write(&compressed, bytes).unwrap();
+
+    crush_cmd()
+        .args(["decompress", "--recover"])
+        .arg(&intact)
+        .assert()
+        .success();
+
+    let report = dir.path().join("report.json");
+    std::fs::remove_file(dir.path().join("intact.csv")).unwrap();
+    crush_cmd()
+        .args(["decompress", "--recover", "--keep-going", "--report"])
+        .arg(&report)
+        .arg(&compressed)
+        .arg(&intact)
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "1 of 2 inputs were only partly recovered",
+        ));
+
+    let json: serde_json::Value = serde_json::from_slice(&read_file(&report)).unwrap();
+    assert_eq!(json["succeeded"], 1);
+    assert_eq!(json["damaged"], 1);
+    assert_eq!(json["failed"], 0);
+    let files = json["files"].as_array().unwrap();
+    let damaged = files
+        .iter()
+        .find(|file| file["status"] == "damaged")
+        .unwrap();
+    assert_eq!(damaged["path"], compressed.to_str().unwrap());
+    let recovery = &damaged["recovery"];
+    assert_eq!(recovery["expected_size"], readings().len());
+    assert_eq!(recovery["lost_bytes"], BLOCK);
+    let regions = recovery["damaged"].as_array().unwrap();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0]["lost_bytes"], BLOCK);
+    assert!(regions[0]["resumed_at"].is_u64());
+    assert!(files
+        .iter()
+        .any(|file| file["status"] == "ok" && file.get("recovery").is_none()));
+}
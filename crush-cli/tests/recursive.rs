@@ -0,0 +1,139 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Create logs/app.log, logs/app.txt, logs/old/app.1.log and logs/cache/tmp.log
+fn create_log_tree(dir: &Path) -> PathBuf {
+    let root = dir.join("logs");
+    fs::create_dir_all(root.join("old")).unwrap();
+    fs::create_dir_all(root.join("cache")).unwrap();
+    for name in ["app.log", "app.txt", "old/app.1.log", "cache/tmp.log"] {
+        fs::write(root.join(name), format!("{name}: ").repeat(200)).unwrap();
+    }
+    root
+}
+
+/// Every regular file under the directory is compressed next to the original
+#[test]
+fn test_compress_recursive_in_place() {
+    let dir = test_dir();
+    let root = create_log_tree(dir.path());
+    // Already compressed and empty files are left alone
+    create_test_file(&root, "done.crush", b"not touched");
+    create_test_file(&root, "empty.log", b"");
+
+    crush_cmd()
+        .args(["compress", "-r"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 4 files, skipped 2"));
+
+    for name in ["app.log", "app.txt", "old/app.1.log", "cache/tmp.log"] {
+        assert_file_exists(&root.join(format!("{name}.crush")));
+    }
+    assert!(!root.join("done.crush.crush").exists());
+    assert!(!root.join("empty.log.crush").exists());
+}
+
+/// Include and exclude globs select files and prune directories
+#[test]
+fn test_compress_recursive_include_exclude() {
+    let dir = test_dir();
+    let root = create_log_tree(dir.path());
+
+    crush_cmd()
+        .args(["compress", "-r", "--include", "*.log", "--exclude", "cache"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 2 files, skipped 2"));
+
+    assert_file_exists(&root.join("app.log.crush"));
+    assert_file_exists(&root.join("old/app.1.log.crush"));
+    assert!(!root.join("app.txt.crush").exists());
+    assert!(!root.join("cache/tmp.log.crush").exists());
+}
+
+/// Decompressing a tree restores every `.crush` file in place
+#[test]
+fn test_decompress_recursive_roundtrip() {
+    let dir = test_dir();
+    let root = create_log_tree(dir.path());
+    let original = fs::read(root.join("old/app.1.log")).unwrap();
+
+    crush_cmd()
+        .args(["compress", "-r"])
+        .arg(&root)
+        .assert()
+        .success();
+    for name in ["app.log", "app.txt", "old/app.1.log", "cache/tmp.log"] {
+        fs::remove_file(root.join(name)).unwrap();
+    }
+
+    crush_cmd()
+        .args(["decompress", "--recursive"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Decompressed 4 files, skipped 0"));
+    assert_eq!(fs::read(root.join("old/app.1.log")).unwrap(), original);
+}
+
+/// Symlinks are skipped unless --follow-symlinks is given
+#[cfg(unix)]
+#[test]
+fn test_compress_recursive_symlinks() {
+    let dir = test_dir();
+    let root = dir.path().join("tree");
+    fs::create_dir(&root).unwrap();
+    let outside = dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+    create_test_file(&outside, "data.txt", b"linked data");
+    std::os::unix::fs::symlink(&outside, root.join("linked")).unwrap();
+
+    crush_cmd()
+        .args(["compress", "-r"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 0 files, skipped 1"));
+    assert!(!outside.join("data.txt.crush").exists());
+
+    crush_cmd()
+        .args(["compress", "-r", "--follow-symlinks"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compressed 1 file, skipped 0"));
+    assert_file_exists(&outside.join("data.txt.crush"));
+}
+
+/// Directories need --recursive, which writes in place only
+#[test]
+fn test_recursive_argument_validation() {
+    let dir = test_dir();
+    let root = create_log_tree(dir.path());
+
+    crush_cmd()
+        .arg("compress")
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--recursive"));
+
+    crush_cmd()
+        .args(["compress", "-r", "--stdout"])
+        .arg(&root)
+        .assert()
+        .failure();
+
+    crush_cmd()
+        .args(["decompress", "--include", "*.crush"])
+        .arg(&root)
+        .assert()
+        .failure();
+}
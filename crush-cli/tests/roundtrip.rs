@@ -87,3 +87,38 @@ fn test_roundtrip_large_random_file() -> std::io::Result<()> {
 
     Ok(())
 }
+
+/// Roundtrip test for a file spanning several compression blocks
+///
+/// Files are streamed through the compressor in fixed-size chunks, so this
+/// exercises block boundaries on both the compress and decompress paths.
+#[test]
+fn test_roundtrip_multi_block_file() -> std::io::Result<()> {
+    let dir = test_dir();
+    let content: Vec<u8> = (0..5 * 1024 * 1024 + 123)
+        .map(|i: usize| (i % 251) as u8)
+        .collect();
+    let original = create_test_file(dir.path(), "multi.bin", &content);
+    let compressed = dir.path().join("multi.bin.crush");
+    let decompressed = dir.path().join("multi.out");
+
+    crush_cmd()
+        .arg("compress")
+        .arg(&original)
+        .assert()
+        .success();
+
+    assert_compressed(&original, &compressed);
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .arg("-o")
+        .arg(&decompressed)
+        .assert()
+        .success();
+
+    assert_eq!(read_file(&decompressed), content);
+
+    Ok(())
+}
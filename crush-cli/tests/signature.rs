@@ -0,0 +1,183 @@
+mod common;
+
+use common::*;
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+fn artifact() -> Vec<u8> {
+    b"release 1.4.2 build artifact\n".repeat(4000)
+}
+
+/// Generate `NAME.key` and `NAME.pub` in `dir`
+fn keygen(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    crush_cmd()
+        .arg("keygen")
+        .arg(dir.join(name))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Public key:"));
+    (
+        dir.join(format!("{name}.key")),
+        dir.join(format!("{name}.pub")),
+    )
+}
+
+/// Compress the artifact and return the compressed path
+fn compressed_artifact(dir: &Path) -> PathBuf {
+    let input = create_test_file(dir, "app.tar", &artifact());
+    crush_cmd().arg("compress").arg(&input).assert().success();
+    std::fs::remove_file(&input).unwrap();
+    dir.join("app.tar.crush")
+}
+
+/// Embedded signatures verify, show in inspect and do not affect decompression
+#[test]
+fn test_embedded_sign_and_verify() {
+    let dir = test_dir();
+    let (secret, public) = keygen(dir.path(), "release");
+    let compressed = compressed_artifact(dir.path());
+
+    crush_cmd()
+        .args(["sign", "--key"])
+        .arg(&secret)
+        .arg(&compressed)
+        .assert()
+        .success();
+    assert!(!dir.path().join("app.tar.crush.sig").exists());
+
+    crush_cmd()
+        .args(["verify-signature", "--public-key"])
+        .arg(&public)
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("signature OK"));
+
+    let json = crush_cmd()
+        .args(["inspect", "--format", "json"])
+        .arg(&compressed)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    let public_hex = String::from_utf8(read_file(&public)).unwrap();
+    assert_eq!(json[0]["signature"]["valid"], true);
+    assert_eq!(json[0]["signature"]["detached"], false);
+    assert_eq!(json[0]["signature"]["public_key"], public_hex.trim());
+
+    crush_cmd()
+        .arg("decompress")
+        .arg(&compressed)
+        .assert()
+        .success();
+    assert_eq!(read_file(&dir.path().join("app.tar")), artifact());
+}
+
+/// Detached signatures go to FILE.sig and are found by verify and inspect
+#[test]
+fn test_detached_sign_and_verify() {
+    let dir = test_dir();
+    let (secret, public) = keygen(dir.path(), "release");
+    let compressed = compressed_artifact(dir.path());
+    let original = read_file(&compressed);
+
+    crush_cmd()
+        .args(["sign", "--detached", "--key"])
+        .arg(&secret)
+        .arg(&compressed)
+        .assert()
+        .success();
+    assert_eq!(read_file(&compressed), original);
+    assert_eq!(file_size(&dir.path().join("app.tar.crush.sig")), 100);
+
+    crush_cmd()
+        .args(["verify-signature", "--public-key"])
+        .arg(&public)
+        .arg(&compressed)
+        .assert()
+        .success();
+    crush_cmd()
+        .arg("inspect")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Signature: VALID"))
+        .stdout(predicate::str::contains("detached"));
+
+    // Signing again needs --force
+    crush_cmd()
+        .args(["sign", "--detached", "--key"])
+        .arg(&secret)
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(2);
+}
+
+/// Modified files, untrusted keys and unsigned files all fail verification
+#[test]
+fn test_verification_failures() {
+    let dir = test_dir();
+    let (secret, _) = keygen(dir.path(), "release");
+    let (_, other_public) = keygen(dir.path(), "other");
+    let compressed = compressed_artifact(dir.path());
+
+    crush_cmd()
+        .arg("verify-signature")
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("File is not signed"));
+
+    crush_cmd()
+        .args(["sign", "--key"])
+        .arg(&secret)
+        .arg(&compressed)
+        .assert()
+        .success();
+    crush_cmd()
+        .args(["verify-signature", "--public-key"])
+        .arg(&other_public)
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("untrusted key"));
+
+    let mut bytes = read_file(&compressed);
+    bytes[40] ^= 0x01;
+    std::fs::write(&compressed, bytes).unwrap();
+    crush_cmd()
+        .arg("verify-signature")
+        .arg(&compressed)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Signature verification failed"));
+    crush_cmd()
+        .arg("inspect")
+        .arg(&compressed)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Signature: INVALID"));
+}
+
+/// Key files are not overwritten by accident
+#[test]
+fn test_keygen_refuses_overwrite() {
+    let dir = test_dir();
+    let (secret, _) = keygen(dir.path(), "release");
+    let before = read_file(&secret);
+
+    crush_cmd()
+        .arg("keygen")
+        .arg(dir.path().join("release"))
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("already exists"));
+    assert_eq!(read_file(&secret), before);
+}
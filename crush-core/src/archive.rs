@@ -0,0 +1,563 @@
+//! Multi-file archive container
+//!
+//! An archive bundles files, directories and symlinks, each with its own
+//! [`FileMetadata`]. Every file is compressed independently into a complete
+//! Crush file, so the plugin is selected per entry (already-compressed files
+//! are stored) and each entry carries its own CRC32.
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 4    | archive magic ("CRAR")
+//! 4      | 1    | version (1)
+//! 5      | 3    | reserved (must be zero)
+//! 8      | ...  | entries
+//! ...    | 1    | end marker (0xFF)
+//! ```
+//!
+//! Each entry is:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 1    | kind (0 = file, 1 = directory, 2 = symlink)
+//! 1      | 2    | path_len (u16)
+//! 3      | n    | path (UTF-8, relative, '/'-separated)
+//! ...    | 2    | metadata_len (u16)
+//! ...    | m    | metadata (same TLV format as in a Crush file)
+//! ...    | 8    | data_len (u64)
+//! ...    | d    | data (a Crush file, a symlink target, or empty)
+//! ```
+//!
+//! Entry paths are checked with [`validate_entry_path`] both when writing and
+//! when reading, so a reader never yields an absolute path or one that
+//! escapes the extraction directory with `..`.
+
+use crate::compression::{compress_with_options, CompressionOptions};
+use crate::decompression::{decompress_with_options, DecompressionOptions};
+use crate::error::{CrushError, Result, ValidationError};
+use crate::plugin::registry::get_plugin_by_magic;
+use crate::plugin::{CrushHeader, FileMetadata};
+use serde::Serialize;
+use std::io::{self, Read, Write};
+
+/// Magic bytes opening an archive
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"CRAR";
+
+/// Current archive format version
+pub const ARCHIVE_VERSION: u8 = 1;
+
+/// Size of the fixed archive header
+const ARCHIVE_HEADER_SIZE: usize = 8;
+
+/// Kind byte marking the end of the entries
+const END_MARKER: u8 = 0xFF;
+
+/// Type of an archive entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// Regular file, stored as a Crush file
+    File,
+
+    /// Directory
+    Directory,
+
+    /// Symbolic link, stored as its target path
+    Symlink,
+}
+
+impl EntryKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::File => 0,
+            Self::Directory => 1,
+            Self::Symlink => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Self::File),
+            1 => Ok(Self::Directory),
+            2 => Ok(Self::Symlink),
+            other => Err(ValidationError::CorruptedData(format!(
+                "Unknown archive entry kind {other}"
+            ))
+            .into()),
+        }
+    }
+}
+
+/// One entry read from an archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveEntry {
+    /// Relative, '/'-separated path inside the archive
+    pub path: String,
+
+    /// Entry type
+    pub kind: EntryKind,
+
+    /// Modification time and permissions
+    pub metadata: FileMetadata,
+
+    /// Uncompressed size in bytes (zero for directories and symlinks)
+    pub size: u64,
+
+    /// Size of the entry's data in the archive
+    pub compressed_size: u64,
+
+    /// Plugin that compressed the file, if it is a file
+    pub plugin: Option<String>,
+
+    /// Link target, if it is a symlink
+    pub link_target: Option<String>,
+}
+
+/// Check that `path` is safe to extract below a destination directory
+///
+/// Paths must be relative, non-empty, and must not contain `..` components,
+/// backslashes, NUL bytes or a Windows drive prefix.
+///
+/// # Errors
+///
+/// Returns [`ValidationError::UnsafePath`] if the path is absolute or could
+/// escape the destination directory.
+pub fn validate_entry_path(path: &str) -> Result<()> {
+    let unsafe_path = |reason: &str| -> Result<()> {
+        Err(ValidationError::UnsafePath(format!("'{path}' {reason}")).into())
+    };
+
+    if path.is_empty() {
+        return unsafe_path("is empty");
+    }
+    if path.starts_with('/') {
+        return unsafe_path("is absolute");
+    }
+    if path.contains('\\') || path.contains('\0') {
+        return unsafe_path("contains a backslash or NUL byte");
+    }
+    if path
+        .split('/')
+        .next()
+        .is_some_and(|first| first.contains(':'))
+    {
+        return unsafe_path("has a drive prefix");
+    }
+    if path.split('/').any(|component| component == "..") {
+        return unsafe_path("contains a '..' component");
+    }
+    Ok(())
+}
+
+/// Writes an archive entry by entry
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::plugin::FileMetadata;
+/// use crush_core::{init_plugins, ArchiveReader, ArchiveWriter, CompressionOptions};
+///
+/// init_plugins().expect("Plugin initialization failed");
+///
+/// let mut writer = ArchiveWriter::new(Vec::new(), &CompressionOptions::default())
+///     .expect("Failed to create writer");
+/// writer.add_directory("docs", &FileMetadata::default()).expect("Add failed");
+/// writer
+///     .add_file("docs/readme.txt", b"Hello, archive!", &FileMetadata::default())
+///     .expect("Add failed");
+/// let archive = writer.finish().expect("Finish failed");
+///
+/// let mut reader = ArchiveReader::new(archive.as_slice()).expect("Invalid archive");
+/// let mut paths = Vec::new();
+/// while let Some(entry) = reader.next_entry().expect("Read failed") {
+///     paths.push(entry.path);
+/// }
+/// assert_eq!(paths, ["docs", "docs/readme.txt"]);
+/// ```
+pub struct ArchiveWriter<W: Write> {
+    inner: W,
+    options: CompressionOptions,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Start an archive on `inner`, compressing files with `options`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive header cannot be written.
+    pub fn new(mut inner: W, options: &CompressionOptions) -> Result<Self> {
+        let mut header = [0u8; ARCHIVE_HEADER_SIZE];
+        header[0..4].copy_from_slice(&ARCHIVE_MAGIC);
+        header[4] = ARCHIVE_VERSION;
+        inner.write_all(&header)?;
+
+        Ok(Self {
+            inner,
+            options: options.clone(),
+        })
+    }
+
+    /// Compress `data` and add it as a file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unsafe, compression fails (including
+    /// cancellation), or writing fails.
+    pub fn add_file(&mut self, path: &str, data: &[u8], metadata: &FileMetadata) -> Result<()> {
+        validate_entry_path(path)?;
+        let compressed = compress_with_options(data, &self.options)?;
+        self.write_entry(EntryKind::File, path, metadata, &compressed)
+    }
+
+    /// Add a directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unsafe or writing fails.
+    pub fn add_directory(&mut self, path: &str, metadata: &FileMetadata) -> Result<()> {
+        self.write_entry(EntryKind::Directory, path, metadata, &[])
+    }
+
+    /// Add a symlink pointing at `target`
+    ///
+    /// The target is stored verbatim; extraction must not follow it when
+    /// writing later entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unsafe, the target is empty or too
+    /// long, or writing fails.
+    pub fn add_symlink(&mut self, path: &str, target: &str, metadata: &FileMetadata) -> Result<()> {
+        if target.is_empty() || target.len() > usize::from(u16::MAX) {
+            return Err(ValidationError::InvalidHeader(format!(
+                "Symlink target for '{path}' must be 1 to {} bytes",
+                u16::MAX
+            ))
+            .into());
+        }
+        self.write_entry(EntryKind::Symlink, path, metadata, target.as_bytes())
+    }
+
+    /// Write the end marker and return the inner writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing or flushing fails.
+    pub fn finish(mut self) -> Result<W> {
+        self.inner.write_all(&[END_MARKER])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_entry(
+        &mut self,
+        kind: EntryKind,
+        path: &str,
+        metadata: &FileMetadata,
+        data: &[u8],
+    ) -> Result<()> {
+        validate_entry_path(path)?;
+        let path_len = u16::try_from(path.len()).map_err(|_| {
+            ValidationError::InvalidHeader(format!("Archive path too long: {path}"))
+        })?;
+        let metadata = metadata.to_bytes();
+        let metadata_len = u16::try_from(metadata.len())
+            .map_err(|_| ValidationError::InvalidMetadata("Metadata too large".to_string()))?;
+
+        self.inner.write_all(&[kind.to_byte()])?;
+        self.inner.write_all(&path_len.to_le_bytes())?;
+        self.inner.write_all(path.as_bytes())?;
+        self.inner.write_all(&metadata_len.to_le_bytes())?;
+        self.inner.write_all(&metadata)?;
+        self.inner.write_all(&(data.len() as u64).to_le_bytes())?;
+        self.inner.write_all(data)?;
+        Ok(())
+    }
+}
+
+/// Reads an archive entry by entry
+///
+/// Call [`ArchiveReader::next_entry`] until it returns `None`; after a file
+/// entry, [`ArchiveReader::read_data`] decompresses its contents. Data that is
+/// not read is skipped.
+pub struct ArchiveReader<R: Read> {
+    inner: R,
+    pending: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    /// Read and validate the archive header from `inner`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is not an archive of a supported version.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0u8; ARCHIVE_HEADER_SIZE];
+        read_exact(&mut inner, &mut header)?;
+
+        let magic: [u8; 4] = [header[0], header[1], header[2], header[3]];
+        if magic != ARCHIVE_MAGIC {
+            return Err(ValidationError::InvalidMagic(magic).into());
+        }
+        if header[4] != ARCHIVE_VERSION {
+            return Err(ValidationError::InvalidHeader(format!(
+                "Unsupported archive version {} (expected {ARCHIVE_VERSION})",
+                header[4]
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            inner,
+            pending: None,
+            finished: false,
+        })
+    }
+
+    /// Read the next entry, or `None` at the end of the archive
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is truncated or malformed, or if an
+    /// entry path is unsafe ([`ValidationError::UnsafePath`]).
+    pub fn next_entry(&mut self) -> Result<Option<ArchiveEntry>> {
+        self.pending = None;
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut kind = [0u8; 1];
+        read_exact(&mut self.inner, &mut kind)?;
+        if kind[0] == END_MARKER {
+            self.finished = true;
+            return Ok(None);
+        }
+        let kind = EntryKind::from_byte(kind[0])?;
+
+        let path = String::from_utf8(self.read_section()?).map_err(|_| {
+            ValidationError::CorruptedData("Archive path is not valid UTF-8".to_string())
+        })?;
+        validate_entry_path(&path)?;
+        let metadata = FileMetadata::from_bytes(&self.read_section()?)?;
+
+        let mut data_len = [0u8; 8];
+        read_exact(&mut self.inner, &mut data_len)?;
+        let data_len = u64::from_le_bytes(data_len);
+        let max_len = match kind {
+            EntryKind::File => u64::MAX,
+            EntryKind::Directory => 0,
+            EntryKind::Symlink => u64::from(u16::MAX),
+        };
+        if data_len > max_len {
+            return Err(ValidationError::CorruptedData(format!(
+                "Archive entry '{path}' claims {data_len} bytes of data"
+            ))
+            .into());
+        }
+
+        // Read through `take` so a forged length cannot force a large allocation
+        let mut data = Vec::new();
+        (&mut self.inner).take(data_len).read_to_end(&mut data)?;
+        if data.len() as u64 != data_len {
+            return Err(truncated());
+        }
+
+        let mut entry = ArchiveEntry {
+            path,
+            kind,
+            metadata,
+            size: 0,
+            compressed_size: data_len,
+            plugin: None,
+            link_target: None,
+        };
+        match kind {
+            EntryKind::File => {
+                let header = data
+                    .get(..CrushHeader::SIZE)
+                    .and_then(|bytes| <[u8; CrushHeader::SIZE]>::try_from(bytes).ok())
+                    .ok_or_else(|| {
+                        ValidationError::CorruptedData(format!(
+                            "Archive entry '{}' is too short",
+                            entry.path
+                        ))
+                    })?;
+                let header = CrushHeader::from_bytes(&header)?;
+                entry.size = header.original_size;
+                entry.plugin = Some(
+                    get_plugin_by_magic(header.magic)
+                        .map_or("unknown", |plugin| plugin.name())
+                        .to_string(),
+                );
+                self.pending = Some(data);
+            }
+            EntryKind::Symlink => {
+                entry.link_target = Some(String::from_utf8(data).map_err(|_| {
+                    ValidationError::CorruptedData("Symlink target is not valid UTF-8".to_string())
+                })?);
+            }
+            EntryKind::Directory => {}
+        }
+
+        Ok(Some(entry))
+    }
+
+    /// Decompress the contents of the file entry last returned by
+    /// [`ArchiveReader::next_entry`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the last entry was not a file, its data was already
+    /// read, or decompression fails or exceeds the limits in `options`.
+    pub fn read_data(&mut self, options: &DecompressionOptions) -> Result<Vec<u8>> {
+        let data = self.pending.take().ok_or_else(|| {
+            CrushError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No file entry data to read",
+            ))
+        })?;
+        Ok(decompress_with_options(&data, options)?.data)
+    }
+
+    /// Read a section prefixed by its u16 length
+    fn read_section(&mut self) -> Result<Vec<u8>> {
+        let mut len = [0u8; 2];
+        read_exact(&mut self.inner, &mut len)?;
+        let mut section = vec![0u8; usize::from(u16::from_le_bytes(len))];
+        read_exact(&mut self.inner, &mut section)?;
+        Ok(section)
+    }
+}
+
+/// Error for an archive that ends early
+fn truncated() -> CrushError {
+    ValidationError::CorruptedData("Truncated archive".to_string()).into()
+}
+
+/// `read_exact` that reports a short read as a truncated archive
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            truncated()
+        } else {
+            e.into()
+        }
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::init_plugins;
+
+    #[test]
+    fn test_validate_entry_path() {
+        for path in ["a", "a/b.txt", "dir/sub/file", ".hidden", "a/./b", "x..y"] {
+            assert!(validate_entry_path(path).is_ok(), "{path}");
+        }
+        for path in [
+            "",
+            "/etc/passwd",
+            "../x",
+            "a/../../x",
+            "a/..",
+            "C:/x",
+            "c:x",
+            "a\\..\\x",
+            "a\0b",
+        ] {
+            assert!(
+                matches!(
+                    validate_entry_path(path),
+                    Err(CrushError::Validation(ValidationError::UnsafePath(_)))
+                ),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        init_plugins().unwrap();
+        let metadata = FileMetadata {
+            mtime: Some(1_700_000_000),
+            #[cfg(unix)]
+            permissions: Some(0o100_644),
+        };
+
+        let mut writer = ArchiveWriter::new(Vec::new(), &CompressionOptions::default()).unwrap();
+        writer
+            .add_directory("dir", &FileMetadata::default())
+            .unwrap();
+        writer
+            .add_file("dir/a.txt", &b"hello ".repeat(1000), &metadata)
+            .unwrap();
+        writer
+            .add_file("empty", b"", &FileMetadata::default())
+            .unwrap();
+        writer
+            .add_symlink("link", "dir/a.txt", &FileMetadata::default())
+            .unwrap();
+        let archive = writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::new(archive.as_slice()).unwrap();
+        let dir = reader.next_entry().unwrap().unwrap();
+        assert_eq!((dir.path.as_str(), dir.kind), ("dir", EntryKind::Directory));
+        assert!(reader.read_data(&DecompressionOptions::default()).is_err());
+
+        let file = reader.next_entry().unwrap().unwrap();
+        assert_eq!(file.kind, EntryKind::File);
+        assert_eq!(file.metadata, metadata);
+        assert_eq!(file.size, 6000);
+        assert!(file.compressed_size < 6000);
+        assert!(file.plugin.is_some());
+        let data = reader.read_data(&DecompressionOptions::default()).unwrap();
+        assert_eq!(data, b"hello ".repeat(1000));
+
+        // Unread data is skipped
+        let empty = reader.next_entry().unwrap().unwrap();
+        assert_eq!((empty.path.as_str(), empty.size), ("empty", 0));
+
+        let link = reader.next_entry().unwrap().unwrap();
+        assert_eq!(link.kind, EntryKind::Symlink);
+        assert_eq!(link.link_target.as_deref(), Some("dir/a.txt"));
+
+        assert!(reader.next_entry().unwrap().is_none());
+        assert!(reader.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_writer_rejects_unsafe_paths() {
+        init_plugins().unwrap();
+        let mut writer = ArchiveWriter::new(Vec::new(), &CompressionOptions::default()).unwrap();
+        assert!(writer
+            .add_file("../escape", b"data", &FileMetadata::default())
+            .is_err());
+        assert!(writer
+            .add_directory("/abs", &FileMetadata::default())
+            .is_err());
+        assert!(writer
+            .add_symlink("link", "", &FileMetadata::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_reader_rejects_malformed_archives() {
+        assert!(ArchiveReader::new(&b"CRA"[..]).is_err());
+        assert!(ArchiveReader::new(&b"CRIX\x01\0\0\0"[..]).is_err());
+        assert!(ArchiveReader::new(&b"CRAR\x02\0\0\0"[..]).is_err());
+
+        // Missing end marker
+        let mut reader = ArchiveReader::new(&b"CRAR\x01\0\0\0"[..]).unwrap();
+        assert!(reader.next_entry().is_err());
+
+        // Directory entry claiming data
+        let mut archive = b"CRAR\x01\0\0\0\x01\x01\0d\0\0".to_vec();
+        archive.extend_from_slice(&5u64.to_le_bytes());
+        let mut reader = ArchiveReader::new(archive.as_slice()).unwrap();
+        assert!(reader.next_entry().is_err());
+    }
+}
@@ -0,0 +1,610 @@
+//! Multi-block container layout
+//!
+//! Large inputs are split into fixed-size blocks that are compressed
+//! independently, which lets both compression and decompression run on the
+//! rayon thread pool. The block section follows the optional metadata in a
+//! Crush file whose header has the `HAS_BLOCKS` flag set:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 4    | block_size (u32, nominal uncompressed block size)
+//! 4      | ...  | block frames
+//! ...    | 12   | end marker (all-zero frame header)
+//! ```
+//!
+//! Each block frame is:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 4    | uncompressed_len (u32, never zero)
+//! 4      | 4    | compressed_len (u32)
+//! 8      | 4    | crc32 (u32, CRC32 of the compressed bytes)
+//! 12     | n    | compressed bytes
+//! ```
+//!
+//! With the `PARITY` header extension flag, a Reed-Solomon parity record
+//! precedes every frame, the end marker included (see [`crate::parity`]).
+//!
+//! Blocks that do not shrink are stored uncompressed instead, with
+//! `compressed_len` equal to `uncompressed_len`. Writers mark this with the
+//! `STORED_BLOCKS` header flag; without it, every frame is decompressed.
+//!
+//! Output written incrementally by [`crate::CrushWriter`] sets the `STREAMED`
+//! header flag, leaves `original_size` at zero, and appends a 12-byte
+//! trailer after the end marker:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 8    | original_size (u64, total uncompressed size)
+//! 8      | 4    | crc32 (u32, CRC32 of everything between header and trailer)
+//! ```
+
+use crate::error::{PluginError, Result, ValidationError};
+use crate::parity;
+use crate::plugin::{CompressionAlgorithm, PluginParams};
+use crc32fast::Hasher;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Default uncompressed block size (1 megabyte)
+pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Smallest accepted block size (4 kilobytes)
+pub const MIN_BLOCK_SIZE: usize = 4 * 1024;
+
+/// Largest accepted block size (256 megabytes)
+pub const MAX_BLOCK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Size of the trailer that follows the end marker in streamed output
+pub const STREAM_TRAILER_SIZE: usize = 12;
+
+/// Header of a single block frame (12 bytes, little-endian)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockFrame {
+    /// Number of uncompressed bytes in this block
+    pub uncompressed_len: u32,
+
+    /// Number of compressed bytes following the frame header
+    pub compressed_len: u32,
+
+    /// CRC32 of the compressed bytes
+    pub crc32: u32,
+}
+
+impl BlockFrame {
+    /// Size of a serialized frame header in bytes
+    pub const SIZE: usize = 12;
+
+    /// Frame header that terminates the block section
+    pub const END: Self = Self {
+        uncompressed_len: 0,
+        compressed_len: 0,
+        crc32: 0,
+    };
+
+    /// Check if this frame header is the end-of-blocks marker
+    #[must_use]
+    pub fn is_end(&self) -> bool {
+        self.uncompressed_len == 0
+    }
+
+    /// Check if this frame holds its block uncompressed
+    ///
+    /// Only meaningful when the header has the `STORED_BLOCKS` flag.
+    #[must_use]
+    pub fn is_stored(&self) -> bool {
+        self.compressed_len == self.uncompressed_len
+    }
+
+    /// Serialize frame header to bytes (little-endian)
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..4].copy_from_slice(&self.uncompressed_len.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.compressed_len.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.crc32.to_le_bytes());
+        bytes
+    }
+
+    /// Deserialize frame header from bytes
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+        Self {
+            uncompressed_len: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            compressed_len: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            crc32: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+        }
+    }
+}
+
+/// A block frame located inside a block section
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlockEntry<'a> {
+    pub frame: BlockFrame,
+    pub data: &'a [u8],
+}
+
+/// Validate a requested block size
+pub(crate) fn validate_block_size(block_size: usize) -> Result<()> {
+    if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) {
+        return Err(ValidationError::InvalidBlockSize(format!(
+            "{block_size} bytes (must be between {MIN_BLOCK_SIZE} and {MAX_BLOCK_SIZE})"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Compress a single block and return its frame header plus compressed bytes
+///
+/// A block that does not shrink is returned as is, as a stored frame (the
+/// output's header must set the `STORED_BLOCKS` flag).
+pub(crate) fn compress_block(
+    plugin: &dyn CompressionAlgorithm,
+    block: &[u8],
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<(BlockFrame, Vec<u8>)> {
+    let mut compressed = plugin.compress(block, cancel_flag)?;
+    if compressed.len() >= block.len() {
+        compressed = block.to_vec();
+    }
+
+    let uncompressed_len = u32::try_from(block.len())
+        .map_err(|_| ValidationError::InvalidBlockSize(format!("{} bytes", block.len())))?;
+    let compressed_len = u32::try_from(compressed.len()).map_err(|_| {
+        PluginError::OperationFailed(format!(
+            "Compressed block too large: {} bytes",
+            compressed.len()
+        ))
+    })?;
+
+    let mut hasher = Hasher::new();
+    hasher.update(&compressed);
+
+    let frame = BlockFrame {
+        uncompressed_len,
+        compressed_len,
+        crc32: hasher.finalize(),
+    };
+    Ok((frame, compressed))
+}
+
+/// Decompress a single block, verifying its CRC32 and uncompressed length
+///
+/// Decoding aborts once the output outgrows the frame's `uncompressed_len`.
+///
+/// `stored_blocks` is the header's `STORED_BLOCKS` flag; when set, stored
+/// frames are copied instead of decompressed.
+pub(crate) fn decompress_block(
+    plugin: &dyn CompressionAlgorithm,
+    entry: &BlockEntry<'_>,
+    stored_blocks: bool,
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<Vec<u8>> {
+    let mut hasher = Hasher::new();
+    hasher.update(entry.data);
+    let computed_crc = hasher.finalize();
+
+    if computed_crc != entry.frame.crc32 {
+        return Err(ValidationError::CrcMismatch {
+            expected: entry.frame.crc32,
+            actual: computed_crc,
+        }
+        .into());
+    }
+
+    if stored_blocks && entry.frame.is_stored() {
+        return Ok(entry.data.to_vec());
+    }
+
+    let declared = entry.frame.uncompressed_len as usize;
+    if declared > MAX_BLOCK_SIZE {
+        return Err(ValidationError::CorruptedData(format!(
+            "Block frame claims {declared} uncompressed bytes"
+        ))
+        .into());
+    }
+    let decompressed =
+        plugin.decompress_with_limit(entry.data, &PluginParams::new(), declared, cancel_flag)?;
+
+    if decompressed.len() != entry.frame.uncompressed_len as usize {
+        return Err(ValidationError::CorruptedData(format!(
+            "Block size mismatch: frame says {} bytes, got {} bytes",
+            entry.frame.uncompressed_len,
+            decompressed.len()
+        ))
+        .into());
+    }
+
+    Ok(decompressed)
+}
+
+/// Compress a block and compute its parity record, if `parity_shards` is set
+pub(crate) fn compress_block_with_parity(
+    plugin: &dyn CompressionAlgorithm,
+    block: &[u8],
+    parity_shards: Option<u8>,
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<(BlockFrame, Vec<u8>, Option<Vec<u8>>)> {
+    let (frame, data) = compress_block(plugin, block, cancel_flag)?;
+    let record = parity_shards
+        .map(|shards| parity::encode_record(&frame, &data, shards))
+        .transpose()?;
+    Ok((frame, data, record))
+}
+
+/// Compress `input` into a block section using the rayon thread pool
+///
+/// Blocks are compressed in parallel and written in input order, each
+/// preceded by its parity record if `parity_shards` is set. The
+/// `cancel_flag` is shared by every block so a timeout or Ctrl+C stops all
+/// workers.
+pub(crate) fn compress_blocks(
+    plugin: &dyn CompressionAlgorithm,
+    input: &[u8],
+    block_size: usize,
+    parity_shards: Option<u8>,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<Vec<u8>> {
+    validate_block_size(block_size)?;
+
+    let blocks: Vec<(BlockFrame, Vec<u8>, Option<Vec<u8>>)> = input
+        .par_chunks(block_size)
+        .map(|block| {
+            if cancel_flag.load(Ordering::Acquire) {
+                return Err(PluginError::Cancelled.into());
+            }
+            compress_block_with_parity(plugin, block, parity_shards, Arc::clone(cancel_flag))
+        })
+        .collect::<Result<_>>()?;
+
+    let body_len: usize = blocks
+        .iter()
+        .map(|(_, data, record)| {
+            BlockFrame::SIZE + data.len() + record.as_ref().map_or(0, Vec::len)
+        })
+        .sum();
+    let mut output = Vec::with_capacity(4 + body_len + BlockFrame::SIZE);
+
+    #[allow(clippy::cast_possible_truncation)]
+    let block_size_u32 = block_size as u32; // validated <= MAX_BLOCK_SIZE
+    output.extend_from_slice(&block_size_u32.to_le_bytes());
+    for (frame, data, record) in &blocks {
+        if let Some(record) = record {
+            output.extend_from_slice(record);
+        }
+        output.extend_from_slice(&frame.to_bytes());
+        output.extend_from_slice(data);
+    }
+    if let Some(shards) = parity_shards {
+        output.extend_from_slice(&parity::encode_record(&BlockFrame::END, &[], shards)?);
+    }
+    output.extend_from_slice(&BlockFrame::END.to_bytes());
+
+    Ok(output)
+}
+
+/// Parse a block section into its nominal block size and block entries
+///
+/// Returns the entries plus the number of bytes consumed (including the end
+/// marker), so callers can locate any trailer that follows the section.
+/// With `parity`, the parity record before each frame is skipped; it is not
+/// checked, see [`crate::parity`] for repairing damaged blocks.
+pub(crate) fn parse_blocks(
+    section: &[u8],
+    parity: bool,
+) -> Result<(u32, Vec<BlockEntry<'_>>, usize)> {
+    if section.len() < 4 {
+        return Err(ValidationError::InvalidHeader(
+            "Truncated: blocks flag set but no block size".to_string(),
+        )
+        .into());
+    }
+    let block_size = u32::from_le_bytes([section[0], section[1], section[2], section[3]]);
+
+    let mut entries = Vec::new();
+    let mut pos = 4;
+    loop {
+        let protected_len = if parity {
+            let (record_len, protected_len) = parity::skip_record(&section[pos..])?;
+            pos += record_len;
+            Some(protected_len)
+        } else {
+            None
+        };
+        let frame_bytes: [u8; BlockFrame::SIZE] = section
+            .get(pos..pos + BlockFrame::SIZE)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ValidationError::CorruptedData(format!(
+                    "Truncated block frame header at offset {pos}"
+                ))
+            })?;
+        let frame = BlockFrame::from_bytes(&frame_bytes);
+        pos += BlockFrame::SIZE;
+
+        if protected_len.is_some_and(|len| len != BlockFrame::SIZE + frame.compressed_len as usize)
+        {
+            return Err(ValidationError::CorruptedData(format!(
+                "Parity record of block {} does not match its frame",
+                entries.len()
+            ))
+            .into());
+        }
+        if frame.is_end() {
+            break;
+        }
+
+        let data_end = pos + frame.compressed_len as usize;
+        let data = section.get(pos..data_end).ok_or_else(|| {
+            ValidationError::CorruptedData(format!(
+                "Truncated block {}: expected {} compressed bytes",
+                entries.len(),
+                frame.compressed_len
+            ))
+        })?;
+        entries.push(BlockEntry { frame, data });
+        pos = data_end;
+    }
+
+    Ok((block_size, entries, pos))
+}
+
+/// Trailer that follows the end marker in streamed output
+///
+/// Streamed output cannot carry its checksum or size in the header (neither
+/// is known until the input ends), so both are written after the blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StreamTrailer {
+    /// Total uncompressed size
+    pub(crate) original_size: u64,
+    /// CRC32 of everything between the header and the trailer
+    pub(crate) crc32: u32,
+}
+
+impl StreamTrailer {
+    /// Serialize the trailer to little-endian bytes
+    pub(crate) fn to_bytes(self) -> [u8; STREAM_TRAILER_SIZE] {
+        let mut bytes = [0u8; STREAM_TRAILER_SIZE];
+        bytes[0..8].copy_from_slice(&self.original_size.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.crc32.to_le_bytes());
+        bytes
+    }
+
+    /// Deserialize a trailer from little-endian bytes
+    pub(crate) fn from_bytes(bytes: &[u8; STREAM_TRAILER_SIZE]) -> Self {
+        Self {
+            original_size: u64::from_le_bytes([
+                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+            ]),
+            crc32: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+        }
+    }
+
+    /// Verify the trailer's CRC32 against the bytes it covers
+    pub(crate) fn verify(self, content: &[u8]) -> Result<()> {
+        let mut hasher = Hasher::new();
+        hasher.update(content);
+        let actual = hasher.finalize();
+        if actual == self.crc32 {
+            Ok(())
+        } else {
+            Err(ValidationError::CrcMismatch {
+                expected: self.crc32,
+                actual,
+            }
+            .into())
+        }
+    }
+}
+
+/// Split the body of streamed output (everything after the header) into the
+/// content covered by the trailer and the trailer itself
+pub(crate) fn split_stream_trailer(body: &[u8]) -> Result<(&[u8], StreamTrailer)> {
+    let content_len = body.len().checked_sub(STREAM_TRAILER_SIZE).ok_or_else(|| {
+        ValidationError::CorruptedData(format!(
+            "Truncated stream: {} bytes is too short for the {STREAM_TRAILER_SIZE}-byte trailer",
+            body.len()
+        ))
+    })?;
+    let (content, trailer) = body.split_at(content_len);
+    let trailer: [u8; STREAM_TRAILER_SIZE] = trailer
+        .try_into()
+        .map_err(|_| ValidationError::CorruptedData("Invalid stream trailer".to_string()))?;
+    Ok((content, StreamTrailer::from_bytes(&trailer)))
+}
+
+/// Decompress a parsed block section in parallel and concatenate the output
+pub(crate) fn decompress_blocks(
+    plugin: &dyn CompressionAlgorithm,
+    entries: &[BlockEntry<'_>],
+    stored_blocks: bool,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<Vec<u8>> {
+    let blocks: Vec<Vec<u8>> = entries
+        .par_iter()
+        .map(|entry| {
+            if cancel_flag.load(Ordering::Acquire) {
+                return Err(PluginError::Cancelled.into());
+            }
+            decompress_block(plugin, entry, stored_blocks, Arc::clone(cancel_flag))
+        })
+        .collect::<Result<_>>()?;
+
+    let total: usize = blocks.iter().map(Vec::len).sum();
+    let mut output = Vec::with_capacity(total);
+    for block in blocks {
+        output.extend_from_slice(&block);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::plugin::default::DeflatePlugin;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frame = BlockFrame {
+            uncompressed_len: 1024,
+            compressed_len: 512,
+            crc32: 0xDEAD_BEEF,
+        };
+        assert_eq!(BlockFrame::from_bytes(&frame.to_bytes()), frame);
+        assert!(!frame.is_end());
+        assert!(BlockFrame::END.is_end());
+    }
+
+    #[test]
+    fn test_validate_block_size() {
+        assert!(validate_block_size(DEFAULT_BLOCK_SIZE).is_ok());
+        assert!(validate_block_size(MIN_BLOCK_SIZE).is_ok());
+        assert!(validate_block_size(0).is_err());
+        assert!(validate_block_size(MAX_BLOCK_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_blocks_roundtrip() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        #[allow(clippy::cast_possible_truncation)]
+        let input: Vec<u8> = (0..100_000_u32).map(|i| (i % 251) as u8).collect();
+
+        let section =
+            compress_blocks(&DeflatePlugin, &input, MIN_BLOCK_SIZE, None, &cancel_flag).unwrap();
+        let (block_size, entries, consumed) = parse_blocks(&section, false).unwrap();
+
+        assert_eq!(block_size as usize, MIN_BLOCK_SIZE);
+        assert_eq!(entries.len(), input.len().div_ceil(MIN_BLOCK_SIZE));
+        assert_eq!(consumed, section.len());
+
+        let output = decompress_blocks(&DeflatePlugin, &entries, true, &cancel_flag).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_blocks_with_parity_roundtrip() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        #[allow(clippy::cast_possible_truncation)]
+        let input: Vec<u8> = (0..50_000_u32).map(|i| (i % 199) as u8).collect();
+
+        let plain =
+            compress_blocks(&DeflatePlugin, &input, MIN_BLOCK_SIZE, None, &cancel_flag).unwrap();
+        let section = compress_blocks(
+            &DeflatePlugin,
+            &input,
+            MIN_BLOCK_SIZE,
+            Some(2),
+            &cancel_flag,
+        )
+        .unwrap();
+        assert!(section.len() > plain.len());
+        assert!(parse_blocks(&section, false).is_err());
+
+        let (_, entries, consumed) = parse_blocks(&section, true).unwrap();
+        assert_eq!(entries.len(), input.len().div_ceil(MIN_BLOCK_SIZE));
+        assert_eq!(consumed, section.len());
+        let output = decompress_blocks(&DeflatePlugin, &entries, true, &cancel_flag).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_blocks_incompressible_stored() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let mut seed = 0x9E37_79B9_u32;
+        let mut input: Vec<u8> = (0..2 * MIN_BLOCK_SIZE)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                seed.to_le_bytes()[3]
+            })
+            .collect();
+        input.extend_from_slice(&[0x42; MIN_BLOCK_SIZE]);
+
+        let section =
+            compress_blocks(&DeflatePlugin, &input, MIN_BLOCK_SIZE, None, &cancel_flag).unwrap();
+        let (_, entries, _) = parse_blocks(&section, false).unwrap();
+
+        // Random blocks are stored as is; the repetitive block is compressed
+        assert!(entries[0].frame.is_stored() && entries[1].frame.is_stored());
+        assert_eq!(entries[0].data, &input[..MIN_BLOCK_SIZE]);
+        assert!(!entries[2].frame.is_stored());
+        assert!(section.len() <= input.len() + 4 + 4 * BlockFrame::SIZE);
+
+        let output = decompress_blocks(&DeflatePlugin, &entries, true, &cancel_flag).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_blocks_empty_input() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let section =
+            compress_blocks(&DeflatePlugin, &[], MIN_BLOCK_SIZE, None, &cancel_flag).unwrap();
+        let (_, entries, _) = parse_blocks(&section, false).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_blocks_corrupted_block_detected() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let input = vec![0x42u8; 3 * MIN_BLOCK_SIZE];
+        let mut section =
+            compress_blocks(&DeflatePlugin, &input, MIN_BLOCK_SIZE, None, &cancel_flag).unwrap();
+
+        // Flip a byte inside the first block's compressed data
+        section[4 + BlockFrame::SIZE] ^= 0xFF;
+
+        let (_, entries, _) = parse_blocks(&section, false).unwrap();
+        let result = decompress_blocks(&DeflatePlugin, &entries, true, &cancel_flag);
+        assert!(matches!(
+            result,
+            Err(crate::error::CrushError::Validation(
+                ValidationError::CrcMismatch { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_blocks_truncated_section() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let input = vec![0x42u8; 2 * MIN_BLOCK_SIZE];
+        let section =
+            compress_blocks(&DeflatePlugin, &input, MIN_BLOCK_SIZE, None, &cancel_flag).unwrap();
+
+        let truncated = &section[..section.len() - BlockFrame::SIZE - 1];
+        assert!(parse_blocks(truncated, false).is_err());
+    }
+
+    #[test]
+    fn test_stream_trailer_roundtrip() {
+        let content = b"block section bytes";
+        let mut hasher = Hasher::new();
+        hasher.update(content);
+        let trailer = StreamTrailer {
+            original_size: 12_345,
+            crc32: hasher.finalize(),
+        };
+
+        let mut body = content.to_vec();
+        body.extend_from_slice(&trailer.to_bytes());
+        let (parsed_content, parsed) = split_stream_trailer(&body).unwrap();
+
+        assert_eq!(parsed_content, content);
+        assert_eq!(parsed, trailer);
+        assert!(parsed.verify(parsed_content).is_ok());
+        assert!(parsed.verify(b"other bytes").is_err());
+        assert!(split_stream_trailer(&body[..STREAM_TRAILER_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn test_blocks_cancelled() {
+        let cancel_flag = Arc::new(AtomicBool::new(true));
+        let input = vec![0x42u8; 2 * MIN_BLOCK_SIZE];
+        let result = compress_blocks(&DeflatePlugin, &input, MIN_BLOCK_SIZE, None, &cancel_flag);
+        assert!(result.is_err());
+    }
+}
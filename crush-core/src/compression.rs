@@ -2,38 +2,75 @@
 //!
 //! Provides the public `compress()` API that compresses data using the default
 //! DEFLATE plugin and wraps it with a Crush header.
+//!
+//! Inputs larger than the configured block size are split into independently
+//! compressed blocks that run in parallel on the rayon thread pool (see
+//! [`crate::block`]). Smaller inputs use the single-stream layout.
+//!
+//! Output never grows by more than the container framing: if the selected
+//! plugin does not shrink the input, it is written with the stored plugin
+//! instead (see [`crate::plugin::stored`]).
 
+use crate::block::{self, DEFAULT_BLOCK_SIZE};
 use crate::cancel::CancellationToken;
-use crate::error::Result;
+use crate::encryption::{self, EncryptionKey};
+use crate::error::{EncryptionError, Result};
+use crate::hash::HashAlgorithm;
+use crate::index::BlockIndex;
+use crate::parity;
+use crate::plugin::params::ConfiguredPlugin;
 use crate::plugin::registry::{get_default_plugin, get_plugin_by_magic};
+use crate::plugin::stored::STORED_MAGIC;
 use crate::plugin::{
-    run_with_timeout, run_with_timeout_and_cancel, CrushHeader, FileMetadata, PluginSelector,
-    ScoringWeights,
+    run_with_timeout, run_with_timeout_and_cancel, CompressionAlgorithm, CrushHeader, FileMetadata,
+    PluginMetadata, PluginParams, PluginSelector, SamplingConfig, ScoringWeights,
 };
 use crc32fast::Hasher;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Duration;
 
-/// Default timeout for compression operations (0 = no timeout)
+/// Default timeout for compression and decompression operations (0 = no timeout)
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(0);
 
 /// Compression options for plugin selection and scoring
 #[derive(Clone)]
 pub struct CompressionOptions {
     /// Optional plugin name for manual override
-    plugin_name: Option<String>,
+    pub(crate) plugin_name: Option<String>,
 
     /// Scoring weights for automatic selection
-    weights: ScoringWeights,
+    pub(crate) weights: ScoringWeights,
 
     /// Timeout for compression operation
-    timeout: Duration,
+    pub(crate) timeout: Duration,
 
     /// Optional file metadata
-    file_metadata: Option<FileMetadata>,
+    pub(crate) file_metadata: Option<FileMetadata>,
 
     /// Optional cancellation token for Ctrl+C support
-    cancel_token: Option<Arc<dyn CancellationToken>>,
+    pub(crate) cancel_token: Option<Arc<dyn CancellationToken>>,
+
+    /// Uncompressed block size for parallel multi-block compression
+    pub(crate) block_size: usize,
+
+    /// Append a block index for random-access reads
+    pub(crate) seekable: bool,
+
+    /// Plugin-specific parameters, validated against the selected plugin
+    pub(crate) plugin_params: PluginParams,
+
+    /// Empirical selection by trial-compressing input samples
+    pub(crate) sampling: Option<SamplingConfig>,
+
+    /// Key to encrypt the output with
+    pub(crate) encryption: Option<EncryptionKey>,
+
+    /// Hash of the uncompressed data to record in the output
+    pub(crate) content_hash: Option<HashAlgorithm>,
+
+    /// Reed-Solomon parity shards to store with every block
+    pub(crate) parity: Option<u8>,
 }
 
 impl CompressionOptions {
@@ -46,6 +83,13 @@ impl CompressionOptions {
             timeout: DEFAULT_TIMEOUT,
             file_metadata: None,
             cancel_token: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+            seekable: false,
+            plugin_params: PluginParams::new(),
+            sampling: None,
+            encryption: None,
+            content_hash: None,
+            parity: None,
         }
     }
 
@@ -83,6 +127,91 @@ impl CompressionOptions {
         self.cancel_token = Some(token);
         self
     }
+
+    /// Set the uncompressed block size for parallel compression
+    ///
+    /// Inputs larger than this are split into blocks of this size that are
+    /// compressed concurrently. Must be between [`block::MIN_BLOCK_SIZE`] and
+    /// [`block::MAX_BLOCK_SIZE`]; invalid sizes are rejected at compression time.
+    #[must_use]
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Append a block index so byte ranges can be read without decoding the
+    /// whole file (see [`crate::read_range`])
+    ///
+    /// Seekable output always uses the multi-block layout, even for inputs
+    /// smaller than one block.
+    #[must_use]
+    pub fn with_seekable(mut self, seekable: bool) -> Self {
+        self.seekable = seekable;
+        self
+    }
+
+    /// Set a plugin-specific parameter (e.g. `level` or `window_log`)
+    ///
+    /// Parameters are validated against the selected plugin's
+    /// [`CompressionAlgorithm::parameters`] at compression time and recorded
+    /// in the output, so decompression needs no extra options.
+    #[must_use]
+    pub fn with_plugin_param(mut self, name: &str, value: i64) -> Self {
+        self.plugin_params.set(name, value);
+        self
+    }
+
+    /// Replace all plugin-specific parameters
+    #[must_use]
+    pub fn with_plugin_params(mut self, params: PluginParams) -> Self {
+        self.plugin_params = params;
+        self
+    }
+
+    /// Select the plugin by trial-compressing samples of the input
+    ///
+    /// Automatic selection then scores the ratio and throughput measured on
+    /// the samples instead of each plugin's static metadata (see
+    /// [`PluginSelector::select_sampled`]). Streaming compression does not
+    /// see its input up front and samples only the first block.
+    #[must_use]
+    pub fn with_sampling(mut self, config: SamplingConfig) -> Self {
+        self.sampling = Some(config);
+        self
+    }
+
+    /// Encrypt the output with ChaCha20-Poly1305 (see [`crate::encryption`])
+    ///
+    /// Everything after the header is encrypted and authenticated, so
+    /// decompression then needs the same key. Encrypted output cannot be
+    /// seekable.
+    #[must_use]
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.encryption = Some(key);
+        self
+    }
+
+    /// Record a hash of the uncompressed data (see [`crate::hash`])
+    ///
+    /// Decompression verifies the output against it, and [`crate::inspect`]
+    /// reports it without decompressing.
+    #[must_use]
+    pub fn with_content_hash(mut self, algorithm: HashAlgorithm) -> Self {
+        self.content_hash = Some(algorithm);
+        self
+    }
+
+    /// Store `parity_shards` Reed-Solomon parity shards with every block
+    /// (see [`crate::parity`])
+    ///
+    /// Decompression then repairs up to that many damaged bytes per block.
+    /// Parity always uses the multi-block layout and cannot be combined with
+    /// encryption. Valid counts are 1 to [`crate::parity::MAX_PARITY_SHARDS`].
+    #[must_use]
+    pub fn with_parity(mut self, parity_shards: u8) -> Self {
+        self.parity = Some(parity_shards);
+        self
+    }
 }
 
 impl std::fmt::Debug for CompressionOptions {
@@ -96,6 +225,13 @@ impl std::fmt::Debug for CompressionOptions {
                 "cancel_token",
                 &self.cancel_token.as_ref().map(|_| "Some(...)"),
             )
+            .field("block_size", &self.block_size)
+            .field("seekable", &self.seekable)
+            .field("plugin_params", &self.plugin_params)
+            .field("sampling", &self.sampling)
+            .field("encryption", &self.encryption)
+            .field("content_hash", &self.content_hash)
+            .field("parity", &self.parity)
             .finish()
     }
 }
@@ -106,14 +242,119 @@ impl Default for CompressionOptions {
     }
 }
 
+/// Select the plugin requested by `options` (manual override or automatic scoring)
+///
+/// When `input` is given, automatic selection only considers plugins whose
+/// `detect()` accepts its first bytes, and trial-compresses samples of it if
+/// sampling is enabled. Input that is already compressed throughout selects
+/// the stored plugin, unless plugin parameters are set: those are meant for a
+/// compressor. The plugin is returned bound to the validated plugin parameters
+/// from `options`.
+pub(crate) fn select_plugin(
+    options: &CompressionOptions,
+    input: Option<&[u8]>,
+) -> Result<(PluginMetadata, ConfiguredPlugin)> {
+    let selector = PluginSelector::new(options.weights);
+
+    let selected_metadata = if let Some(ref plugin_name) = options.plugin_name {
+        // Manual override
+        selector.select_by_name(plugin_name)?
+    } else {
+        // Automatic selection, content-aware when the input is known up front
+        let selected = match (input, options.sampling) {
+            (Some(input), Some(config)) => selector.select_sampled(input, &config)?,
+            (Some(input), None) => selector.select_for(input)?,
+            (None, _) => selector.select()?,
+        };
+        if selected.magic_number == STORED_MAGIC && !options.plugin_params.is_empty() {
+            selector.select()?
+        } else {
+            selected
+        }
+    };
+
+    // Get the actual plugin from registry
+    let plugin = get_plugin_by_magic(selected_metadata.magic_number).ok_or_else(|| {
+        crate::error::PluginError::NotFound(format!(
+            "Plugin '{}' metadata found but not in registry",
+            selected_metadata.name
+        ))
+    })?;
+
+    let plugin = ConfiguredPlugin::new(plugin, options.plugin_params.clone())?;
+    Ok((selected_metadata, plugin))
+}
+
+/// Build the length-prefixed file metadata section (empty if no metadata is set)
+pub(crate) fn metadata_section(options: &CompressionOptions) -> Vec<u8> {
+    let metadata_bytes = options
+        .file_metadata
+        .as_ref()
+        .map_or(Vec::new(), FileMetadata::to_bytes);
+
+    let mut section = Vec::new();
+    if !metadata_bytes.is_empty() {
+        #[allow(clippy::cast_possible_truncation)]
+        let metadata_len = metadata_bytes.len() as u16; // FileMetadata is always < 64KB
+        section.extend_from_slice(&metadata_len.to_le_bytes());
+        section.extend_from_slice(&metadata_bytes);
+    }
+    section
+}
+
+/// Payload layout chosen by [`compress_payload`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// One compressed stream
+    Single,
+    /// Independently compressed blocks (see [`crate::block`])
+    Blocks,
+    /// The input itself, to be written with the stored plugin's magic number
+    Stored,
+}
+
+/// Compress the payload, choosing between the single-stream and multi-block layouts
+///
+/// Returns the compressed payload and the layout used. `force_blocks` selects
+/// the multi-block layout regardless of input size, and so does
+/// `parity_shards`. If compression does not shrink the input, the payload is
+/// the input itself ([`Layout::Stored`]), except with `force_blocks`, where
+/// only the blocks that did not shrink are stored.
+fn compress_payload(
+    plugin: &dyn CompressionAlgorithm,
+    input: &[u8],
+    block_size: usize,
+    parity_shards: Option<u8>,
+    force_blocks: bool,
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<(Vec<u8>, Layout)> {
+    let force_blocks = force_blocks || parity_shards.is_some();
+    let (payload, layout) = if force_blocks || input.len() > block_size {
+        let section =
+            block::compress_blocks(plugin, input, block_size, parity_shards, &cancel_flag)?;
+        (section, Layout::Blocks)
+    } else {
+        (plugin.compress(input, cancel_flag)?, Layout::Single)
+    };
+
+    if payload.len() >= input.len() && !force_blocks {
+        Ok((input.to_vec(), Layout::Stored))
+    } else {
+        Ok((payload, layout))
+    }
+}
+
 /// Compress data using the default compression algorithm
 ///
-/// Uses the DEFLATE plugin (magic number `0x43525100`) to compress the input data.
+/// Uses the DEFLATE plugin (magic number `0x43525100`) to compress the input data,
+/// or the stored plugin if DEFLATE does not shrink it.
 /// The compressed output includes a 16-byte Crush header with:
 /// - Magic number identifying the plugin
 /// - Original uncompressed size
 /// - CRC32 checksum of the compressed payload
 ///
+/// Inputs larger than [`DEFAULT_BLOCK_SIZE`] are compressed in parallel blocks.
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -144,9 +385,21 @@ pub fn compress(input: &[u8]) -> Result<Vec<u8>> {
     let input_owned = input.to_vec();
 
     // Compress the data with timeout protection
-    let compressed_payload = run_with_timeout(DEFAULT_TIMEOUT, move |cancel_flag| {
-        plugin.compress(&input_owned, cancel_flag)
+    let (compressed_payload, layout) = run_with_timeout(DEFAULT_TIMEOUT, move |cancel_flag| {
+        compress_payload(
+            plugin,
+            &input_owned,
+            DEFAULT_BLOCK_SIZE,
+            None,
+            false,
+            cancel_flag,
+        )
     })?;
+    let magic = if layout == Layout::Stored {
+        STORED_MAGIC
+    } else {
+        default_magic
+    };
 
     // Calculate CRC32 of compressed payload
     let mut hasher = Hasher::new();
@@ -154,7 +407,10 @@ pub fn compress(input: &[u8]) -> Result<Vec<u8>> {
     let crc32 = hasher.finalize();
 
     // Create header with original size and CRC32
-    let header = CrushHeader::new(default_magic, input.len() as u64).with_crc32();
+    let mut header = CrushHeader::new(magic, input.len() as u64).with_crc32();
+    if layout == Layout::Blocks {
+        header = header.with_blocks().with_stored_blocks();
+    }
 
     // Build final output: header + compressed payload
     let mut output = Vec::with_capacity(CrushHeader::SIZE + 4 + compressed_payload.len());
@@ -171,13 +427,20 @@ pub fn compress(input: &[u8]) -> Result<Vec<u8>> {
 /// - Manual plugin override by name
 /// - Automatic selection with custom scoring weights
 ///
+/// Inputs larger than the configured block size (see
+/// [`CompressionOptions::with_block_size`]) are compressed in parallel blocks.
+/// Input the selected plugin cannot shrink is written with the stored plugin,
+/// so the output is never larger than the input plus the header and sections.
+///
 /// # Errors
 ///
 /// Returns an error if:
 /// - Specified plugin is not found (manual override)
 /// - No plugins are available (automatic selection)
 /// - Compression operation fails
+/// - The configured block size or parity shard count is invalid
 /// - Operation exceeds the specified timeout (0 = no timeout)
+/// - Encryption is combined with seekable output or parity
 ///
 /// # Examples
 ///
@@ -203,49 +466,51 @@ pub fn compress_with_options(input: &[u8], options: &CompressionOptions) -> Resu
         }
     }
 
-    // Select plugin based on options
-    let selector = PluginSelector::new(options.weights);
+    check_encryption(options)?;
 
-    let selected_metadata = if let Some(ref plugin_name) = options.plugin_name {
-        // Manual override
-        selector.select_by_name(plugin_name)?
-    } else {
-        // Automatic selection
-        selector.select()?
-    };
-
-    // Get the actual plugin from registry
-    let plugin = get_plugin_by_magic(selected_metadata.magic_number).ok_or_else(|| {
-        crate::error::PluginError::NotFound(format!(
-            "Plugin '{}' metadata found but not in registry",
-            selected_metadata.name
-        ))
-    })?;
+    // Select plugin based on options
+    let (selected_metadata, plugin) = select_plugin(options, Some(input))?;
 
     // Clone input for move into timeout closure
     let input_owned = input.to_vec();
     let timeout = options.timeout;
     let cancel_token = options.cancel_token.clone();
+    let block_size = options.block_size;
+    let seekable = options.seekable;
+    let parity = options.parity;
+    block::validate_block_size(block_size)?;
+    if let Some(parity_shards) = parity {
+        parity::validate_parity_shards(parity_shards)?;
+    }
 
     // Compress the data with timeout and cancellation protection
-    let compressed_payload =
+    let (compressed_payload, layout) =
         run_with_timeout_and_cancel(timeout, cancel_token, move |cancel_flag| {
-            plugin.compress(&input_owned, cancel_flag)
+            compress_payload(
+                &plugin,
+                &input_owned,
+                block_size,
+                parity,
+                seekable,
+                cancel_flag,
+            )
         })?;
 
-    // Handle file metadata
-    let metadata_bytes = options
-        .file_metadata
-        .as_ref()
-        .map_or(Vec::new(), super::plugin::metadata::FileMetadata::to_bytes);
+    // Stored output needs no plugin parameters
+    let (magic, params_section) = if layout == Layout::Stored {
+        (STORED_MAGIC, Vec::new())
+    } else {
+        (
+            selected_metadata.magic_number,
+            options.plugin_params.to_section()?,
+        )
+    };
 
-    let mut payload_with_metadata = Vec::new();
-    if !metadata_bytes.is_empty() {
-        #[allow(clippy::cast_possible_truncation)]
-        let metadata_len = metadata_bytes.len() as u16; // FileMetadata is always < 64KB
-        payload_with_metadata.extend_from_slice(&metadata_len.to_le_bytes());
-        payload_with_metadata.extend_from_slice(&metadata_bytes);
-    }
+    // Handle file metadata and plugin parameters
+    let mut payload_with_metadata = metadata_section(options);
+    let has_metadata = !payload_with_metadata.is_empty();
+    payload_with_metadata.extend_from_slice(&params_section);
+    let section_offset = (CrushHeader::SIZE + 4 + payload_with_metadata.len()) as u64;
     payload_with_metadata.extend_from_slice(&compressed_payload);
 
     // Calculate CRC32 of compressed payload + metadata
@@ -254,11 +519,25 @@ pub fn compress_with_options(input: &[u8], options: &CompressionOptions) -> Resu
     let crc32 = hasher.finalize();
 
     // Create header with original size and CRC32
-    let mut header =
-        CrushHeader::new(selected_metadata.magic_number, input.len() as u64).with_crc32();
-    if !metadata_bytes.is_empty() {
+    let mut header = CrushHeader::new(magic, input.len() as u64).with_crc32();
+    if has_metadata {
         header = header.with_metadata();
     }
+    if !params_section.is_empty() {
+        header = header.with_params();
+    }
+    if layout == Layout::Blocks {
+        header = header.with_blocks().with_stored_blocks();
+    }
+    if seekable {
+        header = header.with_seekable();
+    }
+    if let Some(algorithm) = options.content_hash {
+        header = header.with_hash_algorithm(algorithm);
+    }
+    if parity.is_some() {
+        header = header.with_parity();
+    }
 
     // Build final output: header + CRC32 + payload_with_metadata
     let mut output = Vec::with_capacity(CrushHeader::SIZE + 4 + payload_with_metadata.len());
@@ -266,7 +545,43 @@ pub fn compress_with_options(input: &[u8], options: &CompressionOptions) -> Resu
     output.extend_from_slice(&crc32.to_le_bytes());
     output.extend_from_slice(&payload_with_metadata);
 
-    Ok(output)
+    // The content hash and block index follow, outside the CRC32-covered payload
+    if let Some(algorithm) = options.content_hash {
+        output.extend_from_slice(&algorithm.hash(input).digest);
+    }
+    if seekable {
+        let index = BlockIndex::from_section(&compressed_payload, section_offset, parity)?;
+        output.extend_from_slice(&index.to_bytes());
+    }
+
+    match options.encryption {
+        Some(ref key) => encryption::encrypt_file(&output, key),
+        None => Ok(output),
+    }
+}
+
+/// Reject option combinations encryption does not support
+///
+/// The block index of a seekable file points into the payload, which is
+/// unreadable without decrypting the whole file. Parity records would be
+/// encrypted along with the blocks, and a damaged byte fails authentication
+/// before any block could be repaired.
+pub(crate) fn check_encryption(options: &CompressionOptions) -> Result<()> {
+    if options.encryption.is_none() {
+        return Ok(());
+    }
+    if options.seekable {
+        return Err(
+            EncryptionError::Unsupported("seekable files cannot be encrypted".to_string()).into(),
+        );
+    }
+    if options.parity.is_some() {
+        return Err(EncryptionError::Unsupported(
+            "files with parity cannot be encrypted".to_string(),
+        )
+        .into());
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -278,8 +593,8 @@ mod tests {
     #[allow(clippy::unwrap_used)]
     fn test_compress_basic() {
         init_plugins().unwrap();
-        let data = b"Hello, Crush!";
-        let compressed = compress(data).unwrap();
+        let data = b"Hello, Crush! ".repeat(10);
+        let compressed = compress(&data).unwrap();
 
         // Should have at least the header
         assert!(compressed.len() >= CrushHeader::SIZE);
@@ -318,6 +633,7 @@ mod tests {
         assert!(options.plugin_name.is_none());
         assert!(options.file_metadata.is_none());
         assert!(options.cancel_token.is_none());
+        assert_eq!(options.block_size, DEFAULT_BLOCK_SIZE);
     }
 
     #[test]
@@ -369,6 +685,12 @@ mod tests {
         assert!(options.cancel_token.is_some());
     }
 
+    #[test]
+    fn test_compression_options_with_block_size() {
+        let options = CompressionOptions::new().with_block_size(64 * 1024);
+        assert_eq!(options.block_size, 64 * 1024);
+    }
+
     #[test]
     fn test_compression_options_debug() {
         let options = CompressionOptions::new().with_plugin("test");
@@ -393,14 +715,32 @@ mod tests {
     #[allow(clippy::unwrap_used)]
     fn test_compress_with_options_manual_plugin() {
         init_plugins().unwrap();
-        let data = b"Test data";
+        let data = b"Test data ".repeat(20);
         let options = CompressionOptions::default().with_plugin("deflate");
-        let compressed = compress_with_options(data, &options).unwrap();
+        let compressed = compress_with_options(&data, &options).unwrap();
 
         assert!(compressed.len() >= CrushHeader::SIZE);
         assert_eq!(&compressed[0..4], &[0x43, 0x52, 0x01, 0x00]); // DEFLATE magic
     }
 
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_compress_never_expands() {
+        use crate::decompress;
+
+        init_plugins().unwrap();
+        // Too short for DEFLATE to shrink
+        let data = b"Test data";
+        for compressed in [
+            compress(data).unwrap(),
+            compress_with_options(data, &CompressionOptions::default().with_plugin("lz4")).unwrap(),
+        ] {
+            assert_eq!(&compressed[0..4], &STORED_MAGIC);
+            assert_eq!(compressed.len(), CrushHeader::SIZE + 4 + data.len());
+            assert_eq!(decompress(&compressed).unwrap().data, data);
+        }
+    }
+
     #[test]
     #[allow(clippy::unwrap_used)]
     fn test_compress_with_options_invalid_plugin() {
@@ -487,6 +827,53 @@ mod tests {
         assert_eq!(result.data, original);
     }
 
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_compress_with_options_uses_blocks_for_large_input() {
+        use crate::decompress;
+
+        init_plugins().unwrap();
+        #[allow(clippy::cast_possible_truncation)]
+        let original: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let compressed = compress_with_options(&original, &options).unwrap();
+
+        let header_bytes: [u8; CrushHeader::SIZE] =
+            compressed[..CrushHeader::SIZE].try_into().unwrap();
+        let header = CrushHeader::from_bytes(&header_bytes).unwrap();
+        assert!(header.has_blocks());
+
+        let result = decompress(&compressed).unwrap();
+        assert_eq!(result.data, original);
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_compress_with_options_small_input_single_stream() {
+        init_plugins().unwrap();
+        let compressed = compress_with_options(b"small", &CompressionOptions::default()).unwrap();
+
+        let header_bytes: [u8; CrushHeader::SIZE] =
+            compressed[..CrushHeader::SIZE].try_into().unwrap();
+        let header = CrushHeader::from_bytes(&header_bytes).unwrap();
+        assert!(!header.has_blocks());
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_compress_with_options_invalid_block_size() {
+        init_plugins().unwrap();
+        let options = CompressionOptions::default().with_block_size(0);
+        let result = compress_with_options(b"data", &options);
+
+        assert!(matches!(
+            result,
+            Err(crate::error::CrushError::Validation(
+                crate::error::ValidationError::InvalidBlockSize(_)
+            ))
+        ));
+    }
+
     #[test]
     #[allow(clippy::unwrap_used)]
     fn test_compression_options_builder_chain() {
@@ -2,18 +2,310 @@
 //!
 //! Provides the public `decompress()` API that reads Crush-compressed data,
 //! validates headers and checksums, routes to the correct plugin, and decompresses.
+//! Multi-block payloads are decoded in parallel on the rayon thread pool.
+//!
+//! Decoding never produces more than the header declares: each block stops at
+//! its frame's `uncompressed_len` and single-stream payloads stop at
+//! `original_size`. [`DecompressionOptions`] additionally caps the declared
+//! size and expansion ratio, so untrusted input is rejected before decoding.
+//!
+//! Files written with parity are repaired first (see [`crate::parity`]), so a
+//! few damaged bytes per block do not fail the checksums.
 
+use crate::block;
+use crate::cancel::CancellationToken;
+use crate::compression::DEFAULT_TIMEOUT;
+use crate::encryption::{self, EncryptionKey};
 use crate::error::{PluginError, Result, ValidationError};
+use crate::hash::{self, ContentHash};
+use crate::index::BlockIndex;
+use crate::parity::{self, RepairReport};
+use crate::plugin::params::ConfiguredPlugin;
 use crate::plugin::registry::get_plugin_by_magic;
-use crate::plugin::{list_plugins, CrushHeader, FileMetadata};
+use crate::plugin::{
+    list_plugins, run_with_timeout_and_cancel, CompressionAlgorithm, CrushHeader, FileMetadata,
+    PluginParams,
+};
+use crate::signature;
 use crc32fast::Hasher;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Decompression options for limits, timeout and cancellation
+///
+/// The size and ratio limits guard against untrusted input: both are checked
+/// against the sizes declared in the file before any data is decoded, and
+/// decoding stops early if a payload produces more than it declares.
+#[derive(Clone)]
+pub struct DecompressionOptions {
+    /// Maximum decompressed size in bytes
+    pub(crate) max_output_size: Option<u64>,
+
+    /// Maximum decompressed size divided by compressed size
+    pub(crate) max_ratio: Option<f64>,
+
+    /// Timeout for decompression operation
+    pub(crate) timeout: Duration,
+
+    /// Optional cancellation token for Ctrl+C support
+    pub(crate) cancel_token: Option<Arc<dyn CancellationToken>>,
+
+    /// Key for encrypted input
+    pub(crate) encryption: Option<EncryptionKey>,
+}
+
+impl DecompressionOptions {
+    /// Create decompression options with default settings (no limits or timeout)
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_output_size: None,
+            max_ratio: None,
+            timeout: DEFAULT_TIMEOUT,
+            cancel_token: None,
+            encryption: None,
+        }
+    }
+
+    /// Set timeout for decompression operation
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set cancellation token for Ctrl+C support
+    #[must_use]
+    pub fn with_cancel_token(mut self, token: Arc<dyn CancellationToken>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Reject output larger than `bytes`
+    #[must_use]
+    pub fn with_max_output_size(mut self, bytes: u64) -> Self {
+        self.max_output_size = Some(bytes);
+        self
+    }
+
+    /// Reject output more than `ratio` times larger than the compressed input
+    #[must_use]
+    pub fn with_max_ratio(mut self, ratio: f64) -> Self {
+        self.max_ratio = Some(ratio);
+        self
+    }
+
+    /// Decrypt encrypted input with `key`
+    ///
+    /// Unencrypted input ignores the key.
+    #[must_use]
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.encryption = Some(key);
+        self
+    }
+
+    /// Check `output_size` bytes decoded from `input_size` bytes against the limits
+    pub(crate) fn check(&self, output_size: u64, input_size: u64) -> Result<()> {
+        self.check_output_size(output_size)?;
+
+        if let Some(max) = self.max_ratio {
+            #[allow(clippy::cast_precision_loss)] // Only compared against a limit
+            let ratio = output_size as f64 / input_size.max(1) as f64;
+            if ratio > max {
+                return Err(ValidationError::OutputLimitExceeded(format!(
+                    "expansion ratio {ratio:.1} exceeds the maximum of {max}"
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check `output_size` against the maximum output size alone, for when
+    /// the input size is not known yet
+    pub(crate) fn check_output_size(&self, output_size: u64) -> Result<()> {
+        match self.max_output_size {
+            Some(max) if output_size > max => Err(ValidationError::OutputLimitExceeded(format!(
+                "{output_size} bytes exceeds the maximum output size of {max} bytes"
+            ))
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Debug for DecompressionOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecompressionOptions")
+            .field("max_output_size", &self.max_output_size)
+            .field("max_ratio", &self.max_ratio)
+            .field("timeout", &self.timeout)
+            .field(
+                "cancel_token",
+                &self.cancel_token.as_ref().map(|_| "Some(...)"),
+            )
+            .field("encryption", &self.encryption)
+            .finish()
+    }
+}
+
+impl Default for DecompressionOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read the payload CRC32 stored at `offset`, just past the header
+pub(crate) fn read_stored_crc(input: &[u8], offset: usize) -> Result<u32> {
+    input
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| {
+            ValidationError::InvalidHeader(
+                "Truncated: CRC32 flag set but no CRC32 data".to_string(),
+            )
+            .into()
+        })
+}
+
+/// Parse the optional metadata and plugin parameter sections
+///
+/// `payload_start` is the offset just past the header and CRC32. Returns the
+/// parsed sections and the offset of the compressed payload.
+pub(crate) fn parse_sections(
+    input: &[u8],
+    header: &CrushHeader,
+    mut payload_start: usize,
+) -> Result<(FileMetadata, PluginParams, usize)> {
+    let metadata = if header.has_metadata() {
+        if input.len() < payload_start + 2 {
+            return Err(ValidationError::InvalidHeader(
+                "Truncated: metadata flag set but no metadata length".to_string(),
+            )
+            .into());
+        }
+        let metadata_len =
+            u16::from_le_bytes([input[payload_start], input[payload_start + 1]]) as usize;
+        payload_start += 2;
+
+        if input.len() < payload_start + metadata_len {
+            return Err(ValidationError::InvalidHeader(
+                "Truncated: metadata length exceeds payload size".to_string(),
+            )
+            .into());
+        }
+        let metadata_bytes = &input[payload_start..payload_start + metadata_len];
+        payload_start += metadata_len;
+
+        FileMetadata::from_bytes(metadata_bytes)?
+    } else {
+        FileMetadata::default()
+    };
+
+    let params = if header.has_params() {
+        let (params, consumed) = PluginParams::from_section(&input[payload_start..])?;
+        payload_start += consumed;
+        params
+    } else {
+        PluginParams::new()
+    };
+
+    Ok((metadata, params, payload_start))
+}
+
+/// Decompress a payload using either the single-stream or multi-block layout
+///
+/// For multi-block payloads, `payload` must end exactly at the end marker
+/// (any stream trailer has already been split off by the caller). Decoding
+/// aborts once the output would exceed `expected_size`.
+fn decompress_payload(
+    plugin: &dyn CompressionAlgorithm,
+    header: &CrushHeader,
+    payload: &[u8],
+    expected_size: usize,
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<Vec<u8>> {
+    if !header.has_blocks() {
+        return plugin.decompress_with_limit(
+            payload,
+            &PluginParams::new(),
+            expected_size,
+            cancel_flag,
+        );
+    }
+
+    let (_, entries, consumed) = block::parse_blocks(payload, header.has_parity())?;
+    let declared: u64 = entries
+        .iter()
+        .map(|entry| u64::from(entry.frame.uncompressed_len))
+        .sum();
+    if declared != expected_size as u64 {
+        return Err(ValidationError::CorruptedData(format!(
+            "Size mismatch: header says {expected_size} bytes, blocks hold {declared} bytes"
+        ))
+        .into());
+    }
+    let rest = &payload[consumed..];
+    if !rest.is_empty() {
+        return Err(ValidationError::CorruptedData(format!(
+            "Unexpected {} trailing bytes after block section",
+            rest.len()
+        ))
+        .into());
+    }
+
+    block::decompress_blocks(plugin, &entries, header.has_stored_blocks(), &cancel_flag)
+}
+
+/// Verify and strip the block index, content hash and stream trailer, if present
+///
+/// Seekable files end with a block index, a content hash precedes it, and
+/// streamed output carries its size and CRC32 in a trailer after the blocks.
+/// Returns the input without any of them, the expected original size and the
+/// stored content hash.
+fn strip_trailers<'a>(
+    input: &'a [u8],
+    header: &CrushHeader,
+) -> Result<(&'a [u8], u64, Option<ContentHash>)> {
+    let input = if header.is_seekable() {
+        let (content, _) = BlockIndex::split(input)?;
+        if content.len() < CrushHeader::SIZE {
+            return Err(ValidationError::CorruptedData(
+                "Block index overlaps the file header".to_string(),
+            )
+            .into());
+        }
+        content
+    } else {
+        input
+    };
+
+    let (input, content_hash) = hash::split_footer(input, header)?;
+
+    if !header.is_streamed() {
+        return Ok((input, header.original_size, content_hash));
+    }
+
+    let (content, trailer) = block::split_stream_trailer(&input[CrushHeader::SIZE..])?;
+    trailer.verify(content)?;
+    Ok((
+        &input[..CrushHeader::SIZE + content.len()],
+        trailer.original_size,
+        content_hash,
+    ))
+}
 
 #[derive(Debug)]
 pub struct DecompressionResult {
     pub data: Vec<u8>,
     pub metadata: FileMetadata,
+    /// Content hash stored in the file, verified against `data`
+    pub content_hash: Option<ContentHash>,
+    /// Blocks repaired from their parity records (see [`crate::parity`])
+    pub repairs: RepairReport,
 }
 
 /// Decompress Crush-compressed data
@@ -30,6 +322,10 @@ pub struct DecompressionResult {
 /// - Required plugin is not registered
 /// - CRC32 checksum validation fails
 /// - Decompression operation fails
+/// - The output does not match the stored content hash
+///   ([`ValidationError::HashMismatch`])
+/// - A block of a file with parity is damaged beyond repair
+///   ([`ValidationError::Unrepairable`])
 ///
 /// # Examples
 ///
@@ -43,6 +339,49 @@ pub struct DecompressionResult {
 /// assert_eq!(data.as_slice(), decompressed.data.as_slice());
 /// ```
 pub fn decompress(input: &[u8]) -> Result<DecompressionResult> {
+    decompress_with_options(input, &DecompressionOptions::default())
+}
+
+/// Decompress Crush-compressed data with custom options
+///
+/// The declared original size is checked against the limits before anything
+/// is decoded, and the output must match it exactly. Decoding runs under the
+/// options' timeout and stops promptly when the cancellation token fires.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decompress`], and:
+/// - [`ValidationError::OutputLimitExceeded`] if the output would exceed the
+///   maximum size or expansion ratio
+/// - Operation exceeds the specified timeout (0 = no timeout)
+/// - Operation is cancelled via the cancellation token
+/// - [`EncryptionError::KeyRequired`](crate::EncryptionError::KeyRequired) if
+///   the input is encrypted and no key is set, or
+///   [`EncryptionError::WrongKey`](crate::EncryptionError::WrongKey) if the key
+///   does not match
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::{compress, decompress_with_options, init_plugins, DecompressionOptions};
+///
+/// init_plugins().expect("Plugin initialization failed");
+/// let compressed = compress(&[0u8; 100_000]).expect("Compression failed");
+///
+/// let options = DecompressionOptions::new().with_max_output_size(10_000);
+/// assert!(decompress_with_options(&compressed, &options).is_err());
+/// ```
+pub fn decompress_with_options(
+    input: &[u8],
+    options: &DecompressionOptions,
+) -> Result<DecompressionResult> {
+    // Check if already cancelled before starting
+    if let Some(ref token) = options.cancel_token {
+        if token.is_cancelled() {
+            return Err(crate::error::CrushError::Cancelled);
+        }
+    }
+
     // Validate minimum size (header + CRC32 if present)
     if input.len() < CrushHeader::SIZE {
         return Err(ValidationError::InvalidHeader(format!(
@@ -59,22 +398,30 @@ pub fn decompress(input: &[u8]) -> Result<DecompressionResult> {
         .map_err(|_| ValidationError::InvalidHeader("Failed to read header".to_string()))?;
     let header = CrushHeader::from_bytes(&header_bytes)?;
 
+    // The signature is verified separately, see `crate::signature`
+    let (input, _) = signature::split_trailer(input, &header)?;
+
+    if header.is_encrypted() {
+        let decrypted = encryption::decrypt_file(input, options.encryption.as_ref())?;
+        return decompress_with_options(&decrypted, options);
+    }
+
+    // Repair damaged blocks before anything is checked against them
+    let (repaired, repairs) = parity::repair_blocks(input, &header)?;
+    let input = repaired.as_ref();
+
+    let input_size = input.len() as u64;
+    let (input, original_size, content_hash) = strip_trailers(input, &header)?;
+    options.check(original_size, input_size)?;
+    let expected_size = usize::try_from(original_size).map_err(|_| {
+        ValidationError::InvalidHeader("Original size exceeds platform limits".to_string())
+    })?;
+
     let mut payload_start = CrushHeader::SIZE;
 
     // Handle CRC32
     if header.has_crc32() {
-        if input.len() < payload_start + 4 {
-            return Err(ValidationError::InvalidHeader(
-                "Truncated: CRC32 flag set but no CRC32 data".to_string(),
-            )
-            .into());
-        }
-        let stored_crc = u32::from_le_bytes([
-            input[payload_start],
-            input[payload_start + 1],
-            input[payload_start + 2],
-            input[payload_start + 3],
-        ]);
+        let stored_crc = read_stored_crc(input, payload_start)?;
         payload_start += 4;
 
         let payload_for_crc = &input[payload_start..];
@@ -91,33 +438,11 @@ pub fn decompress(input: &[u8]) -> Result<DecompressionResult> {
         }
     }
 
-    // Handle metadata
-    let metadata = if header.has_metadata() {
-        if input.len() < payload_start + 2 {
-            return Err(ValidationError::InvalidHeader(
-                "Truncated: metadata flag set but no metadata length".to_string(),
-            )
-            .into());
-        }
-        let metadata_len =
-            u16::from_le_bytes([input[payload_start], input[payload_start + 1]]) as usize;
-        payload_start += 2;
-
-        if input.len() < payload_start + metadata_len {
-            return Err(ValidationError::InvalidHeader(
-                "Truncated: metadata length exceeds payload size".to_string(),
-            )
-            .into());
-        }
-        let metadata_bytes = &input[payload_start..payload_start + metadata_len];
-        payload_start += metadata_len;
+    // Handle metadata and plugin parameters
+    let (metadata, params, payload_start) = parse_sections(input, &header, payload_start)?;
 
-        FileMetadata::from_bytes(metadata_bytes)?
-    } else {
-        FileMetadata::default()
-    };
-
-    let compressed_payload = &input[payload_start..];
+    // Clone payload for move into timeout closure
+    let compressed_payload = input[payload_start..].to_vec();
 
     // Find plugin by magic number from registry
     let plugin = get_plugin_by_magic(header.magic).ok_or_else(|| {
@@ -135,29 +460,42 @@ pub fn decompress(input: &[u8]) -> Result<DecompressionResult> {
         ))
     })?;
 
-    // Create cancellation flag (not yet connected to timeout system)
-    let cancel_flag = Arc::new(AtomicBool::new(false));
-
-    // Decompress the payload
-    let decompressed = plugin.decompress(compressed_payload, cancel_flag)?;
+    let plugin = ConfiguredPlugin::new(plugin, params)?;
+
+    // Decompress the payload with timeout and cancellation protection
+    let decompressed = run_with_timeout_and_cancel(
+        options.timeout,
+        options.cancel_token.clone(),
+        move |cancel_flag| {
+            decompress_payload(
+                &plugin,
+                &header,
+                &compressed_payload,
+                expected_size,
+                cancel_flag,
+            )
+        },
+    )?;
 
     // Validate decompressed size matches header
-    let expected_size = usize::try_from(header.original_size).map_err(|_| {
-        ValidationError::InvalidHeader("Original size exceeds platform limits".to_string())
-    })?;
-
     if decompressed.len() != expected_size {
         return Err(ValidationError::CorruptedData(format!(
             "Size mismatch: header says {} bytes, got {} bytes",
-            header.original_size,
+            original_size,
             decompressed.len()
         ))
         .into());
     }
 
+    if let Some(ref expected) = content_hash {
+        hash::verify(expected, &expected.algorithm.hash(&decompressed))?;
+    }
+
     Ok(DecompressionResult {
         data: decompressed,
         metadata,
+        content_hash,
+        repairs,
     })
 }
 
@@ -338,6 +676,40 @@ mod tests {
         assert!(result.metadata.permissions.is_none());
     }
 
+    #[test]
+    fn test_decompress_multi_block() {
+        use crate::{compress_with_options, CompressionOptions};
+
+        init_plugins().expect("Failed to init");
+        let original: Vec<u8> = (0..50_000_u32).map(|i| (i % 199) as u8).collect();
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let compressed = compress_with_options(&original, &options).expect("Compression failed");
+
+        let result = decompress(&compressed).expect("Decompression failed");
+        assert_eq!(result.data, original);
+    }
+
+    #[test]
+    fn test_decompress_multi_block_trailing_garbage() {
+        use crate::{compress_with_options, CompressionOptions};
+
+        init_plugins().expect("Failed to init");
+        let original = vec![0x42u8; 3 * block::MIN_BLOCK_SIZE];
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let mut compressed =
+            compress_with_options(&original, &options).expect("Compression failed");
+
+        // Append garbage and recompute the CRC so only the layout check can catch it
+        compressed.extend_from_slice(b"garbage");
+        let mut hasher = Hasher::new();
+        hasher.update(&compressed[CrushHeader::SIZE + 4..]);
+        let crc = hasher.finalize().to_le_bytes();
+        compressed[CrushHeader::SIZE..CrushHeader::SIZE + 4].copy_from_slice(&crc);
+
+        let result = decompress(&compressed);
+        assert!(result.is_err());
+    }
+
     #[test]
     #[allow(clippy::unwrap_used)]
     fn test_decompress_corrupted_payload() {
@@ -0,0 +1,901 @@
+//! Authenticated encryption of Crush payloads
+//!
+//! Encrypted files keep the 16-byte [`CrushHeader`] in the clear, with the
+//! `ENCRYPTED` flag set, followed by an encryption section and the rest of
+//! the file (CRC32, metadata, parameters, payload and trailers) sealed with
+//! ChaCha20-Poly1305 in fixed-size chunks:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 1    | cipher (1 = ChaCha20-Poly1305)
+//! 1      | 1    | key derivation (0 = raw 256-bit key, 1 = Argon2id passphrase)
+//! 2      | 4    | Argon2 memory cost in KiB (u32, little-endian)
+//! 6      | 4    | Argon2 iterations (u32, little-endian)
+//! 10     | 4    | Argon2 parallelism (u32, little-endian)
+//! 14     | 16   | salt (Argon2id only, zero for raw keys)
+//! 30     | 16   | file salt (random per file)
+//! 46     | 7    | nonce prefix (random per file)
+//! 53     | 3    | reserved (zero)
+//! 56     | 16   | key check (Poly1305 tag over bytes 0..56)
+//! ```
+//!
+//! Chunks are not sealed with the raw or passphrase-derived key itself but
+//! with a per-file key, HKDF-SHA256 of that key over the file salt. Files
+//! encrypted with the same key file, or with one passphrase derivation, thus
+//! never share a cipher key, and nonces only have to be unique within a file.
+//!
+//! Each chunk holds [`CHUNK_SIZE`] bytes of plaintext plus a 16-byte tag; the
+//! last chunk is always shorter (possibly empty), so truncation at a chunk
+//! boundary is detected. Chunk nonces are the prefix, a big-endian chunk
+//! counter and a final-chunk flag, and the header and section bytes are
+//! authenticated with every chunk. The key check lets readers tell a wrong
+//! key apart from corrupted data before decrypting anything.
+
+use crate::error::{CrushError, EncryptionError, Result, ValidationError};
+use crate::plugin::metadata::{ext_flags, flags};
+use crate::plugin::CrushHeader;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+use zeroize::Zeroizing;
+
+/// Size of the encryption section that follows the header
+pub const SECTION_SIZE: usize = 72;
+
+/// Plaintext bytes per encrypted chunk
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Poly1305 tag appended to every chunk
+pub const TAG_SIZE: usize = 16;
+
+const CIPHER_CHACHA20_POLY1305: u8 = 1;
+const KDF_RAW: u8 = 0;
+const KDF_ARGON2ID: u8 = 1;
+const SALT_SIZE: usize = 16;
+const FILE_SALT_SIZE: usize = 16;
+const NONCE_PREFIX_SIZE: usize = 7;
+/// Bytes of the section covered by the key check
+const SECTION_PARAMS_SIZE: usize = 56;
+/// HKDF info binding per-file keys to chunk encryption
+const FILE_KEY_INFO: &[u8] = b"crush chacha20-poly1305 file key";
+
+/// Nonce flag byte of a chunk that is not the last one
+const CHUNK_FLAG: u8 = 0;
+/// Nonce flag byte of the last chunk
+const FINAL_CHUNK_FLAG: u8 = 1;
+/// Nonce flag byte of the key check
+const KEY_CHECK_FLAG: u8 = 2;
+
+/// Largest Argon2 memory cost accepted from a file (one gibibyte), so crafted
+/// files cannot make readers allocate without bound
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 64;
+
+/// Argon2id cost parameters for passphrase keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory cost in kibibytes
+    pub memory_kib: u32,
+    /// Number of passes over memory
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The OWASP-recommended Argon2id baseline: 19 mebibytes, 2 iterations, 1 lane
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+enum KeySource {
+    Passphrase(Zeroizing<String>, KdfParams),
+    Raw(Zeroizing<[u8; 32]>),
+}
+
+/// Key derived from a passphrase, cached with the salt it was derived for
+struct DerivedKey {
+    salt: [u8; SALT_SIZE],
+    params: KdfParams,
+    key: Zeroizing<[u8; 32]>,
+}
+
+struct KeyInner {
+    source: KeySource,
+    derived: Mutex<Option<DerivedKey>>,
+}
+
+/// Passphrase or raw key used to encrypt or decrypt Crush files
+///
+/// Clones share the derived key: a passphrase is run through Argon2id once
+/// and the result reused for every file encrypted with the same
+/// `EncryptionKey` (each file still gets its own key, see the module docs), and
+/// when decrypting, for every file that carries the same salt.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    inner: Arc<KeyInner>,
+}
+
+impl EncryptionKey {
+    /// Key derived from `passphrase` with Argon2id and the default costs
+    #[must_use]
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self::from_passphrase_with_params(passphrase, KdfParams::default())
+    }
+
+    /// Key derived from `passphrase` with custom Argon2id costs
+    ///
+    /// The costs are recorded in the file; decryption uses the recorded
+    /// costs whatever the key was created with.
+    #[must_use]
+    pub fn from_passphrase_with_params(passphrase: &str, params: KdfParams) -> Self {
+        Self::with_source(KeySource::Passphrase(
+            Zeroizing::new(passphrase.to_string()),
+            params,
+        ))
+    }
+
+    /// Raw 256-bit key, used as is
+    #[must_use]
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self::with_source(KeySource::Raw(Zeroizing::new(key)))
+    }
+
+    /// Raw key from a slice that must be exactly 32 bytes long
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::InvalidKey`] if `key` is not 32 bytes
+    pub fn from_slice(key: &[u8]) -> Result<Self> {
+        let key: [u8; 32] = key.try_into().map_err(|_| {
+            EncryptionError::InvalidKey(format!("expected 32 bytes, got {}", key.len()))
+        })?;
+        Ok(Self::from_bytes(key))
+    }
+
+    fn with_source(source: KeySource) -> Self {
+        Self {
+            inner: Arc::new(KeyInner {
+                source,
+                derived: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Key derivation parameters and salt for a new file
+    fn params_for_encryption(&self) -> (u8, KdfParams, [u8; SALT_SIZE]) {
+        match &self.inner.source {
+            KeySource::Raw(_) => (
+                KDF_RAW,
+                KdfParams {
+                    memory_kib: 0,
+                    iterations: 0,
+                    parallelism: 0,
+                },
+                [0; SALT_SIZE],
+            ),
+            KeySource::Passphrase(_, params) => {
+                let cached = self
+                    .inner
+                    .derived
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .as_ref()
+                    .filter(|derived| derived.params == *params)
+                    .map(|derived| derived.salt);
+                let salt = cached.unwrap_or_else(|| {
+                    let mut salt = [0; SALT_SIZE];
+                    OsRng.fill_bytes(&mut salt);
+                    salt
+                });
+                (KDF_ARGON2ID, *params, salt)
+            }
+        }
+    }
+
+    /// The 256-bit cipher key for a file with the given derivation settings
+    fn cipher_key(
+        &self,
+        kdf: u8,
+        params: KdfParams,
+        salt: &[u8; SALT_SIZE],
+    ) -> Result<Zeroizing<[u8; 32]>> {
+        match (&self.inner.source, kdf) {
+            (KeySource::Raw(key), KDF_RAW) => Ok(key.clone()),
+            (KeySource::Passphrase(passphrase, _), KDF_ARGON2ID) => {
+                let mut cached = self
+                    .inner
+                    .derived
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if let Some(derived) = cached
+                    .as_ref()
+                    .filter(|derived| derived.salt == *salt && derived.params == params)
+                {
+                    return Ok(derived.key.clone());
+                }
+                let key = derive_key(passphrase.as_bytes(), params, salt)?;
+                *cached = Some(DerivedKey {
+                    salt: *salt,
+                    params,
+                    key: key.clone(),
+                });
+                Ok(key)
+            }
+            (KeySource::Raw(_), _) => Err(EncryptionError::WrongKeyKind(
+                "file was encrypted with a passphrase, not a key file".to_string(),
+            )
+            .into()),
+            (KeySource::Passphrase(..), _) => Err(EncryptionError::WrongKeyKind(
+                "file was encrypted with a key file, not a passphrase".to_string(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.inner.source {
+            KeySource::Passphrase(..) => "passphrase",
+            KeySource::Raw(_) => "raw",
+        };
+        f.debug_struct("EncryptionKey")
+            .field("kind", &kind)
+            .finish()
+    }
+}
+
+fn derive_key(
+    passphrase: &[u8],
+    params: KdfParams,
+    salt: &[u8; SALT_SIZE],
+) -> Result<Zeroizing<[u8; 32]>> {
+    if params.memory_kib > MAX_MEMORY_KIB
+        || params.iterations > MAX_ITERATIONS
+        || params.parallelism > MAX_PARALLELISM
+    {
+        return Err(EncryptionError::Unsupported(format!(
+            "Argon2 costs out of range: {} KiB, {} iterations, {} lanes",
+            params.memory_kib, params.iterations, params.parallelism
+        ))
+        .into());
+    }
+    let argon2_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(32),
+    )
+    .map_err(|e| EncryptionError::KeyDerivation(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+        .hash_password_into(passphrase, salt, key.as_mut())
+        .map_err(|e| EncryptionError::KeyDerivation(e.to_string()))?;
+    Ok(key)
+}
+
+/// Key for the chunks of one file: HKDF-SHA256 of `key` over its file salt
+fn file_key(key: &[u8; 32], file_salt: &[u8; FILE_SALT_SIZE]) -> Result<Zeroizing<[u8; 32]>> {
+    let mut file_key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(file_salt), key)
+        .expand(FILE_KEY_INFO, file_key.as_mut())
+        .map_err(|e| EncryptionError::KeyDerivation(e.to_string()))?;
+    Ok(file_key)
+}
+
+/// Cipher description reported by [`crate::inspect`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct EncryptionInfo {
+    /// AEAD cipher, e.g. `chacha20-poly1305`
+    pub cipher: &'static str,
+    /// How the key was obtained: `argon2id` for passphrases, `raw` for key files
+    pub kdf: &'static str,
+}
+
+impl EncryptionInfo {
+    /// Describe the encryption section of a file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Unsupported`] for unknown ciphers or key
+    /// derivation functions
+    pub fn from_section(section: &[u8; SECTION_SIZE]) -> Result<Self> {
+        if section[0] != CIPHER_CHACHA20_POLY1305 {
+            return Err(EncryptionError::Unsupported(format!("cipher id {}", section[0])).into());
+        }
+        let kdf = match section[1] {
+            KDF_RAW => "raw",
+            KDF_ARGON2ID => "argon2id",
+            other => {
+                return Err(
+                    EncryptionError::Unsupported(format!("key derivation id {other}")).into(),
+                )
+            }
+        };
+        Ok(Self {
+            cipher: "chacha20-poly1305",
+            kdf,
+        })
+    }
+}
+
+/// Seals or opens the chunks of one file
+pub(crate) struct ChunkCipher {
+    cipher: ChaCha20Poly1305,
+    nonce_prefix: [u8; NONCE_PREFIX_SIZE],
+    aad: Vec<u8>,
+    counter: u32,
+}
+
+impl ChunkCipher {
+    fn nonce(&self, flag: u8) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_SIZE..11].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[11] = flag;
+        nonce.into()
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        self.counter = self.counter.checked_add(1).ok_or_else(|| {
+            EncryptionError::Unsupported("too many chunks for one file".to_string())
+        })?;
+        Ok(())
+    }
+
+    /// Encrypt one chunk; `last` marks the final, short chunk
+    fn seal(&mut self, plaintext: &[u8], last: bool) -> Result<Vec<u8>> {
+        let flag = if last { FINAL_CHUNK_FLAG } else { CHUNK_FLAG };
+        let sealed = self
+            .cipher
+            .encrypt(
+                &self.nonce(flag),
+                Payload {
+                    msg: plaintext,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Unsupported("chunk encryption failed".to_string()))?;
+        self.advance()?;
+        Ok(sealed)
+    }
+
+    /// Decrypt and authenticate one chunk
+    fn open(&mut self, ciphertext: &[u8], last: bool) -> Result<Vec<u8>> {
+        let flag = if last { FINAL_CHUNK_FLAG } else { CHUNK_FLAG };
+        let plaintext = self
+            .cipher
+            .decrypt(
+                &self.nonce(flag),
+                Payload {
+                    msg: ciphertext,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| {
+                ValidationError::CorruptedData(format!(
+                    "encrypted chunk {} failed authentication",
+                    self.counter
+                ))
+            })?;
+        self.advance()?;
+        Ok(plaintext)
+    }
+}
+
+/// Key check tag: the Poly1305 tag of an empty message under the section parameters
+fn key_check(cipher: &ChunkCipher, params: &[u8]) -> Result<[u8; TAG_SIZE]> {
+    let tag = cipher
+        .cipher
+        .encrypt(
+            &cipher.nonce(KEY_CHECK_FLAG),
+            Payload {
+                msg: &[],
+                aad: params,
+            },
+        )
+        .map_err(|_| EncryptionError::Unsupported("key check failed".to_string()))?;
+    tag.try_into()
+        .map_err(|_| EncryptionError::Unsupported("unexpected tag size".to_string()).into())
+}
+
+fn chunk_aad(header: &[u8; CrushHeader::SIZE], params: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(CrushHeader::SIZE + params.len());
+    aad.extend_from_slice(header);
+    // Files are signed after encryption, so the signed flag is left out
+    aad[13] &= !ext_flags::SIGNED;
+    aad.extend_from_slice(params);
+    aad
+}
+
+/// Build the encryption section for a new file and the cipher for its chunks
+///
+/// `header` must already carry the `ENCRYPTED` flag.
+pub(crate) fn begin_encryption(
+    key: &EncryptionKey,
+    header: &[u8; CrushHeader::SIZE],
+) -> Result<([u8; SECTION_SIZE], ChunkCipher)> {
+    let (kdf, params, salt) = key.params_for_encryption();
+    let mut file_salt = [0u8; FILE_SALT_SIZE];
+    OsRng.fill_bytes(&mut file_salt);
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+    OsRng.fill_bytes(&mut nonce_prefix);
+
+    let mut section = [0u8; SECTION_SIZE];
+    section[0] = CIPHER_CHACHA20_POLY1305;
+    section[1] = kdf;
+    section[2..6].copy_from_slice(&params.memory_kib.to_le_bytes());
+    section[6..10].copy_from_slice(&params.iterations.to_le_bytes());
+    section[10..14].copy_from_slice(&params.parallelism.to_le_bytes());
+    section[14..30].copy_from_slice(&salt);
+    section[30..46].copy_from_slice(&file_salt);
+    section[46..53].copy_from_slice(&nonce_prefix);
+
+    let cipher_key = file_key(&*key.cipher_key(kdf, params, &salt)?, &file_salt)?;
+    let cipher = ChunkCipher {
+        cipher: ChaCha20Poly1305::new(cipher_key.as_ref().into()),
+        nonce_prefix,
+        aad: chunk_aad(header, &section[..SECTION_PARAMS_SIZE]),
+        counter: 0,
+    };
+    let check = key_check(&cipher, &section[..SECTION_PARAMS_SIZE])?;
+    section[SECTION_PARAMS_SIZE..].copy_from_slice(&check);
+    Ok((section, cipher))
+}
+
+/// Verify `key` against a file's encryption section and return the cipher
+/// for its chunks
+///
+/// # Errors
+///
+/// Returns [`EncryptionError::KeyRequired`] without a key,
+/// [`EncryptionError::WrongKey`] if the key check fails, and
+/// [`EncryptionError::Unsupported`] for unknown ciphers or excessive costs
+pub(crate) fn begin_decryption(
+    key: Option<&EncryptionKey>,
+    header: &[u8; CrushHeader::SIZE],
+    section: &[u8; SECTION_SIZE],
+) -> Result<ChunkCipher> {
+    EncryptionInfo::from_section(section)?;
+    let key = key.ok_or(EncryptionError::KeyRequired)?;
+
+    let le_u32 = |at: usize| {
+        u32::from_le_bytes([
+            section[at],
+            section[at + 1],
+            section[at + 2],
+            section[at + 3],
+        ])
+    };
+    let params = KdfParams {
+        memory_kib: le_u32(2),
+        iterations: le_u32(6),
+        parallelism: le_u32(10),
+    };
+    let mut salt = [0u8; SALT_SIZE];
+    salt.copy_from_slice(&section[14..30]);
+    let mut file_salt = [0u8; FILE_SALT_SIZE];
+    file_salt.copy_from_slice(&section[30..46]);
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+    nonce_prefix.copy_from_slice(&section[46..53]);
+
+    let cipher_key = file_key(&*key.cipher_key(section[1], params, &salt)?, &file_salt)?;
+    let cipher = ChunkCipher {
+        cipher: ChaCha20Poly1305::new(cipher_key.as_ref().into()),
+        nonce_prefix,
+        aad: chunk_aad(header, &section[..SECTION_PARAMS_SIZE]),
+        counter: 0,
+    };
+    if key_check(&cipher, &section[..SECTION_PARAMS_SIZE])? != section[SECTION_PARAMS_SIZE..] {
+        return Err(EncryptionError::WrongKey.into());
+    }
+    Ok(cipher)
+}
+
+/// Encrypt a complete in-memory Crush file
+///
+/// Sets the `ENCRYPTED` flag in the header and seals everything after it.
+pub(crate) fn encrypt_file(plain: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
+    let (header, rest) = split_header(plain)?;
+    let mut header = header;
+    header[12] |= flags::ENCRYPTED;
+
+    let (section, mut cipher) = begin_encryption(key, &header)?;
+    let chunks = rest.len() / CHUNK_SIZE + 1;
+    let mut output = Vec::with_capacity(plain.len() + SECTION_SIZE + chunks * TAG_SIZE);
+    output.extend_from_slice(&header);
+    output.extend_from_slice(&section);
+
+    // A full chunk is never the last one, so the final chunk may be empty
+    let mut remaining = rest;
+    while remaining.len() >= CHUNK_SIZE {
+        output.extend_from_slice(&cipher.seal(&remaining[..CHUNK_SIZE], false)?);
+        remaining = &remaining[CHUNK_SIZE..];
+    }
+    output.extend_from_slice(&cipher.seal(remaining, true)?);
+    Ok(output)
+}
+
+/// Decrypt a complete in-memory Crush file
+///
+/// Returns the file as it was before encryption, with the `ENCRYPTED` flag
+/// cleared. `input` must not include a signature trailer; the `SIGNED` flag
+/// is cleared as well, since the signature belongs to the encrypted file.
+pub(crate) fn decrypt_file(input: &[u8], key: Option<&EncryptionKey>) -> Result<Vec<u8>> {
+    match decrypt_prefix(input, key)? {
+        (output, None) => Ok(output),
+        (_, Some((_, error))) => Err(error),
+    }
+}
+
+/// Offset of a chunk that failed to decrypt, and the error it failed with
+pub(crate) type DamagedChunk = (usize, CrushError);
+
+/// Decrypt an in-memory Crush file up to its first damaged chunk
+///
+/// Like [`decrypt_file`], but a chunk that fails authentication or is
+/// truncated ends decryption instead of failing it: returns the plaintext
+/// of the chunks before it, plus the chunk's offset in `input` and the
+/// error. A missing or wrong key still fails.
+pub(crate) fn decrypt_prefix(
+    input: &[u8],
+    key: Option<&EncryptionKey>,
+) -> Result<(Vec<u8>, Option<DamagedChunk>)> {
+    let (header, rest) = split_header(input)?;
+    let section: &[u8; SECTION_SIZE] = rest
+        .get(..SECTION_SIZE)
+        .and_then(|section| section.try_into().ok())
+        .ok_or_else(|| {
+            ValidationError::InvalidHeader("Truncated: missing encryption section".to_string())
+        })?;
+    let mut cipher = begin_decryption(key, &header, section)?;
+
+    let mut output = Vec::with_capacity(input.len());
+    let mut plain_header = header;
+    plain_header[12] &= !flags::ENCRYPTED;
+    plain_header[13] &= !ext_flags::SIGNED;
+    output.extend_from_slice(&plain_header);
+
+    let mut offset = CrushHeader::SIZE + SECTION_SIZE;
+    loop {
+        let remaining = &input[offset..];
+        if remaining.len() < TAG_SIZE {
+            let error =
+                ValidationError::CorruptedData("Truncated encrypted payload".to_string()).into();
+            return Ok((output, Some((offset, error))));
+        }
+        let last = remaining.len() < CHUNK_SIZE + TAG_SIZE;
+        let len = remaining.len().min(CHUNK_SIZE + TAG_SIZE);
+        match cipher.open(&remaining[..len], last) {
+            Ok(plaintext) => output.extend_from_slice(&plaintext),
+            Err(error) => return Ok((output, Some((offset, error)))),
+        }
+        offset += len;
+        if last {
+            return Ok((output, None));
+        }
+    }
+}
+
+fn split_header(input: &[u8]) -> Result<([u8; CrushHeader::SIZE], &[u8])> {
+    let header: [u8; CrushHeader::SIZE] = input
+        .get(..CrushHeader::SIZE)
+        .and_then(|header| header.try_into().ok())
+        .ok_or_else(|| ValidationError::InvalidHeader("Input too short".to_string()))?;
+    Ok((header, &input[CrushHeader::SIZE..]))
+}
+
+/// Writer that seals everything written after [`EncryptWriter::begin`]
+///
+/// Until then bytes pass straight through, so the header can be written in
+/// the clear first.
+pub(crate) struct EncryptWriter<W: Write> {
+    inner: W,
+    cipher: Option<ChunkCipher>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            cipher: None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Encrypt everything written from now on with `cipher`
+    pub(crate) fn begin(&mut self, cipher: ChunkCipher) {
+        self.cipher = Some(cipher);
+        self.buffer = Vec::with_capacity(CHUNK_SIZE);
+    }
+
+    pub(crate) fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Seal the final chunk and return the inner writer
+    pub(crate) fn finish(mut self) -> Result<W> {
+        if let Some(ref mut cipher) = self.cipher {
+            let sealed = cipher.seal(&self.buffer, true)?;
+            self.inner.write_all(&sealed)?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(ref mut cipher) = self.cipher else {
+            return self.inner.write(buf);
+        };
+        let take = (CHUNK_SIZE - self.buffer.len()).min(buf.len());
+        self.buffer.extend_from_slice(&buf[..take]);
+        // Seal a full chunk only once more data arrives, since the final
+        // chunk must be short
+        if self.buffer.len() == CHUNK_SIZE && take < buf.len() {
+            let sealed = cipher.seal(&self.buffer, false).map_err(io::Error::other)?;
+            self.inner.write_all(&sealed)?;
+            self.buffer.clear();
+        }
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader that opens everything read after [`DecryptReader::begin`]
+pub(crate) struct DecryptReader<R: Read> {
+    inner: R,
+    cipher: Option<ChunkCipher>,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            cipher: None,
+            buffer: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Decrypt everything read from now on with `cipher`
+    pub(crate) fn begin(&mut self, cipher: ChunkCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Check that the encrypted stream ends here
+    ///
+    /// Opens the final chunk if it has not been read yet (it is empty when
+    /// the plaintext fills whole chunks), so a stream cut at a chunk
+    /// boundary is rejected. Does nothing for unencrypted streams.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        if self.cipher.is_none() {
+            return Ok(());
+        }
+        while self.position >= self.buffer.len() && !self.finished {
+            self.next_chunk()?;
+        }
+        if self.position < self.buffer.len() {
+            return Err(ValidationError::CorruptedData(
+                "Unexpected data after encrypted stream".to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Read and open the next chunk into the buffer
+    fn next_chunk(&mut self) -> Result<()> {
+        let mut sealed = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+        let mut filled = 0;
+        while filled < sealed.len() {
+            match self.inner.read(&mut sealed[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if filled < TAG_SIZE {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let last = filled < sealed.len();
+        if let Some(ref mut cipher) = self.cipher {
+            self.buffer = cipher.open(&sealed[..filled], last)?;
+        }
+        self.position = 0;
+        self.finished = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cipher.is_none() {
+            return self.inner.read(buf);
+        }
+        while self.position >= self.buffer.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.next_chunk().map_err(|e| match e {
+                crate::error::CrushError::Io(e) => e,
+                other => io::Error::new(io::ErrorKind::InvalidData, other),
+            })?;
+        }
+        let n = (self.buffer.len() - self.position).min(buf.len());
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    /// Cheap Argon2 costs so tests stay fast
+    fn test_key(passphrase: &str) -> EncryptionKey {
+        EncryptionKey::from_passphrase_with_params(
+            passphrase,
+            KdfParams {
+                memory_kib: 64,
+                iterations: 1,
+                parallelism: 1,
+            },
+        )
+    }
+
+    fn plain_file(len: usize) -> Vec<u8> {
+        let mut file = CrushHeader::new([0x43, 0x52, 0x01, 0x00], len as u64)
+            .to_bytes()
+            .to_vec();
+        #[allow(clippy::cast_possible_truncation)]
+        file.extend((0..len).map(|i| (i % 251) as u8));
+        file
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = test_key("correct horse");
+        for len in [0, 100, CHUNK_SIZE, 2 * CHUNK_SIZE + 17] {
+            let plain = plain_file(len);
+            let encrypted = encrypt_file(&plain, &key).unwrap();
+            assert_ne!(encrypted[12] & flags::ENCRYPTED, 0);
+            assert_eq!(
+                encrypted.len(),
+                plain.len() + SECTION_SIZE + (len / CHUNK_SIZE + 1) * TAG_SIZE
+            );
+            assert_eq!(decrypt_file(&encrypted, Some(&key)).unwrap(), plain);
+        }
+    }
+
+    #[test]
+    fn test_wrong_key_is_reported() {
+        let encrypted = encrypt_file(&plain_file(1000), &test_key("right")).unwrap();
+
+        let wrong = decrypt_file(&encrypted, Some(&test_key("wrong")));
+        assert!(matches!(
+            wrong,
+            Err(CrushError::Encryption(EncryptionError::WrongKey))
+        ));
+        let missing = decrypt_file(&encrypted, None);
+        assert!(matches!(
+            missing,
+            Err(CrushError::Encryption(EncryptionError::KeyRequired))
+        ));
+        let raw = decrypt_file(&encrypted, Some(&EncryptionKey::from_bytes([7; 32])));
+        assert!(matches!(
+            raw,
+            Err(CrushError::Encryption(EncryptionError::WrongKeyKind(_)))
+        ));
+    }
+
+    #[test]
+    fn test_files_get_their_own_keys() {
+        let header = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 5)
+            .with_encrypted()
+            .to_bytes();
+        for key in [EncryptionKey::from_bytes([3; 32]), test_key("shared")] {
+            let (first_section, mut first) = begin_encryption(&key, &header).unwrap();
+            let (second_section, mut second) = begin_encryption(&key, &header).unwrap();
+            // Passphrase derivations are reused, file salts are not
+            assert_eq!(first_section[14..30], second_section[14..30]);
+            assert_ne!(first_section[30..46], second_section[30..46]);
+
+            // Even colliding nonces do not reuse a keystream
+            second.nonce_prefix = first.nonce_prefix;
+            second.aad.clone_from(&first.aad);
+            assert_ne!(
+                first.seal(b"same chunk", true).unwrap(),
+                second.seal(b"same chunk", true).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let key = EncryptionKey::from_bytes([9; 32]);
+        let encrypted = encrypt_file(&plain_file(3 * CHUNK_SIZE), &key).unwrap();
+
+        // Flipped ciphertext byte
+        let mut flipped = encrypted.clone();
+        flipped[CrushHeader::SIZE + SECTION_SIZE + 10] ^= 1;
+        assert!(matches!(
+            decrypt_file(&flipped, Some(&key)),
+            Err(CrushError::Validation(ValidationError::CorruptedData(_)))
+        ));
+
+        // Dropping the final chunk
+        let truncated = &encrypted[..encrypted.len() - TAG_SIZE];
+        assert!(decrypt_file(truncated, Some(&key)).is_err());
+
+        // Header bytes are authenticated with every chunk
+        let mut header_changed = encrypted;
+        header_changed[4] ^= 1;
+        assert!(decrypt_file(&header_changed, Some(&key)).is_err());
+    }
+
+    #[test]
+    fn test_stream_wrappers_match_in_memory_format() {
+        let key = test_key("stream");
+        let plain = plain_file(CHUNK_SIZE * 2 + 5);
+        let mut header: [u8; CrushHeader::SIZE] = plain[..CrushHeader::SIZE].try_into().unwrap();
+        header[12] |= flags::ENCRYPTED;
+
+        let mut writer = EncryptWriter::new(Vec::new());
+        writer.write_all(&header).unwrap();
+        let (section, cipher) = begin_encryption(&key, &header).unwrap();
+        writer.write_all(&section).unwrap();
+        writer.begin(cipher);
+        for chunk in plain[CrushHeader::SIZE..].chunks(10_000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let encrypted = writer.finish().unwrap();
+        assert_eq!(
+            decrypt_file(&encrypted, Some(&key)).unwrap()[CrushHeader::SIZE..],
+            plain[CrushHeader::SIZE..]
+        );
+
+        let mut reader = DecryptReader::new(encrypted.as_slice());
+        let mut read_header = [0u8; CrushHeader::SIZE];
+        reader.read_exact(&mut read_header).unwrap();
+        let mut read_section = [0u8; SECTION_SIZE];
+        reader.read_exact(&mut read_section).unwrap();
+        reader.begin(begin_decryption(Some(&key), &read_header, &read_section).unwrap());
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, plain[CrushHeader::SIZE..]);
+    }
+}
@@ -24,6 +24,14 @@ pub enum CrushError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// Encryption errors (missing or wrong key, unsupported cipher)
+    #[error("Encryption error: {0}")]
+    Encryption(#[from] EncryptionError),
+
+    /// Signature errors (unsigned file, failed verification, untrusted key)
+    #[error("Signature error: {0}")]
+    Signature(#[from] SignatureError),
+
     /// Operation was cancelled by user (Ctrl+C) or programmatically
     #[error("Operation cancelled")]
     Cancelled,
@@ -84,6 +92,14 @@ pub enum ValidationError {
     #[error("Corrupted data: {0}")]
     CorruptedData(String),
 
+    /// Decompressed data does not match the content hash stored in the file
+    #[error("{algorithm} content hash mismatch: expected {expected}, got {actual}")]
+    HashMismatch {
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
+
     /// Invalid plugin scoring weights
     #[error("Invalid scoring weights: {0}")]
     InvalidWeights(String),
@@ -91,6 +107,98 @@ pub enum ValidationError {
     /// Invalid file metadata format
     #[error("Invalid metadata format: {0}")]
     InvalidMetadata(String),
+
+    /// Invalid block size for multi-block compression
+    #[error("Invalid block size: {0}")]
+    InvalidBlockSize(String),
+
+    /// Plugin parameter unknown to the plugin or out of range
+    #[error("Invalid plugin parameter: {0}")]
+    InvalidParameter(String),
+
+    /// Decompressed output would exceed a size or expansion ratio limit
+    #[error("Output limit exceeded: {0}")]
+    OutputLimitExceeded(String),
+
+    /// Archive entry path is absolute or escapes the destination directory
+    #[error("Unsafe archive path: {0}")]
+    UnsafePath(String),
+
+    /// Parity shard count out of range
+    #[error("Invalid parity: {0}")]
+    InvalidParity(String),
+
+    /// Repair was requested for a file written without parity records
+    #[error("File has no parity data")]
+    NoParity,
+
+    /// A block has more damaged shards than its parity shards can rebuild
+    #[error("Block {block} is damaged beyond repair: {damaged_shards} damaged shards, {parity_shards} parity shards")]
+    Unrepairable {
+        /// Index of the block in the file, counting from zero
+        block: u64,
+        /// Shards whose CRC32 did not match
+        damaged_shards: usize,
+        /// Parity shards stored for the block
+        parity_shards: u8,
+    },
+}
+
+/// Encryption errors
+#[derive(Error, Debug)]
+pub enum EncryptionError {
+    /// File is encrypted but no key was supplied
+    #[error("File is encrypted; a passphrase or key file is required")]
+    KeyRequired,
+
+    /// Supplied key does not match the key the file was encrypted with
+    #[error("Wrong passphrase or key")]
+    WrongKey,
+
+    /// Passphrase supplied for a key-file file, or the other way around
+    #[error("Wrong kind of key: {0}")]
+    WrongKeyKind(String),
+
+    /// Key is malformed (wrong length or encoding)
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    /// Passphrase key derivation failed
+    #[error("Key derivation failed: {0}")]
+    KeyDerivation(String),
+
+    /// Cipher, key derivation function or parameters not supported
+    #[error("Unsupported encryption: {0}")]
+    Unsupported(String),
+}
+
+/// Signature errors
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    /// File carries no embedded signature and no detached one was given
+    #[error("File is not signed")]
+    NotSigned,
+
+    /// Signature does not match the file: either was modified after signing
+    #[error("Signature verification failed")]
+    Invalid,
+
+    /// Signature is valid but was made with a key other than the trusted one
+    #[error("Signed by untrusted key {actual} (expected {expected})")]
+    UntrustedKey {
+        /// Hex-encoded trusted public key
+        expected: String,
+        /// Hex-encoded public key the file was signed with
+        actual: String,
+    },
+
+    /// Signature trailer or detached signature is malformed
+    #[error("Malformed signature: {0}")]
+    Malformed(String),
+
+    /// Signing or public key is malformed
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
 }
 
 /// Type alias for Results using `CrushError`
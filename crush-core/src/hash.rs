@@ -0,0 +1,281 @@
+//! Content hashes of the uncompressed data
+//!
+//! The CRC32 after the header only covers the compressed payload. A content
+//! hash identifies the original bytes instead: it is computed over the
+//! uncompressed input, stored in a footer and checked against the decoded
+//! output, so it can also serve as a content identifier for deduplication.
+//!
+//! The algorithm is recorded in the low two bits of the header's extension
+//! flags (see [`crate::plugin::CrushHeader::hash_algorithm`]) and the digest
+//! follows the payload and any stream trailer, before the block index of
+//! seekable files:
+//!
+//! ```text
+//! ... payload | stream trailer (streamed only) | digest | block index (seekable only)
+//! ```
+
+use crate::error::{Result, ValidationError};
+use crate::plugin::CrushHeader;
+use serde::{Serialize, Serializer};
+use sha2::Digest;
+use std::fmt;
+use std::str::FromStr;
+
+/// Hash function for [`ContentHash`]es
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// BLAKE3, 256-bit digest
+    Blake3,
+    /// SHA-256, 256-bit digest
+    Sha256,
+    /// XXH3, 128-bit digest; fast, but not cryptographic
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// All algorithms, in id order
+    pub const ALL: [Self; 3] = [Self::Blake3, Self::Sha256, Self::Xxh3];
+
+    /// Lowercase name, as accepted by [`FromStr`]
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+            Self::Xxh3 => "xxh3",
+        }
+    }
+
+    /// Digest length in bytes
+    #[must_use]
+    pub fn digest_len(self) -> usize {
+        match self {
+            Self::Blake3 | Self::Sha256 => 32,
+            Self::Xxh3 => 16,
+        }
+    }
+
+    /// Id stored in the header's extension flags (never zero)
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::Blake3 => 1,
+            Self::Sha256 => 2,
+            Self::Xxh3 => 3,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Blake3),
+            2 => Some(Self::Sha256),
+            3 => Some(Self::Xxh3),
+            _ => None,
+        }
+    }
+
+    /// Hash `data` in one step
+    #[must_use]
+    pub fn hash(self, data: &[u8]) -> ContentHash {
+        let mut hasher = ContentHasher::new(self);
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                ValidationError::InvalidParameter(format!(
+                    "unknown hash algorithm '{s}' (expected blake3, sha256 or xxh3)"
+                ))
+            })
+    }
+}
+
+/// Hash of the uncompressed content of a Crush file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentHash {
+    /// Hash function the digest was computed with
+    pub algorithm: HashAlgorithm,
+    /// Raw digest, [`HashAlgorithm::digest_len`] bytes long
+    pub digest: Vec<u8>,
+}
+
+impl ContentHash {
+    /// Lowercase hex encoding of the digest
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.digest)
+    }
+}
+
+impl fmt::Display for ContentHash {
+    /// `algorithm:hexdigest`, e.g. `blake3:af13...`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.to_hex())
+    }
+}
+
+impl Serialize for ContentHash {
+    /// `{"algorithm": "blake3", "digest": "<hex>"}`
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("ContentHash", 2)?;
+        state.serialize_field("algorithm", self.algorithm.name())?;
+        state.serialize_field("digest", &self.to_hex())?;
+        state.end()
+    }
+}
+
+/// Incremental hasher for one of the [`HashAlgorithm`]s
+pub(crate) enum ContentHasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(sha2::Sha256),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl ContentHasher {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Xxh3 => Self::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    pub(crate) fn finalize(&self) -> ContentHash {
+        match self {
+            Self::Blake3(hasher) => ContentHash {
+                algorithm: HashAlgorithm::Blake3,
+                digest: hasher.finalize().as_bytes().to_vec(),
+            },
+            Self::Sha256(hasher) => ContentHash {
+                algorithm: HashAlgorithm::Sha256,
+                digest: hasher.clone().finalize().to_vec(),
+            },
+            Self::Xxh3(hasher) => ContentHash {
+                algorithm: HashAlgorithm::Xxh3,
+                digest: hasher.digest128().to_be_bytes().to_vec(),
+            },
+        }
+    }
+}
+
+/// Lowercase hex encoding of `bytes`
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    use fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Split the digest footer off the end of `input`
+///
+/// `input` must already have any block index removed. Returns `input`
+/// unchanged if the header records no content hash.
+pub(crate) fn split_footer<'a>(
+    input: &'a [u8],
+    header: &CrushHeader,
+) -> Result<(&'a [u8], Option<ContentHash>)> {
+    let Some(algorithm) = header.hash_algorithm() else {
+        return Ok((input, None));
+    };
+    let start = input
+        .len()
+        .checked_sub(algorithm.digest_len())
+        .filter(|&start| start >= CrushHeader::SIZE)
+        .ok_or_else(|| ValidationError::CorruptedData("Truncated content hash".to_string()))?;
+    Ok((
+        &input[..start],
+        Some(ContentHash {
+            algorithm,
+            digest: input[start..].to_vec(),
+        }),
+    ))
+}
+
+/// Check a freshly computed hash against the one stored in the file
+pub(crate) fn verify(expected: &ContentHash, actual: &ContentHash) -> Result<()> {
+    if expected != actual {
+        return Err(ValidationError::HashMismatch {
+            algorithm: expected.algorithm.name(),
+            expected: expected.to_hex(),
+            actual: actual.to_hex(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            HashAlgorithm::Sha256.hash(b"abc").to_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            HashAlgorithm::Blake3.hash(b"").to_hex(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        for algorithm in HashAlgorithm::ALL {
+            assert_eq!(algorithm.hash(b"data").digest.len(), algorithm.digest_len());
+            assert_eq!(HashAlgorithm::from_id(algorithm.id()), Some(algorithm));
+        }
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        for algorithm in HashAlgorithm::ALL {
+            let mut hasher = ContentHasher::new(algorithm);
+            for chunk in data.chunks(777) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), algorithm.hash(&data));
+        }
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(
+            "SHA256".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Sha256
+        );
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+        let hash = HashAlgorithm::Xxh3.hash(b"x");
+        assert!(hash.to_string().starts_with("xxh3:"));
+        assert_eq!(
+            serde_json::to_value(&hash).unwrap()["digest"],
+            hash.to_hex()
+        );
+    }
+}
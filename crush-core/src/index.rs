@@ -0,0 +1,569 @@
+//! Seekable block index
+//!
+//! Multi-block files can carry an index of their blocks so that a byte range
+//! of the uncompressed data can be decoded without reading the whole file
+//! (see [`read_range`]). The index is appended after everything else in the
+//! file and the header's `SEEKABLE` flag is set:
+//!
+//! ```text
+//! Offset | Size   | Field
+//! -------|--------|-------
+//! 0      | 28 * n | index entries (see below)
+//! 28 * n | 4      | entry_count (u32)
+//! +4     | 4      | crc32 (u32, CRC32 of the index entries)
+//! +8     | 4      | index magic ("CRIX")
+//! ```
+//!
+//! Each index entry is:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 8    | compressed_offset (u64, file offset of the block frame)
+//! 8      | 8    | uncompressed_offset (u64)
+//! 16     | 4    | compressed_len (u32)
+//! 20     | 4    | uncompressed_len (u32)
+//! 24     | 4    | crc32 (u32, CRC32 of the compressed bytes)
+//! ```
+//!
+//! The index is not covered by the file's CRC32 or stream trailer; it carries
+//! its own checksum, and each block read through it is verified against the
+//! block frame it points to.
+
+use crate::block::{self, BlockEntry, BlockFrame};
+use crate::error::{PluginError, Result, ValidationError};
+use crate::parity;
+use crate::plugin::params::ConfiguredPlugin;
+use crate::plugin::registry::get_plugin_by_magic;
+use crate::plugin::{CrushHeader, PluginParams};
+use crate::signature;
+use crate::stream::CrushReader;
+use crc32fast::Hasher;
+use rayon::prelude::*;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Magic bytes closing a block index footer
+pub const INDEX_MAGIC: [u8; 4] = *b"CRIX";
+
+/// Size of the fixed footer at the very end of a seekable file
+pub const INDEX_FOOTER_SIZE: usize = 12;
+
+/// Location and checksum of one block in a seekable file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockIndexEntry {
+    /// File offset of the block's frame header
+    pub compressed_offset: u64,
+
+    /// Offset of the block's first byte in the uncompressed data
+    pub uncompressed_offset: u64,
+
+    /// Length of the compressed block data (excluding the frame header)
+    pub compressed_len: u32,
+
+    /// Length of the uncompressed block data
+    pub uncompressed_len: u32,
+
+    /// CRC32 of the compressed block data
+    pub crc32: u32,
+}
+
+impl BlockIndexEntry {
+    /// Size of a serialized index entry in bytes
+    pub const SIZE: usize = 28;
+
+    /// Serialize the entry to little-endian bytes
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.compressed_offset.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.uncompressed_offset.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.compressed_len.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.uncompressed_len.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.crc32.to_le_bytes());
+        bytes
+    }
+
+    /// Deserialize an entry from little-endian bytes
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+        let u64_at = |i: usize| {
+            u64::from_le_bytes([
+                bytes[i],
+                bytes[i + 1],
+                bytes[i + 2],
+                bytes[i + 3],
+                bytes[i + 4],
+                bytes[i + 5],
+                bytes[i + 6],
+                bytes[i + 7],
+            ])
+        };
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Self {
+            compressed_offset: u64_at(0),
+            uncompressed_offset: u64_at(8),
+            compressed_len: u32_at(16),
+            uncompressed_len: u32_at(20),
+            crc32: u32_at(24),
+        }
+    }
+
+    /// Check that a block frame read from the file matches this entry
+    fn matches(&self, frame: &BlockFrame) -> bool {
+        frame.compressed_len == self.compressed_len
+            && frame.uncompressed_len == self.uncompressed_len
+            && frame.crc32 == self.crc32
+    }
+}
+
+/// Index of every block in a seekable file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockIndex {
+    entries: Vec<BlockIndexEntry>,
+}
+
+impl BlockIndex {
+    /// Index entries in file order
+    #[must_use]
+    pub fn entries(&self) -> &[BlockIndexEntry] {
+        &self.entries
+    }
+
+    /// Number of blocks in the file
+    #[must_use]
+    pub fn block_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Total uncompressed size covered by the index
+    #[must_use]
+    pub fn uncompressed_size(&self) -> u64 {
+        self.entries
+            .last()
+            .map_or(0, |e| e.uncompressed_offset + u64::from(e.uncompressed_len))
+    }
+
+    /// Record a block frame written at `compressed_offset`
+    pub(crate) fn push(&mut self, compressed_offset: u64, frame: &BlockFrame) {
+        let uncompressed_offset = self.uncompressed_size();
+        self.entries.push(BlockIndexEntry {
+            compressed_offset,
+            uncompressed_offset,
+            compressed_len: frame.compressed_len,
+            uncompressed_len: frame.uncompressed_len,
+            crc32: frame.crc32,
+        });
+    }
+
+    /// Build the index of a block section that starts at `section_offset`
+    ///
+    /// With `parity_shards`, entries point past each block's parity record.
+    pub(crate) fn from_section(
+        section: &[u8],
+        section_offset: u64,
+        parity_shards: Option<u8>,
+    ) -> Result<Self> {
+        let (_, entries, _) = block::parse_blocks(section, parity_shards.is_some())?;
+        let mut index = Self::default();
+        // Skip the u32 nominal block size that opens the section
+        let mut offset = section_offset + 4;
+        for entry in &entries {
+            let protected_len = BlockFrame::SIZE + entry.data.len();
+            if let Some(shards) = parity_shards {
+                offset += parity::record_len(protected_len, shards)? as u64;
+            }
+            index.push(offset, &entry.frame);
+            offset += protected_len as u64;
+        }
+        Ok(index)
+    }
+
+    /// Serialize the index entries followed by the footer
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(self.entries.len() * BlockIndexEntry::SIZE + INDEX_FOOTER_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.to_bytes());
+        }
+
+        let mut hasher = Hasher::new();
+        hasher.update(&bytes);
+        let crc32 = hasher.finalize();
+
+        #[allow(clippy::cast_possible_truncation)]
+        let count = self.entries.len() as u32; // one entry per block of at least MIN_BLOCK_SIZE
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes.extend_from_slice(&crc32.to_le_bytes());
+        bytes.extend_from_slice(&INDEX_MAGIC);
+        bytes
+    }
+
+    /// Parse the index footer into the entry count and expected CRC32
+    fn parse_footer(footer: &[u8; INDEX_FOOTER_SIZE]) -> Result<(usize, u32)> {
+        if footer[8..12] != INDEX_MAGIC {
+            return Err(ValidationError::CorruptedData(
+                "Seekable flag set but no block index footer found".to_string(),
+            )
+            .into());
+        }
+        let count = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]) as usize;
+        let crc32 = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]);
+        Ok((count, crc32))
+    }
+
+    /// Decode and verify the serialized index entries
+    fn parse_entries(bytes: &[u8], expected_crc: u32) -> Result<Self> {
+        let mut hasher = Hasher::new();
+        hasher.update(bytes);
+        let actual = hasher.finalize();
+        if actual != expected_crc {
+            return Err(ValidationError::CrcMismatch {
+                expected: expected_crc,
+                actual,
+            }
+            .into());
+        }
+
+        let entries = bytes
+            .chunks_exact(BlockIndexEntry::SIZE)
+            .filter_map(|chunk| chunk.try_into().ok())
+            .map(|chunk: &[u8; BlockIndexEntry::SIZE]| BlockIndexEntry::from_bytes(chunk))
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// Split a complete seekable file into its content and its block index
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the footer is missing, the index is truncated, or
+    /// its CRC32 does not match.
+    pub fn split(input: &[u8]) -> Result<(&[u8], Self)> {
+        let truncated = || ValidationError::CorruptedData("Truncated block index".to_string());
+
+        let footer_start = input
+            .len()
+            .checked_sub(INDEX_FOOTER_SIZE)
+            .ok_or_else(truncated)?;
+        let footer: [u8; INDEX_FOOTER_SIZE] =
+            input[footer_start..].try_into().map_err(|_| truncated())?;
+        let (count, crc32) = Self::parse_footer(&footer)?;
+
+        let index_start = count
+            .checked_mul(BlockIndexEntry::SIZE)
+            .and_then(|len| footer_start.checked_sub(len))
+            .ok_or_else(truncated)?;
+        let index = Self::parse_entries(&input[index_start..footer_start], crc32)?;
+        Ok((&input[..index_start], index))
+    }
+
+    /// Read the block index from the end of a seekable file
+    ///
+    /// The index must be the last thing in the file; [`read_range`] also
+    /// handles signed files, whose signature trailer follows the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if seeking or reading fails, the footer is missing,
+    /// or the index CRC32 does not match.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        Self::read_ending_at(reader, file_len)
+    }
+
+    /// Read the block index that ends at offset `file_len`
+    fn read_ending_at<R: Read + Seek>(reader: &mut R, file_len: u64) -> Result<Self> {
+        let footer_len = INDEX_FOOTER_SIZE as u64;
+        if file_len < footer_len {
+            return Err(ValidationError::CorruptedData("Truncated block index".to_string()).into());
+        }
+        reader.seek(SeekFrom::Start(file_len - footer_len))?;
+        let mut footer = [0u8; INDEX_FOOTER_SIZE];
+        reader.read_exact(&mut footer)?;
+        let (count, crc32) = Self::parse_footer(&footer)?;
+
+        let index_len = (count as u64) * (BlockIndexEntry::SIZE as u64);
+        let index_start = (file_len - footer_len)
+            .checked_sub(index_len)
+            .ok_or_else(|| ValidationError::CorruptedData("Truncated block index".to_string()))?;
+        reader.seek(SeekFrom::Start(index_start))?;
+        let mut bytes = vec![0u8; count * BlockIndexEntry::SIZE];
+        reader.read_exact(&mut bytes)?;
+
+        Self::parse_entries(&bytes, crc32)
+    }
+
+    /// Entries of the blocks overlapping the uncompressed range `[start, end)`
+    fn blocks_for_range(&self, start: u64, end: u64) -> &[BlockIndexEntry] {
+        let first = self
+            .entries
+            .partition_point(|e| e.uncompressed_offset + u64::from(e.uncompressed_len) <= start);
+        let last = self
+            .entries
+            .partition_point(|e| e.uncompressed_offset < end);
+        &self.entries[first..last.max(first)]
+    }
+}
+
+/// Read a byte range of the uncompressed data from a Crush file
+///
+/// For seekable files (compressed with [`crate::CompressionOptions::with_seekable`])
+/// only the blocks overlapping `offset..offset + len` are read and
+/// decompressed. Other files are decoded sequentially up to the end of the
+/// range. A range extending past the end of the data is truncated, so the
+/// result may be shorter than `len`.
+///
+/// Blocks read through the index are not repaired from parity; repair the
+/// file first (see [`crate::repair`]) if a block fails its CRC32.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The header or block index is invalid
+/// - A block read through the index does not match its frame or CRC32
+/// - No plugin is registered for the header's magic number
+/// - Reading, seeking, or decompression fails
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::{compress_with_options, init_plugins, read_range, CompressionOptions};
+/// use std::io::Cursor;
+///
+/// init_plugins().expect("Plugin initialization failed");
+/// let data: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+/// let options = CompressionOptions::default()
+///     .with_block_size(64 * 1024)
+///     .with_seekable(true);
+/// let compressed = compress_with_options(&data, &options).expect("Compression failed");
+///
+/// let range = read_range(Cursor::new(compressed), 150_000, 100).expect("Read failed");
+/// assert_eq!(range, &data[150_000..150_100]);
+/// ```
+pub fn read_range<R: Read + Seek>(mut reader: R, offset: u64, len: usize) -> Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let header = CrushHeader::read_from(&mut reader)?;
+
+    if !header.is_seekable() {
+        reader.seek(SeekFrom::Start(0))?;
+        return read_range_sequential(reader, offset, len);
+    }
+
+    let plugin = get_plugin_by_magic(header.magic).ok_or_else(|| {
+        PluginError::NotFound(format!(
+            "No plugin found for magic number {:02X?}",
+            header.magic
+        ))
+    })?;
+    let plugin = ConfiguredPlugin::new(plugin, read_params(&mut reader, &header)?)?;
+
+    let index_end = reader
+        .seek(SeekFrom::End(0))?
+        .checked_sub(signature::trailer_len(&header) as u64)
+        .ok_or_else(|| ValidationError::CorruptedData("Truncated signature".to_string()))?;
+    let index = BlockIndex::read_ending_at(&mut reader, index_end)?;
+    let start = offset.min(index.uncompressed_size());
+    let end = offset
+        .saturating_add(len as u64)
+        .min(index.uncompressed_size());
+    let needed = index.blocks_for_range(start, end);
+    let Some(first) = needed.first() else {
+        return Ok(Vec::new());
+    };
+
+    let mut blocks = Vec::with_capacity(needed.len());
+    for entry in needed {
+        reader.seek(SeekFrom::Start(entry.compressed_offset))?;
+        let mut frame_bytes = [0u8; BlockFrame::SIZE];
+        reader.read_exact(&mut frame_bytes)?;
+        let frame = BlockFrame::from_bytes(&frame_bytes);
+        if !entry.matches(&frame) {
+            return Err(ValidationError::CorruptedData(format!(
+                "Block index entry at offset {} does not match its block frame",
+                entry.compressed_offset
+            ))
+            .into());
+        }
+        let mut data = vec![0u8; entry.compressed_len as usize];
+        reader.read_exact(&mut data)?;
+        blocks.push((frame, data));
+    }
+
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let decoded = blocks
+        .par_iter()
+        .map(|(frame, data)| {
+            let entry = BlockEntry {
+                frame: *frame,
+                data,
+            };
+            block::decompress_block(
+                &plugin,
+                &entry,
+                header.has_stored_blocks(),
+                Arc::clone(&cancel_flag),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    #[allow(clippy::cast_possible_truncation)]
+    let skip = (start - first.uncompressed_offset) as usize; // < one block
+    #[allow(clippy::cast_possible_truncation)]
+    let take = (end - start) as usize; // <= len
+    Ok(decoded.concat().into_iter().skip(skip).take(take).collect())
+}
+
+/// Read the plugin parameters following the header, skipping the CRC32 and
+/// metadata sections
+///
+/// `reader` must be positioned just after the header.
+fn read_params<R: Read>(reader: &mut R, header: &CrushHeader) -> Result<PluginParams> {
+    if !header.has_params() {
+        return Ok(PluginParams::new());
+    }
+    if header.has_crc32() {
+        reader.read_exact(&mut [0u8; 4])?;
+    }
+    let mut len_bytes = [0u8; 2];
+    if header.has_metadata() {
+        reader.read_exact(&mut len_bytes)?;
+        let metadata_len = u64::from(u16::from_le_bytes(len_bytes));
+        io::copy(&mut reader.by_ref().take(metadata_len), &mut io::sink())?;
+    }
+    reader.read_exact(&mut len_bytes)?;
+    let mut params_bytes = vec![0u8; u16::from_le_bytes(len_bytes) as usize];
+    reader.read_exact(&mut params_bytes)?;
+    PluginParams::from_bytes(&params_bytes)
+}
+
+/// Fallback for files without a block index: decode from the start
+fn read_range_sequential<R: Read>(reader: R, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut decoder = CrushReader::new(reader)?;
+    io::copy(&mut (&mut decoder).take(offset), &mut io::sink())?;
+
+    let mut output = Vec::new();
+    decoder.take(len as u64).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{compress_with_options, init_plugins, CompressionOptions};
+    use std::io::Cursor;
+
+    fn sample_data(len: u32) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn seekable_options() -> CompressionOptions {
+        CompressionOptions::default()
+            .with_block_size(block::MIN_BLOCK_SIZE)
+            .with_seekable(true)
+    }
+
+    #[test]
+    fn test_index_entry_roundtrip() {
+        let entry = BlockIndexEntry {
+            compressed_offset: 1 << 40,
+            uncompressed_offset: 123_456,
+            compressed_len: 789,
+            uncompressed_len: 4096,
+            crc32: 0xDEAD_BEEF,
+        };
+        assert_eq!(BlockIndexEntry::from_bytes(&entry.to_bytes()), entry);
+    }
+
+    #[test]
+    fn test_index_split_roundtrip() {
+        let mut index = BlockIndex::default();
+        let frame = BlockFrame {
+            uncompressed_len: 4096,
+            compressed_len: 100,
+            crc32: 1,
+        };
+        index.push(20, &frame);
+        index.push(132, &frame);
+
+        let mut file = b"content".to_vec();
+        file.extend_from_slice(&index.to_bytes());
+        let (content, parsed) = BlockIndex::split(&file).unwrap();
+
+        assert_eq!(content, b"content");
+        assert_eq!(parsed, index);
+        assert_eq!(parsed.uncompressed_size(), 8192);
+        assert_eq!(
+            BlockIndex::read_from(&mut Cursor::new(&file)).unwrap(),
+            index
+        );
+    }
+
+    #[test]
+    fn test_index_corrupted() {
+        let mut index = BlockIndex::default();
+        index.push(
+            20,
+            &BlockFrame {
+                uncompressed_len: 4096,
+                compressed_len: 100,
+                crc32: 1,
+            },
+        );
+        let mut bytes = index.to_bytes();
+        bytes[0] ^= 0xFF;
+        assert!(BlockIndex::split(&bytes).is_err());
+
+        assert!(BlockIndex::split(b"no footer here").is_err());
+    }
+
+    #[test]
+    fn test_read_range_seekable() {
+        init_plugins().unwrap();
+        let data = sample_data(50_000);
+        let compressed = compress_with_options(&data, &seekable_options()).unwrap();
+
+        for (offset, len) in [
+            (0, 10),
+            (4090, 20),
+            (12_288, 4096),
+            (49_990, 100),
+            (60_000, 5),
+        ] {
+            let range = read_range(Cursor::new(&compressed), offset as u64, len).unwrap();
+            let start = offset.min(data.len());
+            let end = (start + len).min(data.len());
+            assert_eq!(range, &data[start..end], "range {offset}+{len}");
+        }
+    }
+
+    #[test]
+    fn test_read_range_not_seekable() {
+        init_plugins().unwrap();
+        let data = sample_data(20_000);
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let compressed = compress_with_options(&data, &options).unwrap();
+
+        let range = read_range(Cursor::new(&compressed), 10_000, 500).unwrap();
+        assert_eq!(range, &data[10_000..10_500]);
+    }
+
+    #[test]
+    fn test_read_range_detects_mismatched_frame() {
+        init_plugins().unwrap();
+        let data = sample_data(20_000);
+        let mut compressed = compress_with_options(&data, &seekable_options()).unwrap();
+
+        // Corrupt the first block's frame header (its uncompressed length)
+        let index = BlockIndex::split(&compressed).unwrap().1;
+        let frame_offset = usize::try_from(index.entries()[0].compressed_offset).unwrap();
+        compressed[frame_offset] ^= 0xFF;
+
+        assert!(read_range(Cursor::new(&compressed), 0, 10).is_err());
+    }
+}
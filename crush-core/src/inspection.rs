@@ -1,6 +1,13 @@
+use crate::block;
+use crate::decompression::{parse_sections, read_stored_crc};
+use crate::encryption::{self, EncryptionInfo};
 use crate::error::{PluginError, Result, ValidationError};
+use crate::hash::{self, ContentHash};
+use crate::index::BlockIndex;
+use crate::parity;
 use crate::plugin::registry::get_plugin_by_magic;
-use crate::plugin::{CrushHeader, FileMetadata};
+use crate::plugin::{CrushHeader, FileMetadata, PluginParams};
+use crate::signature::{self, SignatureStatus};
 use crc32fast::Hasher;
 use serde::Serialize;
 
@@ -11,10 +18,38 @@ pub struct InspectResult {
     pub plugin_name: String,
     pub crc_valid: bool,
     pub metadata: FileMetadata,
+    /// Whether the file carries a block index for random-access reads
+    pub seekable: bool,
+    /// Number of independently compressed blocks in the payload
+    pub block_count: usize,
+    /// Plugin-specific parameters the file was compressed with
+    pub plugin_params: PluginParams,
+    /// Cipher and key derivation of encrypted files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionInfo>,
+    /// Hash of the uncompressed content, if one was recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<ContentHash>,
+    /// Embedded signature and whether it matches the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureStatus>,
+    /// Reed-Solomon parity shards stored with each block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parity_shards: Option<u8>,
 }
 
 /// Inspects a compressed file and returns metadata about its contents.
 ///
+/// Encrypted files only reveal their header and encryption section without
+/// the key: the plugin and cipher are reported, while the CRC32, metadata,
+/// parameters, block count and content hash are left unset (and the original
+/// size is zero for streamed files).
+///
+/// The content hash is reported as stored; it is only verified against the
+/// data by decompression. An embedded signature is checked against the key
+/// recorded in it, which works for encrypted files too; use
+/// [`crate::verify_signature`] to check it against a trusted key.
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -37,22 +72,64 @@ pub fn inspect(input: &[u8]) -> Result<InspectResult> {
         .map_err(|_| ValidationError::InvalidHeader("Failed to read header".to_string()))?;
     let header = CrushHeader::from_bytes(&header_bytes)?;
 
-    let mut payload_start = CrushHeader::SIZE;
-    let mut crc_valid = false;
+    let compressed_size = input.len() as u64;
+    let (input, signature) = signature::embedded_status(input, &header)?;
 
-    if header.has_crc32() {
-        if input.len() < payload_start + 4 {
-            return Err(ValidationError::InvalidHeader(
-                "Truncated: CRC32 flag set but no CRC32 data".to_string(),
+    if header.is_encrypted() {
+        let section: &[u8; encryption::SECTION_SIZE] = input[CrushHeader::SIZE..]
+            .get(..encryption::SECTION_SIZE)
+            .and_then(|section| section.try_into().ok())
+            .ok_or_else(|| {
+                ValidationError::InvalidHeader("Truncated: missing encryption section".to_string())
+            })?;
+        return Ok(InspectResult {
+            original_size: header.original_size,
+            compressed_size,
+            plugin_name: plugin_name(&header)?,
+            crc_valid: false,
+            metadata: FileMetadata::default(),
+            seekable: false,
+            block_count: 0,
+            plugin_params: PluginParams::new(),
+            encryption: Some(EncryptionInfo::from_section(section)?),
+            content_hash: None,
+            signature,
+            parity_shards: None,
+        });
+    }
+
+    // Seekable files end with a block index
+    let (input, index) = if header.is_seekable() {
+        let (content, index) = BlockIndex::split(input)?;
+        if content.len() < CrushHeader::SIZE {
+            return Err(ValidationError::CorruptedData(
+                "Block index overlaps the file header".to_string(),
             )
             .into());
         }
-        let stored_crc = u32::from_le_bytes([
-            input[payload_start],
-            input[payload_start + 1],
-            input[payload_start + 2],
-            input[payload_start + 3],
-        ]);
+        (content, Some(index))
+    } else {
+        (input, None)
+    };
+
+    let (input, content_hash) = hash::split_footer(input, &header)?;
+
+    // Streamed output carries its size and CRC32 in a trailer after the blocks
+    let (input, original_size, mut crc_valid) = if header.is_streamed() {
+        let (content, trailer) = block::split_stream_trailer(&input[CrushHeader::SIZE..])?;
+        (
+            &input[..CrushHeader::SIZE + content.len()],
+            trailer.original_size,
+            trailer.verify(content).is_ok(),
+        )
+    } else {
+        (input, header.original_size, false)
+    };
+
+    let mut payload_start = CrushHeader::SIZE;
+
+    if header.has_crc32() {
+        let stored_crc = read_stored_crc(input, payload_start)?;
         payload_start += 4;
 
         let payload_for_crc = &input[payload_start..];
@@ -63,42 +140,44 @@ pub fn inspect(input: &[u8]) -> Result<InspectResult> {
         crc_valid = stored_crc == computed_crc;
     }
 
-    let metadata = if header.has_metadata() {
-        if input.len() < payload_start + 2 {
-            return Err(ValidationError::InvalidHeader(
-                "Truncated: metadata flag set but no metadata length".to_string(),
-            )
-            .into());
-        }
-        let metadata_len =
-            u16::from_le_bytes([input[payload_start], input[payload_start + 1]]) as usize;
-        payload_start += 2;
+    let (metadata, plugin_params, payload_start) = parse_sections(input, &header, payload_start)?;
 
-        if input.len() < payload_start + metadata_len {
-            return Err(ValidationError::InvalidHeader(
-                "Truncated: metadata length exceeds payload size".to_string(),
-            )
-            .into());
+    // A single-stream payload counts as one block; an unparseable block
+    // section is reported as zero blocks rather than failing the inspection
+    let block_count = match index {
+        Some(ref index) => index.block_count(),
+        None if header.has_blocks() => {
+            block::parse_blocks(&input[payload_start..], header.has_parity())
+                .map_or(0, |(_, entries, _)| entries.len())
         }
-        let metadata_bytes = &input[payload_start..payload_start + metadata_len];
-
-        FileMetadata::from_bytes(metadata_bytes)?
-    } else {
-        FileMetadata::default()
+        None => 1,
     };
 
+    Ok(InspectResult {
+        original_size,
+        compressed_size,
+        plugin_name: plugin_name(&header)?,
+        crc_valid,
+        metadata,
+        seekable: header.is_seekable(),
+        block_count,
+        plugin_params,
+        encryption: None,
+        content_hash,
+        signature,
+        parity_shards: header
+            .has_parity()
+            .then(|| parity::parity_shards(&input[payload_start..]))
+            .flatten(),
+    })
+}
+
+fn plugin_name(header: &CrushHeader) -> Result<String> {
     let plugin = get_plugin_by_magic(header.magic).ok_or_else(|| {
         PluginError::NotFound(format!(
             "No plugin found for magic number {:02X?}",
             header.magic
         ))
     })?;
-
-    Ok(InspectResult {
-        original_size: header.original_size,
-        compressed_size: input.len() as u64,
-        plugin_name: plugin.name().to_string(),
-        crc_valid,
-        metadata,
-    })
+    Ok(plugin.name().to_string())
 }
@@ -7,6 +7,15 @@
 //! Crush provides a flexible plugin-based compression system with:
 //! - **Pluggable algorithms**: Add custom compression algorithms via the [`CompressionAlgorithm`] trait
 //! - **Intelligent selection**: Automatic plugin selection based on scoring weights
+//! - **Parallel blocks**: Large inputs are split into blocks compressed on the rayon thread pool
+//! - **Streaming**: [`CrushWriter`] and [`CrushReader`] compress and decompress with bounded memory
+//! - **Random access**: Seekable files carry a block index so [`read_range`] decodes only the blocks it needs
+//! - **Content hashes**: Optional BLAKE3, SHA-256 or XXH3 digest of the uncompressed data, verified on decompression
+//! - **Encryption**: Optional ChaCha20-Poly1305 encryption with an [`EncryptionKey`] from a passphrase or raw key
+//! - **Parity**: Optional Reed-Solomon parity per block, so decompression repairs a few damaged bytes
+//! - **Recovery**: [`recover`] salvages the intact data of damaged or truncated files and reports what was lost
+//! - **Signatures**: Ed25519 signatures over finished files, embedded in a trailer or detached
+//! - **Archives**: [`ArchiveWriter`] and [`ArchiveReader`] bundle files, directories and symlinks
 //! - **Timeout protection**: Configurable timeouts prevent runaway compression operations
 //! - **Zero-copy design**: Minimal allocations and efficient memory usage
 //!
@@ -67,19 +76,43 @@
 //! }
 //! ```
 
+pub mod archive;
+pub mod block;
 pub mod cancel;
 pub mod compression;
 pub mod decompression;
+pub mod encryption;
 pub mod error;
+pub mod hash;
+pub mod index;
 pub mod inspection;
+pub mod parity;
 pub mod plugin;
+pub mod recovery;
+pub mod signature;
+pub mod stream;
 
+pub use archive::{ArchiveEntry, ArchiveReader, ArchiveWriter, EntryKind};
 pub use cancel::{AtomicCancellationToken, CancellationToken, ResourceTracker};
 pub use compression::{compress, compress_with_options, CompressionOptions};
-pub use decompression::decompress;
-pub use error::{CrushError, PluginError, Result, TimeoutError, ValidationError};
+pub use decompression::{decompress, decompress_with_options, DecompressionOptions};
+pub use encryption::{EncryptionInfo, EncryptionKey, KdfParams};
+pub use error::{
+    CrushError, EncryptionError, PluginError, Result, SignatureError, TimeoutError, ValidationError,
+};
+pub use hash::{ContentHash, HashAlgorithm};
+pub use index::{read_range, BlockIndex, BlockIndexEntry};
 pub use inspection::{inspect, InspectResult};
+pub use parity::{repair, BlockRepair, RepairReport};
 pub use plugin::{
-    calculate_plugin_score, init_plugins, list_plugins, CompressionAlgorithm, CrushHeader,
-    PluginMetadata, PluginSelector, ScoringWeights, COMPRESSION_ALGORITHMS,
+    active_calibration, calculate_plugin_score, calibrate, init_plugins, list_plugins,
+    plugin_parameters, set_calibration, Calibration, CompressionAlgorithm, CrushHeader, ParamSpec,
+    PluginCalibration, PluginMetadata, PluginParams, PluginSelector, SamplingConfig,
+    ScoringWeights, COMPRESSION_ALGORITHMS,
+};
+pub use recovery::{recover, recover_with_options, DamagedRegion, RecoveryReport, RecoveryResult};
+pub use signature::{
+    sign, sign_detached, verify_detached, verify_signature, Signature, SignatureStatus, SigningKey,
+    VerifyingKey,
 };
+pub use stream::{CrushReader, CrushWriter};
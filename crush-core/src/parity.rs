@@ -0,0 +1,635 @@
+//! Reed-Solomon parity records for self-healing block files
+//!
+//! Files compressed with [`crate::CompressionOptions::with_parity`] set the
+//! `PARITY` header extension flag and precede every block frame in the block
+//! section (see [`crate::block`]), including the end marker, with a parity
+//! record:
+//!
+//! ```text
+//! ... block_size | record | frame | data | record | frame | data | ... | record | end marker
+//! ```
+//!
+//! The record protects the frame header and compressed bytes that follow it.
+//! Those bytes are split into `data_shards` equal shards (the last one
+//! zero-padded) and `parity_shards` Reed-Solomon parity shards are computed
+//! over them. A record is:
+//!
+//! ```text
+//! Size                  | Field
+//! ----------------------|-------
+//! 12 * (parity + 1)     | record header, repeated (see below)
+//! 4 * (data + parity)   | crc32 of every data and parity shard (u32 each)
+//! shard_len * parity    | parity shards
+//! ```
+//!
+//! Each record header copy is:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 4    | protected_len (u32, frame header plus compressed bytes)
+//! 4      | 1    | data_shards (u8)
+//! 5      | 1    | parity_shards (u8)
+//! 6      | 2    | reserved (zero)
+//! 8      | 4    | crc32 (u32, CRC32 of bytes 0..8)
+//! ```
+//!
+//! Shards whose CRC32 does not match are treated as erasures and rebuilt
+//! from the others, so a block survives as many damaged shards as it has
+//! parity shards. A damaged byte spoils at most one shard (or one shard
+//! checksum, or one header copy), so up to `parity_shards` damaged bytes per
+//! block are always repairable; damage clustered within one shard counts
+//! once. Repair restores the record as well, so the repaired file is
+//! byte-for-byte the original and its CRC32, stream trailer, content hash and
+//! signature verify again.
+//!
+//! Only the block section is protected: damage to the file header, metadata,
+//! plugin parameters or the sections after the end marker cannot be repaired.
+//! Encrypted files cannot carry parity, since decryption authenticates the
+//! file as a whole before any block could be repaired.
+
+use crate::block::{self, BlockFrame};
+use crate::decompression::parse_sections;
+use crate::error::{Result, ValidationError};
+use crate::plugin::CrushHeader;
+use crc32fast::Hasher;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+/// Largest accepted number of parity shards per block
+pub const MAX_PARITY_SHARDS: u8 = 64;
+
+/// Most data shards a block is split into
+const MAX_DATA_SHARDS: usize = 128;
+
+/// Shortest data shard; smaller blocks are split into fewer shards
+const MIN_SHARD_LEN: usize = 64;
+
+/// Largest protected length a record may claim, matching the frame limit
+/// readers enforce
+const MAX_PROTECTED_LEN: usize = BlockFrame::SIZE + 2 * block::MAX_BLOCK_SIZE;
+
+/// Check that `parity_shards` is in `1..=MAX_PARITY_SHARDS`
+pub(crate) fn validate_parity_shards(parity_shards: u8) -> Result<()> {
+    if (1..=MAX_PARITY_SHARDS).contains(&parity_shards) {
+        Ok(())
+    } else {
+        Err(ValidationError::InvalidParity(format!(
+            "{parity_shards} parity shards (must be between 1 and {MAX_PARITY_SHARDS})"
+        ))
+        .into())
+    }
+}
+
+/// Block repaired from its parity record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRepair {
+    /// Index of the block in the file, counting from zero
+    pub block: u64,
+    /// Number of bytes that differed from the original, including bytes of
+    /// the parity record itself
+    pub repaired_bytes: usize,
+}
+
+/// Blocks repaired while reading a file with parity
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Repaired blocks, in file order
+    pub blocks: Vec<BlockRepair>,
+}
+
+impl RepairReport {
+    /// Whether nothing needed repair
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Total number of repaired bytes across all blocks
+    #[must_use]
+    pub fn repaired_bytes(&self) -> usize {
+        self.blocks.iter().map(|repair| repair.repaired_bytes).sum()
+    }
+
+    pub(crate) fn push(&mut self, block: u64, repaired_bytes: usize) {
+        if repaired_bytes > 0 {
+            self.blocks.push(BlockRepair {
+                block,
+                repaired_bytes,
+            });
+        }
+    }
+}
+
+/// One copy of the header opening a parity record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RecordHeader {
+    protected_len: u32,
+    data_shards: u8,
+    parity_shards: u8,
+}
+
+impl RecordHeader {
+    const SIZE: usize = 12;
+
+    /// Header for a record protecting `protected_len` bytes
+    fn new(protected_len: usize, parity_shards: u8) -> Result<Self> {
+        validate_parity_shards(parity_shards)?;
+        let protected_len = u32::try_from(protected_len).map_err(|_| {
+            ValidationError::InvalidBlockSize(format!("{protected_len} protected bytes"))
+        })?;
+        Ok(Self {
+            protected_len,
+            data_shards: data_shards_for(protected_len as usize),
+            parity_shards,
+        })
+    }
+
+    fn shard_len(self) -> usize {
+        (self.protected_len as usize).div_ceil(usize::from(self.data_shards))
+    }
+
+    fn total_shards(self) -> usize {
+        usize::from(self.data_shards) + usize::from(self.parity_shards)
+    }
+
+    /// Length of the header copies
+    fn copies_len(self) -> usize {
+        (usize::from(self.parity_shards) + 1) * Self::SIZE
+    }
+
+    /// Length of the whole record, header copies included
+    fn record_len(self) -> usize {
+        self.copies_len()
+            + self.total_shards() * 4
+            + usize::from(self.parity_shards) * self.shard_len()
+    }
+
+    fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..4].copy_from_slice(&self.protected_len.to_le_bytes());
+        bytes[4] = self.data_shards;
+        bytes[5] = self.parity_shards;
+        let crc = crc32(&bytes[0..8]);
+        bytes[8..12].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Parse one header copy, or `None` if it is damaged
+    fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        let crc = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        if crc != crc32(&bytes[0..8]) || bytes[6..8] != [0, 0] {
+            return None;
+        }
+        let header = Self {
+            protected_len: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            data_shards: bytes[4],
+            parity_shards: bytes[5],
+        };
+        let protected_len = header.protected_len as usize;
+        let valid = (BlockFrame::SIZE..=MAX_PROTECTED_LEN).contains(&protected_len)
+            && header.data_shards == data_shards_for(protected_len)
+            && (1..=MAX_PARITY_SHARDS).contains(&header.parity_shards);
+        valid.then_some(header)
+    }
+
+    /// Read header copies until an intact one turns up
+    ///
+    /// Returns the header and the copies read so far, damaged ones included.
+    fn read_from<R: Read>(reader: &mut R) -> Result<(Self, Vec<u8>)> {
+        let mut copies = Vec::new();
+        loop {
+            let mut copy = [0u8; Self::SIZE];
+            read_exact(reader, &mut copy)?;
+            copies.extend_from_slice(&copy);
+            if let Some(header) = Self::from_bytes(&copy) {
+                if copies.len() > header.copies_len() {
+                    break;
+                }
+                return Ok((header, copies));
+            }
+            if copies.len() > (usize::from(MAX_PARITY_SHARDS) + 1) * Self::SIZE {
+                break;
+            }
+        }
+        Err(ValidationError::CorruptedData(
+            "Every copy of a parity record header is damaged".to_string(),
+        )
+        .into())
+    }
+}
+
+/// Number of data shards for a record protecting `protected_len` bytes
+#[allow(clippy::cast_possible_truncation)]
+fn data_shards_for(protected_len: usize) -> u8 {
+    protected_len
+        .div_ceil(MIN_SHARD_LEN)
+        .clamp(1, MAX_DATA_SHARDS) as u8 // <= MAX_DATA_SHARDS
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// `read_exact` that reports a truncated record as corrupted data
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            ValidationError::CorruptedData("Truncated parity record".to_string()).into()
+        } else {
+            e.into()
+        }
+    })
+}
+
+fn codec(header: RecordHeader) -> Result<ReedSolomon> {
+    ReedSolomon::new(
+        usize::from(header.data_shards),
+        usize::from(header.parity_shards),
+    )
+    .map_err(|e| ValidationError::InvalidParity(e.to_string()).into())
+}
+
+/// Split `protected` into the record's data shards, zero-padding the last
+fn data_shards(protected: &[u8], header: RecordHeader) -> Vec<Vec<u8>> {
+    let shard_len = header.shard_len();
+    (0..usize::from(header.data_shards))
+        .map(|i| {
+            let mut shard = protected
+                .get(i * shard_len..)
+                .map_or(&[][..], |rest| &rest[..rest.len().min(shard_len)])
+                .to_vec();
+            shard.resize(shard_len, 0);
+            shard
+        })
+        .collect()
+}
+
+/// Length of the parity record protecting `protected_len` bytes
+pub(crate) fn record_len(protected_len: usize, parity_shards: u8) -> Result<usize> {
+    Ok(RecordHeader::new(protected_len, parity_shards)?.record_len())
+}
+
+/// Compute the parity record for a block frame and its compressed bytes
+pub(crate) fn encode_record(frame: &BlockFrame, data: &[u8], parity_shards: u8) -> Result<Vec<u8>> {
+    let protected = [&frame.to_bytes()[..], data].concat();
+    encode(&protected, parity_shards)
+}
+
+fn encode(protected: &[u8], parity_shards: u8) -> Result<Vec<u8>> {
+    let header = RecordHeader::new(protected.len(), parity_shards)?;
+    let mut shards = data_shards(protected, header);
+    shards.resize(header.total_shards(), vec![0u8; header.shard_len()]);
+    codec(header)?
+        .encode(&mut shards)
+        .map_err(|e| ValidationError::InvalidParity(e.to_string()))?;
+
+    let mut record = Vec::with_capacity(header.record_len());
+    for _ in 0..=header.parity_shards {
+        record.extend_from_slice(&header.to_bytes());
+    }
+    for shard in &shards {
+        record.extend_from_slice(&crc32(shard).to_le_bytes());
+    }
+    for shard in &shards[usize::from(header.data_shards)..] {
+        record.extend_from_slice(shard);
+    }
+    Ok(record)
+}
+
+/// Skip the parity record at the start of `bytes`
+///
+/// Returns the record length and the number of protected bytes after it.
+pub(crate) fn skip_record(bytes: &[u8]) -> Result<(usize, usize)> {
+    let mut cursor = bytes;
+    let (header, _) = RecordHeader::read_from(&mut cursor)?;
+    Ok((header.record_len(), header.protected_len as usize))
+}
+
+/// Parity shards per block of a block section, from its first intact record
+///
+/// Returns `None` if the first record is unreadable.
+pub(crate) fn parity_shards(section: &[u8]) -> Option<u8> {
+    let mut cursor = section.get(4..)?;
+    RecordHeader::read_from(&mut cursor)
+        .ok()
+        .map(|(header, _)| header.parity_shards)
+}
+
+/// Parity record and the block bytes it protects, as read from a file
+pub(crate) struct ParityBlock {
+    header: RecordHeader,
+    record: Vec<u8>,
+    protected: Vec<u8>,
+}
+
+impl ParityBlock {
+    /// Read a parity record and the frame and compressed bytes it protects
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let (header, mut record) = RecordHeader::read_from(reader)?;
+        let start = record.len();
+        record.resize(header.record_len(), 0);
+        read_exact(reader, &mut record[start..])?;
+        let mut protected = vec![0u8; header.protected_len as usize];
+        read_exact(reader, &mut protected)?;
+        Ok(Self {
+            header,
+            record,
+            protected,
+        })
+    }
+
+    /// The record and protected bytes, as they are laid out in the file
+    pub(crate) fn parts(&self) -> [&[u8]; 2] {
+        [&self.record, &self.protected]
+    }
+
+    /// Total length in the file
+    pub(crate) fn len(&self) -> usize {
+        self.record.len() + self.protected.len()
+    }
+
+    /// The protected frame header
+    pub(crate) fn frame(&self) -> BlockFrame {
+        let mut frame = [0u8; BlockFrame::SIZE];
+        frame.copy_from_slice(&self.protected[..BlockFrame::SIZE]);
+        BlockFrame::from_bytes(&frame)
+    }
+
+    /// The protected compressed bytes, checked against the frame's length
+    pub(crate) fn into_data(mut self) -> Result<Vec<u8>> {
+        let frame = self.frame();
+        if frame.compressed_len as usize != self.protected.len() - BlockFrame::SIZE {
+            return Err(ValidationError::CorruptedData(format!(
+                "Parity record protects {} bytes but its frame claims {} compressed bytes",
+                self.protected.len() - BlockFrame::SIZE,
+                frame.compressed_len
+            ))
+            .into());
+        }
+        Ok(self.protected.split_off(BlockFrame::SIZE))
+    }
+
+    /// Rebuild damaged shards and restore the record
+    ///
+    /// `block` is the block's index, for the error if the damage is beyond
+    /// repair. Returns the number of bytes that differed from the original.
+    pub(crate) fn repair(&mut self, block: u64) -> Result<usize> {
+        let header = self.header;
+        let data_count = usize::from(header.data_shards);
+        let copies_len = header.copies_len();
+        let parity_start = copies_len + header.total_shards() * 4;
+
+        let copy = header.to_bytes();
+        let copies_intact = self.record[..copies_len]
+            .chunks_exact(RecordHeader::SIZE)
+            .all(|bytes| bytes == copy);
+
+        let mut shards: Vec<Option<Vec<u8>>> = data_shards(&self.protected, header)
+            .into_iter()
+            .chain(
+                self.record[parity_start..]
+                    .chunks_exact(header.shard_len())
+                    .map(<[u8]>::to_vec),
+            )
+            .map(Some)
+            .collect();
+        let mut damaged = 0;
+        for (i, shard) in shards.iter_mut().enumerate() {
+            let at = copies_len + i * 4;
+            let stored = u32::from_le_bytes([
+                self.record[at],
+                self.record[at + 1],
+                self.record[at + 2],
+                self.record[at + 3],
+            ]);
+            if shard.as_deref().map(crc32) != Some(stored) {
+                *shard = None;
+                damaged += 1;
+            }
+        }
+
+        if damaged == 0 && copies_intact {
+            return Ok(0);
+        }
+        if damaged > usize::from(header.parity_shards) {
+            return Err(ValidationError::Unrepairable {
+                block,
+                damaged_shards: damaged,
+                parity_shards: header.parity_shards,
+            }
+            .into());
+        }
+
+        let mut protected = if damaged == 0 {
+            self.protected.clone()
+        } else {
+            codec(header)?
+                .reconstruct_data(&mut shards)
+                .map_err(|e| ValidationError::CorruptedData(format!("Block {block}: {e}")))?;
+            shards[..data_count]
+                .iter()
+                .flatten()
+                .flatten()
+                .copied()
+                .collect()
+        };
+        protected.truncate(self.protected.len());
+        let record = encode(&protected, header.parity_shards)?;
+
+        let repaired =
+            differing_bytes(&self.record, &record) + differing_bytes(&self.protected, &protected);
+        self.record = record;
+        self.protected = protected;
+        Ok(repaired)
+    }
+}
+
+fn differing_bytes(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).filter(|(x, y)| x != y).count()
+}
+
+/// Repair the block section of a file whose header has the parity flag
+///
+/// `input` is the file without any signature trailer, after decryption.
+/// Files without parity, and files with nothing to repair, are returned
+/// borrowed.
+pub(crate) fn repair_blocks<'a>(
+    input: &'a [u8],
+    header: &CrushHeader,
+) -> Result<(Cow<'a, [u8]>, RepairReport)> {
+    let mut output = Cow::Borrowed(input);
+    let mut report = RepairReport::default();
+    if !header.has_parity() {
+        return Ok((output, report));
+    }
+
+    let crc_len = if header.has_crc32() { 4 } else { 0 };
+    let (_, _, section_start) = parse_sections(input, header, CrushHeader::SIZE + crc_len)?;
+    // Skip the u32 nominal block size that opens the section
+    let mut pos = section_start + 4;
+    for index in 0.. {
+        let mut cursor = input.get(pos..).unwrap_or_default();
+        let mut block = ParityBlock::read_from(&mut cursor)?;
+        let repaired = block.repair(index)?;
+        if repaired > 0 {
+            let mut at = pos;
+            for part in block.parts() {
+                output.to_mut()[at..at + part.len()].copy_from_slice(part);
+                at += part.len();
+            }
+            report.push(index, repaired);
+        }
+        if block.frame().is_end() {
+            break;
+        }
+        pos += block.len();
+    }
+    Ok((output, report))
+}
+
+/// Repair the damaged blocks of a file written with parity
+///
+/// Returns the repaired file and what was fixed. A repaired file is
+/// byte-for-byte the file as written, so an embedded signature verifies
+/// again. Only the block section is repaired; use [`crate::decompress`] on
+/// the result to check the rest of the file.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The header is invalid
+/// - The file has no parity ([`ValidationError::NoParity`])
+/// - A block has more damaged shards than parity shards
+///   ([`ValidationError::Unrepairable`])
+/// - A parity record or block is truncated
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::{compress_with_options, decompress, init_plugins, repair, CompressionOptions};
+///
+/// init_plugins().expect("Plugin initialization failed");
+/// let data = b"Archived for the long term. ".repeat(1000);
+/// let options = CompressionOptions::default().with_parity(4);
+/// let mut compressed = compress_with_options(&data, &options).expect("Compression failed");
+///
+/// let middle = compressed.len() / 2;
+/// compressed[middle] ^= 0x01;
+/// let (repaired, report) = repair(&compressed).expect("Repair failed");
+/// assert_eq!(report.repaired_bytes(), 1);
+/// assert_eq!(decompress(&repaired).expect("Decompression failed").data, data);
+/// ```
+pub fn repair(input: &[u8]) -> Result<(Vec<u8>, RepairReport)> {
+    let header_bytes: &[u8; CrushHeader::SIZE] = input
+        .get(..CrushHeader::SIZE)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ValidationError::InvalidHeader(format!(
+                "Input too short: {} bytes, expected at least {}",
+                input.len(),
+                CrushHeader::SIZE
+            ))
+        })?;
+    let header = CrushHeader::from_bytes(header_bytes)?;
+    if !header.has_parity() {
+        return Err(ValidationError::NoParity.into());
+    }
+    let (output, report) = repair_blocks(input, &header)?;
+    Ok((output.into_owned(), report))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn protected(len: usize) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn parity_block(protected: &[u8], parity_shards: u8) -> ParityBlock {
+        let mut file = encode(protected, parity_shards).unwrap();
+        file.extend_from_slice(protected);
+        ParityBlock::read_from(&mut file.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_record_len_matches_encoding() {
+        for len in [12, 13, 64, 1000, 100_000] {
+            let record = encode(&protected(len), 3).unwrap();
+            assert_eq!(record.len(), record_len(len, 3).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_intact_block_needs_no_repair() {
+        let data = protected(5000);
+        let mut block = parity_block(&data, 2);
+        assert_eq!(block.repair(0).unwrap(), 0);
+        assert_eq!(block.protected, data);
+    }
+
+    #[test]
+    fn test_repairs_up_to_parity_shards() {
+        let data = protected(20_000);
+        let original = encode(&data, 4).unwrap();
+        let mut file = [original.clone(), data.clone()].concat();
+        // One byte in a header copy, then four damaged shards: through a
+        // shard checksum, a parity shard and two of the protected bytes
+        for at in [3, 100, original.len() - 1] {
+            file[at] ^= 0xFF;
+        }
+        for at in [0, 19_999] {
+            file[original.len() + at] ^= 0x5A;
+        }
+
+        let mut block = ParityBlock::read_from(&mut file.as_slice()).unwrap();
+        assert_eq!(block.repair(0).unwrap(), 5);
+        assert_eq!(block.record, original);
+        assert_eq!(block.protected, data);
+    }
+
+    #[test]
+    fn test_too_much_damage_is_unrepairable() {
+        let data = protected(20_000);
+        let mut block = parity_block(&data, 2);
+        for at in [100, 5000, 15_000] {
+            block.protected[at] ^= 0x01;
+        }
+        assert!(matches!(
+            block.repair(7),
+            Err(crate::CrushError::Validation(
+                ValidationError::Unrepairable {
+                    block: 7,
+                    damaged_shards: 3,
+                    parity_shards: 2,
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_damaged_first_header_copies() {
+        let data = protected(300);
+        let mut file = [encode(&data, 2).unwrap(), data.clone()].concat();
+        file[0] ^= 0x01;
+        file[RecordHeader::SIZE + 5] ^= 0x01;
+
+        let mut block = ParityBlock::read_from(&mut file.as_slice()).unwrap();
+        assert_eq!(block.repair(0).unwrap(), 2);
+        assert_eq!(block.protected, data);
+    }
+
+    #[test]
+    fn test_validate_parity_shards() {
+        assert!(validate_parity_shards(1).is_ok());
+        assert!(validate_parity_shards(MAX_PARITY_SHARDS).is_ok());
+        assert!(validate_parity_shards(0).is_err());
+        assert!(validate_parity_shards(MAX_PARITY_SHARDS + 1).is_err());
+    }
+}
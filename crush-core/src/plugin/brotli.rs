@@ -0,0 +1,288 @@
+//! Brotli compression plugin
+//!
+//! Provides Brotli (RFC 7932) compression using the pure-Rust `brotli` crate.
+//! Brotli's built-in dictionary of common web and text fragments gives it a
+//! large edge over DEFLATE on JSON, HTML and JS, so the plugin only
+//! claims text-like input in [`CompressionAlgorithm::detect`].
+
+use crate::error::{PluginError, Result};
+use crate::plugin::{
+    read_decoded, CompressionAlgorithm, ParamSpec, PartialOutput, PluginMetadata, PluginParams,
+    COMPRESSION_ALGORITHMS,
+};
+use linkme::distributed_slice;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Brotli quality used for compression (0-11)
+///
+/// Quality 6 keeps most of the ratio benefit on text while staying an order
+/// of magnitude faster than the maximum quality.
+pub const DEFAULT_QUALITY: u32 = 6;
+
+/// Brotli window size as a base-2 logarithm (4 megabytes)
+pub const DEFAULT_LGWIN: u32 = 22;
+
+/// Amount of input fed to the encoder between cancellation checks
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Quality parameter
+const LEVEL: ParamSpec = ParamSpec {
+    name: "level",
+    description: "Brotli quality (0 = fastest, 11 = smallest output)",
+    min: 0,
+    max: 11,
+    default: DEFAULT_QUALITY as i64,
+};
+
+/// Window size parameter; the window is recorded in the Brotli stream itself
+const WINDOW_LOG: ParamSpec = ParamSpec {
+    name: "window_log",
+    description: "Sliding window as a power of two (10-24)",
+    min: 10,
+    max: 24,
+    default: DEFAULT_LGWIN as i64,
+};
+
+/// Brotli compression plugin (RFC 7932)
+pub struct BrotliPlugin;
+
+/// Returns true if `header` looks like text (UTF-8 without binary control bytes)
+///
+/// A multi-byte character cut off at the end of the header is accepted, and a
+/// small share of control characters (e.g. ANSI escapes in logs) is tolerated.
+fn is_text_like(header: &[u8]) -> bool {
+    if header.is_empty() {
+        return false;
+    }
+
+    // Reject invalid UTF-8, except for a character truncated by the header cut
+    if let Err(e) = std::str::from_utf8(header) {
+        if e.error_len().is_some() {
+            return false;
+        }
+    }
+
+    let control = header
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || b == 0x7f)
+        .count();
+    control * 32 <= header.len() && !header.contains(&0)
+}
+
+impl CompressionAlgorithm for BrotliPlugin {
+    fn name(&self) -> &'static str {
+        "brotli"
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "brotli",
+            version: "1.0.0",
+            // Magic number: CR (Crush) + V1 + ID 0x04 (Brotli)
+            magic_number: [0x43, 0x52, 0x01, 0x04],
+            // Measured throughput: ~40 MB/s compression at quality 6 (typical on modern CPU)
+            throughput: 40.0,
+            // Compression ratio: ~0.25 on text (75% size reduction)
+            compression_ratio: 0.25,
+            description: "Brotli compression (RFC 7932), tuned for text and web assets",
+        }
+    }
+
+    fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.compress_with_params(input, &PluginParams::new(), cancel_flag)
+    }
+
+    fn compress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        // Check cancellation before starting
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+
+        let quality = u32::try_from(params.get_or_default(&LEVEL))
+            .map_err(|_| PluginError::OperationFailed("Invalid Brotli quality".to_string()))?;
+        let lgwin = u32::try_from(params.get_or_default(&WINDOW_LOG))
+            .map_err(|_| PluginError::OperationFailed("Invalid Brotli window".to_string()))?;
+        let mut encoder = brotli::CompressorWriter::new(Vec::new(), CHUNK_SIZE, quality, lgwin);
+
+        // Feed input in chunks, checking cancellation between them
+        for chunk in input.chunks(CHUNK_SIZE) {
+            if cancel_flag.load(Ordering::Acquire) {
+                return Err(PluginError::Cancelled.into());
+            }
+            encoder.write_all(chunk).map_err(|e| {
+                PluginError::OperationFailed(format!("Brotli compression failed: {e}"))
+            })?;
+        }
+
+        // Finishing the stream cannot fail when writing to a Vec
+        Ok(encoder.into_inner())
+    }
+
+    fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.decompress_with_limit(input, &PluginParams::new(), usize::MAX, cancel_flag)
+    }
+
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.decompress_prefix(input, params, max_output, cancel_flag)
+            .into_result()
+    }
+
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        read_decoded(
+            brotli::Decompressor::new(input, CHUNK_SIZE),
+            max_output,
+            &cancel_flag,
+            "Brotli",
+        )
+    }
+
+    fn detect(&self, file_header: &[u8]) -> bool {
+        // Brotli only pays off on text; leave binaries to the general-purpose plugins
+        is_text_like(file_header)
+    }
+
+    fn parameters(&self) -> &'static [ParamSpec] {
+        &[LEVEL, WINDOW_LOG]
+    }
+}
+
+/// Register Brotli plugin at compile-time
+#[distributed_slice(COMPRESSION_ALGORITHMS)]
+static BROTLI_PLUGIN: &dyn CompressionAlgorithm = &BrotliPlugin;
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    #[test]
+    fn test_brotli_metadata() {
+        let metadata = BrotliPlugin.metadata();
+
+        assert_eq!(metadata.name, "brotli");
+        assert_eq!(metadata.magic_number, [0x43, 0x52, 0x01, 0x04]);
+        assert!(metadata.throughput > 0.0);
+        assert!(metadata.compression_ratio > 0.0 && metadata.compression_ratio <= 1.0);
+    }
+
+    #[test]
+    fn test_brotli_roundtrip() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let original = br#"{"name": "brotli", "tags": ["text", "web"], "size": 42}"#.repeat(50);
+        let compressed = BrotliPlugin
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+        let decompressed = BrotliPlugin.decompress(&compressed, cancel_flag).unwrap();
+
+        assert_eq!(original, decompressed);
+        assert!(compressed.len() < original.len());
+    }
+
+    #[test]
+    fn test_brotli_empty_and_large() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        for original in [Vec::new(), vec![0x42u8; 3 * CHUNK_SIZE + 5]] {
+            let compressed = BrotliPlugin
+                .compress(&original, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = BrotliPlugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(original, decompressed);
+        }
+    }
+
+    #[test]
+    fn test_brotli_params() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let original = b"<p>Brotli quality parameter test</p>\n".repeat(200);
+
+        assert!(BrotliPlugin
+            .validate_params(&PluginParams::new().with("window_log", 25))
+            .is_err());
+
+        for params in [
+            PluginParams::new().with("level", 0).with("window_log", 10),
+            PluginParams::new().with("level", 11).with("window_log", 24),
+        ] {
+            assert!(BrotliPlugin.validate_params(&params).is_ok());
+            let compressed = BrotliPlugin
+                .compress_with_params(&original, &params, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = BrotliPlugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(decompressed, original);
+        }
+    }
+
+    #[test]
+    fn test_brotli_detect() {
+        let plugin = BrotliPlugin;
+
+        // Text-like content
+        assert!(plugin.detect(b"<!DOCTYPE html><html><body>Hello</body></html>\n"));
+        assert!(plugin.detect(b"{\"key\": \"value\",\r\n\t\"list\": [1, 2, 3]}"));
+        assert!(plugin.detect("caf\u{e9} na\u{ef}ve r\u{e9}sum\u{e9}".as_bytes()));
+        // Multi-byte character truncated at the end of the header
+        assert!(plugin.detect(&"\u{e9}t\u{e9}".as_bytes()[..4]));
+
+        // Binary content
+        assert!(!plugin.detect(&[]));
+        assert!(!plugin.detect(b"\x00\x01\x02\x03"));
+        assert!(!plugin.detect(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"));
+        assert!(!plugin.detect(b"text with a NUL\x00 byte"));
+        assert!(!plugin.detect(&[0xff, 0xfe, 0x41, 0x42]));
+    }
+
+    #[test]
+    fn test_brotli_cancellation() {
+        let cancel_flag = Arc::new(AtomicBool::new(true)); // Pre-cancelled
+
+        let result = BrotliPlugin.compress(b"This should be cancelled", Arc::clone(&cancel_flag));
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+
+        let result = BrotliPlugin.decompress(b"Some data", cancel_flag);
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_brotli_decompress_invalid_data() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let result = BrotliPlugin.decompress(&[0xff; 32], cancel_flag);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Brotli decompression failed"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Runtime calibration of plugin performance
+//!
+//! The `throughput` and `compression_ratio` in [`PluginMetadata`] are typical
+//! figures written by plugin authors. [`calibrate`] measures every registered
+//! plugin on a fixed, generated corpus instead, and the result can be saved to
+//! a cache file and activated with [`set_calibration`]. While a calibration is
+//! active, [`calculate_plugin_score`](crate::calculate_plugin_score) uses the
+//! measured numbers in place of the static ones.
+
+use crate::error::{PluginError, Result};
+use crate::plugin::registry::get_plugin_by_magic;
+use crate::plugin::{list_plugins, PluginMetadata};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+/// Size of the generated calibration corpus in bytes
+pub const CALIBRATION_CORPUS_SIZE: usize = 512 * 1024;
+
+/// Version of the calibration cache format
+const CACHE_VERSION: u32 = 1;
+
+/// Calibration used by plugin scoring, if any
+static ACTIVE_CALIBRATION: RwLock<Option<Calibration>> = RwLock::new(None);
+
+/// Measured performance of one plugin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginCalibration {
+    /// Plugin name, matching [`PluginMetadata::name`]
+    pub name: String,
+
+    /// Measured compression throughput in MB/s
+    pub throughput: f64,
+
+    /// Measured compressed size divided by original size
+    pub compression_ratio: f64,
+}
+
+/// Calibration results for all plugins on this machine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// Cache format version
+    pub version: u32,
+
+    /// Size of the corpus the plugins were measured on
+    pub corpus_size: usize,
+
+    /// Per-plugin measurements
+    pub plugins: Vec<PluginCalibration>,
+}
+
+impl Calibration {
+    /// Measurements for the plugin named `name`
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&PluginCalibration> {
+        self.plugins.iter().find(|p| p.name == name)
+    }
+
+    /// Load a calibration cache file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not a calibration cache
+    /// of the current version, or contains non-positive measurements.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read(path)?;
+        let calibration: Self = serde_json::from_slice(&contents)
+            .map_err(|e| PluginError::InvalidMetadata(format!("Invalid calibration cache: {e}")))?;
+
+        if calibration.version != CACHE_VERSION {
+            return Err(PluginError::InvalidMetadata(format!(
+                "Unsupported calibration cache version {} (expected {CACHE_VERSION})",
+                calibration.version
+            ))
+            .into());
+        }
+        for plugin in &calibration.plugins {
+            let valid = |v: f64| v.is_finite() && v > 0.0;
+            if !valid(plugin.throughput) || !valid(plugin.compression_ratio) {
+                return Err(PluginError::InvalidMetadata(format!(
+                    "Invalid calibration for plugin '{}'",
+                    plugin.name
+                ))
+                .into());
+            }
+        }
+
+        Ok(calibration)
+    }
+
+    /// Write the calibration to a cache file, creating parent directories
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| PluginError::OperationFailed(format!("Serialize calibration: {e}")))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// Measure every registered plugin on the calibration corpus
+///
+/// Each plugin compresses [`CALIBRATION_CORPUS_SIZE`] bytes of generated
+/// text, JSON, binary records and random data once, which takes a few
+/// seconds for the slower plugins. The result is not activated; pass it to
+/// [`set_calibration`] to use it for scoring.
+///
+/// # Errors
+///
+/// Returns an error if a plugin fails to compress the corpus.
+pub fn calibrate() -> Result<Calibration> {
+    let corpus = calibration_corpus(CALIBRATION_CORPUS_SIZE);
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+
+    let mut plugins: Vec<PluginCalibration> = list_plugins()
+        .iter()
+        .filter_map(|metadata| get_plugin_by_magic(metadata.magic_number))
+        .map(|plugin| {
+            let start = Instant::now();
+            let compressed = plugin.compress(&corpus, Arc::clone(&cancel_flag))?;
+            let elapsed = start.elapsed().as_secs_f64().max(1e-6);
+
+            #[allow(clippy::cast_precision_loss)] // Corpus sizes are far below 2^52
+            let (input_len, output_len) = (corpus.len() as f64, compressed.len() as f64);
+            Ok(PluginCalibration {
+                name: plugin.name().to_string(),
+                throughput: input_len / elapsed / 1_000_000.0,
+                compression_ratio: (output_len / input_len).max(1e-6),
+            })
+        })
+        .collect::<Result<_>>()?;
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Calibration {
+        version: CACHE_VERSION,
+        corpus_size: corpus.len(),
+        plugins,
+    })
+}
+
+/// Activate `calibration` for plugin scoring, or restore static metadata with `None`
+pub fn set_calibration(calibration: Option<Calibration>) {
+    if let Ok(mut guard) = ACTIVE_CALIBRATION.write() {
+        *guard = calibration;
+    }
+}
+
+/// The calibration currently used for plugin scoring, if any
+#[must_use]
+pub fn active_calibration() -> Option<Calibration> {
+    ACTIVE_CALIBRATION
+        .read()
+        .ok()
+        .and_then(|guard| guard.clone())
+}
+
+/// Replace `plugin`'s throughput and ratio with the active calibration's
+pub(crate) fn calibrated(plugin: &PluginMetadata) -> PluginMetadata {
+    let guard = ACTIVE_CALIBRATION.read().ok();
+    match guard.as_ref().and_then(|g| g.as_ref()?.get(plugin.name)) {
+        Some(measured) => PluginMetadata {
+            throughput: measured.throughput,
+            compression_ratio: measured.compression_ratio,
+            ..*plugin
+        },
+        None => *plugin,
+    }
+}
+
+/// Generate the deterministic calibration corpus
+///
+/// A mix of English-like text, JSON records, little-endian binary records and
+/// incompressible random bytes, so no plugin is measured only on its best case.
+fn calibration_corpus(size: usize) -> Vec<u8> {
+    const TEXT: &str = "the quick brown fox jumps over the lazy dog while data blocks \
+                        stream into an archive at a good compression ratio on this machine";
+    let words: Vec<&str> = TEXT.split_whitespace().collect();
+
+    let mut corpus = Vec::with_capacity(size + 64);
+    let mut seed = 0x2545_f491_u32;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        seed >> 8
+    };
+
+    let mut record = 0_u32;
+    while corpus.len() < size {
+        match record % 8 {
+            // Text
+            0..=2 => {
+                for _ in 0..12 {
+                    corpus.extend_from_slice(words[next() as usize % words.len()].as_bytes());
+                    corpus.push(b' ');
+                }
+                corpus.extend_from_slice(b".\n");
+            }
+            // JSON
+            3 | 4 => {
+                let line = format!(
+                    "{{\"id\": {record}, \"name\": \"{}\", \"score\": {}}}\n",
+                    words[next() as usize % words.len()],
+                    next() % 1000
+                );
+                corpus.extend_from_slice(line.as_bytes());
+            }
+            // Binary records
+            5 | 6 => {
+                for i in 0..8_u32 {
+                    corpus.extend_from_slice(&(record * 8 + i).to_le_bytes());
+                    corpus.extend_from_slice(&(next() % 256).to_le_bytes());
+                }
+            }
+            // Random bytes
+            _ => {
+                for _ in 0..16 {
+                    corpus.extend_from_slice(&next().to_le_bytes()[..3]);
+                }
+            }
+        }
+        record += 1;
+    }
+
+    corpus.truncate(size);
+    corpus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_corpus() {
+        let corpus = calibration_corpus(64 * 1024);
+        assert_eq!(corpus.len(), 64 * 1024);
+        assert_eq!(corpus, calibration_corpus(64 * 1024));
+        assert!(corpus.windows(4).any(|w| w == b"the "));
+    }
+
+    #[test]
+    fn test_calibration_lookup() {
+        let calibration = Calibration {
+            version: CACHE_VERSION,
+            corpus_size: 1,
+            plugins: vec![PluginCalibration {
+                name: "zstd".to_string(),
+                throughput: 123.0,
+                compression_ratio: 0.5,
+            }],
+        };
+
+        assert!(calibration.get("zstd").is_some());
+        assert!(calibration.get("deflate").is_none());
+    }
+}
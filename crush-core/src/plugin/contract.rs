@@ -3,9 +3,10 @@
 //! This module defines the `CompressionAlgorithm` trait that all plugins must implement.
 //! Plugins register themselves at compile-time using the `linkme` distributed slice pattern.
 
-use crate::error::Result;
-use crate::plugin::PluginMetadata;
-use std::sync::atomic::AtomicBool;
+use crate::error::{CrushError, PluginError, Result, ValidationError};
+use crate::plugin::{ParamSpec, PluginMetadata, PluginParams};
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 /// Trait that all compression plugins must implement
@@ -141,4 +142,200 @@ pub trait CompressionAlgorithm: Send + Sync {
     /// This method should execute in sub-millisecond time as it may be called
     /// for every plugin during file type detection.
     fn detect(&self, file_header: &[u8]) -> bool;
+
+    /// Tunable parameters accepted by this plugin
+    ///
+    /// Defaults to none. Plugins that accept parameters should also override
+    /// [`compress_with_params`](Self::compress_with_params) and, if any
+    /// parameter affects decoding, [`decompress_with_params`](Self::decompress_with_params).
+    fn parameters(&self) -> &'static [ParamSpec] {
+        &[]
+    }
+
+    /// Validate parameters before compression
+    ///
+    /// The default implementation checks names and ranges against
+    /// [`parameters`](Self::parameters). Override to add cross-parameter checks.
+    ///
+    /// # Errors
+    ///
+    /// - `ValidationError::InvalidParameter` for unknown or out-of-range parameters
+    fn validate_params(&self, params: &PluginParams) -> Result<()> {
+        params.validate(self.name(), self.parameters())
+    }
+
+    /// Compress input data using validated plugin parameters
+    ///
+    /// The parameters are recorded in the output and passed back to
+    /// [`decompress_with_params`](Self::decompress_with_params). The default
+    /// implementation ignores them and calls [`compress`](Self::compress).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`compress`](Self::compress).
+    fn compress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        let _ = params;
+        self.compress(input, cancel_flag)
+    }
+
+    /// Decompress data compressed with the given plugin parameters
+    ///
+    /// The default implementation ignores them and calls
+    /// [`decompress`](Self::decompress).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`decompress`](Self::decompress).
+    fn decompress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        let _ = params;
+        self.decompress(input, cancel_flag)
+    }
+
+    /// Decompress data, producing at most `max_output` bytes
+    ///
+    /// Used by the library with the size the Crush header or block frame
+    /// declares, so a corrupted or malicious payload cannot grow the output
+    /// past it. The default implementation decompresses fully with
+    /// [`decompress_with_params`](Self::decompress_with_params) and checks
+    /// afterwards; plugins that decode incrementally should override it to
+    /// stop as soon as the limit is passed.
+    ///
+    /// # Errors
+    ///
+    /// - `ValidationError::OutputLimitExceeded` if the output exceeds `max_output`
+    /// - Otherwise the same as [`decompress`](Self::decompress)
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        let output = self.decompress_with_params(input, params, cancel_flag)?;
+        check_output_limit(output.len(), max_output)?;
+        Ok(output)
+    }
+
+    /// Decompress as much of damaged or truncated data as possible
+    ///
+    /// Used by [`crate::recover`] to salvage the output decoded before the
+    /// damage. Returns that output together with the error that stopped
+    /// decoding, if any. The default implementation calls
+    /// [`decompress_with_limit`](Self::decompress_with_limit) and salvages
+    /// nothing when it fails; plugins that decode incrementally should
+    /// override it (see [`read_decoded`]) and implement
+    /// `decompress_with_limit` on top of it.
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        match self.decompress_with_limit(input, params, max_output, cancel_flag) {
+            Ok(data) => PartialOutput { data, error: None },
+            Err(error) => PartialOutput::failed(error),
+        }
+    }
+}
+
+/// Output of a decoder that may have stopped early
+#[derive(Debug)]
+pub struct PartialOutput {
+    /// Bytes decoded before decoding stopped
+    pub data: Vec<u8>,
+    /// Error that stopped decoding, or `None` if the input decoded completely
+    pub error: Option<CrushError>,
+}
+
+impl PartialOutput {
+    /// Output of a decoder that failed before producing anything
+    #[must_use]
+    pub fn failed(error: CrushError) -> Self {
+        Self {
+            data: Vec::new(),
+            error: Some(error),
+        }
+    }
+
+    /// The decoded bytes, or the error that stopped decoding
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that stopped decoding, if any.
+    pub fn into_result(self) -> Result<Vec<u8>> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.data),
+        }
+    }
+}
+
+/// Read `decoder` to the end in 64 KB chunks, keeping what it decoded
+///
+/// Stops at the first read error, once the output would exceed `max_output`
+/// (`ValidationError::OutputLimitExceeded`), or when `cancel_flag` is set
+/// (`PluginError::Cancelled`). Read errors become
+/// `PluginError::OperationFailed` naming `algorithm`.
+pub fn read_decoded<R: Read>(
+    mut decoder: R,
+    max_output: usize,
+    cancel_flag: &AtomicBool,
+    algorithm: &str,
+) -> PartialOutput {
+    let mut output = PartialOutput {
+        data: Vec::new(),
+        error: None,
+    };
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        if cancel_flag.load(Ordering::Acquire) {
+            output.error = Some(PluginError::Cancelled.into());
+            break;
+        }
+
+        match decoder.read(&mut buffer) {
+            Ok(0) => break, // EOF
+            Ok(n) => {
+                if let Err(e) = check_output_limit(output.data.len() + n, max_output) {
+                    output.error = Some(e);
+                    break;
+                }
+                output.data.extend_from_slice(&buffer[..n]);
+            }
+            Err(e) => {
+                output.error = Some(
+                    PluginError::OperationFailed(format!("{algorithm} decompression failed: {e}"))
+                        .into(),
+                );
+                break;
+            }
+        }
+    }
+    output
+}
+
+/// Fail with `ValidationError::OutputLimitExceeded` if `len` exceeds `max_output`
+///
+/// # Errors
+///
+/// Returns an error if `len > max_output`.
+pub fn check_output_limit(len: usize, max_output: usize) -> Result<()> {
+    if len > max_output {
+        return Err(ValidationError::OutputLimitExceeded(format!(
+            "decompressed output exceeds {max_output} bytes"
+        ))
+        .into());
+    }
+    Ok(())
 }
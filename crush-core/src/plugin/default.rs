@@ -4,7 +4,10 @@
 //! This plugin is always available and serves as the default compression algorithm.
 
 use crate::error::{PluginError, Result};
-use crate::plugin::{CompressionAlgorithm, PluginMetadata, COMPRESSION_ALGORITHMS};
+use crate::plugin::{
+    read_decoded, CompressionAlgorithm, ParamSpec, PartialOutput, PluginMetadata, PluginParams,
+    COMPRESSION_ALGORITHMS,
+};
 use flate2::read::{DeflateDecoder, DeflateEncoder};
 use flate2::Compression;
 use linkme::distributed_slice;
@@ -12,9 +15,19 @@ use std::io::Read;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+/// Compression level parameter (flate2/zlib levels)
+const LEVEL: ParamSpec = ParamSpec {
+    name: "level",
+    description: "Compression level (0 = store only, 9 = smallest output)",
+    min: 0,
+    max: 9,
+    default: 6,
+};
+
 /// DEFLATE compression plugin (RFC 1951)
 ///
-/// Uses flate2's DEFLATE implementation with default compression level (6).
+/// Uses flate2's DEFLATE implementation with default compression level (6),
+/// tunable through the `level` parameter.
 /// This is the standard compression algorithm used by gzip, zlib, and PNG.
 pub struct DeflatePlugin;
 
@@ -38,12 +51,23 @@ impl CompressionAlgorithm for DeflatePlugin {
     }
 
     fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.compress_with_params(input, &PluginParams::new(), cancel_flag)
+    }
+
+    fn compress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
         // Check cancellation before starting
         if cancel_flag.load(Ordering::Acquire) {
             return Err(PluginError::Cancelled.into());
         }
 
-        let mut encoder = DeflateEncoder::new(input, Compression::default());
+        let level = u32::try_from(params.get_or_default(&LEVEL))
+            .map_err(|_| PluginError::OperationFailed("Invalid DEFLATE level".to_string()))?;
+        let mut encoder = DeflateEncoder::new(input, Compression::new(level));
         let mut compressed = Vec::new();
 
         // Read compressed data in chunks, checking cancellation periodically
@@ -69,34 +93,33 @@ impl CompressionAlgorithm for DeflatePlugin {
     }
 
     fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
-        // Check cancellation before starting
-        if cancel_flag.load(Ordering::Acquire) {
-            return Err(PluginError::Cancelled.into());
-        }
-
-        let mut decoder = DeflateDecoder::new(input);
-        let mut decompressed = Vec::new();
-
-        // Read decompressed data in chunks, checking cancellation periodically
-        let mut buffer = vec![0u8; 64 * 1024]; // 64KB chunks
-        loop {
-            if cancel_flag.load(Ordering::Acquire) {
-                return Err(PluginError::Cancelled.into());
-            }
+        self.decompress_with_limit(input, &PluginParams::new(), usize::MAX, cancel_flag)
+    }
 
-            match decoder.read(&mut buffer) {
-                Ok(0) => break, // EOF
-                Ok(n) => decompressed.extend_from_slice(&buffer[..n]),
-                Err(e) => {
-                    return Err(PluginError::OperationFailed(format!(
-                        "DEFLATE decompression failed: {e}"
-                    ))
-                    .into())
-                }
-            }
-        }
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.decompress_prefix(input, params, max_output, cancel_flag)
+            .into_result()
+    }
 
-        Ok(decompressed)
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        read_decoded(
+            DeflateDecoder::new(input),
+            max_output,
+            &cancel_flag,
+            "DEFLATE",
+        )
     }
 
     fn detect(&self, _file_header: &[u8]) -> bool {
@@ -105,6 +128,10 @@ impl CompressionAlgorithm for DeflatePlugin {
         // For decompression, we use the magic number in the Crush header.
         true
     }
+
+    fn parameters(&self) -> &'static [ParamSpec] {
+        &[LEVEL]
+    }
 }
 
 /// Register DEFLATE plugin at compile-time
@@ -206,6 +233,36 @@ mod tests {
         );
     }
 
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_deflate_level_param() {
+        let plugin = DeflatePlugin;
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let original = b"DEFLATE level parameter test data. ".repeat(200);
+
+        let stored = PluginParams::new().with("level", 0);
+        let best = PluginParams::new().with("level", 9);
+        assert!(plugin.validate_params(&best).is_ok());
+        assert!(plugin
+            .validate_params(&PluginParams::new().with("level", 10))
+            .is_err());
+
+        let stored_out = plugin
+            .compress_with_params(&original, &stored, Arc::clone(&cancel_flag))
+            .unwrap();
+        let best_out = plugin
+            .compress_with_params(&original, &best, Arc::clone(&cancel_flag))
+            .unwrap();
+        assert!(best_out.len() < stored_out.len());
+
+        for compressed in [stored_out, best_out] {
+            let decompressed = plugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(decompressed, original);
+        }
+    }
+
     #[test]
     fn test_deflate_detect() {
         let plugin = DeflatePlugin;
@@ -0,0 +1,229 @@
+//! LZ4 compression plugin
+//!
+//! Provides LZ4 frame compression using the pure-Rust `lz4_flex` crate. LZ4
+//! trades ratio for speed, which suits IPC and caching workloads where
+//! compression latency dominates.
+
+use crate::error::{PluginError, Result};
+use crate::plugin::{
+    read_decoded, CompressionAlgorithm, ParamSpec, PartialOutput, PluginMetadata, PluginParams,
+    COMPRESSION_ALGORITHMS,
+};
+use linkme::distributed_slice;
+use lz4_flex::frame::{BlockSize, FrameDecoder, FrameEncoder, FrameInfo};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Amount of input fed to the encoder between cancellation checks
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Frame block size parameter, using the LZ4 frame format's block size IDs
+///
+/// The block size is stored in the LZ4 frame header, so decoding does not
+/// need it.
+const BLOCK_SIZE_ID: ParamSpec = ParamSpec {
+    name: "block_size_id",
+    description: "LZ4 frame block size (4 = 64KB, 5 = 256KB, 6 = 1MB, 7 = 4MB)",
+    min: 4,
+    max: 7,
+    default: 4,
+};
+
+/// LZ4 compression plugin (LZ4 frame format)
+pub struct Lz4Plugin;
+
+impl CompressionAlgorithm for Lz4Plugin {
+    fn name(&self) -> &'static str {
+        "lz4"
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "lz4",
+            version: "1.0.0",
+            // Magic number: CR (Crush) + V1 + ID 0x02 (LZ4)
+            magic_number: [0x43, 0x52, 0x01, 0x02],
+            // Measured throughput: ~750 MB/s compression (typical on modern CPU)
+            throughput: 750.0,
+            // Compression ratio: ~0.50 (50% size reduction on text)
+            compression_ratio: 0.50,
+            description: "LZ4 frame compression, optimized for speed",
+        }
+    }
+
+    fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.compress_with_params(input, &PluginParams::new(), cancel_flag)
+    }
+
+    fn compress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        // Check cancellation before starting
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+
+        let block_size = match params.get_or_default(&BLOCK_SIZE_ID) {
+            5 => BlockSize::Max256KB,
+            6 => BlockSize::Max1MB,
+            7 => BlockSize::Max4MB,
+            _ => BlockSize::Max64KB,
+        };
+        let frame_info = FrameInfo::new().block_size(block_size);
+        let mut encoder = FrameEncoder::with_frame_info(frame_info, Vec::new());
+
+        // Feed input in chunks, checking cancellation between them
+        for chunk in input.chunks(CHUNK_SIZE) {
+            if cancel_flag.load(Ordering::Acquire) {
+                return Err(PluginError::Cancelled.into());
+            }
+            encoder.write_all(chunk).map_err(|e| {
+                PluginError::OperationFailed(format!("LZ4 compression failed: {e}"))
+            })?;
+        }
+
+        Ok(encoder
+            .finish()
+            .map_err(|e| PluginError::OperationFailed(format!("LZ4 compression failed: {e}")))?)
+    }
+
+    fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.decompress_with_limit(input, &PluginParams::new(), usize::MAX, cancel_flag)
+    }
+
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.decompress_prefix(input, params, max_output, cancel_flag)
+            .into_result()
+    }
+
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        read_decoded(FrameDecoder::new(input), max_output, &cancel_flag, "LZ4")
+    }
+
+    fn detect(&self, _file_header: &[u8]) -> bool {
+        // LZ4 handles all data types; routing during decompression uses the
+        // magic number in the Crush header.
+        true
+    }
+
+    fn parameters(&self) -> &'static [ParamSpec] {
+        &[BLOCK_SIZE_ID]
+    }
+}
+
+/// Register LZ4 plugin at compile-time
+#[distributed_slice(COMPRESSION_ALGORITHMS)]
+static LZ4_PLUGIN: &dyn CompressionAlgorithm = &Lz4Plugin;
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    #[test]
+    fn test_lz4_metadata() {
+        let metadata = Lz4Plugin.metadata();
+
+        assert_eq!(metadata.name, "lz4");
+        assert_eq!(metadata.magic_number, [0x43, 0x52, 0x01, 0x02]);
+        assert!(metadata.throughput > 0.0);
+        assert!(metadata.compression_ratio > 0.0 && metadata.compression_ratio <= 1.0);
+    }
+
+    #[test]
+    fn test_lz4_roundtrip() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let original = b"Hello, LZ4! This is a test of the compression algorithm.".repeat(50);
+        let compressed = Lz4Plugin
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+        let decompressed = Lz4Plugin.decompress(&compressed, cancel_flag).unwrap();
+
+        assert_eq!(original, decompressed);
+        assert!(compressed.len() < original.len());
+    }
+
+    #[test]
+    fn test_lz4_empty_and_large() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        for original in [Vec::new(), vec![0x42u8; 3 * CHUNK_SIZE + 5]] {
+            let compressed = Lz4Plugin
+                .compress(&original, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = Lz4Plugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(original, decompressed);
+        }
+    }
+
+    #[test]
+    fn test_lz4_block_size_param() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let original = b"LZ4 block size parameter test data. ".repeat(20_000);
+
+        assert!(Lz4Plugin
+            .validate_params(&PluginParams::new().with("block_size_id", 8))
+            .is_err());
+
+        for id in 4..=7 {
+            let params = PluginParams::new().with("block_size_id", id);
+            assert!(Lz4Plugin.validate_params(&params).is_ok());
+            let compressed = Lz4Plugin
+                .compress_with_params(&original, &params, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = Lz4Plugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(decompressed, original);
+        }
+    }
+
+    #[test]
+    fn test_lz4_cancellation() {
+        let cancel_flag = Arc::new(AtomicBool::new(true)); // Pre-cancelled
+
+        let result = Lz4Plugin.compress(b"This should be cancelled", Arc::clone(&cancel_flag));
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+
+        let result = Lz4Plugin.decompress(b"Some data", cancel_flag);
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_lz4_decompress_invalid_data() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let result = Lz4Plugin.decompress(b"This is not compressed data!", cancel_flag);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("LZ4 decompression failed"));
+    }
+}
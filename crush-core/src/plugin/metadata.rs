@@ -1,6 +1,7 @@
 //! Plugin metadata and file format structures
 
 use crate::error::{Result, ValidationError};
+use crate::hash::HashAlgorithm;
 use std::io::{Read, Write};
 
 /// Metadata describing a compression plugin's capabilities and performance
@@ -37,13 +38,29 @@ pub struct PluginMetadata {
 /// 0      | 4    | magic_number ([u8; 4])
 /// 4      | 8    | original_size (u64, little-endian)
 /// 12     | 1    | flags (u8)
-/// 13     | 3    | reserved (padding to 16 bytes)
+/// 13     | 1    | extension flags (u8)
+/// 14     | 2    | reserved (padding to 16 bytes)
 /// ```
 ///
 /// Flags byte (bit fields):
 /// - Bit 0: Has CRC32 (if set, CRC32 follows header)
 /// - Bit 1: Has metadata (if set, variable-length metadata follows header)
-/// - Bits 2-7: Reserved for future use
+/// - Bit 2: Has blocks (if set, payload uses the multi-block layout, see [`crate::block`])
+/// - Bit 3: Streamed (if set, `original_size` is zero and the real size follows the blocks)
+/// - Bit 4: Seekable (if set, a block index footer ends the file, see [`crate::index`])
+/// - Bit 5: Has params (if set, a plugin parameter section follows the metadata)
+/// - Bit 6: Stored blocks (if set, blocks that did not shrink are stored uncompressed)
+/// - Bit 7: Encrypted (if set, an encryption section follows the header and the
+///   rest of the file is encrypted, see [`crate::encryption`])
+///
+/// Extension flags byte:
+/// - Bits 0-1: Content hash algorithm (0 = none, otherwise a digest of the
+///   uncompressed data follows the payload, see [`crate::hash`])
+/// - Bit 2: Signed (if set, an Ed25519 signature trailer ends the file, see
+///   [`crate::signature`])
+/// - Bit 3: Parity (Reed-Solomon records precede every block frame, see
+///   [`crate::parity`])
+/// - Bits 4-7: Reserved for future use
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct CrushHeader {
@@ -56,8 +73,11 @@ pub struct CrushHeader {
     /// Feature flags (see struct documentation)
     pub flags: u8,
 
+    /// Extension flags, once the feature flags ran out (see struct documentation)
+    pub ext_flags: u8,
+
     /// Reserved bytes for future extensions (must be zero)
-    pub reserved: [u8; 3],
+    pub reserved: [u8; 2],
 }
 
 /// Header feature flags
@@ -67,6 +87,37 @@ pub mod flags {
 
     /// Variable-length metadata section present
     pub const HAS_METADATA: u8 = 0x02;
+
+    /// Payload is split into independently compressed blocks
+    pub const HAS_BLOCKS: u8 = 0x04;
+
+    /// Written incrementally: original size is stored in a trailer after the blocks
+    pub const STREAMED: u8 = 0x08;
+
+    /// Block index footer present at the end of the file
+    pub const SEEKABLE: u8 = 0x10;
+
+    /// Plugin parameter section present after the metadata section
+    pub const HAS_PARAMS: u8 = 0x20;
+
+    /// Block frames whose compressed length equals their uncompressed length
+    /// hold the block uncompressed
+    pub const STORED_BLOCKS: u8 = 0x40;
+
+    /// Everything after the header and encryption section is encrypted
+    pub const ENCRYPTED: u8 = 0x80;
+}
+
+/// Header extension flags
+pub mod ext_flags {
+    /// Mask of the content hash algorithm id (see [`crate::hash::HashAlgorithm`])
+    pub const HASH_ALGORITHM: u8 = 0x03;
+
+    /// Ed25519 signature trailer present at the very end of the file
+    pub const SIGNED: u8 = 0x04;
+
+    /// Every block is preceded by a Reed-Solomon parity record (see [`crate::parity`])
+    pub const PARITY: u8 = 0x08;
 }
 
 impl CrushHeader {
@@ -86,7 +137,8 @@ impl CrushHeader {
             magic,
             flags: 0,
             original_size,
-            reserved: [0; 3],
+            ext_flags: 0,
+            reserved: [0; 2],
         }
     }
 
@@ -104,6 +156,69 @@ impl CrushHeader {
         self
     }
 
+    /// Create a header with blocks flag set
+    #[must_use]
+    pub fn with_blocks(mut self) -> Self {
+        self.flags |= flags::HAS_BLOCKS;
+        self
+    }
+
+    /// Create a header with streamed flag set
+    #[must_use]
+    pub fn with_streamed(mut self) -> Self {
+        self.flags |= flags::STREAMED;
+        self
+    }
+
+    /// Create a header with seekable flag set
+    #[must_use]
+    pub fn with_seekable(mut self) -> Self {
+        self.flags |= flags::SEEKABLE;
+        self
+    }
+
+    /// Create a header with plugin parameters flag set
+    #[must_use]
+    pub fn with_params(mut self) -> Self {
+        self.flags |= flags::HAS_PARAMS;
+        self
+    }
+
+    /// Create a header with stored blocks flag set
+    #[must_use]
+    pub fn with_stored_blocks(mut self) -> Self {
+        self.flags |= flags::STORED_BLOCKS;
+        self
+    }
+
+    /// Create a header with encrypted flag set
+    #[must_use]
+    pub fn with_encrypted(mut self) -> Self {
+        self.flags |= flags::ENCRYPTED;
+        self
+    }
+
+    /// Create a header with signed flag set
+    #[must_use]
+    pub fn with_signed(mut self) -> Self {
+        self.ext_flags |= ext_flags::SIGNED;
+        self
+    }
+
+    /// Create a header with parity flag set
+    #[must_use]
+    pub fn with_parity(mut self) -> Self {
+        self.ext_flags |= ext_flags::PARITY;
+        self
+    }
+
+    /// Create a header recording a content hash of the uncompressed data
+    #[must_use]
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.ext_flags = (self.ext_flags & !ext_flags::HASH_ALGORITHM) | algorithm.id();
+        self
+    }
+
     /// Check if this header has a valid Crush magic number prefix
     #[must_use]
     pub fn has_valid_prefix(&self) -> bool {
@@ -134,6 +249,60 @@ impl CrushHeader {
         (self.flags & flags::HAS_METADATA) != 0
     }
 
+    /// Check if blocks flag is set
+    #[must_use]
+    pub fn has_blocks(&self) -> bool {
+        (self.flags & flags::HAS_BLOCKS) != 0
+    }
+
+    /// Check if streamed flag is set
+    #[must_use]
+    pub fn is_streamed(&self) -> bool {
+        (self.flags & flags::STREAMED) != 0
+    }
+
+    /// Check if seekable flag is set
+    #[must_use]
+    pub fn is_seekable(&self) -> bool {
+        (self.flags & flags::SEEKABLE) != 0
+    }
+
+    /// Check if a plugin parameter section is present
+    #[must_use]
+    pub fn has_params(&self) -> bool {
+        (self.flags & flags::HAS_PARAMS) != 0
+    }
+
+    /// Check if blocks that did not shrink may be stored uncompressed
+    #[must_use]
+    pub fn has_stored_blocks(&self) -> bool {
+        (self.flags & flags::STORED_BLOCKS) != 0
+    }
+
+    /// Check if encrypted flag is set
+    #[must_use]
+    pub fn is_encrypted(&self) -> bool {
+        (self.flags & flags::ENCRYPTED) != 0
+    }
+
+    /// Check if signed flag is set
+    #[must_use]
+    pub fn is_signed(&self) -> bool {
+        (self.ext_flags & ext_flags::SIGNED) != 0
+    }
+
+    /// Check if parity flag is set
+    #[must_use]
+    pub fn has_parity(&self) -> bool {
+        (self.ext_flags & ext_flags::PARITY) != 0
+    }
+
+    /// Algorithm of the content hash stored in the file, if any
+    #[must_use]
+    pub fn hash_algorithm(&self) -> Option<HashAlgorithm> {
+        HashAlgorithm::from_id(self.ext_flags & ext_flags::HASH_ALGORITHM)
+    }
+
     /// Serialize header to bytes (little-endian)
     #[must_use]
     pub fn to_bytes(&self) -> [u8; Self::SIZE] {
@@ -148,8 +317,11 @@ impl CrushHeader {
         // Flags (1 byte)
         bytes[12] = self.flags;
 
-        // Reserved (3 bytes, must be zero)
-        bytes[13..16].copy_from_slice(&self.reserved);
+        // Extension flags (1 byte)
+        bytes[13] = self.ext_flags;
+
+        // Reserved (2 bytes, must be zero)
+        bytes[14..16].copy_from_slice(&self.reserved);
 
         bytes
     }
@@ -168,7 +340,8 @@ impl CrushHeader {
                 bytes[4], bytes[5], bytes[6], bytes[7], bytes[8], bytes[9], bytes[10], bytes[11],
             ]),
             flags: bytes[12],
-            reserved: [bytes[13], bytes[14], bytes[15]],
+            ext_flags: bytes[13],
+            reserved: [bytes[14], bytes[15]],
         };
 
         // Validate Crush format
@@ -202,10 +375,18 @@ impl CrushHeader {
     ///
     /// Returns an error if:
     /// - The read operation fails
+    /// - The input ends before a full header
     /// - The header validation fails (invalid magic or version)
     pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
-        let mut bytes = [0u8; Self::SIZE];
-        reader.read_exact(&mut bytes)?;
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        reader.take(Self::SIZE as u64).read_to_end(&mut bytes)?;
+        let bytes = <[u8; Self::SIZE]>::try_from(bytes.as_slice()).map_err(|_| {
+            ValidationError::InvalidHeader(format!(
+                "Input too short: {} bytes, expected at least {}",
+                bytes.len(),
+                Self::SIZE
+            ))
+        })?;
         Self::from_bytes(&bytes)
     }
 }
@@ -379,7 +560,8 @@ mod tests {
             magic: [0xFF, 0xFF, 0x01, 0x00],
             original_size: 100,
             flags: 0,
-            reserved: [0; 3],
+            ext_flags: 0,
+            reserved: [0; 2],
         };
         assert!(!invalid.has_valid_prefix());
     }
@@ -394,7 +576,8 @@ mod tests {
             magic: [0x43, 0x52, 0x99, 0x00],
             original_size: 100,
             flags: 0,
-            reserved: [0; 3],
+            ext_flags: 0,
+            reserved: [0; 2],
         };
         assert!(!invalid.has_valid_version());
     }
@@ -411,6 +594,124 @@ mod tests {
         assert_eq!(bytes[12] & flags::HAS_METADATA, flags::HAS_METADATA);
     }
 
+    #[test]
+    fn test_has_blocks_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100);
+        assert!(!without.has_blocks());
+
+        let with = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100).with_blocks();
+        assert!(with.has_blocks());
+
+        let bytes = with.to_bytes();
+        assert_eq!(bytes[12] & flags::HAS_BLOCKS, flags::HAS_BLOCKS);
+    }
+
+    #[test]
+    fn test_streamed_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100);
+        assert!(!without.is_streamed());
+
+        let with = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 0)
+            .with_blocks()
+            .with_streamed();
+        assert!(with.is_streamed());
+        assert!(with.has_blocks());
+
+        let bytes = with.to_bytes();
+        assert_eq!(bytes[12] & flags::STREAMED, flags::STREAMED);
+    }
+
+    #[test]
+    fn test_seekable_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100);
+        assert!(!without.is_seekable());
+
+        let with = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100)
+            .with_blocks()
+            .with_seekable();
+        assert!(with.is_seekable());
+        assert!(!with.is_streamed());
+
+        let bytes = with.to_bytes();
+        assert_eq!(bytes[12] & flags::SEEKABLE, flags::SEEKABLE);
+    }
+
+    #[test]
+    fn test_params_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100);
+        assert!(!without.has_params());
+
+        let with = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100).with_params();
+        assert!(with.has_params());
+
+        let bytes = with.to_bytes();
+        assert_eq!(bytes[12] & flags::HAS_PARAMS, flags::HAS_PARAMS);
+    }
+
+    #[test]
+    fn test_stored_blocks_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100).with_blocks();
+        assert!(!without.has_stored_blocks());
+
+        let with = without.with_stored_blocks();
+        assert!(with.has_stored_blocks());
+
+        let bytes = with.to_bytes();
+        assert_eq!(bytes[12] & flags::STORED_BLOCKS, flags::STORED_BLOCKS);
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_hash_algorithm_ext_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100);
+        assert_eq!(without.hash_algorithm(), None);
+
+        for algorithm in HashAlgorithm::ALL {
+            let bytes = without.with_hash_algorithm(algorithm).to_bytes();
+            assert_ne!(bytes[13] & ext_flags::HASH_ALGORITHM, 0);
+            let parsed = CrushHeader::from_bytes(&bytes).unwrap();
+            assert_eq!(parsed.hash_algorithm(), Some(algorithm));
+            assert_eq!(parsed.flags, 0);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_signed_ext_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100)
+            .with_hash_algorithm(HashAlgorithm::Sha256);
+        assert!(!without.is_signed());
+
+        let parsed = CrushHeader::from_bytes(&without.with_signed().to_bytes()).unwrap();
+        assert!(parsed.is_signed());
+        assert_eq!(parsed.hash_algorithm(), Some(HashAlgorithm::Sha256));
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_parity_ext_flag() {
+        let header = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100).with_signed();
+        assert!(!header.has_parity());
+
+        let parsed = CrushHeader::from_bytes(&header.with_parity().to_bytes()).unwrap();
+        assert!(parsed.has_parity());
+        assert!(parsed.is_signed());
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_encrypted_flag() {
+        let without = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100);
+        assert!(!without.is_encrypted());
+
+        let with = without.with_encrypted();
+        assert!(with.is_encrypted());
+
+        let bytes = with.to_bytes();
+        assert_eq!(bytes[12] & flags::ENCRYPTED, flags::ENCRYPTED);
+        assert!(CrushHeader::from_bytes(&bytes).unwrap().is_encrypted());
+    }
+
     #[test]
     fn test_combined_flags() {
         let header = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 100)
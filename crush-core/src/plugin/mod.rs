@@ -4,17 +4,29 @@
 //! custom compression algorithms. Plugins are registered at compile-time using
 //! the `linkme` crate for zero runtime overhead.
 
+pub mod brotli;
+pub mod bzip2;
+pub mod calibration;
 pub mod contract;
 pub mod default;
+pub mod lz4;
 pub mod metadata;
+pub mod params;
 pub mod registry;
 pub mod selector;
+pub mod stored;
 pub mod timeout;
+pub mod xz;
+pub mod zstd;
 
-pub use contract::CompressionAlgorithm;
+pub use calibration::{
+    active_calibration, calibrate, set_calibration, Calibration, PluginCalibration,
+};
+pub use contract::{check_output_limit, read_decoded, CompressionAlgorithm, PartialOutput};
 pub use metadata::{CrushHeader, FileMetadata, PluginMetadata};
-pub use registry::{init_plugins, list_plugins};
-pub use selector::{calculate_plugin_score, PluginSelector, ScoringWeights};
+pub use params::{ParamSpec, PluginParams};
+pub use registry::{init_plugins, list_plugins, plugin_parameters};
+pub use selector::{calculate_plugin_score, PluginSelector, SamplingConfig, ScoringWeights};
 pub use timeout::{run_with_timeout, run_with_timeout_and_cancel, TimeoutGuard};
 
 use linkme::distributed_slice;
@@ -0,0 +1,404 @@
+//! Plugin-specific tunable parameters
+//!
+//! Plugins describe the parameters they accept (e.g. `level`, `window_log`)
+//! as [`ParamSpec`]s. Callers pass values through
+//! [`CompressionOptions::with_plugin_param`](crate::CompressionOptions::with_plugin_param)
+//! as a [`PluginParams`] map, which is validated against the selected plugin
+//! and recorded in the compressed output so decompression sees the same values.
+//!
+//! # Wire Format
+//!
+//! ```text
+//! [count u8] then per parameter: [name_len u8][name (UTF-8)][value i64 LE]
+//! ```
+
+use crate::error::{Result, ValidationError};
+use crate::plugin::{CompressionAlgorithm, PartialOutput, PluginMetadata};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Description of a tunable parameter accepted by a plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamSpec {
+    /// Parameter name (e.g. `level`)
+    pub name: &'static str,
+
+    /// Human-readable description
+    pub description: &'static str,
+
+    /// Minimum accepted value (inclusive)
+    pub min: i64,
+
+    /// Maximum accepted value (inclusive)
+    pub max: i64,
+
+    /// Value used when the parameter is not set
+    pub default: i64,
+}
+
+/// Plugin parameter values, keyed by parameter name
+///
+/// Parameters are kept sorted by name so the serialized form is deterministic.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(transparent)]
+pub struct PluginParams {
+    values: BTreeMap<String, i64>,
+}
+
+impl PluginParams {
+    /// Create an empty parameter set
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a parameter value, replacing any previous value
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, value: i64) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Set a parameter value, replacing any previous value
+    pub fn set(&mut self, name: impl Into<String>, value: i64) {
+        self.values.insert(name.into(), value);
+    }
+
+    /// Value of parameter `name`, if set
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<i64> {
+        self.values.get(name).copied()
+    }
+
+    /// Value of `spec`'s parameter, or its default if not set
+    #[must_use]
+    pub fn get_or_default(&self, spec: &ParamSpec) -> i64 {
+        self.get(spec.name).unwrap_or(spec.default)
+    }
+
+    /// Returns true if no parameters are set
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over `(name, value)` pairs in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, i64)> {
+        self.values
+            .iter()
+            .map(|(name, &value)| (name.as_str(), value))
+    }
+
+    /// Check every parameter against `specs`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidationError::InvalidParameter` if a parameter is not
+    /// described by `specs` or its value is out of range.
+    pub fn validate(&self, plugin_name: &str, specs: &[ParamSpec]) -> Result<()> {
+        for (name, value) in self.iter() {
+            let spec = specs.iter().find(|s| s.name == name).ok_or_else(|| {
+                let accepted = if specs.is_empty() {
+                    "none".to_string()
+                } else {
+                    specs.iter().map(|s| s.name).collect::<Vec<_>>().join(", ")
+                };
+                ValidationError::InvalidParameter(format!(
+                    "Plugin '{plugin_name}' does not accept parameter '{name}' (accepted: {accepted})"
+                ))
+            })?;
+
+            if !(spec.min..=spec.max).contains(&value) {
+                return Err(ValidationError::InvalidParameter(format!(
+                    "Parameter '{name}' for plugin '{plugin_name}' must be between {} and {}, got {value}",
+                    spec.min, spec.max
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize parameters (see the module-level wire format)
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidationError::InvalidParameter` if there are more than 255
+    /// parameters or a name is longer than 255 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let count = u8::try_from(self.values.len()).map_err(|_| {
+            ValidationError::InvalidParameter("Too many plugin parameters".to_string())
+        })?;
+
+        let mut bytes = vec![count];
+        for (name, value) in self.iter() {
+            let name_len = u8::try_from(name.len()).map_err(|_| {
+                ValidationError::InvalidParameter(format!("Parameter name too long: '{name}'"))
+            })?;
+            bytes.push(name_len);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Deserialize parameters written by [`PluginParams::to_bytes`]
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidationError::InvalidMetadata` if the bytes are truncated,
+    /// contain trailing data, or a name is not valid UTF-8.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let truncated =
+            || ValidationError::InvalidMetadata("Truncated plugin parameters".to_string());
+
+        let (&count, mut rest) = bytes.split_first().ok_or_else(truncated)?;
+        let mut params = Self::new();
+        for _ in 0..count {
+            let (&name_len, after_len) = rest.split_first().ok_or_else(truncated)?;
+            let name_len = name_len as usize;
+            if after_len.len() < name_len + 8 {
+                return Err(truncated().into());
+            }
+            let name = std::str::from_utf8(&after_len[..name_len]).map_err(|_| {
+                ValidationError::InvalidMetadata("Plugin parameter name is not UTF-8".to_string())
+            })?;
+            let mut value = [0u8; 8];
+            value.copy_from_slice(&after_len[name_len..name_len + 8]);
+            params.set(name, i64::from_le_bytes(value));
+            rest = &after_len[name_len + 8..];
+        }
+
+        if !rest.is_empty() {
+            return Err(ValidationError::InvalidMetadata(format!(
+                "Unexpected {} trailing bytes after plugin parameters",
+                rest.len()
+            ))
+            .into());
+        }
+        Ok(params)
+    }
+
+    /// Build the length-prefixed section stored in compressed output
+    /// (empty if no parameters are set)
+    pub(crate) fn to_section(&self) -> Result<Vec<u8>> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+        let bytes = self.to_bytes()?;
+        // At most 255 entries of 1 + 255 + 8 bytes plus the count: always < 64KB
+        #[allow(clippy::cast_possible_truncation)]
+        let len = bytes.len() as u16;
+        let mut section = len.to_le_bytes().to_vec();
+        section.extend_from_slice(&bytes);
+        Ok(section)
+    }
+
+    /// Parse a length-prefixed section at the start of `input`
+    ///
+    /// Returns the parameters and the number of bytes consumed.
+    pub(crate) fn from_section(input: &[u8]) -> Result<(Self, usize)> {
+        let len_bytes: [u8; 2] = input
+            .get(..2)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ValidationError::InvalidHeader(
+                    "Truncated: parameters flag set but no parameters length".to_string(),
+                )
+            })?;
+        let len = u16::from_le_bytes(len_bytes) as usize;
+        let bytes = input.get(2..2 + len).ok_or_else(|| {
+            ValidationError::InvalidHeader(
+                "Truncated: parameters length exceeds payload size".to_string(),
+            )
+        })?;
+        Ok((Self::from_bytes(bytes)?, 2 + len))
+    }
+}
+
+impl std::fmt::Display for PluginParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (name, value)) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A registered plugin bound to the parameters it was configured with
+///
+/// Implements [`CompressionAlgorithm`] by forwarding `compress`/`decompress`
+/// to the plugin's `*_with_params` methods (and `decompress_with_limit` and
+/// `decompress_prefix` with the bound parameters), so the block and streaming code
+/// can treat a configured plugin like any other.
+pub(crate) struct ConfiguredPlugin {
+    plugin: &'static dyn CompressionAlgorithm,
+    params: PluginParams,
+}
+
+impl ConfiguredPlugin {
+    /// Bind `params` to `plugin`, validating them first
+    pub(crate) fn new(
+        plugin: &'static dyn CompressionAlgorithm,
+        params: PluginParams,
+    ) -> Result<Self> {
+        plugin.validate_params(&params)?;
+        Ok(Self { plugin, params })
+    }
+
+    /// The bound parameters
+    pub(crate) fn params(&self) -> &PluginParams {
+        &self.params
+    }
+}
+
+impl CompressionAlgorithm for ConfiguredPlugin {
+    fn name(&self) -> &'static str {
+        self.plugin.name()
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        self.plugin.metadata()
+    }
+
+    fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.plugin
+            .compress_with_params(input, &self.params, cancel_flag)
+    }
+
+    fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.plugin
+            .decompress_with_params(input, &self.params, cancel_flag)
+    }
+
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.plugin
+            .decompress_with_limit(input, &self.params, max_output, cancel_flag)
+    }
+
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        self.plugin
+            .decompress_prefix(input, &self.params, max_output, cancel_flag)
+    }
+
+    fn detect(&self, file_header: &[u8]) -> bool {
+        self.plugin.detect(file_header)
+    }
+
+    fn parameters(&self) -> &'static [ParamSpec] {
+        self.plugin.parameters()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    const SPECS: &[ParamSpec] = &[
+        ParamSpec {
+            name: "level",
+            description: "Compression level",
+            min: 1,
+            max: 9,
+            default: 6,
+        },
+        ParamSpec {
+            name: "window_log",
+            description: "Window size (log2)",
+            min: 10,
+            max: 24,
+            default: 22,
+        },
+    ];
+
+    #[test]
+    fn test_params_roundtrip() {
+        let params = PluginParams::new().with("window_log", 20).with("level", 9);
+        let bytes = params.to_bytes().unwrap();
+        assert_eq!(PluginParams::from_bytes(&bytes).unwrap(), params);
+
+        let empty = PluginParams::new();
+        assert_eq!(empty.to_bytes().unwrap(), vec![0]);
+        assert!(PluginParams::from_bytes(&[0]).unwrap().is_empty());
+        assert!(empty.to_section().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_params_section_roundtrip() {
+        let params = PluginParams::new().with("level", -5);
+        let mut section = params.to_section().unwrap();
+        let section_len = section.len();
+        section.extend_from_slice(b"payload");
+
+        let (parsed, consumed) = PluginParams::from_section(&section).unwrap();
+        assert_eq!(parsed, params);
+        assert_eq!(consumed, section_len);
+
+        assert!(PluginParams::from_section(&[5]).is_err());
+        assert!(PluginParams::from_section(&section[..section_len - 1]).is_err());
+    }
+
+    #[test]
+    fn test_params_from_bytes_malformed() {
+        let bytes = PluginParams::new().with("level", 3).to_bytes().unwrap();
+
+        assert!(PluginParams::from_bytes(&[]).is_err());
+        assert!(PluginParams::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(PluginParams::from_bytes(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_params_validate() {
+        let valid = PluginParams::new().with("level", 9).with("window_log", 10);
+        assert!(valid.validate("test", SPECS).is_ok());
+
+        let out_of_range = PluginParams::new().with("level", 10);
+        assert!(matches!(
+            out_of_range.validate("test", SPECS),
+            Err(CrushError::Validation(ValidationError::InvalidParameter(_)))
+        ));
+
+        let unknown = PluginParams::new().with("strategy", 1);
+        let err = unknown.validate("test", SPECS).unwrap_err().to_string();
+        assert!(err.contains("strategy"));
+        assert!(err.contains("level, window_log"));
+
+        assert!(PluginParams::new()
+            .with("level", 1)
+            .validate("test", &[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_params_get_and_display() {
+        let params = PluginParams::new().with("level", 3).with("level", 5);
+        assert_eq!(params.get("level"), Some(5));
+        assert_eq!(params.get_or_default(&SPECS[1]), 22);
+        assert_eq!(
+            params.clone().with("window_log", 20).to_string(),
+            "level=5, window_log=20"
+        );
+    }
+}
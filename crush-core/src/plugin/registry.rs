@@ -4,7 +4,7 @@
 //! distributed slice with runtime validation and management capabilities.
 
 use crate::error::{PluginError, Result};
-use crate::plugin::{CompressionAlgorithm, PluginMetadata, COMPRESSION_ALGORITHMS};
+use crate::plugin::{CompressionAlgorithm, ParamSpec, PluginMetadata, COMPRESSION_ALGORITHMS};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
@@ -184,6 +184,29 @@ pub fn list_plugins() -> Vec<PluginMetadata> {
         .unwrap_or_default()
 }
 
+/// Describe the tunable parameters accepted by the plugin named `name`
+///
+/// Returns `None` if no registered plugin has that name.
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::{init_plugins, plugin_parameters};
+///
+/// init_plugins().expect("Failed to initialize plugins");
+/// for spec in plugin_parameters("zstd").unwrap_or_default() {
+///     println!("{} ({}..={}): {}", spec.name, spec.min, spec.max, spec.description);
+/// }
+/// ```
+#[must_use]
+pub fn plugin_parameters(name: &str) -> Option<&'static [ParamSpec]> {
+    list_plugins()
+        .into_iter()
+        .find(|p| p.name == name)
+        .and_then(|p| get_plugin_by_magic(p.magic_number))
+        .map(CompressionAlgorithm::parameters)
+}
+
 /// Get a plugin by magic number (internal use)
 ///
 /// Used by decompression to route to the correct plugin based on file header.
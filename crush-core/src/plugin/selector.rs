@@ -3,9 +3,35 @@
 //! Implements intelligent plugin selection based on performance metadata.
 //! Uses configurable scoring weights (default 70% throughput, 30% compression ratio)
 //! with logarithmic throughput scaling and min-max normalization.
+//!
+//! [`PluginSelector::select_sampled`] scores measured numbers instead: it
+//! trial-compresses slices of the actual input with each candidate plugin
+//! within a time budget (see [`SamplingConfig`]).
+//!
+//! Input that is already compressed throughout (see
+//! [`looks_compressed_throughout`]) always goes to the stored plugin;
+//! otherwise the stored plugin is left out of scoring.
 
 use crate::error::{PluginError, Result, ValidationError};
-use crate::plugin::{list_plugins, PluginMetadata};
+use crate::plugin::calibration::calibrated;
+use crate::plugin::registry::get_plugin_by_magic;
+use crate::plugin::stored::{looks_compressed_throughout, STORED_MAGIC};
+use crate::plugin::{list_plugins, CompressionAlgorithm, PluginMetadata};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of leading input bytes passed to plugins' `detect()` during selection
+pub(crate) const DETECT_HEADER_SIZE: usize = 4096;
+
+/// Default size of each sampled slice for empirical selection
+pub const DEFAULT_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Default number of slices sampled across the input
+pub const DEFAULT_SAMPLE_COUNT: usize = 3;
+
+/// Default wall-clock budget for trial compressions
+pub const DEFAULT_SAMPLE_BUDGET: Duration = Duration::from_millis(250);
 
 /// Scoring weights for plugin selection
 ///
@@ -89,6 +115,10 @@ impl Default for ScoringWeights {
 ///
 /// Score in range [0.0, 1.0] where higher is better
 ///
+/// While a calibration is active (see [`set_calibration`](crate::set_calibration)),
+/// the measured throughput and ratio replace the metadata values of every
+/// plugin it covers.
+///
 /// # Examples
 ///
 /// ```
@@ -113,6 +143,17 @@ pub fn calculate_plugin_score(
     plugin: &PluginMetadata,
     all_plugins: &[PluginMetadata],
     weights: &ScoringWeights,
+) -> f64 {
+    let plugin = calibrated(plugin);
+    let all_plugins: Vec<PluginMetadata> = all_plugins.iter().map(calibrated).collect();
+    score_metadata(&plugin, &all_plugins, weights)
+}
+
+/// Score `plugin` using its throughput and ratio exactly as given
+fn score_metadata(
+    plugin: &PluginMetadata,
+    all_plugins: &[PluginMetadata],
+    weights: &ScoringWeights,
 ) -> f64 {
     if all_plugins.is_empty() {
         return 0.0;
@@ -167,6 +208,111 @@ pub fn calculate_plugin_score(
     weights.throughput * norm_throughput + weights.compression_ratio * norm_ratio
 }
 
+/// Configuration for empirical, sample-based plugin selection
+///
+/// `sample_count` slices of `sample_size` bytes are taken evenly across the
+/// input (the whole input is used if it is smaller than that). Candidates are
+/// measured fastest-first, and no new trial starts once `time_budget` has
+/// elapsed; at least one candidate is always measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplingConfig {
+    /// Size of each sampled slice in bytes
+    pub sample_size: usize,
+
+    /// Number of slices taken across the input
+    pub sample_count: usize,
+
+    /// Wall-clock budget for all trial compressions
+    pub time_budget: Duration,
+}
+
+impl SamplingConfig {
+    /// Create a sampling configuration with the default slice count
+    #[must_use]
+    pub fn new(sample_size: usize, time_budget: Duration) -> Self {
+        Self {
+            sample_size,
+            sample_count: DEFAULT_SAMPLE_COUNT,
+            time_budget,
+        }
+    }
+
+    /// Set the number of slices taken across the input
+    #[must_use]
+    pub fn with_sample_count(mut self, sample_count: usize) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Slices of `input` to trial-compress
+    fn samples<'a>(&self, input: &'a [u8]) -> Vec<&'a [u8]> {
+        let size = self.sample_size.max(1);
+        let count = self.sample_count.max(1);
+        if input.len() <= size.saturating_mul(count) {
+            return vec![input];
+        }
+
+        // Evenly spaced slices, the first at the start and the last at the end
+        let span = input.len() - size;
+        (0..count)
+            .map(|i| {
+                let start = if count == 1 {
+                    0
+                } else {
+                    i * span / (count - 1)
+                };
+                &input[start..start + size]
+            })
+            .collect()
+    }
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_SIZE, DEFAULT_SAMPLE_BUDGET)
+    }
+}
+
+/// Trial-compress `samples` and return measured metadata for `candidate`
+///
+/// Returns `None` if the plugin fails on the sample data.
+fn measure(
+    plugin: &dyn CompressionAlgorithm,
+    candidate: &PluginMetadata,
+    samples: &[&[u8]],
+    deadline: Instant,
+) -> Option<PluginMetadata> {
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let start = Instant::now();
+    let mut input_len = 0;
+    let mut output_len = 0;
+
+    for sample in samples {
+        // Keep whatever was measured before the budget ran out
+        if input_len > 0 && Instant::now() >= deadline {
+            break;
+        }
+        output_len += plugin
+            .compress(sample, Arc::clone(&cancel_flag))
+            .ok()?
+            .len();
+        input_len += sample.len();
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Sample sizes are far below 2^52
+    let (input_len, output_len) = (input_len as f64, output_len as f64);
+    let elapsed = start.elapsed().as_secs_f64().max(1e-6);
+
+    Some(PluginMetadata {
+        throughput: input_len / elapsed / 1_000_000.0,
+        compression_ratio: output_len / input_len.max(1.0),
+        ..*candidate
+    })
+}
+
+/// Scoring function used to rank candidate plugins
+type ScoreFn = fn(&PluginMetadata, &[PluginMetadata], &ScoringWeights) -> f64;
+
 /// Plugin selector with scoring logic
 pub struct PluginSelector {
     weights: ScoringWeights,
@@ -182,14 +328,82 @@ impl PluginSelector {
     /// Select the best plugin based on scoring
     ///
     /// Returns the plugin with the highest score. In case of ties,
-    /// selects alphabetically by name.
+    /// selects alphabetically by name. The stored plugin is never selected
+    /// without seeing the content.
     ///
     /// # Errors
     ///
     /// Returns an error if no plugins are available.
     pub fn select(&self) -> Result<PluginMetadata> {
-        let plugins = list_plugins();
+        self.select_from(&scored_plugins(), calculate_plugin_score)
+    }
+
+    /// Select the best plugin for `input`, or for content starting with it
+    ///
+    /// Only plugins whose [`detect`](crate::plugin::CompressionAlgorithm::detect)
+    /// accepts the first bytes are scored, so content-specific plugins (e.g.
+    /// Brotli for text) are considered only for matching input. Falls back to
+    /// all plugins if none claims the content. Content that is already
+    /// compressed throughout selects the stored plugin regardless of the
+    /// weights.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no plugins are available.
+    pub fn select_for(&self, input: &[u8]) -> Result<PluginMetadata> {
+        self.select_from(&candidates(input), calculate_plugin_score)
+    }
+
+    /// Select the best plugin by trial-compressing samples of `input`
+    ///
+    /// Candidates are the plugins [`select_for`](Self::select_for) would
+    /// consider. Each is scored with the same weights, but on the ratio and
+    /// throughput measured on the samples rather than its static metadata.
+    /// Candidates not reached within the time budget, or failing on the
+    /// samples, are left out. Empty input falls back to static scoring.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no plugins are available.
+    pub fn select_sampled(&self, input: &[u8], config: &SamplingConfig) -> Result<PluginMetadata> {
+        let mut candidates = candidates(input);
+        if input.is_empty() {
+            return self.select_from(&candidates, calculate_plugin_score);
+        }
+
+        // Fastest first, so a tight budget still measures the quick plugins
+        candidates.sort_by(|a, b| {
+            b.throughput
+                .partial_cmp(&a.throughput)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let samples = config.samples(input);
+        let deadline = Instant::now() + config.time_budget;
+        let mut measured = Vec::with_capacity(candidates.len());
+        for candidate in &candidates {
+            if !measured.is_empty() && Instant::now() >= deadline {
+                break;
+            }
+            if let Some(plugin) = get_plugin_by_magic(candidate.magic_number) {
+                measured.extend(measure(plugin, candidate, &samples, deadline));
+            }
+        }
 
+        if measured.is_empty() {
+            return self.select_from(&candidates, calculate_plugin_score);
+        }
+
+        // Report the winner with its static metadata, not the sample numbers
+        let winner = self.select_from(&measured, score_metadata)?;
+        Ok(candidates
+            .into_iter()
+            .find(|c| c.name == winner.name)
+            .unwrap_or(winner))
+    }
+
+    /// Score `plugins` against each other with `score` and return the best one
+    fn select_from(&self, plugins: &[PluginMetadata], score: ScoreFn) -> Result<PluginMetadata> {
         if plugins.is_empty() {
             return Err(PluginError::NotFound(
                 "No plugins available. Call init_plugins() first.".to_string(),
@@ -201,7 +415,7 @@ impl PluginSelector {
         let mut scored_plugins: Vec<(f64, &PluginMetadata)> = plugins
             .iter()
             .map(|plugin| {
-                let score = calculate_plugin_score(plugin, &plugins, &self.weights);
+                let score = score(plugin, plugins, &self.weights);
                 (score, plugin)
             })
             .collect();
@@ -240,6 +454,42 @@ impl PluginSelector {
     }
 }
 
+/// All registered plugins except the stored plugin
+fn scored_plugins() -> Vec<PluginMetadata> {
+    list_plugins()
+        .into_iter()
+        .filter(|p| p.magic_number != STORED_MAGIC)
+        .collect()
+}
+
+/// Plugins whose `detect()` accepts `file_header`, or all plugins if none does
+///
+/// Already-compressed content has the stored plugin as its only candidate.
+fn candidates(input: &[u8]) -> Vec<PluginMetadata> {
+    if looks_compressed_throughout(input) {
+        if let Some(stored) = list_plugins()
+            .into_iter()
+            .find(|p| p.magic_number == STORED_MAGIC)
+        {
+            return vec![stored];
+        }
+    }
+
+    let file_header = &input[..input.len().min(DETECT_HEADER_SIZE)];
+    let plugins = scored_plugins();
+    let candidates: Vec<PluginMetadata> = plugins
+        .iter()
+        .filter(|p| get_plugin_by_magic(p.magic_number).is_some_and(|a| a.detect(file_header)))
+        .copied()
+        .collect();
+
+    if candidates.is_empty() {
+        plugins
+    } else {
+        candidates
+    }
+}
+
 impl Default for PluginSelector {
     fn default() -> Self {
         Self::new(ScoringWeights::default())
@@ -330,4 +580,24 @@ mod tests {
         assert!((fast_balanced - fast_score).abs() > 1e-6);
         assert!((slow_balanced - slow_score).abs() > 1e-6);
     }
+
+    #[test]
+    fn test_sampling_slices() {
+        let input: Vec<u8> = (0..=255).collect();
+        let config = SamplingConfig::new(10, Duration::ZERO);
+
+        // Evenly spaced slices covering both ends of the input
+        let samples = config.samples(&input);
+        assert_eq!(samples.len(), DEFAULT_SAMPLE_COUNT);
+        assert_eq!(samples[0], &input[..10]);
+        assert_eq!(samples[1], &input[123..133]);
+        assert_eq!(samples[2], &input[246..]);
+
+        // Small inputs are sampled whole
+        assert_eq!(config.samples(&input[..30]), vec![&input[..30]]);
+        assert_eq!(
+            config.with_sample_count(1).samples(&input),
+            vec![&input[..10]]
+        );
+    }
 }
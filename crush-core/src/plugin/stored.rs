@@ -0,0 +1,363 @@
+//! Stored (identity) plugin for already-compressed input
+//!
+//! JPEGs, ZIP archives, video and existing `.gz` files do not shrink when
+//! compressed again; running them through DEFLATE or zstd only costs CPU and
+//! usually grows them by a few bytes. The stored plugin copies its input
+//! unchanged, and automatic selection routes input to it when the first bytes
+//! carry a known compressed-format signature or look like random data, and the
+//! rest of the input looks random too (see [`looks_compressed_throughout`]).
+//!
+//! The stored plugin never takes part in scoring: its ratio of 1.0 and memcpy
+//! throughput would otherwise win every throughput-weighted selection.
+
+use crate::error::{PluginError, Result};
+use crate::plugin::{
+    check_output_limit, CompressionAlgorithm, PluginMetadata, PluginParams, COMPRESSION_ALGORITHMS,
+};
+use linkme::distributed_slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Magic number of the stored plugin: CR (Crush) + V1 + ID 0x06 (stored)
+pub const STORED_MAGIC: [u8; 4] = [0x43, 0x52, 0x01, 0x06];
+
+/// Shannon entropy (bits per byte) at or above which a sample counts as
+/// already compressed
+///
+/// Text sits around 4-5 bits per byte and typical binaries around 6; the
+/// output of any general-purpose compressor is close to 8.
+pub const ENTROPY_THRESHOLD: f64 = 7.5;
+
+/// Largest fraction of repeated 4-byte windows in a sample that still counts
+/// as already compressed
+///
+/// Byte entropy ignores order, so cyclic data such as `0, 1, .., 255, 0, 1, ..`
+/// has maximal entropy yet compresses to almost nothing. Compressed data
+/// practically never repeats a 4-byte sequence within a few kilobytes.
+const MAX_REPEATED_FRACTION: f64 = 0.1;
+
+/// Smallest sample whose entropy is meaningful
+///
+/// A sample of `n` bytes cannot exceed `log2(n)` bits per byte, so short
+/// samples are judged by their signature only.
+const MIN_ENTROPY_SAMPLE: usize = 512;
+
+/// Size of the windows sampled by [`looks_compressed_throughout`]
+const ENTROPY_WINDOW_SIZE: usize = 4096;
+
+/// Number of evenly spaced windows [`looks_compressed_throughout`] checks
+/// after the first bytes
+const ENTROPY_WINDOWS: usize = 16;
+
+/// Chunk size for copying, so cancellation is checked periodically
+const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Signatures of common compressed formats: name, offset, magic bytes
+const SIGNATURES: &[(&str, usize, &[u8])] = &[
+    ("gzip", 0, &[0x1F, 0x8B]),
+    ("zip", 0, b"PK\x03\x04"),
+    ("zip", 0, b"PK\x05\x06"),
+    ("bzip2", 0, b"BZh"),
+    ("xz", 0, &[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00]),
+    ("zstd", 0, &[0x28, 0xB5, 0x2F, 0xFD]),
+    ("lz4", 0, &[0x04, 0x22, 0x4D, 0x18]),
+    ("7z", 0, &[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]),
+    ("rar", 0, b"Rar!\x1A\x07"),
+    ("crush", 0, &[0x43, 0x52, 0x01]),
+    ("jpeg", 0, &[0xFF, 0xD8, 0xFF]),
+    ("png", 0, b"\x89PNG\r\n\x1A\n"),
+    ("gif", 0, b"GIF8"),
+    ("webp", 8, b"WEBP"),
+    ("mp4", 4, b"ftyp"),
+    ("matroska", 0, &[0x1A, 0x45, 0xDF, 0xA3]),
+    ("ogg", 0, b"OggS"),
+    ("flac", 0, b"fLaC"),
+    ("mp3", 0, b"ID3"),
+];
+
+/// Name of the compressed format whose signature `header` starts with, if any
+#[must_use]
+pub fn compressed_format(header: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(_, offset, magic)| {
+            header
+                .get(*offset..*offset + magic.len())
+                .is_some_and(|bytes| bytes == *magic)
+        })
+        .map(|(name, _, _)| *name)
+}
+
+/// Shannon entropy of `sample` in bits per byte (0.0 for an empty sample)
+#[must_use]
+pub fn shannon_entropy(sample: &[u8]) -> f64 {
+    if sample.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0_usize; 256];
+    for &byte in sample {
+        counts[byte as usize] += 1;
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Sample sizes are far below 2^52
+    let len = sample.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            #[allow(clippy::cast_precision_loss)]
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Fraction of the 4-byte windows in `sample` that occurred earlier in it
+///
+/// Uses a small hash table of last positions, like an LZ77 match finder, so
+/// matches further back than a colliding window are missed.
+fn repeated_fraction(sample: &[u8]) -> f64 {
+    const HASH_BITS: u32 = 12;
+    let windows = sample.len().saturating_sub(3);
+    if windows == 0 {
+        return 0.0;
+    }
+
+    let mut last_seen = vec![usize::MAX; 1 << HASH_BITS];
+    let mut repeated = 0_usize;
+    for (i, window) in sample.windows(4).enumerate() {
+        let value = u32::from_le_bytes([window[0], window[1], window[2], window[3]]);
+        let hash = (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let previous = last_seen[hash];
+        if previous != usize::MAX && sample[previous..previous + 4] == *window {
+            repeated += 1;
+        }
+        last_seen[hash] = i;
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Sample sizes are far below 2^52
+    let fraction = repeated as f64 / windows as f64;
+    fraction
+}
+
+/// Check whether `sample` (the first bytes of the input) is already compressed
+///
+/// True if it starts with a known compressed-format signature, or is long
+/// enough to judge, has an entropy of at least [`ENTROPY_THRESHOLD`] and
+/// hardly repeats itself.
+#[must_use]
+pub fn looks_compressed(sample: &[u8]) -> bool {
+    compressed_format(sample).is_some() || looks_random(sample)
+}
+
+/// Check whether all of `input` is already compressed
+///
+/// The first bytes only speak for themselves: a PNG signature in front of a
+/// text dump, or a random prefix before a log, says nothing about the rest.
+/// True if the first bytes look compressed (see [`looks_compressed`]) and
+/// each of [`ENTROPY_WINDOWS`] windows spread over the rest of the input looks
+/// random as well.
+#[must_use]
+pub fn looks_compressed_throughout(input: &[u8]) -> bool {
+    let (head, rest) = input.split_at(input.len().min(ENTROPY_WINDOW_SIZE));
+    looks_compressed(head)
+        && (0..ENTROPY_WINDOWS)
+            .map(|i| {
+                let start = i * rest.len() / ENTROPY_WINDOWS;
+                &rest[start..rest.len().min(start + ENTROPY_WINDOW_SIZE)]
+            })
+            .filter(|window| window.len() >= MIN_ENTROPY_SAMPLE)
+            .all(looks_random)
+}
+
+/// Whether `sample` is long enough to judge, has an entropy of at least
+/// [`ENTROPY_THRESHOLD`] and hardly repeats itself
+fn looks_random(sample: &[u8]) -> bool {
+    sample.len() >= MIN_ENTROPY_SAMPLE
+        && shannon_entropy(sample) >= ENTROPY_THRESHOLD
+        && repeated_fraction(sample) <= MAX_REPEATED_FRACTION
+}
+
+/// Copy `input` in chunks, checking cancellation between chunks
+fn copy_with_cancel(input: &[u8], cancel_flag: &AtomicBool) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    for chunk in input.chunks(COPY_CHUNK_SIZE) {
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+        output.extend_from_slice(chunk);
+    }
+    Ok(output)
+}
+
+/// Stored plugin: the payload is the input, unchanged
+pub struct StoredPlugin;
+
+impl CompressionAlgorithm for StoredPlugin {
+    fn name(&self) -> &'static str {
+        "stored"
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "stored",
+            version: "1.0.0",
+            magic_number: STORED_MAGIC,
+            // Throughput: a memory copy, several GB/s on modern CPUs
+            throughput: 5000.0,
+            // Compression ratio: 1.0 (output is the input)
+            compression_ratio: 1.0,
+            description: "Stores data uncompressed, for input that is already compressed",
+        }
+    }
+
+    fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        // Check cancellation before starting
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+        copy_with_cancel(input, &cancel_flag)
+    }
+
+    fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.decompress_with_limit(input, &PluginParams::new(), usize::MAX, cancel_flag)
+    }
+
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        // Check cancellation before starting
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+        // The output is the input, so the limit is known before copying
+        check_output_limit(input.len(), max_output)?;
+        copy_with_cancel(input, &cancel_flag)
+    }
+
+    fn detect(&self, file_header: &[u8]) -> bool {
+        looks_compressed(file_header)
+    }
+}
+
+/// Register stored plugin at compile-time
+#[distributed_slice(COMPRESSION_ALGORITHMS)]
+static STORED_PLUGIN: &dyn CompressionAlgorithm = &StoredPlugin;
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    /// Deterministic pseudo-random bytes
+    fn noise(len: usize) -> Vec<u8> {
+        let mut seed = 0x1234_5678_u32;
+        (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stored_metadata() {
+        let metadata = StoredPlugin.metadata();
+
+        assert_eq!(metadata.name, "stored");
+        assert_eq!(metadata.magic_number, STORED_MAGIC);
+        assert!(metadata.throughput > 0.0);
+        assert!(metadata.compression_ratio > 0.0 && metadata.compression_ratio <= 1.0);
+    }
+
+    #[test]
+    fn test_stored_roundtrip() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        for original in [Vec::new(), noise(3 * COPY_CHUNK_SIZE + 7)] {
+            let compressed = StoredPlugin
+                .compress(&original, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(compressed, original);
+            let decompressed = StoredPlugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(decompressed, original);
+        }
+    }
+
+    #[test]
+    fn test_stored_cancellation() {
+        let cancel_flag = Arc::new(AtomicBool::new(true)); // Pre-cancelled
+
+        let result = StoredPlugin.compress(b"This should be cancelled", Arc::clone(&cancel_flag));
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+
+        let result = StoredPlugin.decompress(b"Some data", cancel_flag);
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_compressed_format_signatures() {
+        assert_eq!(compressed_format(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("jpeg"));
+        assert_eq!(compressed_format(b"\x89PNG\r\n\x1a\n\x00\x00"), Some("png"));
+        assert_eq!(compressed_format(b"PK\x03\x04\x14\x00"), Some("zip"));
+        assert_eq!(compressed_format(&[0x1F, 0x8B, 0x08, 0x00]), Some("gzip"));
+        assert_eq!(
+            compressed_format(b"\x00\x00\x00\x20ftypisom\x00\x00"),
+            Some("mp4")
+        );
+        assert_eq!(
+            compressed_format(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Some("webp")
+        );
+
+        assert_eq!(compressed_format(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(compressed_format(b"plain text"), None);
+        assert_eq!(compressed_format(&[0xFF]), None);
+        assert_eq!(compressed_format(&[]), None);
+    }
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert!(shannon_entropy(&[]).abs() < 1e-9);
+        assert!(shannon_entropy(&[0x42; 1000]).abs() < 1e-9);
+
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert!((shannon_entropy(&all_bytes) - 8.0).abs() < 1e-9);
+
+        let text = b"The quick brown fox jumps over the lazy dog. ".repeat(100);
+        assert!(shannon_entropy(&text) < 5.0);
+        assert!(shannon_entropy(&noise(4096)) > ENTROPY_THRESHOLD);
+    }
+
+    #[test]
+    fn test_looks_compressed() {
+        assert!(looks_compressed(&noise(4096)));
+        assert!(looks_compressed(&[0xFF, 0xD8, 0xFF, 0xE1]));
+        assert!(StoredPlugin.detect(&noise(4096)));
+
+        // Too short to judge by entropy, and compressible content
+        assert!(!looks_compressed(&noise(64)));
+        #[allow(clippy::cast_possible_truncation)]
+        let cyclic: Vec<u8> = (0..4096_u32).map(|i| (i % 241) as u8).collect();
+        assert!(shannon_entropy(&cyclic) > ENTROPY_THRESHOLD);
+        assert!(!looks_compressed(&cyclic));
+        assert!(!looks_compressed(&b"Hello, world! ".repeat(300)));
+        assert!(!looks_compressed(&[]));
+    }
+}
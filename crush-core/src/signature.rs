@@ -0,0 +1,425 @@
+//! Ed25519 signatures over Crush files
+//!
+//! A signature proves who produced a file. It covers the header and
+//! everything after it, and is either embedded in a trailer at the very end
+//! of the file, with the `SIGNED` extension flag set in the header, or kept
+//! in a detached `.sig` file next to it. Both use the same 100-byte layout:
+//!
+//! ```text
+//! Offset | Size | Field
+//! -------|------|-------
+//! 0      | 4    | magic ("CSIG")
+//! 4      | 32   | Ed25519 public key of the signer
+//! 36     | 64   | Ed25519 signature
+//! ```
+//!
+//! An embedded signature covers the file with the `SIGNED` flag already set
+//! and without the trailer; a detached one covers the file exactly as it is.
+//! The public key is stored so that `inspect` can check a signature without
+//! being told whose it is, but only verification against a trusted key (see
+//! [`verify_signature`]) proves who signed.
+//!
+//! Signing happens after compression and leaves the payload untouched, so
+//! encrypted files can be signed and verified without their key.
+
+use crate::error::{Result, SignatureError, ValidationError};
+use crate::hash;
+use crate::plugin::metadata::ext_flags;
+use crate::plugin::CrushHeader;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::Signer;
+use serde::Serialize;
+use std::fmt;
+use zeroize::Zeroizing;
+
+const SIGNATURE_MAGIC: [u8; 4] = *b"CSIG";
+
+/// Ed25519 key that signs Crush files
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl SigningKey {
+    /// New random key from the operating system's RNG
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut seed = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(seed.as_mut());
+        Self::from_bytes(&seed)
+    }
+
+    /// Key from its 32-byte secret seed
+    #[must_use]
+    pub fn from_bytes(seed: &[u8; 32]) -> Self {
+        Self(ed25519_dalek::SigningKey::from_bytes(seed))
+    }
+
+    /// Key from a seed slice that must be exactly 32 bytes long
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SignatureError::InvalidKey`] if `seed` is not 32 bytes
+    pub fn from_slice(seed: &[u8]) -> Result<Self> {
+        let seed: &[u8; 32] = seed.try_into().map_err(|_| {
+            SignatureError::InvalidKey(format!("expected 32 bytes, got {}", seed.len()))
+        })?;
+        Ok(Self::from_bytes(seed))
+    }
+
+    /// The 32-byte secret seed
+    #[must_use]
+    pub fn to_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.0.to_bytes())
+    }
+
+    /// Public half of the key, for verification
+    #[must_use]
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.verifying_key())
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("public_key", &self.verifying_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Ed25519 public key that verifies Crush file signatures
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VerifyingKey(ed25519_dalek::VerifyingKey);
+
+impl VerifyingKey {
+    /// Key from its 32-byte encoding
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SignatureError::InvalidKey`] if the bytes are not a valid
+    /// Ed25519 public key
+    pub fn from_bytes(key: &[u8; 32]) -> Result<Self> {
+        ed25519_dalek::VerifyingKey::from_bytes(key)
+            .map(Self)
+            .map_err(|_| SignatureError::InvalidKey("not an Ed25519 public key".to_string()).into())
+    }
+
+    /// Key from a slice that must be exactly 32 bytes long
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SignatureError::InvalidKey`] if `key` is not 32 bytes or not
+    /// a valid Ed25519 public key
+    pub fn from_slice(key: &[u8]) -> Result<Self> {
+        let key: &[u8; 32] = key.try_into().map_err(|_| {
+            SignatureError::InvalidKey(format!("expected 32 bytes, got {}", key.len()))
+        })?;
+        Self::from_bytes(key)
+    }
+
+    /// The 32-byte encoding
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// Lowercase hex encoding
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        hash::to_hex(self.0.as_bytes())
+    }
+}
+
+impl fmt::Display for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VerifyingKey({})", self.to_hex())
+    }
+}
+
+/// Signature over a Crush file, with the public key that made it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    public_key: VerifyingKey,
+    signature: ed25519_dalek::Signature,
+}
+
+impl Signature {
+    /// Size of an encoded signature, embedded or detached
+    pub const SIZE: usize = 100;
+
+    /// Public key of the signer, as recorded in the signature
+    #[must_use]
+    pub fn public_key(&self) -> VerifyingKey {
+        self.public_key
+    }
+
+    /// Encode as stored in a trailer or `.sig` file
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..4].copy_from_slice(&SIGNATURE_MAGIC);
+        bytes[4..36].copy_from_slice(&self.public_key.to_bytes());
+        bytes[36..].copy_from_slice(&self.signature.to_bytes());
+        bytes
+    }
+
+    /// Decode a trailer or the contents of a `.sig` file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SignatureError::Malformed`] if `bytes` has the wrong length or
+    /// magic, or does not hold a valid public key
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: &[u8; Self::SIZE] = bytes.try_into().map_err(|_| {
+            SignatureError::Malformed(format!(
+                "expected {} bytes, got {}",
+                Self::SIZE,
+                bytes.len()
+            ))
+        })?;
+        if bytes[0..4] != SIGNATURE_MAGIC {
+            return Err(SignatureError::Malformed("bad signature magic".to_string()).into());
+        }
+        let public_key = VerifyingKey::from_slice(&bytes[4..36])
+            .map_err(|_| SignatureError::Malformed("invalid public key".to_string()))?;
+        let signature = ed25519_dalek::Signature::from_slice(&bytes[36..])
+            .map_err(|_| SignatureError::Malformed("invalid signature".to_string()))?;
+        Ok(Self {
+            public_key,
+            signature,
+        })
+    }
+
+    fn create(message: &[u8], key: &SigningKey) -> Self {
+        Self {
+            public_key: key.verifying_key(),
+            signature: key.0.sign(message),
+        }
+    }
+
+    fn check(&self, message: &[u8]) -> Result<()> {
+        self.public_key
+            .0
+            .verify_strict(message, &self.signature)
+            .map_err(|_| SignatureError::Invalid.into())
+    }
+}
+
+/// Signature state of a file, as reported by [`crate::inspect`]
+///
+/// `valid` only says the signature matches the file and the key recorded in
+/// it; whether that key is trusted is for the caller to decide.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SignatureStatus {
+    /// Signature algorithm (always "ed25519")
+    pub algorithm: &'static str,
+    /// Hex-encoded public key of the signer
+    pub public_key: String,
+    /// Whether the signature matches the file
+    pub valid: bool,
+    /// Whether the signature came from a detached `.sig` file
+    pub detached: bool,
+}
+
+impl SignatureStatus {
+    fn new(signature: &Signature, message: &[u8], detached: bool) -> Self {
+        Self {
+            algorithm: "ed25519",
+            public_key: signature.public_key.to_hex(),
+            valid: signature.check(message).is_ok(),
+            detached,
+        }
+    }
+
+    /// Status of a detached `signature` over the complete file `input`
+    #[must_use]
+    pub fn detached(input: &[u8], signature: &Signature) -> Self {
+        Self::new(signature, input, true)
+    }
+}
+
+/// Embed a signature over `input` in a trailer
+///
+/// Returns the signed file. An existing embedded signature is replaced.
+///
+/// # Errors
+///
+/// Returns an error if `input` does not start with a valid Crush header or
+/// its signature trailer is truncated.
+pub fn sign(input: &[u8], key: &SigningKey) -> Result<Vec<u8>> {
+    let header = read_header(input)?;
+    let (content, _) = split_trailer(input, &header)?;
+
+    let mut output = Vec::with_capacity(content.len() + Signature::SIZE);
+    output.extend_from_slice(content);
+    output[13] |= ext_flags::SIGNED;
+    let signature = Signature::create(&output, key);
+    output.extend_from_slice(&signature.to_bytes());
+    Ok(output)
+}
+
+/// Sign `input` as is, for a detached `.sig` file
+///
+/// # Errors
+///
+/// Returns an error if `input` does not start with a valid Crush header.
+pub fn sign_detached(input: &[u8], key: &SigningKey) -> Result<Signature> {
+    read_header(input)?;
+    Ok(Signature::create(input, key))
+}
+
+/// Verify the signature embedded in `input`
+///
+/// With a `trusted` key, the file must also have been signed with that key.
+/// Returns the public key of the signer.
+///
+/// # Errors
+///
+/// Returns [`SignatureError::NotSigned`] if the file has no embedded
+/// signature, [`SignatureError::Invalid`] if the signature does not match,
+/// and [`SignatureError::UntrustedKey`] if it was made with another key.
+pub fn verify_signature(input: &[u8], trusted: Option<&VerifyingKey>) -> Result<VerifyingKey> {
+    let header = read_header(input)?;
+    let (content, signature) = split_trailer(input, &header)?;
+    let signature = signature.ok_or(SignatureError::NotSigned)?;
+    signature.check(content)?;
+    check_trusted(signature.public_key, trusted)
+}
+
+/// Verify a detached `signature` over `input`
+///
+/// Behaves like [`verify_signature`] for a signature kept outside the file.
+///
+/// # Errors
+///
+/// Returns [`SignatureError::Invalid`] if the signature does not match and
+/// [`SignatureError::UntrustedKey`] if it was made with another key.
+pub fn verify_detached(
+    input: &[u8],
+    signature: &Signature,
+    trusted: Option<&VerifyingKey>,
+) -> Result<VerifyingKey> {
+    read_header(input)?;
+    signature.check(input)?;
+    check_trusted(signature.public_key, trusted)
+}
+
+fn check_trusted(actual: VerifyingKey, trusted: Option<&VerifyingKey>) -> Result<VerifyingKey> {
+    match trusted {
+        Some(expected) if *expected != actual => Err(SignatureError::UntrustedKey {
+            expected: expected.to_hex(),
+            actual: actual.to_hex(),
+        }
+        .into()),
+        _ => Ok(actual),
+    }
+}
+
+pub(crate) fn read_header(input: &[u8]) -> Result<CrushHeader> {
+    let bytes: &[u8; CrushHeader::SIZE] = input
+        .get(..CrushHeader::SIZE)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ValidationError::InvalidHeader(format!(
+                "Input too short: {} bytes, expected at least {}",
+                input.len(),
+                CrushHeader::SIZE
+            ))
+        })?;
+    CrushHeader::from_bytes(bytes)
+}
+
+/// Length of the signature trailer at the end of a file with this header
+pub(crate) fn trailer_len(header: &CrushHeader) -> usize {
+    if header.is_signed() {
+        Signature::SIZE
+    } else {
+        0
+    }
+}
+
+/// Split the signature trailer off the end of `input`
+///
+/// Returns `input` unchanged if the header is not signed.
+pub(crate) fn split_trailer<'a>(
+    input: &'a [u8],
+    header: &CrushHeader,
+) -> Result<(&'a [u8], Option<Signature>)> {
+    if !header.is_signed() {
+        return Ok((input, None));
+    }
+    let start = input
+        .len()
+        .checked_sub(Signature::SIZE)
+        .filter(|&start| start >= CrushHeader::SIZE)
+        .ok_or_else(|| ValidationError::CorruptedData("Truncated signature".to_string()))?;
+    Ok((
+        &input[..start],
+        Some(Signature::from_bytes(&input[start..])?),
+    ))
+}
+
+/// Split the signature trailer off `input` and report its status, if any
+pub(crate) fn embedded_status<'a>(
+    input: &'a [u8],
+    header: &CrushHeader,
+) -> Result<(&'a [u8], Option<SignatureStatus>)> {
+    let (content, signature) = split_trailer(input, header)?;
+    let status = signature.map(|signature| SignatureStatus::new(&signature, content, false));
+    Ok((content, status))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn file() -> Vec<u8> {
+        let mut file = CrushHeader::new([0x43, 0x52, 0x01, 0x00], 5)
+            .to_bytes()
+            .to_vec();
+        file.extend_from_slice(b"payload");
+        file
+    }
+
+    #[test]
+    fn test_signature_encoding() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signature = sign_detached(&file(), &key).unwrap();
+        let bytes = signature.to_bytes();
+        assert_eq!(&bytes[0..4], b"CSIG");
+        assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+        assert_eq!(signature.public_key(), key.verifying_key());
+
+        assert!(Signature::from_bytes(&bytes[1..]).is_err());
+        let mut bad_magic = bytes;
+        bad_magic[0] = b'X';
+        assert!(Signature::from_bytes(&bad_magic).is_err());
+    }
+
+    #[test]
+    fn test_resigning_replaces_trailer() {
+        let first = sign(&file(), &SigningKey::from_bytes(&[1; 32])).unwrap();
+        let second_key = SigningKey::from_bytes(&[2; 32]);
+        let second = sign(&first, &second_key).unwrap();
+        assert_eq!(second.len(), file().len() + Signature::SIZE);
+        assert_eq!(
+            verify_signature(&second, None).unwrap(),
+            second_key.verifying_key()
+        );
+    }
+
+    #[test]
+    fn test_key_roundtrip() {
+        let key = SigningKey::generate();
+        let restored = SigningKey::from_slice(key.to_bytes().as_ref()).unwrap();
+        assert_eq!(restored.verifying_key(), key.verifying_key());
+        assert!(SigningKey::from_slice(&[0; 31]).is_err());
+        assert!(!format!("{key:?}").contains(&hash::to_hex(key.to_bytes().as_ref())));
+    }
+}
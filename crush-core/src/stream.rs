@@ -0,0 +1,1213 @@
+//! Streaming compression and decompression
+//!
+//! [`CrushWriter`] and [`CrushReader`] produce and consume the Crush format
+//! incrementally through [`std::io::Write`] and [`std::io::Read`], so callers
+//! can wrap files, pipes and sockets without holding the whole input in memory.
+//!
+//! The writer always emits the multi-block layout (see [`crate::block`]) with
+//! the `STREAMED` header flag, since the total size is only known once the
+//! stream ends. Memory use is bounded by the block size times the number of
+//! blocks compressed concurrently (one per rayon worker thread).
+//!
+//! Encrypted streams are sealed and opened chunk by chunk as they pass
+//! through (see [`crate::encryption`]), so encryption does not change the
+//! memory bound.
+
+use crate::block::{self, BlockEntry, BlockFrame, StreamTrailer, STREAM_TRAILER_SIZE};
+use crate::compression::check_encryption;
+use crate::compression::{metadata_section, select_plugin, CompressionOptions};
+use crate::decompression::DecompressionOptions;
+use crate::encryption::{self, DecryptReader, EncryptWriter};
+use crate::error::{CrushError, PluginError, Result, ValidationError};
+use crate::hash::{self, ContentHash, ContentHasher};
+use crate::index::{BlockIndex, BlockIndexEntry, INDEX_FOOTER_SIZE};
+use crate::parity::{self, ParityBlock, RepairReport};
+use crate::plugin::params::ConfiguredPlugin;
+use crate::plugin::registry::get_plugin_by_magic;
+use crate::plugin::stored::STORED_MAGIC;
+use crate::plugin::{
+    run_with_timeout_and_cancel, CompressionAlgorithm, CrushHeader, FileMetadata, PluginParams,
+};
+use crate::signature::{self, Signature};
+use crc32fast::Hasher;
+use rayon::prelude::*;
+use std::io::{self, Read, Write};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Convert a library error into an I/O error for the `Read`/`Write` traits
+///
+/// The original `CrushError` is preserved and can be recovered with
+/// [`io::Error::get_ref`] and `downcast_ref`.
+fn to_io_error(error: CrushError) -> io::Error {
+    match error {
+        CrushError::Io(e) => e,
+        CrushError::Cancelled => io::Error::new(io::ErrorKind::Interrupted, error),
+        CrushError::Validation(_) => io::Error::new(io::ErrorKind::InvalidData, error),
+        other => io::Error::other(other),
+    }
+}
+
+/// Report input that ends within the header sections like input shorter than
+/// the header, rather than as a bare I/O error
+fn truncated(error: io::Error, section: &str) -> CrushError {
+    if error.kind() == io::ErrorKind::UnexpectedEof {
+        ValidationError::InvalidHeader(format!("Input too short: truncated {section}")).into()
+    } else {
+        error.into()
+    }
+}
+
+/// Largest single-stream payload that decodes to `original_size` bytes
+///
+/// Compression stores input that does not shrink, so payloads are no larger
+/// than their output; the slack covers the worst-case expansion of every
+/// plugin, for files written without that fallback.
+fn max_payload_size(original_size: u64) -> u64 {
+    original_size
+        .saturating_add(original_size / 64)
+        .saturating_add(64 * 1024)
+}
+
+/// Number of blocks compressed or decompressed concurrently per batch
+fn batch_len() -> usize {
+    rayon::current_num_threads().max(1)
+}
+
+/// Streaming compressor implementing [`std::io::Write`]
+///
+/// Data written to a `CrushWriter` is split into blocks of the configured
+/// block size; full batches of blocks are compressed in parallel and written
+/// to the inner writer as they complete. Call [`CrushWriter::finish`] to
+/// flush the final block and write the stream trailer; dropping the writer
+/// without finishing leaves an incomplete stream that readers will reject.
+///
+/// The options' timeout, if set, applies to each batch of blocks, and the
+/// cancellation token is checked before every batch. Blocks that do not
+/// shrink are stored uncompressed, so the output never grows by more than the
+/// block framing.
+///
+/// # Examples
+///
+/// ```
+/// use crush_core::{init_plugins, CompressionOptions, CrushReader, CrushWriter};
+/// use std::io::{Read, Write};
+///
+/// init_plugins().expect("Plugin initialization failed");
+///
+/// let mut writer = CrushWriter::new(Vec::new(), &CompressionOptions::default())
+///     .expect("Failed to create writer");
+/// writer.write_all(b"Hello, streaming Crush!").expect("Write failed");
+/// let compressed = writer.finish().expect("Finish failed");
+///
+/// let mut reader = CrushReader::new(compressed.as_slice()).expect("Failed to create reader");
+/// let mut output = Vec::new();
+/// reader.read_to_end(&mut output).expect("Read failed");
+/// assert_eq!(output, b"Hello, streaming Crush!");
+/// ```
+pub struct CrushWriter<W: Write> {
+    inner: EncryptWriter<W>,
+    plugin: Arc<ConfiguredPlugin>,
+    magic: [u8; 4],
+    header_written: bool,
+    options: CompressionOptions,
+    current: Vec<u8>,
+    pending: Vec<Vec<u8>>,
+    total_in: u64,
+    hasher: Hasher,
+    content_hasher: Option<ContentHasher>,
+    position: u64,
+    index: Option<BlockIndex>,
+}
+
+impl<W: Write> CrushWriter<W> {
+    /// Create a streaming compressor that writes to `inner`
+    ///
+    /// Selects the plugin from `options` (manual override or automatic
+    /// scoring). The header is written along with the first compressed
+    /// batch, so automatic selection can look at the first block. The stored
+    /// plugin is never selected for the whole stream: the first block says
+    /// nothing about the rest, and blocks that do not shrink are stored one
+    /// by one anyway.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configured block size or parity shard count is invalid
+    /// - No suitable plugin is found
+    /// - The plugin parameters are invalid for the selected plugin
+    /// - Encryption is combined with seekable output or parity
+    pub fn new(inner: W, options: &CompressionOptions) -> Result<Self> {
+        block::validate_block_size(options.block_size)?;
+        if let Some(parity_shards) = options.parity {
+            parity::validate_parity_shards(parity_shards)?;
+        }
+        check_encryption(options)?;
+        let (selected_metadata, plugin) = select_plugin(options, None)?;
+
+        Ok(Self {
+            inner: EncryptWriter::new(inner),
+            plugin: Arc::new(plugin),
+            magic: selected_metadata.magic_number,
+            header_written: false,
+            options: options.clone(),
+            current: Vec::with_capacity(options.block_size),
+            pending: Vec::new(),
+            total_in: 0,
+            hasher: Hasher::new(),
+            content_hasher: options.content_hash.map(ContentHasher::new),
+            position: 0,
+            index: options.seekable.then(BlockIndex::default),
+        })
+    }
+
+    /// Write the header, metadata, parameters and block size, once
+    ///
+    /// With automatic selection, the plugin is chosen again for the first
+    /// pending block now that its content is known, unless that selects the
+    /// stored plugin.
+    fn write_header(&mut self) -> Result<()> {
+        if self.header_written {
+            return Ok(());
+        }
+        self.header_written = true;
+
+        if self.options.plugin_name.is_none() {
+            if let Some(first) = self.pending.first() {
+                let (selected_metadata, plugin) = select_plugin(&self.options, Some(first))?;
+                if selected_metadata.magic_number != STORED_MAGIC {
+                    self.magic = selected_metadata.magic_number;
+                    self.plugin = Arc::new(plugin);
+                }
+            }
+        }
+
+        let metadata = metadata_section(&self.options);
+        let params = self.plugin.params().to_section()?;
+        let mut header = CrushHeader::new(self.magic, 0)
+            .with_blocks()
+            .with_streamed()
+            .with_stored_blocks();
+        if !metadata.is_empty() {
+            header = header.with_metadata();
+        }
+        if !params.is_empty() {
+            header = header.with_params();
+        }
+        if self.options.seekable {
+            header = header.with_seekable();
+        }
+        if self.options.encryption.is_some() {
+            header = header.with_encrypted();
+        }
+        if let Some(algorithm) = self.options.content_hash {
+            header = header.with_hash_algorithm(algorithm);
+        }
+        if self.options.parity.is_some() {
+            header = header.with_parity();
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let block_size = self.options.block_size as u32; // validated <= MAX_BLOCK_SIZE
+
+        header.write_to(&mut self.inner)?;
+        self.position = CrushHeader::SIZE as u64;
+        if let Some(ref key) = self.options.encryption {
+            let (section, cipher) = encryption::begin_encryption(key, &header.to_bytes())?;
+            self.inner.write_all(&section)?;
+            self.inner.begin(cipher);
+        }
+        self.write_hashed(&metadata)?;
+        self.write_hashed(&params)?;
+        self.write_hashed(&block_size.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Write bytes covered by the trailer's CRC32 to the inner writer
+    fn write_hashed(&mut self, bytes: &[u8]) -> Result<()> {
+        self.hasher.update(bytes);
+        self.inner.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Total number of uncompressed bytes written so far
+    #[must_use]
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// Get a reference to the inner writer
+    #[must_use]
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// Compress all buffered data, including a partial final block
+    fn flush_blocks(&mut self) -> Result<()> {
+        if !self.current.is_empty() {
+            let block = std::mem::replace(
+                &mut self.current,
+                Vec::with_capacity(self.options.block_size),
+            );
+            self.pending.push(block);
+        }
+        self.compress_pending()
+    }
+
+    /// Compress the pending batch of blocks in parallel and write their frames
+    fn compress_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.write_header()?;
+
+        let blocks = std::mem::take(&mut self.pending);
+        let plugin = Arc::clone(&self.plugin);
+        let parity_shards = self.options.parity;
+        let frames = run_with_timeout_and_cancel(
+            self.options.timeout,
+            self.options.cancel_token.clone(),
+            move |cancel_flag: Arc<AtomicBool>| {
+                blocks
+                    .par_iter()
+                    .map(|block| {
+                        block::compress_block_with_parity(
+                            plugin.as_ref(),
+                            block,
+                            parity_shards,
+                            Arc::clone(&cancel_flag),
+                        )
+                    })
+                    .collect::<Result<Vec<_>>>()
+            },
+        )?;
+
+        for (frame, data, record) in frames {
+            if let Some(record) = record {
+                self.write_hashed(&record)?;
+            }
+            if let Some(ref mut index) = self.index {
+                index.push(self.position, &frame);
+            }
+            self.write_hashed(&frame.to_bytes())?;
+            self.write_hashed(&data)?;
+        }
+        Ok(())
+    }
+
+    /// Finish the stream and return the inner writer
+    ///
+    /// Compresses any buffered data, then writes the end-of-blocks marker,
+    /// the trailer holding the total uncompressed size and CRC32, the content
+    /// hash and the block index if the options requested them. Encrypted
+    /// streams then seal their final chunk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression, cancellation, or writing fails.
+    pub fn finish(mut self) -> Result<W> {
+        self.flush_blocks()?;
+        self.write_header()?;
+        if let Some(parity_shards) = self.options.parity {
+            self.write_hashed(&parity::encode_record(
+                &BlockFrame::END,
+                &[],
+                parity_shards,
+            )?)?;
+        }
+        self.write_hashed(&BlockFrame::END.to_bytes())?;
+        let trailer = StreamTrailer {
+            original_size: self.total_in,
+            crc32: self.hasher.clone().finalize(),
+        };
+        self.inner.write_all(&trailer.to_bytes())?;
+        if let Some(ref content_hasher) = self.content_hasher {
+            self.inner.write_all(&content_hasher.finalize().digest)?;
+        }
+        if let Some(ref index) = self.index {
+            self.inner.write_all(&index.to_bytes())?;
+        }
+        let mut inner = self.inner.finish()?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for CrushWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let block_size = self.options.block_size;
+        let mut remaining = buf;
+
+        while !remaining.is_empty() {
+            let space = block_size - self.current.len();
+            let take = space.min(remaining.len());
+            self.current.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+
+            if self.current.len() == block_size {
+                let block = std::mem::replace(&mut self.current, Vec::with_capacity(block_size));
+                self.pending.push(block);
+                if self.pending.len() >= batch_len() {
+                    self.compress_pending().map_err(to_io_error)?;
+                }
+            }
+        }
+
+        if let Some(ref mut content_hasher) = self.content_hasher {
+            content_hasher.update(buf);
+        }
+        self.total_in += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    /// Compress everything buffered so far (possibly as a short block) and
+    /// flush the inner writer
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_blocks().map_err(to_io_error)?;
+        self.inner.flush()
+    }
+}
+
+/// Reader adapter that holds back the last `hold` bytes of its input
+///
+/// Signed files end with a signature trailer that is not part of the
+/// stream; holding it back lets the readers above see the file as if it
+/// ended just before it.
+struct TrailerReader<R: Read> {
+    inner: R,
+    hold: usize,
+    pending: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> TrailerReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hold: 0,
+            pending: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// Hold back the last `len` bytes from now on
+    fn hold_back(&mut self, len: usize) {
+        self.hold = len;
+    }
+
+    fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// The held-back bytes, once the input has been read to the end
+    fn trailer(&self) -> &[u8] {
+        &self.pending
+    }
+}
+
+impl<R: Read> Read for TrailerReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.hold == 0 {
+            return self.inner.read(buf);
+        }
+        while self.pending.len() <= self.hold && !self.eof {
+            let start = self.pending.len();
+            self.pending.resize(start + buf.len().max(8 * 1024), 0);
+            let n = match self.inner.read(&mut self.pending[start..]) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.pending.truncate(start);
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(e);
+                }
+            };
+            self.pending.truncate(start + n);
+            self.eof = n == 0;
+        }
+        let n = self.pending.len().saturating_sub(self.hold).min(buf.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+/// Reader adapter that feeds every byte read through a CRC32 hasher
+struct CrcReader<R: Read> {
+    inner: R,
+    hasher: Option<Hasher>,
+}
+
+impl<R: Read> CrcReader<R> {
+    fn read_exact_hashed(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf)?;
+        if let Some(ref mut hasher) = self.hasher {
+            hasher.update(buf);
+        }
+        Ok(())
+    }
+
+    /// Read the remaining bytes, at most `limit` of them, leaving the last
+    /// `unhashed` out of the CRC32
+    fn read_to_end_hashed(
+        &mut self,
+        buf: &mut Vec<u8>,
+        limit: u64,
+        unhashed: usize,
+    ) -> io::Result<()> {
+        let start = buf.len();
+        (&mut self.inner).take(limit).read_to_end(buf)?;
+        if let Some(ref mut hasher) = self.hasher {
+            let end = buf.len().saturating_sub(unhashed).max(start);
+            hasher.update(&buf[start..end]);
+        }
+        Ok(())
+    }
+}
+
+/// Streaming decompressor implementing [`std::io::Read`]
+///
+/// Reads the Crush header on construction, then decodes blocks on demand:
+/// each batch of block frames is read, CRC-checked and decompressed in
+/// parallel, so memory use stays bounded by the block size times the batch
+/// length. Block CRC32s, the whole-payload CRC32 (if present) and the total
+/// size are all verified as the data streams, and so is the content hash of
+/// the output if the file carries one.
+///
+/// Files written in the single-stream layout (small inputs from
+/// [`crate::compress_with_options`]) are decoded in one step when first read.
+///
+/// Limits from [`DecompressionOptions`] are checked against the sizes each
+/// batch declares before it is decoded. The options' timeout, if set, applies
+/// to each batch, and the cancellation token is checked before every batch.
+pub struct CrushReader<R: Read> {
+    inner: CrcReader<DecryptReader<TrailerReader<R>>>,
+    plugin: Arc<ConfiguredPlugin>,
+    header: CrushHeader,
+    metadata: FileMetadata,
+    options: DecompressionOptions,
+    stored_crc: Option<u32>,
+    content_hasher: Option<ContentHasher>,
+    content_hash: Option<ContentHash>,
+    repairs: RepairReport,
+    blocks_read: u64,
+    buffer: Vec<u8>,
+    position: usize,
+    total_in: u64,
+    total_out: u64,
+    finished: bool,
+    cancelled: bool,
+}
+
+impl<R: Read> CrushReader<R> {
+    /// Create a streaming decompressor that reads from `inner`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The header is missing, invalid, or truncated
+    /// - The metadata section is malformed
+    /// - No plugin is registered for the header's magic number
+    /// - The stream is encrypted and no key is set, or the key is wrong
+    pub fn new(inner: R) -> Result<Self> {
+        Self::with_options(inner, &DecompressionOptions::default())
+    }
+
+    /// Create a streaming decompressor with custom options
+    ///
+    /// Reads fail with [`ValidationError::OutputLimitExceeded`] once the
+    /// stream would exceed the maximum output size or expansion ratio, and
+    /// with [`CrushError::Cancelled`] once the cancellation token fires.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`CrushReader::new`].
+    pub fn with_options(inner: R, options: &DecompressionOptions) -> Result<Self> {
+        let mut inner = DecryptReader::new(TrailerReader::new(inner));
+        let header = CrushHeader::read_from(&mut inner)?;
+        // Signatures are verified separately, see `crate::signature`
+        inner.get_mut().hold_back(signature::trailer_len(&header));
+        if header.is_encrypted() {
+            let mut section = [0u8; encryption::SECTION_SIZE];
+            inner
+                .read_exact(&mut section)
+                .map_err(|e| truncated(e, "encryption section"))?;
+            let cipher = encryption::begin_decryption(
+                options.encryption.as_ref(),
+                &header.to_bytes(),
+                &section,
+            )?;
+            inner.begin(cipher);
+        }
+
+        let stored_crc = if header.has_crc32() {
+            let mut crc_bytes = [0u8; 4];
+            inner
+                .read_exact(&mut crc_bytes)
+                .map_err(|e| truncated(e, "CRC32"))?;
+            Some(u32::from_le_bytes(crc_bytes))
+        } else {
+            None
+        };
+
+        // Streamed output carries its CRC32 in the trailer instead of the header
+        let mut inner = CrcReader {
+            inner,
+            hasher: (stored_crc.is_some() || header.is_streamed()).then(Hasher::new),
+        };
+
+        let metadata = if header.has_metadata() {
+            let mut len_bytes = [0u8; 2];
+            inner
+                .read_exact_hashed(&mut len_bytes)
+                .map_err(|e| truncated(e, "metadata"))?;
+            let mut metadata_bytes = vec![0u8; u16::from_le_bytes(len_bytes) as usize];
+            inner
+                .read_exact_hashed(&mut metadata_bytes)
+                .map_err(|e| truncated(e, "metadata"))?;
+            FileMetadata::from_bytes(&metadata_bytes)?
+        } else {
+            FileMetadata::default()
+        };
+
+        let params = if header.has_params() {
+            let mut len_bytes = [0u8; 2];
+            inner
+                .read_exact_hashed(&mut len_bytes)
+                .map_err(|e| truncated(e, "plugin parameters"))?;
+            let mut params_bytes = vec![0u8; u16::from_le_bytes(len_bytes) as usize];
+            inner
+                .read_exact_hashed(&mut params_bytes)
+                .map_err(|e| truncated(e, "plugin parameters"))?;
+            PluginParams::from_bytes(&params_bytes)?
+        } else {
+            PluginParams::new()
+        };
+
+        if header.has_blocks() {
+            // Nominal block size is informational; frames carry their own lengths
+            let mut block_size = [0u8; 4];
+            inner
+                .read_exact_hashed(&mut block_size)
+                .map_err(|e| truncated(e, "block size"))?;
+        }
+
+        let plugin = get_plugin_by_magic(header.magic).ok_or_else(|| {
+            PluginError::NotFound(format!(
+                "No plugin found for magic number {:02X?}",
+                header.magic
+            ))
+        })?;
+
+        Ok(Self {
+            inner,
+            plugin: Arc::new(ConfiguredPlugin::new(plugin, params)?),
+            header,
+            metadata,
+            options: options.clone(),
+            stored_crc,
+            content_hasher: header.hash_algorithm().map(ContentHasher::new),
+            content_hash: None,
+            repairs: RepairReport::default(),
+            blocks_read: 0,
+            buffer: Vec::new(),
+            position: 0,
+            total_in: 0,
+            total_out: 0,
+            finished: false,
+            cancelled: false,
+        })
+    }
+
+    /// The Crush header read from the stream
+    #[must_use]
+    pub fn header(&self) -> &CrushHeader {
+        &self.header
+    }
+
+    /// File metadata stored in the stream
+    #[must_use]
+    pub fn metadata(&self) -> &FileMetadata {
+        &self.metadata
+    }
+
+    /// Total number of decompressed bytes produced so far
+    #[must_use]
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    /// Content hash stored in the stream, once it has been read to the end
+    /// and the output verified against it
+    #[must_use]
+    pub fn content_hash(&self) -> Option<&ContentHash> {
+        self.content_hash.as_ref()
+    }
+
+    /// Blocks repaired from their parity records so far (see [`crate::parity`])
+    #[must_use]
+    pub fn repairs(&self) -> &RepairReport {
+        &self.repairs
+    }
+
+    /// Get a reference to the inner reader
+    #[must_use]
+    pub fn get_ref(&self) -> &R {
+        self.inner.inner.get_ref().get_ref()
+    }
+
+    /// Read the next block frame and its compressed bytes
+    ///
+    /// With parity, the block is repaired if needed, and the CRC32 covers the
+    /// repaired bytes so it matches the file as written.
+    fn read_block(&mut self) -> Result<(BlockFrame, Vec<u8>)> {
+        if !self.header.has_parity() {
+            let mut frame_bytes = [0u8; BlockFrame::SIZE];
+            self.inner.read_exact_hashed(&mut frame_bytes)?;
+            self.blocks_read += 1;
+            let frame = BlockFrame::from_bytes(&frame_bytes);
+            if frame.is_end() {
+                return Ok((frame, Vec::new()));
+            }
+            if frame.compressed_len as usize > 2 * block::MAX_BLOCK_SIZE {
+                return Err(ValidationError::CorruptedData(format!(
+                    "Block frame claims {} compressed bytes",
+                    frame.compressed_len
+                ))
+                .into());
+            }
+            let mut data = vec![0u8; frame.compressed_len as usize];
+            self.inner.read_exact_hashed(&mut data)?;
+            return Ok((frame, data));
+        }
+
+        let mut block = ParityBlock::read_from(&mut self.inner.inner)?;
+        let repaired = block.repair(self.blocks_read)?;
+        self.repairs.push(self.blocks_read, repaired);
+        self.blocks_read += 1;
+        if let Some(ref mut hasher) = self.inner.hasher {
+            for part in block.parts() {
+                hasher.update(part);
+            }
+        }
+        Ok((block.frame(), block.into_data()?))
+    }
+
+    /// Decode the next batch of blocks into the internal buffer
+    fn fill_buffer(&mut self) -> Result<()> {
+        if !self.header.has_blocks() {
+            return self.decode_single_stream();
+        }
+
+        let mut frames = Vec::new();
+        let mut end_reached = false;
+        while frames.len() < batch_len() {
+            let (frame, data) = self.read_block()?;
+            if frame.is_end() {
+                end_reached = true;
+                break;
+            }
+            self.total_in += (BlockFrame::SIZE + data.len()) as u64;
+            frames.push((frame, data));
+        }
+
+        let declared: u64 = frames
+            .iter()
+            .map(|(frame, _)| u64::from(frame.uncompressed_len))
+            .sum();
+        self.options
+            .check(self.total_out + declared, self.total_in)?;
+
+        let plugin = Arc::clone(&self.plugin);
+        let stored_blocks = self.header.has_stored_blocks();
+        let blocks = run_with_timeout_and_cancel(
+            self.options.timeout,
+            self.options.cancel_token.clone(),
+            move |cancel_flag: Arc<AtomicBool>| {
+                frames
+                    .par_iter()
+                    .map(|(frame, data)| {
+                        let entry = BlockEntry {
+                            frame: *frame,
+                            data,
+                        };
+                        block::decompress_block(
+                            plugin.as_ref(),
+                            &entry,
+                            stored_blocks,
+                            Arc::clone(&cancel_flag),
+                        )
+                    })
+                    .collect::<Result<Vec<_>>>()
+            },
+        )?;
+
+        self.buffer.clear();
+        self.position = 0;
+        for block in blocks {
+            self.buffer.extend_from_slice(&block);
+        }
+        self.total_out += self.buffer.len() as u64;
+        if let Some(ref mut content_hasher) = self.content_hasher {
+            content_hasher.update(&self.buffer);
+        }
+
+        if end_reached {
+            self.finish_stream(None)?;
+        }
+        Ok(())
+    }
+
+    /// Decode a single-stream payload in one step
+    ///
+    /// The payload is buffered whole, so the output limit is checked against
+    /// the size in the header first, and input longer than a payload of that
+    /// size can be is rejected without buffering the rest.
+    fn decode_single_stream(&mut self) -> Result<()> {
+        self.options.check_output_size(self.header.original_size)?;
+        let digest_len = self
+            .header
+            .hash_algorithm()
+            .map_or(0, hash::HashAlgorithm::digest_len);
+        let max_len = max_payload_size(self.header.original_size).saturating_add(digest_len as u64);
+
+        let mut payload = Vec::new();
+        self.inner
+            .read_to_end_hashed(&mut payload, max_len.saturating_add(1), digest_len)?;
+        if payload.len() as u64 > max_len {
+            return Err(ValidationError::CorruptedData(format!(
+                "Payload exceeds the {max_len} bytes possible for an original size of {} bytes",
+                self.header.original_size
+            ))
+            .into());
+        }
+        let digest_start = payload
+            .len()
+            .checked_sub(digest_len)
+            .ok_or_else(|| ValidationError::CorruptedData("Truncated content hash".to_string()))?;
+        let digest = payload.split_off(digest_start);
+        self.total_in = payload.len() as u64;
+        self.options
+            .check(self.header.original_size, self.total_in)?;
+        let expected_size = usize::try_from(self.header.original_size).map_err(|_| {
+            ValidationError::InvalidHeader("Original size exceeds platform limits".to_string())
+        })?;
+
+        let plugin = Arc::clone(&self.plugin);
+        self.buffer = run_with_timeout_and_cancel(
+            self.options.timeout,
+            self.options.cancel_token.clone(),
+            move |cancel_flag| {
+                plugin.decompress_with_limit(
+                    &payload,
+                    &PluginParams::new(),
+                    expected_size,
+                    cancel_flag,
+                )
+            },
+        )?;
+        self.position = 0;
+        self.total_out = self.buffer.len() as u64;
+        if let Some(ref mut content_hasher) = self.content_hasher {
+            content_hasher.update(&self.buffer);
+        }
+        self.finish_stream(Some(digest))
+    }
+
+    /// Verify the trailer, total size, whole-payload CRC32 and content hash
+    /// at end of stream
+    ///
+    /// `digest` is the content hash when the caller has already read it;
+    /// otherwise it is read after the trailer.
+    fn finish_stream(&mut self, digest: Option<Vec<u8>>) -> Result<()> {
+        self.finished = true;
+
+        let expected_size = if self.header.is_streamed() {
+            let mut trailer = [0u8; STREAM_TRAILER_SIZE];
+            self.inner.inner.read_exact(&mut trailer)?;
+            let trailer = StreamTrailer::from_bytes(&trailer);
+            self.stored_crc = Some(trailer.crc32);
+            trailer.original_size
+        } else {
+            self.header.original_size
+        };
+
+        if let Some(content_hasher) = self.content_hasher.take() {
+            let actual = content_hasher.finalize();
+            let digest = if let Some(digest) = digest {
+                digest
+            } else {
+                let mut digest = vec![0u8; actual.algorithm.digest_len()];
+                self.inner.inner.read_exact(&mut digest)?;
+                digest
+            };
+            let expected = ContentHash {
+                algorithm: actual.algorithm,
+                digest,
+            };
+            hash::verify(&expected, &actual)?;
+            self.content_hash = Some(expected);
+        }
+
+        if let (Some(expected), Some(hasher)) = (self.stored_crc, self.inner.hasher.take()) {
+            let actual = hasher.finalize();
+            if expected != actual {
+                return Err(ValidationError::CrcMismatch { expected, actual }.into());
+            }
+        }
+
+        if self.total_out != expected_size {
+            return Err(ValidationError::CorruptedData(format!(
+                "Size mismatch: header says {} bytes, got {} bytes",
+                expected_size, self.total_out
+            ))
+            .into());
+        }
+
+        self.inner.inner.finish()?;
+        self.finish_trailers()
+    }
+
+    /// Read the block index and signature trailer, if the header announces
+    /// them, and check that nothing else follows
+    ///
+    /// Rejects the same trailing data as [`crate::decompress`]. Signatures
+    /// are only parsed here; they are verified separately, see
+    /// [`crate::signature`].
+    fn finish_trailers(&mut self) -> Result<()> {
+        let trailing =
+            || ValidationError::CorruptedData("Unexpected data after end of stream".to_string());
+
+        // At most one index entry per block frame read
+        let index_len = if self.header.is_seekable() {
+            usize::try_from(self.blocks_read)
+                .ok()
+                .and_then(|blocks| blocks.checked_mul(BlockIndexEntry::SIZE))
+                .and_then(|len| len.checked_add(INDEX_FOOTER_SIZE))
+                .ok_or_else(trailing)?
+        } else {
+            0
+        };
+        let mut rest = Vec::new();
+        (&mut self.inner.inner)
+            .take(index_len as u64 + 1)
+            .read_to_end(&mut rest)?;
+        if rest.len() > index_len {
+            return Err(trailing().into());
+        }
+        if self.header.is_seekable() {
+            let (content, _) = BlockIndex::split(&rest)?;
+            if !content.is_empty() {
+                return Err(trailing().into());
+            }
+        }
+
+        if self.header.is_signed() {
+            Signature::from_bytes(self.inner.inner.get_ref().trailer())?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for CrushReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            if self.cancelled {
+                // std::io helpers retry `Interrupted`, so later reads fail hard
+                return Err(io::Error::other(CrushError::Cancelled));
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill_buffer().map_err(|e| match e {
+                CrushError::Io(io_err) if io_err.kind() == io::ErrorKind::UnexpectedEof => {
+                    to_io_error(
+                        ValidationError::CorruptedData("Truncated stream".to_string()).into(),
+                    )
+                }
+                CrushError::Cancelled => {
+                    self.cancelled = true;
+                    to_io_error(CrushError::Cancelled)
+                }
+                other => to_io_error(other),
+            })?;
+        }
+
+        let available = &self.buffer[self.position..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{compress_with_options, decompress, init_plugins};
+
+    fn sample_data(len: u32) -> Vec<u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        (0..len).map(|i| (i % 241) as u8).collect()
+    }
+
+    fn stream_compress(data: &[u8], options: &CompressionOptions) -> Vec<u8> {
+        let mut writer = CrushWriter::new(Vec::new(), options).unwrap();
+        // Write in uneven chunks to exercise block boundary handling
+        for chunk in data.chunks(3_333) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_writer_reader_roundtrip() {
+        init_plugins().unwrap();
+        let data = sample_data(100_000);
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let compressed = stream_compress(&data, &options);
+
+        let mut reader = CrushReader::new(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+
+        assert_eq!(output, data);
+        assert_eq!(reader.total_out(), data.len() as u64);
+    }
+
+    #[test]
+    fn test_writer_output_decompresses_in_memory() {
+        init_plugins().unwrap();
+        let data = sample_data(50_000);
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let compressed = stream_compress(&data, &options);
+
+        let header_bytes: [u8; CrushHeader::SIZE] =
+            compressed[..CrushHeader::SIZE].try_into().unwrap();
+        let header = CrushHeader::from_bytes(&header_bytes).unwrap();
+        assert!(header.is_streamed());
+
+        assert_eq!(decompress(&compressed).unwrap().data, data);
+    }
+
+    #[test]
+    fn test_writer_empty_stream() {
+        init_plugins().unwrap();
+        let writer = CrushWriter::new(Vec::new(), &CompressionOptions::default()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = CrushReader::new(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_writer_flush_emits_short_block() {
+        init_plugins().unwrap();
+        let mut writer = CrushWriter::new(Vec::new(), &CompressionOptions::default()).unwrap();
+        writer.write_all(b"first part").unwrap();
+        writer.flush().unwrap();
+        let len_after_flush = writer.get_ref().len();
+        writer.write_all(b" second part").unwrap();
+        let compressed = writer.finish().unwrap();
+
+        assert!(len_after_flush > CrushHeader::SIZE + 4);
+        assert_eq!(
+            decompress(&compressed).unwrap().data,
+            b"first part second part"
+        );
+    }
+
+    #[test]
+    fn test_reader_with_metadata() {
+        init_plugins().unwrap();
+        let metadata = FileMetadata {
+            mtime: Some(1_234_567_890),
+            #[cfg(unix)]
+            permissions: Some(0o640),
+        };
+        let options = CompressionOptions::default().with_file_metadata(metadata.clone());
+        let compressed = stream_compress(b"metadata stream", &options);
+
+        let reader = CrushReader::new(compressed.as_slice()).unwrap();
+        assert_eq!(reader.metadata(), &metadata);
+    }
+
+    #[test]
+    fn test_reader_decodes_in_memory_formats() {
+        init_plugins().unwrap();
+        for (data, block_size) in [
+            (sample_data(1_000), block::DEFAULT_BLOCK_SIZE),
+            (sample_data(80_000), block::MIN_BLOCK_SIZE),
+        ] {
+            let options = CompressionOptions::default().with_block_size(block_size);
+            let compressed = compress_with_options(&data, &options).unwrap();
+
+            let mut reader = CrushReader::new(compressed.as_slice()).unwrap();
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output).unwrap();
+            assert_eq!(output, data);
+        }
+    }
+
+    #[test]
+    fn test_reader_bounds_single_stream_payload() {
+        init_plugins().unwrap();
+        let data = sample_data(1_000);
+        let compressed = compress_with_options(&data, &CompressionOptions::default()).unwrap();
+        let error = |options: &DecompressionOptions| {
+            // Endless input after the file: reading it all would never finish
+            let input = compressed.as_slice().chain(io::repeat(0x5A));
+            let mut reader = CrushReader::with_options(input, options).unwrap();
+            let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+            *err.into_inner().unwrap().downcast::<CrushError>().unwrap()
+        };
+
+        assert!(matches!(
+            error(&DecompressionOptions::default()),
+            CrushError::Validation(ValidationError::CorruptedData(_))
+        ));
+        assert!(matches!(
+            error(&DecompressionOptions::default().with_max_output_size(999)),
+            CrushError::Validation(ValidationError::OutputLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_reader_detects_corrupted_block() {
+        init_plugins().unwrap();
+        let data = sample_data(20_000);
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let mut compressed = stream_compress(&data, &options);
+
+        // Flip a byte inside the first block's compressed data
+        let first_block = CrushHeader::SIZE + 4 + BlockFrame::SIZE;
+        compressed[first_block] ^= 0xFF;
+
+        let mut reader = CrushReader::new(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        let err = reader.read_to_end(&mut output).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_reader_detects_truncated_stream() {
+        init_plugins().unwrap();
+        let data = sample_data(20_000);
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let compressed = stream_compress(&data, &options);
+        let truncated = &compressed[..compressed.len() - STREAM_TRAILER_SIZE - 1];
+
+        let mut reader = CrushReader::new(truncated).unwrap();
+        let mut output = Vec::new();
+        assert!(reader.read_to_end(&mut output).is_err());
+    }
+
+    #[test]
+    fn test_reader_rejects_short_header() {
+        init_plugins().unwrap();
+        let file = stream_compress(&sample_data(1000), &CompressionOptions::default());
+
+        for len in [0, 5, CrushHeader::SIZE, CrushHeader::SIZE + 2] {
+            assert!(
+                matches!(
+                    CrushReader::new(&file[..len]),
+                    Err(CrushError::Validation(ValidationError::InvalidHeader(_)))
+                ),
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reader_rejects_trailing_data() {
+        init_plugins().unwrap();
+        let data = sample_data(20_000);
+        let options = CompressionOptions::default().with_block_size(block::MIN_BLOCK_SIZE);
+        let key = crate::SigningKey::from_bytes(&[9; 32]);
+        let files = [
+            stream_compress(&data, &options),
+            stream_compress(&data, &options.clone().with_seekable(true)),
+            signature::sign(&stream_compress(&data, &options), &key).unwrap(),
+            signature::sign(&stream_compress(&data, &options.with_seekable(true)), &key).unwrap(),
+        ];
+
+        for file in files {
+            let mut output = Vec::new();
+            CrushReader::new(file.as_slice())
+                .unwrap()
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(output, data);
+
+            for garbage in [&[0u8][..], &[0x5A; 5000]] {
+                let appended = [&file[..], garbage].concat();
+                assert!(decompress(&appended).is_err());
+                let mut reader = CrushReader::new(appended.as_slice()).unwrap();
+                assert!(reader.read_to_end(&mut Vec::new()).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_writer_seekable() {
+        init_plugins().unwrap();
+        let data = sample_data(50_000);
+        let options = CompressionOptions::default()
+            .with_block_size(block::MIN_BLOCK_SIZE)
+            .with_seekable(true);
+        let compressed = stream_compress(&data, &options);
+
+        let range = crate::read_range(io::Cursor::new(&compressed), 20_000, 5_000).unwrap();
+        assert_eq!(range, &data[20_000..25_000]);
+        assert_eq!(decompress(&compressed).unwrap().data, data);
+
+        let mut reader = CrushReader::new(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_writer_cancelled() {
+        use crate::cancel::{AtomicCancellationToken, CancellationToken};
+
+        init_plugins().unwrap();
+        let token = Arc::new(AtomicCancellationToken::new());
+        token.cancel();
+        let options = CompressionOptions::default().with_cancel_token(token);
+
+        let mut writer = CrushWriter::new(Vec::new(), &options).unwrap();
+        writer.write_all(b"cancelled").unwrap();
+        assert!(matches!(writer.finish(), Err(CrushError::Cancelled)));
+    }
+
+    #[test]
+    fn test_reader_cancelled() {
+        use crate::cancel::{AtomicCancellationToken, CancellationToken};
+
+        init_plugins().unwrap();
+        let data = vec![7u8; 300_000];
+        let mut writer = CrushWriter::new(
+            Vec::new(),
+            &CompressionOptions::default().with_block_size(64 * 1024),
+        )
+        .unwrap();
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let token = Arc::new(AtomicCancellationToken::new());
+        token.cancel();
+        let options = DecompressionOptions::default().with_cancel_token(token);
+        let mut reader = CrushReader::with_options(compressed.as_slice(), &options).unwrap();
+
+        // read_to_end retries the first Interrupted error, then gives up
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        let source = error.get_ref().and_then(|e| e.downcast_ref::<CrushError>());
+        assert!(matches!(source, Some(CrushError::Cancelled)));
+    }
+}
@@ -0,0 +1,81 @@
+//! Integration tests for runtime plugin calibration
+//!
+//! Kept in a separate test binary because the active calibration is process-wide.
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    active_calibration, calculate_plugin_score, calibrate, init_plugins, list_plugins,
+    set_calibration, Calibration, PluginCalibration, PluginSelector, Result, ScoringWeights,
+};
+
+/// Calibration measures every plugin and survives a save/load roundtrip
+#[test]
+fn test_calibrate_save_load() -> Result<()> {
+    init_plugins()?;
+    let calibration = calibrate()?;
+
+    let plugins = list_plugins();
+    assert_eq!(calibration.plugins.len(), plugins.len());
+    for plugin in &plugins {
+        let measured = calibration.get(plugin.name);
+        assert!(measured.is_some(), "{} was not calibrated", plugin.name);
+        if let Some(measured) = measured {
+            assert!(measured.throughput > 0.0 && measured.throughput.is_finite());
+            assert!(measured.compression_ratio > 0.0 && measured.compression_ratio <= 1.0);
+        }
+    }
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("cache").join("calibration.json");
+    calibration.save(&path)?;
+    let loaded = Calibration::load(&path)?;
+    assert_eq!(loaded.plugins.len(), calibration.plugins.len());
+    for (loaded, measured) in loaded.plugins.iter().zip(&calibration.plugins) {
+        // JSON keeps floats to within an ulp or so
+        assert_eq!(loaded.name, measured.name);
+        assert!((loaded.throughput / measured.throughput - 1.0).abs() < 1e-9);
+        assert!((loaded.compression_ratio / measured.compression_ratio - 1.0).abs() < 1e-9);
+    }
+
+    std::fs::write(&path, b"{\"not\": \"a calibration\"}")?;
+    assert!(Calibration::load(&path).is_err());
+    assert!(Calibration::load(&dir.path().join("missing.json")).is_err());
+
+    Ok(())
+}
+
+/// Scoring uses the active calibration instead of the static metadata
+#[test]
+fn test_scoring_uses_active_calibration() -> Result<()> {
+    init_plugins()?;
+    let weights = ScoringWeights::new(0.9, 0.1)?;
+    let selector = PluginSelector::new(weights);
+    let plugins = list_plugins();
+    let lz4 = plugins.iter().find(|p| p.name == "lz4").copied();
+    assert!(lz4.is_some());
+    let Some(lz4) = lz4 else { return Ok(()) };
+
+    assert_eq!(selector.select()?.name, "lz4");
+    let static_score = calculate_plugin_score(&lz4, &plugins, &weights);
+
+    // On this imaginary machine LZ4 is the slowest plugin with the worst ratio
+    let measured = Calibration {
+        version: 1,
+        corpus_size: 1,
+        plugins: vec![PluginCalibration {
+            name: "lz4".to_string(),
+            throughput: 0.5,
+            compression_ratio: 0.9,
+        }],
+    };
+    set_calibration(Some(measured.clone()));
+    assert_eq!(active_calibration(), Some(measured));
+    assert_ne!(selector.select()?.name, "lz4");
+    assert!(calculate_plugin_score(&lz4, &plugins, &weights) < static_score);
+
+    set_calibration(None);
+    assert_eq!(selector.select()?.name, "lz4");
+
+    Ok(())
+}
@@ -1,7 +1,10 @@
 //! Integration tests for compression/decompression cancellation
 
 use crush_core::cancel::{AtomicCancellationToken, CancellationToken};
-use crush_core::{compress_with_options, decompress, init_plugins, CompressionOptions, CrushError};
+use crush_core::{
+    compress_with_options, decompress_with_options, init_plugins, CompressionOptions, CrushError,
+    DecompressionOptions,
+};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -46,13 +49,22 @@ fn test_decompress_respects_cancellation() {
     let input = b"test data".repeat(10000);
     let compressed = compress_with_options(&input, &CompressionOptions::default()).unwrap();
 
-    // Now try to decompress with cancellation
-    let _cancel_token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
-    // Note: Decompression with cancel token will be implemented later
-    // For now, just test that regular decompression works
-    let _result = decompress(&compressed);
+    // Now try to decompress with a cancelled token
+    let cancel_token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+    let options = DecompressionOptions::default().with_cancel_token(Arc::clone(&cancel_token));
+    assert_eq!(
+        decompress_with_options(&compressed, &options).unwrap().data,
+        input
+    );
+
+    cancel_token.cancel();
+    let result = decompress_with_options(&compressed, &options);
 
-    // TODO: This test will need to be updated once decompress_with_cancel is implemented
+    // Should return Cancelled error
+    assert!(
+        matches!(result, Err(CrushError::Cancelled)),
+        "Expected Cancelled error, got: {result:?}"
+    );
 }
 
 /// T018: Test that incomplete files are cleaned up on cancellation
@@ -153,3 +165,35 @@ fn test_cancel_during_parallel_processing() {
         "Expected Ok or Cancelled error, got: {result:?}"
     );
 }
+
+/// Additional test: Verify cancellation during parallel decompression
+#[test]
+#[allow(clippy::unwrap_used, clippy::cast_possible_truncation)]
+fn test_cancel_during_parallel_decompression() {
+    setup();
+
+    let input: Vec<u8> = (0..50_000_000_u32).map(|i| (i % 251) as u8).collect();
+    let options = CompressionOptions::default().with_block_size(1024 * 1024);
+    let compressed = compress_with_options(&input, &options).unwrap();
+    let cancel_token: Arc<dyn CancellationToken> = Arc::new(AtomicCancellationToken::new());
+
+    // Clone token for background cancellation
+    let token_clone = Arc::clone(&cancel_token);
+
+    // Start decompression
+    let handle = thread::spawn(move || {
+        let options = DecompressionOptions::default().with_cancel_token(token_clone);
+        decompress_with_options(&compressed, &options).map(|result| result.data.len())
+    });
+
+    // Cancel mid-operation
+    thread::sleep(Duration::from_millis(20));
+    cancel_token.cancel();
+
+    // Fast machines may finish decoding before the cancel signal arrives
+    let result = handle.join().unwrap();
+    assert!(
+        matches!(result, Ok(50_000_000) | Err(CrushError::Cancelled)),
+        "Expected Ok or Cancelled error, got: {result:?}"
+    );
+}
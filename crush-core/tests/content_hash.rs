@@ -0,0 +1,116 @@
+//! Integration tests for content hashes of the uncompressed data
+
+#![allow(clippy::panic_in_result_fn, clippy::unwrap_used)]
+
+use crush_core::{
+    compress_with_options, decompress, init_plugins, inspect, read_range, CompressionOptions,
+    CrushError, CrushReader, CrushWriter, HashAlgorithm, Result, ValidationError,
+};
+use std::io::{Cursor, Read, Write};
+
+fn text(len: usize) -> Vec<u8> {
+    b"Content hashes identify the original bytes. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+fn is_hash_mismatch(result: &Result<impl std::fmt::Debug>) -> bool {
+    matches!(
+        result,
+        Err(CrushError::Validation(ValidationError::HashMismatch { .. }))
+    )
+}
+
+/// Every algorithm round-trips in single-stream, block and seekable layouts
+#[test]
+fn test_content_hash_roundtrip() -> Result<()> {
+    init_plugins()?;
+    let data = text(300_000);
+    for algorithm in HashAlgorithm::ALL {
+        let layouts = [
+            CompressionOptions::default(),
+            CompressionOptions::default().with_block_size(64 * 1024),
+            CompressionOptions::default()
+                .with_block_size(64 * 1024)
+                .with_seekable(true),
+        ];
+        for options in layouts {
+            let compressed = compress_with_options(&data, &options.with_content_hash(algorithm))?;
+            let result = decompress(&compressed)?;
+            assert_eq!(result.data, data);
+            assert_eq!(result.content_hash, Some(algorithm.hash(&data)));
+            assert_eq!(inspect(&compressed)?.content_hash, result.content_hash);
+        }
+    }
+    Ok(())
+}
+
+/// Files without a content hash decompress and inspect as before
+#[test]
+fn test_no_content_hash_by_default() -> Result<()> {
+    init_plugins()?;
+    let compressed = compress_with_options(&text(1000), &CompressionOptions::default())?;
+    assert_eq!(decompress(&compressed)?.content_hash, None);
+    assert_eq!(inspect(&compressed)?.content_hash, None);
+    Ok(())
+}
+
+/// A damaged digest is caught even though the payload CRC32 still matches
+#[test]
+fn test_tampered_digest() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default().with_content_hash(HashAlgorithm::Sha256);
+    let mut compressed = compress_with_options(&text(10_000), &options)?;
+    let last = compressed.len() - 1;
+    compressed[last] ^= 0x01;
+
+    assert!(is_hash_mismatch(&decompress(&compressed)));
+    let mut reader = CrushReader::new(compressed.as_slice())?;
+    let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert!(error.to_string().contains("sha256 content hash mismatch"));
+    Ok(())
+}
+
+/// Streams written by `CrushWriter` carry the same hash as in-memory output
+#[test]
+fn test_streaming_content_hash() -> Result<()> {
+    init_plugins()?;
+    let data = text(2 * 1024 * 1024 + 77);
+    let expected = HashAlgorithm::Blake3.hash(&data);
+    for seekable in [false, true] {
+        let options = CompressionOptions::default()
+            .with_block_size(256 * 1024)
+            .with_seekable(seekable)
+            .with_content_hash(HashAlgorithm::Blake3);
+
+        let mut writer = CrushWriter::new(Vec::new(), &options)?;
+        for chunk in data.chunks(100_000) {
+            writer.write_all(chunk)?;
+        }
+        let streamed = writer.finish()?;
+        assert_eq!(
+            decompress(&streamed)?.content_hash.as_ref(),
+            Some(&expected)
+        );
+        assert_eq!(inspect(&streamed)?.content_hash.as_ref(), Some(&expected));
+        if seekable {
+            assert_eq!(
+                read_range(Cursor::new(&streamed), 1000, 64)?,
+                &data[1000..1064]
+            );
+        }
+
+        let in_memory = compress_with_options(&data, &options)?;
+        for compressed in [streamed, in_memory] {
+            let mut reader = CrushReader::new(compressed.as_slice())?;
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output)?;
+            assert_eq!(output, data);
+            assert_eq!(reader.content_hash(), Some(&expected));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! Integration tests for decompression limits on untrusted input
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    compress, compress_with_options, decompress, decompress_with_options, init_plugins,
+    list_plugins, CompressionOptions, CrushError, CrushReader, CrushWriter, DecompressionOptions,
+    Result, ValidationError,
+};
+use std::io::{Read, Write};
+
+/// Compressible text, so every plugin except stored shrinks it
+fn text(len: usize) -> Vec<u8> {
+    b"All work and no play makes Jack a dull boy. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+/// Overwrite the header's `original_size` field
+fn set_original_size(compressed: &mut [u8], size: u64) {
+    compressed[4..12].copy_from_slice(&size.to_le_bytes());
+}
+
+/// Whether `result` failed with `OutputLimitExceeded`
+fn is_limit_error(result: &Result<impl std::fmt::Debug>) -> bool {
+    matches!(
+        result,
+        Err(CrushError::Validation(
+            ValidationError::OutputLimitExceeded(_)
+        ))
+    )
+}
+
+/// The maximum output size is checked before decoding
+#[test]
+fn test_max_output_size() -> Result<()> {
+    init_plugins()?;
+    let data = vec![0u8; 1024 * 1024];
+    let compressed = compress(&data)?;
+
+    let options = DecompressionOptions::new().with_max_output_size(1024);
+    assert!(is_limit_error(&decompress_with_options(
+        &compressed,
+        &options
+    )));
+
+    let options = DecompressionOptions::new().with_max_output_size(data.len() as u64);
+    assert_eq!(decompress_with_options(&compressed, &options)?.data, data);
+
+    Ok(())
+}
+
+/// The expansion ratio is measured against the whole compressed input
+#[test]
+fn test_max_ratio() -> Result<()> {
+    init_plugins()?;
+    let data = vec![0u8; 4 * 1024 * 1024];
+    let compressed = compress(&data)?;
+    assert!(compressed.len() * 100 < data.len());
+
+    let options = DecompressionOptions::new().with_max_ratio(100.0);
+    assert!(is_limit_error(&decompress_with_options(
+        &compressed,
+        &options
+    )));
+
+    let options = DecompressionOptions::new().with_max_ratio(1e9);
+    assert_eq!(decompress_with_options(&compressed, &options)?.data, data);
+
+    Ok(())
+}
+
+/// A payload that decodes to more than its header declares is cut off early
+#[test]
+fn test_understated_original_size_aborts_every_plugin() -> Result<()> {
+    init_plugins()?;
+    let data = text(64 * 1024);
+
+    for plugin in list_plugins() {
+        let options = CompressionOptions::default().with_plugin(plugin.name);
+        let mut compressed = compress_with_options(&data, &options)?;
+        set_original_size(&mut compressed, 1000);
+
+        let result = decompress(&compressed);
+        assert!(is_limit_error(&result), "{}: {result:?}", plugin.name);
+    }
+
+    Ok(())
+}
+
+/// Block frames must add up to the declared size before anything is decoded
+#[test]
+fn test_block_sizes_must_match_header() -> Result<()> {
+    init_plugins()?;
+    let data = text(300_000);
+    let options = CompressionOptions::default().with_block_size(64 * 1024);
+
+    for size in [1000, data.len() as u64 + 1] {
+        let mut compressed = compress_with_options(&data, &options)?;
+        set_original_size(&mut compressed, size);
+        assert!(matches!(
+            decompress(&compressed),
+            Err(CrushError::Validation(ValidationError::CorruptedData(_)))
+        ));
+    }
+
+    Ok(())
+}
+
+/// Output must match the declared size exactly, not just stay below it
+#[test]
+fn test_overstated_original_size_is_rejected() -> Result<()> {
+    init_plugins()?;
+    let data = text(10_000);
+    let mut compressed = compress(&data)?;
+    set_original_size(&mut compressed, data.len() as u64 + 1);
+
+    assert!(matches!(
+        decompress(&compressed),
+        Err(CrushError::Validation(ValidationError::CorruptedData(_)))
+    ));
+
+    Ok(())
+}
+
+/// The streaming reader enforces limits batch by batch
+#[test]
+fn test_reader_limits() -> Result<()> {
+    init_plugins()?;
+    let data = vec![0u8; 2 * 1024 * 1024];
+    let mut writer = CrushWriter::new(
+        Vec::new(),
+        &CompressionOptions::default().with_block_size(64 * 1024),
+    )?;
+    writer.write_all(&data)?;
+    let compressed = writer.finish()?;
+
+    for options in [
+        DecompressionOptions::new().with_max_output_size(1024 * 1024),
+        DecompressionOptions::new().with_max_ratio(10.0),
+    ] {
+        let mut reader = CrushReader::with_options(compressed.as_slice(), &options)?;
+        let error = reader.read_to_end(&mut Vec::new()).err();
+        let source = error
+            .as_ref()
+            .and_then(|e| e.get_ref())
+            .and_then(|e| e.downcast_ref::<CrushError>());
+        assert!(
+            matches!(
+                source,
+                Some(CrushError::Validation(
+                    ValidationError::OutputLimitExceeded(_)
+                ))
+            ),
+            "{error:?}"
+        );
+    }
+
+    let options = DecompressionOptions::new().with_max_output_size(data.len() as u64);
+    let mut restored = Vec::new();
+    CrushReader::with_options(compressed.as_slice(), &options)?.read_to_end(&mut restored)?;
+    assert_eq!(restored, data);
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! Integration tests for encrypted compression
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::plugin::FileMetadata;
+use crush_core::{
+    compress_with_options, decompress, decompress_with_options, init_plugins, inspect,
+    CompressionOptions, CrushError, CrushReader, CrushWriter, DecompressionOptions,
+    EncryptionError, EncryptionKey, KdfParams, Result, ValidationError,
+};
+use std::io::{Read, Write};
+
+/// Passphrase key with cheap Argon2 costs so tests stay fast
+fn passphrase(passphrase: &str) -> EncryptionKey {
+    EncryptionKey::from_passphrase_with_params(
+        passphrase,
+        KdfParams {
+            memory_kib: 256,
+            iterations: 1,
+            parallelism: 1,
+        },
+    )
+}
+
+fn text(len: usize) -> Vec<u8> {
+    b"Backups leave the network encrypted. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+fn is_wrong_key(result: &Result<impl std::fmt::Debug>) -> bool {
+    matches!(
+        result,
+        Err(CrushError::Encryption(EncryptionError::WrongKey))
+    )
+}
+
+/// In-memory and multi-block output round-trip with the same key
+#[test]
+fn test_encrypted_roundtrip() -> Result<()> {
+    init_plugins()?;
+    let key = passphrase("hunter2");
+    for data in [Vec::new(), text(1000), text(3 * 1024 * 1024)] {
+        let options = CompressionOptions::default()
+            .with_block_size(1024 * 1024)
+            .with_file_metadata(FileMetadata {
+                mtime: Some(1_700_000_000),
+                ..FileMetadata::default()
+            })
+            .with_encryption(key.clone());
+        let encrypted = compress_with_options(&data, &options)?;
+
+        let decrypt = DecompressionOptions::new().with_encryption(key.clone());
+        let result = decompress_with_options(&encrypted, &decrypt)?;
+        assert_eq!(result.data, data);
+        assert_eq!(result.metadata.mtime, Some(1_700_000_000));
+    }
+    Ok(())
+}
+
+/// A wrong key is reported as such, not as a CRC mismatch
+#[test]
+fn test_wrong_key() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default().with_encryption(passphrase("right"));
+    let encrypted = compress_with_options(&text(10_000), &options)?;
+
+    let wrong = DecompressionOptions::new().with_encryption(passphrase("wrong"));
+    assert!(is_wrong_key(&decompress_with_options(&encrypted, &wrong)));
+    assert!(is_wrong_key(
+        &CrushReader::with_options(encrypted.as_slice(), &wrong).map(|_| ())
+    ));
+
+    assert!(matches!(
+        decompress(&encrypted),
+        Err(CrushError::Encryption(EncryptionError::KeyRequired))
+    ));
+    Ok(())
+}
+
+/// Modified ciphertext fails authentication
+#[test]
+fn test_tampered_ciphertext() -> Result<()> {
+    init_plugins()?;
+    let key = EncryptionKey::from_bytes([0x42; 32]);
+    let options = CompressionOptions::default().with_encryption(key.clone());
+    let mut encrypted = compress_with_options(&text(10_000), &options)?;
+    let last = encrypted.len() - 1;
+    encrypted[last] ^= 0x01;
+
+    let decrypt = DecompressionOptions::new().with_encryption(key);
+    assert!(matches!(
+        decompress_with_options(&encrypted, &decrypt),
+        Err(CrushError::Validation(ValidationError::CorruptedData(_)))
+    ));
+    Ok(())
+}
+
+/// Streams written by `CrushWriter` decrypt in memory and the other way around
+#[test]
+fn test_streaming_encryption() -> Result<()> {
+    init_plugins()?;
+    let key = passphrase("stream");
+    let data = text(2 * 1024 * 1024 + 123);
+    let options = CompressionOptions::default()
+        .with_block_size(256 * 1024)
+        .with_encryption(key.clone());
+    let decrypt = DecompressionOptions::new().with_encryption(key);
+
+    let mut writer = CrushWriter::new(Vec::new(), &options)?;
+    for chunk in data.chunks(100_000) {
+        writer.write_all(chunk)?;
+    }
+    let streamed = writer.finish()?;
+    assert_eq!(decompress_with_options(&streamed, &decrypt)?.data, data);
+
+    let in_memory = compress_with_options(&data, &options)?;
+    for encrypted in [streamed, in_memory] {
+        let mut reader = CrushReader::with_options(encrypted.as_slice(), &decrypt)?;
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output)?;
+        assert_eq!(output, data);
+    }
+    Ok(())
+}
+
+/// Seekable output needs the block index in the clear, so it cannot be encrypted
+#[test]
+fn test_seekable_encryption_rejected() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default()
+        .with_seekable(true)
+        .with_encryption(passphrase("x"));
+    assert!(matches!(
+        compress_with_options(b"data", &options),
+        Err(CrushError::Encryption(EncryptionError::Unsupported(_)))
+    ));
+    assert!(CrushWriter::new(Vec::new(), &options).is_err());
+    Ok(())
+}
+
+/// Inspection reports the cipher without needing the key
+#[test]
+fn test_inspect_encrypted() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default()
+        .with_plugin("deflate")
+        .with_encryption(passphrase("inspect"));
+    let encrypted = compress_with_options(&text(5_000), &options)?;
+
+    let result = inspect(&encrypted)?;
+    assert_eq!(result.plugin_name, "deflate");
+    assert_eq!(result.original_size, 5_000);
+    let encryption = result
+        .encryption
+        .as_ref()
+        .map(|info| (info.cipher, info.kdf));
+    assert_eq!(encryption, Some(("chacha20-poly1305", "argon2id")));
+    Ok(())
+}
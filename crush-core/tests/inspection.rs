@@ -5,12 +5,17 @@
 use crush_core::plugin::FileMetadata;
 use crush_core::{compress_with_options, init_plugins, inspect, CompressionOptions, Result};
 
+/// Options pinned to deflate so assertions don't depend on automatic selection
+fn deflate_options() -> CompressionOptions {
+    CompressionOptions::default().with_plugin("deflate")
+}
+
 #[test]
 fn test_inspect_valid_file_with_crc() -> Result<()> {
     init_plugins()?;
 
-    let data = b"Hello, inspection test!";
-    let compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let data = b"Hello, inspection test! ".repeat(4);
+    let compressed = compress_with_options(&data, &deflate_options())?;
 
     let result = inspect(&compressed)?;
 
@@ -33,7 +38,7 @@ fn test_inspect_with_metadata() -> Result<()> {
         permissions: Some(0o644),
     };
 
-    let options = CompressionOptions::default().with_file_metadata(metadata.clone());
+    let options = deflate_options().with_file_metadata(metadata.clone());
     let compressed = compress_with_options(data, &options)?;
 
     let result = inspect(&compressed)?;
@@ -94,7 +99,7 @@ fn test_inspect_truncated_crc() -> Result<()> {
     init_plugins()?;
 
     let data = b"Test";
-    let mut compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let mut compressed = compress_with_options(data, &deflate_options())?;
 
     // Truncate to remove CRC32 data (keep header but remove CRC32 bytes)
     compressed.truncate(16); // Just the header, no CRC32
@@ -119,7 +124,7 @@ fn test_inspect_invalid_crc() -> Result<()> {
     init_plugins()?;
 
     let data = b"Test data for CRC validation";
-    let mut compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let mut compressed = compress_with_options(data, &deflate_options())?;
 
     // Corrupt the CRC32 value (bytes 16-19 after header)
     if compressed.len() > 19 {
@@ -142,7 +147,7 @@ fn test_inspect_corrupted_payload() -> Result<()> {
     init_plugins()?;
 
     let data = b"Test data";
-    let mut compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let mut compressed = compress_with_options(data, &deflate_options())?;
 
     // Corrupt the payload (not the CRC)
     if compressed.len() > 25 {
@@ -165,12 +170,13 @@ fn test_inspect_empty_data() -> Result<()> {
     init_plugins()?;
 
     let data = b"";
-    let compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let compressed = compress_with_options(data, &deflate_options())?;
 
     let result = inspect(&compressed)?;
 
     assert_eq!(result.original_size, 0);
-    assert_eq!(result.plugin_name, "deflate");
+    // Nothing to compress, so the payload is stored
+    assert_eq!(result.plugin_name, "stored");
     assert!(result.crc_valid);
 
     Ok(())
@@ -181,7 +187,7 @@ fn test_inspect_large_data() -> Result<()> {
     init_plugins()?;
 
     let data = vec![0x42u8; 10_000]; // 10KB
-    let compressed = compress_with_options(&data, &CompressionOptions::default())?;
+    let compressed = compress_with_options(&data, &deflate_options())?;
 
     let result = inspect(&compressed)?;
 
@@ -273,7 +279,7 @@ fn test_inspect_default_metadata() -> Result<()> {
 
     let data = b"Test without metadata";
     // Use default options (no metadata)
-    let compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let compressed = compress_with_options(data, &deflate_options())?;
 
     let result = inspect(&compressed)?;
 
@@ -290,7 +296,7 @@ fn test_inspect_result_serialization() -> Result<()> {
     init_plugins()?;
 
     let data = b"Serialization test";
-    let compressed = compress_with_options(data, &CompressionOptions::default())?;
+    let compressed = compress_with_options(data, &deflate_options())?;
 
     let result = inspect(&compressed)?;
 
@@ -309,3 +315,28 @@ fn test_inspect_result_serialization() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_inspect_reports_blocks_and_seekable() -> Result<()> {
+    init_plugins()?;
+
+    #[allow(clippy::cast_possible_truncation)] // Intentional: i % 251 always fits in u8
+    let data: Vec<u8> = (0..40_000_u32).map(|i| (i % 251) as u8).collect();
+
+    let plain = compress_with_options(&data, &deflate_options())?;
+    let result = inspect(&plain)?;
+    assert!(!result.seekable);
+    assert_eq!(result.block_count, 1);
+
+    let options = CompressionOptions::default()
+        .with_block_size(8 * 1024)
+        .with_seekable(true);
+    let seekable = compress_with_options(&data, &options)?;
+    let result = inspect(&seekable)?;
+    assert!(result.seekable);
+    assert_eq!(result.block_count, 5);
+    assert_eq!(result.original_size, data.len() as u64);
+    assert!(result.crc_valid, "CRC should be valid");
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+//! Integration tests for Reed-Solomon parity and block repair
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    compress_with_options, decompress, init_plugins, inspect, read_range, repair, sign,
+    verify_signature, CompressionOptions, CrushError, CrushReader, CrushWriter, EncryptionError,
+    EncryptionKey, HashAlgorithm, Result, SigningKey, ValidationError,
+};
+use std::io::{Cursor, Read, Write};
+
+fn text(len: usize) -> Vec<u8> {
+    b"Archival media suffers occasional bit rot. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+/// Data that does not compress, so blocks keep their full size
+fn noise(len: usize) -> Vec<u8> {
+    let mut seed = 0x2545_F491_u32;
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed.to_le_bytes()[0]
+        })
+        .collect()
+}
+
+/// Flip one byte at each fraction of the file, away from the header and tail
+fn damage(file: &mut [u8], fractions: &[usize]) {
+    for &percent in fractions {
+        let at = file.len() * percent / 100;
+        file[at] ^= 0x10;
+    }
+}
+
+fn read_all(file: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let mut reader = CrushReader::new(file)?;
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output)?;
+    Ok((output, reader.repairs().repaired_bytes()))
+}
+
+/// Damaged bytes are repaired transparently in every layout
+#[test]
+fn test_decompress_repairs_damage() -> Result<()> {
+    init_plugins()?;
+    let data = [text(300_000), noise(200_000)].concat();
+    let layouts = [
+        CompressionOptions::default(),
+        CompressionOptions::default().with_block_size(64 * 1024),
+        CompressionOptions::default()
+            .with_block_size(64 * 1024)
+            .with_seekable(true)
+            .with_content_hash(HashAlgorithm::Sha256),
+    ];
+    for options in layouts {
+        let options = options.with_parity(4);
+        let compressed = compress_with_options(&data, &options)?;
+        assert_eq!(inspect(&compressed)?.parity_shards, Some(4));
+
+        let mut damaged = compressed.clone();
+        damage(&mut damaged, &[30, 55, 80]);
+        let result = decompress(&damaged)?;
+        assert_eq!(result.data, data);
+        assert_eq!(result.repairs.repaired_bytes(), 3);
+        assert_eq!(read_all(&damaged)?, (data.clone(), 3));
+
+        let (repaired, report) = repair(&damaged)?;
+        assert_eq!(repaired, compressed);
+        assert_eq!(report.repaired_bytes(), 3);
+        assert!(decompress(&compressed)?.repairs.is_empty());
+    }
+    Ok(())
+}
+
+/// Streams written by `CrushWriter` carry the same parity records
+#[test]
+fn test_streaming_parity() -> Result<()> {
+    init_plugins()?;
+    let data = text(1_000_000);
+    let options = CompressionOptions::default()
+        .with_block_size(128 * 1024)
+        .with_seekable(true)
+        .with_parity(2);
+
+    let mut writer = CrushWriter::new(Vec::new(), &options)?;
+    for chunk in data.chunks(70_000) {
+        writer.write_all(chunk)?;
+    }
+    let streamed = writer.finish()?;
+    assert_eq!(
+        read_range(Cursor::new(&streamed), 500_000, 100)?,
+        &data[500_000..500_100]
+    );
+
+    let mut damaged = streamed.clone();
+    damage(&mut damaged, &[20, 50]);
+    assert_eq!(read_all(&damaged)?, (data.clone(), 2));
+    assert_eq!(decompress(&damaged)?.data, data);
+    assert_eq!(repair(&damaged)?.0, streamed);
+    Ok(())
+}
+
+/// More damaged shards than parity shards fail with `Unrepairable`
+#[test]
+fn test_damage_beyond_repair() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default().with_parity(2);
+    let mut compressed = compress_with_options(&noise(10_000), &options)?;
+    let len = compressed.len();
+    for at in [len - 9000, len - 5000, len - 1000] {
+        compressed[at] ^= 0x01;
+    }
+
+    let unrepairable = |result: Result<_>| {
+        matches!(
+            result,
+            Err(CrushError::Validation(ValidationError::Unrepairable {
+                block: 0,
+                damaged_shards: 3,
+                parity_shards: 2,
+            }))
+        )
+    };
+    assert!(unrepairable(decompress(&compressed).map(|_| ())));
+    assert!(unrepairable(repair(&compressed).map(|_| ())));
+    let error = read_all(&compressed).err().map(|e| e.to_string());
+    assert!(error.is_some_and(|e| e.contains("damaged beyond repair")));
+    Ok(())
+}
+
+/// Repair restores the signed bytes, so the signature verifies again
+#[test]
+fn test_repair_signed_file() -> Result<()> {
+    init_plugins()?;
+    let key = SigningKey::from_bytes(&[0x66; 32]);
+    let options = CompressionOptions::default().with_parity(1);
+    let signed = sign(&compress_with_options(&text(200_000), &options)?, &key)?;
+
+    let mut damaged = signed.clone();
+    damage(&mut damaged, &[60]);
+    assert!(verify_signature(&damaged, None).is_err());
+
+    let (repaired, report) = repair(&damaged)?;
+    assert_eq!(report.blocks.len(), 1);
+    assert_eq!(repaired, signed);
+    verify_signature(&repaired, Some(&key.verifying_key()))?;
+    Ok(())
+}
+
+/// Invalid shard counts, parity with encryption, and repairing files
+/// without parity are rejected
+#[test]
+fn test_parity_errors() -> Result<()> {
+    init_plugins()?;
+    let data = text(1000);
+    assert!(matches!(
+        compress_with_options(&data, &CompressionOptions::default().with_parity(0)),
+        Err(CrushError::Validation(ValidationError::InvalidParity(_)))
+    ));
+    let encrypted = CompressionOptions::default()
+        .with_parity(2)
+        .with_encryption(EncryptionKey::from_bytes([0x77; 32]));
+    assert!(matches!(
+        compress_with_options(&data, &encrypted),
+        Err(CrushError::Encryption(EncryptionError::Unsupported(_)))
+    ));
+
+    let plain = compress_with_options(&data, &CompressionOptions::default())?;
+    assert!(matches!(
+        repair(&plain),
+        Err(CrushError::Validation(ValidationError::NoParity))
+    ));
+    assert_eq!(inspect(&plain)?.parity_shards, None);
+    Ok(())
+}
@@ -31,6 +31,22 @@ fn test_plugin_discovery_deflate() -> Result<()> {
     Ok(())
 }
 
+/// Test that `init_plugins()` discovers the zstd plugin
+#[test]
+fn test_plugin_discovery_zstd() -> Result<()> {
+    init_plugins()?;
+
+    let plugins = list_plugins();
+    let zstd = plugins.iter().find(|p| p.name == "zstd");
+    assert!(zstd.is_some(), "zstd plugin should be discovered");
+
+    #[allow(clippy::unwrap_used)] // Safe: just asserted is_some() above
+    let zstd = zstd.unwrap();
+    assert_eq!(zstd.magic_number, [0x43, 0x52, 0x01, 0x01]);
+
+    Ok(())
+}
+
 /// Test that `list_plugins()` returns empty before initialization
 #[test]
 fn test_list_plugins_before_init() {
@@ -0,0 +1,122 @@
+//! Integration tests for plugin-specific parameters
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    compress_with_options, decompress, init_plugins, inspect, plugin_parameters, read_range,
+    CompressionOptions, CrushError, CrushReader, CrushWriter, PluginParams, Result,
+    ValidationError,
+};
+use std::io::{Cursor, Read, Write};
+
+fn sample_data() -> Vec<u8> {
+    b"Plugin parameter integration test data with some repetition. ".repeat(5_000)
+}
+
+/// Parameters are recorded in the output and reported by `inspect`
+#[test]
+fn test_params_recorded_and_roundtrip() -> Result<()> {
+    init_plugins()?;
+    let data = sample_data();
+    let options = CompressionOptions::default()
+        .with_plugin("zstd")
+        .with_plugin_param("level", 19)
+        .with_plugin_param("window_log", 30);
+
+    let compressed = compress_with_options(&data, &options)?;
+    assert_eq!(decompress(&compressed)?.data, data);
+
+    let result = inspect(&compressed)?;
+    assert_eq!(result.plugin_name, "zstd");
+    assert_eq!(
+        result.plugin_params,
+        PluginParams::new().with("level", 19).with("window_log", 30)
+    );
+
+    Ok(())
+}
+
+/// Files compressed without parameters carry no parameter section
+#[test]
+fn test_no_params_by_default() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default().with_plugin("deflate");
+    let compressed = compress_with_options(&sample_data(), &options)?;
+
+    assert!(inspect(&compressed)?.plugin_params.is_empty());
+
+    Ok(())
+}
+
+/// Unknown names and out-of-range values are rejected before compressing
+#[test]
+fn test_invalid_params_rejected() -> Result<()> {
+    init_plugins()?;
+    let data = sample_data();
+
+    for options in [
+        CompressionOptions::default()
+            .with_plugin("deflate")
+            .with_plugin_param("level", 12),
+        CompressionOptions::default()
+            .with_plugin("lz4")
+            .with_plugin_param("level", 1),
+        CompressionOptions::default()
+            .with_plugin("zstd")
+            .with_plugin_param("window_log", 4),
+    ] {
+        let result = compress_with_options(&data, &options);
+        assert!(
+            matches!(
+                result,
+                Err(CrushError::Validation(ValidationError::InvalidParameter(_)))
+            ),
+            "expected InvalidParameter, got {result:?}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Every plugin describes its parameters with sane ranges
+#[test]
+fn test_plugin_parameters_described() -> Result<()> {
+    init_plugins()?;
+
+    for name in ["deflate", "zstd", "lz4", "xz", "brotli", "bzip2"] {
+        let params = plugin_parameters(name).unwrap_or_default();
+        assert!(!params.is_empty(), "{name} has no parameters");
+        for spec in params {
+            assert!(spec.min <= spec.default && spec.default <= spec.max);
+            assert!(!spec.description.is_empty());
+        }
+    }
+    assert!(plugin_parameters("nonexistent").is_none());
+
+    Ok(())
+}
+
+/// Streaming and random-access reads use the recorded parameters
+#[test]
+fn test_params_streaming_and_read_range() -> Result<()> {
+    init_plugins()?;
+    let data = sample_data();
+    let options = CompressionOptions::default()
+        .with_plugin("zstd")
+        .with_plugin_param("window_log", 29)
+        .with_block_size(64 * 1024)
+        .with_seekable(true);
+
+    let mut writer = CrushWriter::new(Vec::new(), &options)?;
+    writer.write_all(&data)?;
+    let compressed = writer.finish()?;
+
+    let mut restored = Vec::new();
+    CrushReader::new(Cursor::new(&compressed))?.read_to_end(&mut restored)?;
+    assert_eq!(restored, data);
+
+    let range = read_range(Cursor::new(&compressed), 100_000, 50_000)?;
+    assert_eq!(range, &data[100_000..150_000]);
+
+    Ok(())
+}
@@ -5,7 +5,11 @@
 
 #![allow(clippy::panic_in_result_fn)]
 
-use crush_core::{compress_with_options, init_plugins, CompressionOptions, Result, ScoringWeights};
+use crush_core::{
+    compress_with_options, decompress, init_plugins, inspect, list_plugins, CompressionOptions,
+    PluginSelector, Result, SamplingConfig, ScoringWeights,
+};
+use std::time::Duration;
 
 /// Test that plugin scoring selects the highest-scoring plugin
 ///
@@ -39,12 +43,12 @@ fn test_plugin_scoring_selection() -> Result<()> {
 fn test_manual_plugin_override() -> Result<()> {
     init_plugins()?;
 
-    let data = b"Test data for manual plugin override.";
+    let data = b"Test data for manual plugin override. ".repeat(4);
 
     // Explicitly request DEFLATE plugin by name
     let options = CompressionOptions::default().with_plugin("deflate");
 
-    let compressed = compress_with_options(data, &options)?;
+    let compressed = compress_with_options(&data, &options)?;
 
     // Verify compression succeeded
     assert!(!compressed.is_empty());
@@ -55,6 +59,23 @@ fn test_manual_plugin_override() -> Result<()> {
     Ok(())
 }
 
+/// Test that the zstd plugin round-trips when requested by name
+#[test]
+fn test_manual_plugin_override_zstd() -> Result<()> {
+    init_plugins()?;
+
+    let data = b"Test data for the zstd plugin. ".repeat(100);
+    let options = CompressionOptions::default().with_plugin("zstd");
+
+    let compressed = compress_with_options(&data, &options)?;
+
+    // Verify the header has the zstd magic number
+    assert_eq!(&compressed[0..4], &[0x43, 0x52, 0x01, 0x01]);
+    assert_eq!(decompress(&compressed)?.data, data);
+
+    Ok(())
+}
+
 /// Test that requesting a non-existent plugin returns an error
 #[test]
 fn test_manual_override_nonexistent_plugin() -> Result<()> {
@@ -117,6 +138,51 @@ fn test_custom_scoring_weights() -> Result<()> {
     Ok(())
 }
 
+/// Test that throughput-heavy weights select the fastest plugin (LZ4)
+#[test]
+fn test_throughput_weights_select_lz4() -> Result<()> {
+    init_plugins()?;
+
+    let selected = PluginSelector::new(ScoringWeights::new(0.9, 0.1)?).select()?;
+    assert_eq!(selected.name, "lz4");
+
+    Ok(())
+}
+
+/// Test that ratio-heavy weights select the best-ratio plugin (xz)
+#[test]
+fn test_ratio_weights_select_xz() -> Result<()> {
+    init_plugins()?;
+
+    let selected = PluginSelector::new(ScoringWeights::new(0.1, 0.9)?).select()?;
+    assert_eq!(selected.name, "xz");
+
+    Ok(())
+}
+
+/// Test that content-aware selection considers Brotli for text only
+#[test]
+fn test_content_aware_selection_brotli() -> Result<()> {
+    init_plugins()?;
+
+    let selector = PluginSelector::new(ScoringWeights::new(0.25, 0.75)?);
+
+    let text = br#"{"id": 1, "name": "widget", "tags": ["a", "b"]}"#;
+    assert_eq!(selector.select_for(text)?.name, "brotli");
+
+    let binary = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00];
+    assert_ne!(selector.select_for(&binary)?.name, "brotli");
+
+    // Brotli-compressed output still round-trips
+    let data = text.repeat(20);
+    let options = CompressionOptions::default().with_weights(ScoringWeights::new(0.25, 0.75)?);
+    let compressed = compress_with_options(&data, &options)?;
+    assert_eq!(&compressed[0..4], &[0x43, 0x52, 0x01, 0x04]);
+    assert_eq!(decompress(&compressed)?.data, data);
+
+    Ok(())
+}
+
 /// Test that invalid scoring weights are rejected
 #[test]
 fn test_invalid_scoring_weights() {
@@ -203,3 +269,57 @@ fn test_scoring_calculation() -> Result<()> {
 
     Ok(())
 }
+
+/// Sample-based selection measures the input instead of trusting metadata
+///
+/// With ratio-only weights and an ample budget every plugin is measured, so
+/// the pick must be the plugin that actually compresses this input smallest.
+#[test]
+fn test_sampled_selection_picks_measured_best_ratio() -> Result<()> {
+    init_plugins()?;
+    #[allow(clippy::cast_possible_truncation)] // Intentional: values always fit in u8
+    let data: Vec<u8> = (0..40_000_u32)
+        .map(|i| ((i * 7) % 13 + (i / 1000) % 5) as u8)
+        .collect();
+
+    let mut best = None;
+    for plugin in list_plugins() {
+        let options = CompressionOptions::default().with_plugin(plugin.name);
+        let size = compress_with_options(&data, &options)?.len();
+        if best.is_none_or(|(best_size, _)| size < best_size) {
+            best = Some((size, plugin.name));
+        }
+    }
+
+    let sampling = SamplingConfig::new(64 * 1024, Duration::from_secs(60));
+    let selector = PluginSelector::new(ScoringWeights::new(0.0, 1.0)?);
+    let selected = selector.select_sampled(&data, &sampling)?;
+    assert_eq!(Some(selected.name), best.map(|(_, name)| name));
+
+    Ok(())
+}
+
+/// Sampling through `CompressionOptions` roundtrips, even with no budget
+#[test]
+fn test_sampled_selection_via_options() -> Result<()> {
+    init_plugins()?;
+    let data = b"Sampled selection through compression options. ".repeat(10_000);
+
+    for budget in [Duration::ZERO, Duration::from_secs(5)] {
+        let options = CompressionOptions::default()
+            .with_sampling(SamplingConfig::new(16 * 1024, budget).with_sample_count(4));
+        let compressed = compress_with_options(&data, &options)?;
+
+        let plugin_name = inspect(&compressed)?.plugin_name;
+        assert!(list_plugins().iter().any(|p| p.name == plugin_name));
+        assert_eq!(decompress(&compressed)?.data, data);
+    }
+
+    // Empty input falls back to static scoring
+    let options = CompressionOptions::default().with_sampling(SamplingConfig::default());
+    assert!(decompress(&compress_with_options(b"", &options)?)?
+        .data
+        .is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Integration tests for random-access reads from seekable files
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    compress_with_options, init_plugins, read_range, BlockIndex, CompressionOptions, CrushWriter,
+    Result,
+};
+use std::fs::File;
+use std::io::Write;
+
+fn sample_data() -> Vec<u8> {
+    #[allow(clippy::cast_possible_truncation)] // Intentional: i % 251 always fits in u8
+    (0..1_000_000_u32).map(|i| (i % 251) as u8).collect()
+}
+
+/// Read ranges from a seekable file on disk, including ranges that span
+/// block boundaries and ranges past the end of the data
+#[test]
+fn test_read_range_from_file() -> Result<()> {
+    init_plugins()?;
+    let data = sample_data();
+    let options = CompressionOptions::default()
+        .with_block_size(64 * 1024)
+        .with_seekable(true);
+    let compressed = compress_with_options(&data, &options)?;
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("trace.crush");
+    std::fs::write(&path, &compressed)?;
+
+    let index = BlockIndex::read_from(&mut File::open(&path)?)?;
+    assert_eq!(index.block_count(), data.len().div_ceil(64 * 1024));
+    assert_eq!(index.uncompressed_size(), data.len() as u64);
+
+    let range = read_range(File::open(&path)?, 65_000, 2_000)?;
+    assert_eq!(range, &data[65_000..67_000]);
+
+    let tail = read_range(File::open(&path)?, 999_900, 1_000)?;
+    assert_eq!(tail, &data[999_900..]);
+
+    assert!(read_range(File::open(&path)?, 2_000_000, 10)?.is_empty());
+
+    Ok(())
+}
+
+/// Streamed output written with `with_seekable` is readable the same way
+#[test]
+fn test_read_range_streamed() -> Result<()> {
+    init_plugins()?;
+    let data = sample_data();
+    let options = CompressionOptions::default()
+        .with_block_size(64 * 1024)
+        .with_seekable(true);
+
+    let mut writer = CrushWriter::new(Vec::new(), &options)?;
+    writer.write_all(&data)?;
+    let compressed = writer.finish()?;
+
+    let range = read_range(std::io::Cursor::new(compressed), 500_000, 300_000)?;
+    assert_eq!(range, &data[500_000..800_000]);
+
+    Ok(())
+}
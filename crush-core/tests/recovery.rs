@@ -0,0 +1,196 @@
+//! Integration tests for salvaging damaged and truncated files
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    compress_with_options, init_plugins, recover, recover_with_options, sign, CompressionOptions,
+    CrushError, CrushWriter, DecompressionOptions, EncryptionError, EncryptionKey, HashAlgorithm,
+    Result, SigningKey,
+};
+use std::io::Write;
+
+const BLOCK: usize = 64 * 1024;
+
+fn lines(len: usize) -> Vec<u8> {
+    (0..)
+        .flat_map(|i: u32| format!("{i:08} sensor reading within range\n").into_bytes())
+        .take(len)
+        .collect()
+}
+
+/// Truncated single-stream files keep the output decoded before the end
+#[test]
+fn test_truncated_stream_keeps_prefix() -> Result<()> {
+    init_plugins()?;
+    let data = lines(300_000);
+    for plugin in ["zstd", "deflate", "lz4"] {
+        let options = CompressionOptions::default().with_plugin(plugin);
+        let mut compressed = compress_with_options(&data, &options)?;
+        compressed.truncate(compressed.len() * 6 / 10);
+
+        let result = recover(&compressed)?;
+        let report = &result.report;
+        assert!(!report.is_intact(), "{plugin}");
+        assert!(!result.data.is_empty(), "{plugin}");
+        assert!(data.starts_with(&result.data), "{plugin}");
+        assert_eq!(report.expected_size, Some(data.len() as u64));
+        assert_eq!(report.damaged.len(), 1);
+        assert_eq!(report.damaged[0].resumed_at, None);
+        assert_eq!(
+            report.lost_bytes(),
+            Some((data.len() - result.data.len()) as u64)
+        );
+    }
+    Ok(())
+}
+
+/// Block files keep every intact block around a damaged one
+#[test]
+fn test_damaged_block_is_skipped() -> Result<()> {
+    init_plugins()?;
+    let data = lines(10 * BLOCK);
+    let options = CompressionOptions::default()
+        .with_block_size(BLOCK)
+        .with_content_hash(HashAlgorithm::Blake3);
+    let mut compressed = compress_with_options(&data, &options)?;
+    let middle = compressed.len() / 2;
+    compressed[middle] ^= 0x20;
+
+    let result = recover(&compressed)?;
+    let report = &result.report;
+    assert!(report.error.as_deref().is_some_and(|e| e.contains("CRC32")));
+    assert_eq!(report.damaged.len(), 1);
+    let region = &report.damaged[0];
+    assert!(region.offset < middle as u64);
+    assert!(region.resumed_at.is_some_and(|at| at > middle as u64));
+    assert_eq!(region.lost_bytes, Some(BLOCK as u64));
+    assert_eq!(region.output_offset % BLOCK as u64, 0);
+
+    let lost = usize::try_from(region.output_offset).unwrap_or(usize::MAX);
+    let expected = [&data[..lost], &data[lost + BLOCK..]].concat();
+    assert_eq!(result.data, expected);
+    Ok(())
+}
+
+/// Truncated streamed output keeps its complete blocks and signed files lose
+/// only their signature
+#[test]
+fn test_truncated_blocks() -> Result<()> {
+    init_plugins()?;
+    let data = lines(6 * BLOCK + 1000);
+    let options = CompressionOptions::default().with_block_size(BLOCK);
+    let mut writer = CrushWriter::new(Vec::new(), &options)?;
+    writer.write_all(&data)?;
+    let mut streamed = writer.finish()?;
+    streamed.truncate(streamed.len() * 3 / 4);
+
+    let result = recover(&streamed)?;
+    assert_eq!(result.data.len() % BLOCK, 0);
+    assert!(result.data.len() >= 3 * BLOCK);
+    assert!(data.starts_with(&result.data));
+    // The streamed size was in the lost trailer
+    assert_eq!(result.report.expected_size, None);
+    assert_eq!(result.report.lost_bytes(), None);
+
+    let key = SigningKey::from_bytes(&[0x31; 32]);
+    let mut signed = sign(&compress_with_options(&data, &options)?, &key)?;
+    signed.truncate(signed.len() - 200);
+    let result = recover(&signed)?;
+    assert!(data.starts_with(&result.data));
+    assert_eq!(result.report.lost_bytes(), Some(1000));
+    Ok(())
+}
+
+/// Encrypted files are recovered up to their first damaged chunk
+#[test]
+fn test_encrypted_recovery() -> Result<()> {
+    init_plugins()?;
+    let data = lines(8 * BLOCK);
+    let key = EncryptionKey::from_bytes([0x52; 32]);
+    let options = CompressionOptions::default()
+        .with_plugin("stored")
+        .with_block_size(BLOCK)
+        .with_encryption(key.clone());
+    let mut compressed = compress_with_options(&data, &options)?;
+    let damaged_at = compressed.len() / 2;
+    compressed[damaged_at] ^= 0x01;
+
+    let result = recover_with_options(
+        &compressed,
+        &DecompressionOptions::default().with_encryption(key),
+    )?;
+    assert!(result.data.len() >= 2 * BLOCK);
+    assert!(data.starts_with(&result.data));
+    let report = &result.report;
+    assert_eq!(report.damaged.len(), 1);
+    assert!(report.damaged[0].offset <= damaged_at as u64);
+    assert_eq!(
+        report.lost_bytes(),
+        Some((data.len() - result.data.len()) as u64)
+    );
+
+    let wrong = DecompressionOptions::default().with_encryption(EncryptionKey::from_bytes([0; 32]));
+    assert!(matches!(
+        recover_with_options(&compressed, &wrong),
+        Err(CrushError::Encryption(EncryptionError::WrongKey))
+    ));
+    Ok(())
+}
+
+/// Blocks beyond their parity's reach are lost; the others are repaired
+#[test]
+fn test_recovery_with_parity() -> Result<()> {
+    init_plugins()?;
+    let data = lines(6 * BLOCK);
+    let options = CompressionOptions::default()
+        .with_plugin("stored")
+        .with_block_size(BLOCK)
+        .with_parity(1);
+    let mut compressed = compress_with_options(&data, &options)?;
+
+    // Stored 64 KB blocks with one parity shard: each parity record is two
+    // header copies, 129 shard CRC32s and a 513-byte shard, and the end
+    // marker's record and frame close the section
+    let block_len = 2 * 12 + 129 * 4 + 513 + 12 + BLOCK;
+    let first = compressed.len() - 6 * block_len - (2 * 12 + 2 * 4 + 12 + 12);
+    let data_of = |block: usize| first + block * block_len + 1053;
+    // Two damaged shards in block 2, one in block 4
+    compressed[data_of(2) + 100] ^= 0x01;
+    compressed[data_of(2) + 1000] ^= 0x01;
+    compressed[data_of(4) + 5000] ^= 0x01;
+
+    let result = recover(&compressed)?;
+    let report = &result.report;
+    assert!(report
+        .error
+        .as_deref()
+        .is_some_and(|e| e.contains("damaged beyond repair")));
+    assert_eq!(report.damaged.len(), 1);
+    assert_eq!(report.damaged[0].offset, (first + 2 * block_len) as u64);
+    assert_eq!(report.damaged[0].lost_bytes, Some(BLOCK as u64));
+    assert_eq!(report.repairs.blocks.len(), 1);
+    assert_eq!(report.repairs.blocks[0].block, 4);
+    assert_eq!(
+        result.data,
+        [&data[..2 * BLOCK], &data[3 * BLOCK..]].concat()
+    );
+    Ok(())
+}
+
+/// Intact files decompress normally; a damaged header cannot be recovered
+#[test]
+fn test_intact_and_unrecoverable() -> Result<()> {
+    init_plugins()?;
+    let data = lines(1000);
+    let mut compressed = compress_with_options(&data, &CompressionOptions::default())?;
+
+    let result = recover(&compressed)?;
+    assert_eq!(result.data, data);
+    assert!(result.report.is_intact());
+    assert_eq!(result.report.expected_size, Some(1000));
+
+    compressed[0] ^= 0xFF;
+    assert!(recover(&compressed).is_err());
+    assert!(recover(&[]).is_err());
+    Ok(())
+}
@@ -5,7 +5,26 @@
 
 #![allow(clippy::panic_in_result_fn)]
 
-use crush_core::{compress, decompress, init_plugins, Result};
+use crush_core::{
+    compress, compress_with_options, decompress, init_plugins, inspect, list_plugins,
+    CompressionOptions, CrushReader, CrushWriter, Result,
+};
+use std::io::{Read, Write};
+
+/// Run `test` once per registered plugin, with options pinned to that plugin
+///
+/// Every plugin must pass the same roundtrip suite; the plugin name is
+/// passed along for assertion messages.
+fn for_each_plugin(mut test: impl FnMut(&str, CompressionOptions) -> Result<()>) -> Result<()> {
+    init_plugins()?;
+    for plugin in list_plugins() {
+        test(
+            plugin.name,
+            CompressionOptions::default().with_plugin(plugin.name),
+        )?;
+    }
+    Ok(())
+}
 
 /// Test basic roundtrip: compress data and decompress it back
 ///
@@ -36,7 +55,23 @@ fn test_roundtrip_basic() -> Result<()> {
         "Decompressed data should match original"
     );
 
-    Ok(())
+    // Every plugin must roundtrip and record itself in the header, unless it
+    // could not shrink this short input and the payload was stored instead
+    for_each_plugin(|plugin, options| {
+        let compressed = compress_with_options(original, &options)?;
+        let recorded = inspect(&compressed)?.plugin_name;
+        assert!(
+            recorded == plugin || recorded == "stored",
+            "{plugin}: header records {recorded}"
+        );
+        assert!(compressed.len() <= original.len() + 20);
+        assert_eq!(
+            original.as_slice(),
+            decompress(&compressed)?.data.as_slice(),
+            "{plugin}: decompressed data should match original"
+        );
+        Ok(())
+    })
 }
 
 /// Test roundtrip with empty data
@@ -50,7 +85,15 @@ fn test_roundtrip_empty() -> Result<()> {
 
     assert_eq!(original.as_slice(), decompressed.data.as_slice());
 
-    Ok(())
+    for_each_plugin(|plugin, options| {
+        let compressed = compress_with_options(original, &options)?;
+        let decompressed = decompress(&compressed)?;
+        assert!(
+            decompressed.data.is_empty(),
+            "{plugin}: expected empty output"
+        );
+        Ok(())
+    })
 }
 
 /// Test roundtrip with large data (>1MB)
@@ -61,16 +104,70 @@ fn test_roundtrip_large() -> Result<()> {
     #[allow(clippy::cast_possible_truncation)] // Intentional: i % 256 always fits in u8
     let original: Vec<u8> = (0..1_000_000_u32).map(|i| (i % 256) as u8).collect();
 
-    let compressed = compress(&original)?;
-    let decompressed = decompress(&compressed)?;
+    for_each_plugin(|plugin, options| {
+        let compressed = compress_with_options(&original, &options)?;
+        let decompressed = decompress(&compressed)?;
 
-    assert_eq!(original, decompressed.data);
-    assert!(
-        compressed.len() < original.len(),
-        "Compressed size should be less than original for repetitive data"
-    );
+        assert_eq!(original, decompressed.data, "{plugin}: roundtrip mismatch");
+        assert!(
+            compressed.len() < original.len() || plugin == "stored",
+            "{plugin}: compressed size should be less than original for repetitive data"
+        );
+        Ok(())
+    })
+}
 
-    Ok(())
+/// Test roundtrip with data spanning many parallel blocks
+///
+/// Uses a small block size so the input is split into dozens of blocks that
+/// are compressed and decompressed on the rayon thread pool.
+#[test]
+fn test_roundtrip_multi_block() -> Result<()> {
+    init_plugins()?;
+    #[allow(clippy::cast_possible_truncation)] // Intentional: i % 253 always fits in u8
+    let original: Vec<u8> = (0..2_000_000_u32).map(|i| (i % 253) as u8).collect();
+
+    for_each_plugin(|plugin, options| {
+        let options = options.with_block_size(64 * 1024);
+        let compressed = compress_with_options(&original, &options)?;
+        let decompressed = decompress(&compressed)?;
+
+        assert_eq!(original, decompressed.data, "{plugin}: roundtrip mismatch");
+        assert!(
+            compressed.len() < original.len() || plugin == "stored",
+            "{plugin}: no size reduction"
+        );
+        Ok(())
+    })
+}
+
+/// Test streaming roundtrip through `CrushWriter` and `CrushReader`
+///
+/// Streamed output must also be readable by the in-memory `decompress` and
+/// `inspect` APIs, which take the original size from the stream trailer.
+#[test]
+fn test_roundtrip_streaming() -> Result<()> {
+    init_plugins()?;
+    #[allow(clippy::cast_possible_truncation)] // Intentional: i % 251 always fits in u8
+    let original: Vec<u8> = (0..1_500_000_u32).map(|i| (i % 251) as u8).collect();
+
+    for_each_plugin(|plugin, options| {
+        let options = options.with_block_size(64 * 1024);
+        let mut writer = CrushWriter::new(Vec::new(), &options)?;
+        for chunk in original.chunks(10_000) {
+            writer.write_all(chunk)?;
+        }
+        let compressed = writer.finish()?;
+
+        let mut reader = CrushReader::new(compressed.as_slice())?;
+        let mut streamed = Vec::new();
+        reader.read_to_end(&mut streamed)?;
+        assert_eq!(original, streamed, "{plugin}: streamed roundtrip mismatch");
+
+        assert_eq!(original, decompress(&compressed)?.data);
+        assert_eq!(inspect(&compressed)?.original_size, original.len() as u64);
+        Ok(())
+    })
 }
 
 /// Test roundtrip with random data
@@ -91,12 +188,13 @@ fn test_roundtrip_random() -> Result<()> {
         original.push(byte);
     }
 
-    let compressed = compress(&original)?;
-    let decompressed = decompress(&compressed)?;
-
-    assert_eq!(original, decompressed.data);
+    for_each_plugin(|plugin, options| {
+        let compressed = compress_with_options(&original, &options)?;
+        let decompressed = decompress(&compressed)?;
 
-    Ok(())
+        assert_eq!(original, decompressed.data, "{plugin}: roundtrip mismatch");
+        Ok(())
+    })
 }
 
 /// Test corrupted data detection
@@ -158,18 +256,19 @@ mod property_tests {
             vec![0x42; 10_000],               // Medium repetitive
         ];
 
-        for original in test_cases {
-            let compressed = compress(&original)?;
-            let decompressed = decompress(&compressed)?;
-            assert_eq!(
-                original,
-                decompressed.data,
-                "Roundtrip failed for data of length {}",
-                original.len()
-            );
-        }
-
-        Ok(())
+        for_each_plugin(|plugin, options| {
+            for original in &test_cases {
+                let compressed = compress_with_options(original, &options)?;
+                let decompressed = decompress(&compressed)?;
+                assert_eq!(
+                    original,
+                    &decompressed.data,
+                    "{plugin}: roundtrip failed for data of length {}",
+                    original.len()
+                );
+            }
+            Ok(())
+        })
     }
 
     /// Property test: Compression should be deterministic
@@ -179,14 +278,15 @@ mod property_tests {
 
         let data = b"Test data for determinism check";
 
-        let compressed1 = compress(data)?;
-        let compressed2 = compress(data)?;
+        for_each_plugin(|plugin, options| {
+            let compressed1 = compress_with_options(data, &options)?;
+            let compressed2 = compress_with_options(data, &options)?;
 
-        assert_eq!(
-            compressed1, compressed2,
-            "Same input should produce identical compressed output"
-        );
-
-        Ok(())
+            assert_eq!(
+                compressed1, compressed2,
+                "{plugin}: same input should produce identical compressed output"
+            );
+            Ok(())
+        })
     }
 }
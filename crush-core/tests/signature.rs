@@ -0,0 +1,155 @@
+//! Integration tests for embedded and detached signatures
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::{
+    compress_with_options, decompress, decompress_with_options, init_plugins, inspect, read_range,
+    sign, sign_detached, verify_detached, verify_signature, CompressionOptions, CrushError,
+    CrushHeader, CrushReader, CrushWriter, DecompressionOptions, EncryptionKey, HashAlgorithm,
+    Result, Signature, SignatureError, SigningKey,
+};
+use std::io::{Cursor, Read, Write};
+
+fn text(len: usize) -> Vec<u8> {
+    b"Release artifacts are signed by the build. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+fn signature_error(result: Result<impl std::fmt::Debug>) -> Option<SignatureError> {
+    match result {
+        Err(CrushError::Signature(e)) => Some(e),
+        _ => None,
+    }
+}
+
+fn read_all(file: &[u8], options: &DecompressionOptions) -> Result<Vec<u8>> {
+    let mut reader = CrushReader::with_options(file, options)?;
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Signed files verify against the signer's key and still decompress
+#[test]
+fn test_embedded_signature() -> Result<()> {
+    init_plugins()?;
+    let key = SigningKey::from_bytes(&[0x11; 32]);
+    let data = text(500_000);
+    let layouts = [
+        CompressionOptions::default(),
+        CompressionOptions::default().with_block_size(64 * 1024),
+        CompressionOptions::default()
+            .with_block_size(64 * 1024)
+            .with_seekable(true)
+            .with_content_hash(HashAlgorithm::Blake3),
+    ];
+    for options in layouts {
+        let signed = sign(&compress_with_options(&data, &options)?, &key)?;
+        assert_eq!(
+            verify_signature(&signed, Some(&key.verifying_key()))?,
+            key.verifying_key()
+        );
+        assert_eq!(decompress(&signed)?.data, data);
+        assert_eq!(read_all(&signed, &DecompressionOptions::new())?, data);
+        assert_eq!(
+            read_range(Cursor::new(&signed), 200_000, 100)?,
+            &data[200_000..200_100]
+        );
+
+        let status = inspect(&signed)?.signature;
+        assert_eq!(status.as_ref().map(|status| status.valid), Some(true));
+        assert_eq!(
+            status.map(|status| status.public_key),
+            Some(key.verifying_key().to_hex())
+        );
+    }
+
+    let mut writer = CrushWriter::new(Vec::new(), &CompressionOptions::default())?;
+    writer.write_all(&data)?;
+    let streamed = sign(&writer.finish()?, &key)?;
+    assert_eq!(read_all(&streamed, &DecompressionOptions::new())?, data);
+    Ok(())
+}
+
+/// Modified files and files signed by another key are rejected
+#[test]
+fn test_tampered_and_untrusted() -> Result<()> {
+    init_plugins()?;
+    let key = SigningKey::from_bytes(&[0x22; 32]);
+    let other = SigningKey::from_bytes(&[0x33; 32]);
+    let signed = sign(
+        &compress_with_options(&text(10_000), &CompressionOptions::default())?,
+        &key,
+    )?;
+
+    assert!(matches!(
+        signature_error(verify_signature(&signed, Some(&other.verifying_key()))),
+        Some(SignatureError::UntrustedKey { .. })
+    ));
+
+    let mut tampered = signed.clone();
+    tampered[CrushHeader::SIZE + 8] ^= 0x01;
+    assert!(matches!(
+        signature_error(verify_signature(&tampered, None)),
+        Some(SignatureError::Invalid)
+    ));
+    assert_eq!(
+        inspect(&tampered)?.signature.map(|status| status.valid),
+        Some(false)
+    );
+
+    let unsigned = compress_with_options(&text(100), &CompressionOptions::default())?;
+    assert!(matches!(
+        signature_error(verify_signature(&unsigned, None)),
+        Some(SignatureError::NotSigned)
+    ));
+    assert!(inspect(&unsigned)?.signature.is_none());
+    Ok(())
+}
+
+/// Detached signatures cover the file as is and leave it untouched
+#[test]
+fn test_detached_signature() -> Result<()> {
+    init_plugins()?;
+    let key = SigningKey::generate();
+    let file = compress_with_options(&text(20_000), &CompressionOptions::default())?;
+    let signature = Signature::from_bytes(&sign_detached(&file, &key)?.to_bytes())?;
+
+    assert_eq!(
+        verify_detached(&file, &signature, Some(&key.verifying_key()))?,
+        key.verifying_key()
+    );
+    let mut tampered = file.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 0x80;
+    assert!(matches!(
+        signature_error(verify_detached(&tampered, &signature, None)),
+        Some(SignatureError::Invalid)
+    ));
+    Ok(())
+}
+
+/// Encrypted files can be signed and verified without their key
+#[test]
+fn test_signed_encrypted_file() -> Result<()> {
+    init_plugins()?;
+    let data = text(200_000);
+    let encryption = EncryptionKey::from_bytes([0x44; 32]);
+    let options = CompressionOptions::default().with_encryption(encryption.clone());
+    let key = SigningKey::from_bytes(&[0x55; 32]);
+    let signed = sign(&compress_with_options(&data, &options)?, &key)?;
+
+    verify_signature(&signed, Some(&key.verifying_key()))?;
+    let result = inspect(&signed)?;
+    assert!(result.encryption.is_some());
+    assert_eq!(result.signature.map(|status| status.valid), Some(true));
+
+    let decrypt = DecompressionOptions::new().with_encryption(encryption);
+    assert_eq!(decompress_with_options(&signed, &decrypt)?.data, data);
+    assert_eq!(read_all(&signed, &decrypt)?, data);
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! Integration tests for already-compressed input and the stored plugin
+
+#![allow(clippy::panic_in_result_fn)]
+
+use crush_core::plugin::stored::looks_compressed;
+use crush_core::{
+    compress_with_options, decompress, init_plugins, inspect, list_plugins, CompressionOptions,
+    CrushReader, CrushWriter, PluginSelector, Result,
+};
+use std::io::{Read, Write};
+
+/// Deterministic pseudo-random (incompressible) bytes
+fn noise(len: usize) -> Vec<u8> {
+    let mut seed = 0xDEAD_BEEF_u32;
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed.to_le_bytes()[1]
+        })
+        .collect()
+}
+
+/// A fake JPEG: the JPEG signature followed by noise
+fn jpeg_like(len: usize) -> Vec<u8> {
+    let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0];
+    data.extend_from_slice(&noise(len - 4));
+    data
+}
+
+/// Automatic selection stores already-compressed input
+#[test]
+fn test_compressed_input_selects_stored() -> Result<()> {
+    init_plugins()?;
+    let selector = PluginSelector::default();
+    assert_eq!(selector.select_for(&jpeg_like(4096))?.name, "stored");
+    assert_eq!(selector.select_for(&noise(4096))?.name, "stored");
+    assert_ne!(selector.select_for(b"plain old text")?.name, "stored");
+    assert_ne!(selector.select()?.name, "stored");
+
+    for data in [jpeg_like(200_000), noise(3 * 1024 * 1024)] {
+        assert!(looks_compressed(&data[..4096]));
+        let compressed = compress_with_options(&data, &CompressionOptions::default())?;
+        assert_eq!(inspect(&compressed)?.plugin_name, "stored");
+        assert_eq!(decompress(&compressed)?.data, data);
+    }
+
+    Ok(())
+}
+
+/// Plugin parameters opt out of storing, since they tune a compressor
+#[test]
+fn test_plugin_params_skip_stored() -> Result<()> {
+    init_plugins()?;
+    let data = jpeg_like(50_000);
+    let options = CompressionOptions::default().with_plugin_param("level", 3);
+
+    let compressed = compress_with_options(&data, &options)?;
+    assert_eq!(decompress(&compressed)?.data, data);
+
+    Ok(())
+}
+
+/// No plugin ever grows its input by more than the container framing
+#[test]
+fn test_output_never_larger_than_input_plus_header() -> Result<()> {
+    init_plugins()?;
+    let data = noise(300_000);
+
+    for plugin in list_plugins() {
+        let options = CompressionOptions::default().with_plugin(plugin.name);
+
+        // Single-stream and multi-block layouts fall back to a stored payload
+        for block_size in [1024 * 1024, 64 * 1024] {
+            let compressed =
+                compress_with_options(&data, &options.clone().with_block_size(block_size))?;
+            assert!(
+                compressed.len() <= data.len() + 20,
+                "{}: {} bytes for {} bytes of input",
+                plugin.name,
+                compressed.len(),
+                data.len()
+            );
+            assert_eq!(decompress(&compressed)?.data, data);
+        }
+
+        // Streaming stores each block that does not shrink
+        let mut writer = CrushWriter::new(Vec::new(), &options.clone().with_block_size(64 * 1024))?;
+        writer.write_all(&data)?;
+        let compressed = writer.finish()?;
+        let blocks = data.len().div_ceil(64 * 1024);
+        assert!(
+            compressed.len() <= data.len() + 16 + 4 + 12 * (blocks + 1) + 12,
+            "{}: streamed {} bytes for {} bytes of input",
+            plugin.name,
+            compressed.len(),
+            data.len()
+        );
+
+        let mut restored = Vec::new();
+        CrushReader::new(compressed.as_slice())?.read_to_end(&mut restored)?;
+        assert_eq!(restored, data);
+    }
+
+    Ok(())
+}
+
+/// Text that compresses well
+fn text(len: usize) -> Vec<u8> {
+    (0u32..)
+        .flat_map(|i| format!("{i:08} INFO request served in {} ms\n", i % 97).into_bytes())
+        .take(len)
+        .collect()
+}
+
+/// Input that only starts out looking compressed: a random prefix, and a PNG
+/// signature in front of text
+fn compressed_prefix_inputs() -> [Vec<u8>; 2] {
+    let mut random_prefix = noise(1024 * 1024);
+    random_prefix.extend(text(2 * 1024 * 1024));
+    let mut png_prefix = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_prefix.extend(text(2 * 1024 * 1024));
+    [random_prefix, png_prefix]
+}
+
+/// Selection samples the whole input, not just the leading signature or entropy
+#[test]
+fn test_compressed_prefix_is_still_compressed() -> Result<()> {
+    init_plugins()?;
+
+    for data in compressed_prefix_inputs() {
+        assert!(looks_compressed(&data[..4096]));
+        assert_ne!(PluginSelector::default().select_for(&data)?.name, "stored");
+
+        let compressed = compress_with_options(&data, &CompressionOptions::default())?;
+        assert_ne!(inspect(&compressed)?.plugin_name, "stored");
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(decompress(&compressed)?.data, data);
+    }
+
+    Ok(())
+}
+
+/// Streaming keeps a compressor and stores only the blocks that do not shrink
+#[test]
+fn test_streaming_decides_per_block() -> Result<()> {
+    init_plugins()?;
+    let options = CompressionOptions::default().with_block_size(64 * 1024);
+    let stream = |data: &[u8]| -> Result<Vec<u8>> {
+        let mut writer = CrushWriter::new(Vec::new(), &options)?;
+        writer.write_all(data)?;
+        writer.finish()
+    };
+
+    let data = jpeg_like(200_000);
+    let compressed = stream(&data)?;
+    let blocks = data.len().div_ceil(64 * 1024);
+    assert!(compressed.len() <= data.len() + 16 + 4 + 12 * (blocks + 1) + 12);
+    assert_eq!(decompress(&compressed)?.data, data);
+
+    for data in compressed_prefix_inputs() {
+        let compressed = stream(&data)?;
+        assert_ne!(inspect(&compressed)?.plugin_name, "stored");
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(decompress(&compressed)?.data, data);
+    }
+
+    Ok(())
+}